@@ -0,0 +1,218 @@
+//! API key authentication.
+//!
+//! Once keys are configured, [`AuthLayer`] answers every request except the
+//! root page with `401 Unauthorized` unless it carries one of them as
+//! `Authorization: Bearer <key>`. Each key has its own rate limit, which
+//! replaces the per-IP limit of [`RateLimitLayer`](crate::ratelimit::RateLimitLayer)
+//! for requests made with it, so a dashboard behind a proxy can be given
+//! more than an anonymous client. Requests turned away for a missing or
+//! wrong key count against the per-IP limit, so keys cannot be guessed
+//! faster than an anonymous client may send requests.
+
+use crate::error::ApiError;
+use crate::ratelimit::RateLimitLayer;
+use crate::settings::ApiKey;
+use axum::http::{header, Request};
+use axum::response::{IntoResponse, Response};
+use samp_query::ratelimit::RateLimiter;
+use std::collections::HashMap;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Marks a request made with a valid API key, for the layers inside
+/// [`AuthLayer`].
+#[derive(Debug, Clone, Copy)]
+pub struct Authenticated;
+
+/// Paths anyone may request without a key.
+const PUBLIC_PATHS: &[&str] = &["/"];
+
+/// Requires an API key on every request; see the module documentation.
+#[derive(Debug, Clone)]
+pub struct AuthLayer {
+    /// The rate limiter of every key, `None` for keys without a limit.
+    /// Empty when authentication is disabled.
+    keys: Arc<HashMap<String, Option<RateLimiter>>>,
+    /// The per-IP limit of requests without a valid key.
+    clients: RateLimitLayer,
+}
+
+impl AuthLayer {
+    /// Accepts `keys`, limiting those without a rate of their own to
+    /// `per_second` requests per second with bursts of `burst`. Without keys
+    /// every request is let through.
+    pub fn new(keys: &[ApiKey], per_second: f64, burst: u32) -> Self {
+        let keys = keys
+            .iter()
+            .map(|key| {
+                let rate = key.rate_limit.unwrap_or(per_second);
+                (key.key.clone(), (rate > 0.0).then(|| RateLimiter::with_burst(rate, burst)))
+            })
+            .collect();
+        Self {
+            keys: Arc::new(keys),
+            clients: RateLimitLayer::new(0.0, 1),
+        }
+    }
+
+    /// Counts requests rejected for a missing or wrong key against the
+    /// per-IP limit of `clients`, answering `429` instead of `401` once a
+    /// client is over it.
+    pub fn limit_clients(mut self, clients: &RateLimitLayer) -> Self {
+        self.clients = clients.clone();
+        self
+    }
+}
+
+impl<S> Layer<S> for AuthLayer {
+    type Service = Auth<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        Auth {
+            inner,
+            keys: self.keys.clone(),
+            clients: self.clients.clone(),
+        }
+    }
+}
+
+/// The service built by [`AuthLayer`].
+#[derive(Debug, Clone)]
+pub struct Auth<S> {
+    inner: S,
+    keys: Arc<HashMap<String, Option<RateLimiter>>>,
+    clients: RateLimitLayer,
+}
+
+impl<S> Auth<S> {
+    /// Checks the key of `request`, returning the response to send instead
+    /// of handling it, if any.
+    fn check<B>(&self, request: &mut Request<B>) -> Option<Response> {
+        if self.keys.is_empty() || PUBLIC_PATHS.contains(&request.uri().path()) {
+            return None;
+        }
+        let key = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .map(str::trim);
+        let Some(limiter) = key.and_then(|key| self.keys.get(key)) else {
+            return Some(self.clients.check(request).unwrap_or_else(|| ApiError::Unauthorized.into_response()));
+        };
+        if let Some(limiter) = limiter {
+            // Keys have no per-destination limit, so the address is unused.
+            if let Err(retry_after) = limiter.try_acquire(SocketAddr::from(([0, 0, 0, 0], 0))) {
+                return Some(ApiError::RateLimited(retry_after).into_response());
+            }
+        }
+        request.extensions_mut().insert(Authenticated);
+        None
+    }
+}
+
+impl<S, B> Service<Request<B>> for Auth<S>
+where
+    S: Service<Request<B>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<B>) -> Self::Future {
+        if let Some(response) = self.check(&mut request) {
+            return Box::pin(async move { Ok(response) });
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::extract::ConnectInfo;
+    use axum::http::StatusCode;
+
+    fn auth(keys: &[&str]) -> Auth<()> {
+        let keys: Vec<ApiKey> = keys.iter().map(|key| key.to_string().try_into().unwrap()).collect();
+        AuthLayer::new(&keys, 0.0, 1).layer(())
+    }
+
+    fn request(path: &str, key: Option<&str>) -> Request<()> {
+        let mut request = Request::builder().uri(path);
+        if let Some(key) = key {
+            request = request.header(header::AUTHORIZATION, format!("Bearer {}", key));
+        }
+        request.body(()).unwrap()
+    }
+
+    fn status(response: Option<Response>) -> Option<StatusCode> {
+        response.map(|response| response.status())
+    }
+
+    #[test]
+    fn check_without_keys_lets_everything_through() {
+        let mut request = request("/api/v1/servers", None);
+        assert_eq!(status(auth(&[]).check(&mut request)), None);
+        assert!(request.extensions().get::<Authenticated>().is_none());
+    }
+
+    #[test]
+    fn check_requires_a_known_key() {
+        let auth = auth(&["secret"]);
+        assert_eq!(
+            status(auth.check(&mut request("/api/v1/servers", None))),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(
+            status(auth.check(&mut request("/api/v1/servers", Some("guess")))),
+            Some(StatusCode::UNAUTHORIZED)
+        );
+        assert_eq!(status(auth.check(&mut request("/", None))), None);
+
+        let mut request = request("/api/v1/servers", Some("secret"));
+        assert_eq!(status(auth.check(&mut request)), None);
+        assert!(request.extensions().get::<Authenticated>().is_some());
+    }
+
+    #[test]
+    fn check_limits_guesses_per_client_ip() {
+        let clients = RateLimitLayer::new(1.0, 2);
+        let auth = AuthLayer::new(&["secret".to_string().try_into().unwrap()], 0.0, 1)
+            .limit_clients(&clients)
+            .layer(());
+        let guess = |ip: [u8; 4]| {
+            let mut request = request("/api/v1/servers", Some("guess"));
+            request.extensions_mut().insert(ConnectInfo(SocketAddr::from((ip, 50000))));
+            request
+        };
+
+        for _ in 0..2 {
+            assert_eq!(status(auth.check(&mut guess([192, 0, 2, 1]))), Some(StatusCode::UNAUTHORIZED));
+        }
+        assert_eq!(status(auth.check(&mut guess([192, 0, 2, 1]))), Some(StatusCode::TOO_MANY_REQUESTS));
+        assert_eq!(status(auth.check(&mut guess([192, 0, 2, 2]))), Some(StatusCode::UNAUTHORIZED));
+    }
+
+    #[test]
+    fn check_limits_each_key() {
+        let auth = auth(&["limited:1", "unlimited"]);
+        assert_eq!(status(auth.check(&mut request("/api/v1/servers", Some("limited")))), None);
+        assert_eq!(
+            status(auth.check(&mut request("/api/v1/servers", Some("limited")))),
+            Some(StatusCode::TOO_MANY_REQUESTS)
+        );
+        for _ in 0..10 {
+            assert_eq!(status(auth.check(&mut request("/api/v1/servers", Some("unlimited")))), None);
+        }
+    }
+}
@@ -1,13 +1,14 @@
 //! Error handling for the API.
 
 use axum::{
-    http::StatusCode,
+    http::{header, StatusCode},
     response::{IntoResponse, Response},
     Json,
 };
 use samp_query::Error as QueryError;
 use serde::{Deserialize, Serialize};
 use std::net::AddrParseError;
+use std::time::Duration;
 use thiserror::Error;
 
 #[derive(Error, Debug)]
@@ -15,13 +16,58 @@ pub enum ApiError {
     #[error("Failed to parse socket address: {0}")]
     AddrParse(#[from] AddrParseError),
 
+    #[error("Invalid server address: {0}")]
+    InvalidAddress(String),
+
+    /// The hostname in the server address could not be resolved.
+    #[error("Failed to resolve {host}: {reason}")]
+    Resolve { host: String, reason: String },
+
+    /// A query string parameter has an invalid value.
+    #[error("Invalid parameter: {0}")]
+    InvalidParameter(String),
+
+    /// History was requested but no history database is configured.
+    #[error("No history database is configured")]
+    HistoryUnavailable,
+
     #[error("Query error: {0}")]
     Query(#[from] QueryError),
 
-    //note: we're keeping this variant for future use in handling unexpected internal errors
     #[error("Internal server error: {0}")]
-    #[allow(dead_code)]
     Internal(String),
+
+    /// The client sent requests faster than allowed and may retry after the
+    /// given time.
+    #[error("Too many requests")]
+    RateLimited(Duration),
+
+    /// No server is registered under the name.
+    #[error("No server is registered as {0}")]
+    NotRegistered(String),
+
+    /// The request conflicts with the current state, e.g. a name that is
+    /// already taken.
+    #[error("{0}")]
+    Conflict(String),
+
+    /// Too many requests are being handled to take on another one.
+    #[error("The service is overloaded; try again later")]
+    Overloaded,
+
+    /// The request carried no API key, or one that is not configured.
+    #[error("A valid API key is required")]
+    Unauthorized,
+
+    /// The request is not allowed without an API key, even when none are
+    /// configured.
+    #[error("{0}")]
+    Forbidden(String),
+}
+
+/// `retry_after` in whole seconds, rounded up, as `Retry-After` expects.
+fn retry_after_secs(retry_after: Duration) -> u64 {
+    retry_after.as_secs() + u64::from(retry_after.subsec_nanos() > 0)
 }
 
 #[derive(Serialize, Deserialize)]
@@ -30,15 +76,25 @@ pub struct ErrorResponse {
     pub message: String,
     ///error code.
     pub code: String,
+    ///hostname that failed to resolve, for resolution errors.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub host: Option<String>,
 }
 
 impl IntoResponse for ApiError {
     fn into_response(self) -> Response {
+        crate::logging::record_error(&self);
         let (status, error_message) = match &self {
             ApiError::AddrParse(_) => (
                 StatusCode::BAD_REQUEST,
                 format!("Invalid server address: {}", self),
             ),
+            ApiError::InvalidAddress(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            ApiError::Resolve { .. } => (StatusCode::NOT_FOUND, self.to_string()),
+            ApiError::InvalidParameter(_) => (StatusCode::BAD_REQUEST, self.to_string()),
+            ApiError::HistoryUnavailable => (StatusCode::NOT_FOUND, self.to_string()),
+            ApiError::NotRegistered(_) => (StatusCode::NOT_FOUND, self.to_string()),
+            ApiError::Conflict(_) => (StatusCode::CONFLICT, self.to_string()),
             ApiError::Query(QueryError::Timeout) => (
                 StatusCode::GATEWAY_TIMEOUT,
                 "Server did not respond in time".to_string(),
@@ -47,6 +103,14 @@ impl IntoResponse for ApiError {
                 StatusCode::BAD_GATEWAY,
                 "Failed to connect to server".to_string(),
             ),
+            ApiError::Query(QueryError::RateLimited(_)) => (
+                StatusCode::TOO_MANY_REQUESTS,
+                "Too many queries to the server; retry later".to_string(),
+            ),
+            ApiError::Query(QueryError::InvalidResponse(_) | QueryError::Utf8(_)) => (
+                StatusCode::BAD_GATEWAY,
+                format!("Query error: {}", self),
+            ),
             ApiError::Query(QueryError::RconAuthFailed) => (
                 StatusCode::UNAUTHORIZED,
                 "RCON authentication failed".to_string(),
@@ -59,14 +123,41 @@ impl IntoResponse for ApiError {
                 StatusCode::INTERNAL_SERVER_ERROR,
                 format!("Internal server error: {}", self),
             ),
+            ApiError::RateLimited(_) => (StatusCode::TOO_MANY_REQUESTS, self.to_string()),
+            ApiError::Unauthorized => (StatusCode::UNAUTHORIZED, self.to_string()),
+            ApiError::Forbidden(_) => (StatusCode::FORBIDDEN, self.to_string()),
+            ApiError::Overloaded => (StatusCode::SERVICE_UNAVAILABLE, self.to_string()),
         };
 
         let body = Json(ErrorResponse {
             message: error_message,
             code: status.as_u16().to_string(),
+            host: match &self {
+                ApiError::Resolve { host, .. } => Some(host.clone()),
+                _ => None,
+            },
         });
 
-        (status, body).into_response()
+        match self {
+            ApiError::RateLimited(retry_after) | ApiError::Query(QueryError::RateLimited(retry_after)) => {
+                let seconds = retry_after_secs(retry_after).to_string();
+                (status, [(header::RETRY_AFTER, seconds)], body).into_response()
+            }
+            ApiError::Unauthorized => (status, [(header::WWW_AUTHENTICATE, "Bearer".to_string())], body).into_response(),
+            _ => (status, body).into_response(),
+        }
     }
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn retry_after_rounds_up_to_whole_seconds() {
+        assert_eq!(retry_after_secs(Duration::ZERO), 0);
+        assert_eq!(retry_after_secs(Duration::from_nanos(1)), 1);
+        assert_eq!(retry_after_secs(Duration::from_secs(1)), 1);
+        assert_eq!(retry_after_secs(Duration::from_millis(1500)), 2);
+    }
+}
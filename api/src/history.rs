@@ -0,0 +1,119 @@
+//! Historical data.
+//!
+//! `GET /api/v1/servers/:address/history` reads the SQLite database that
+//! `samp-query-cli history record` and `samp-query-cli daemon` write, and
+//! returns a metric averaged over fixed buckets for charting:
+//!
+//! ```text
+//! GET /api/v1/servers/127.0.0.1:7777/history?metric=players&since=24h&step=5m
+//! {"address":"127.0.0.1:7777","metric":"players","since":...,"step_ms":300000,"points":[{"timestamp":...,"value":12.5}]}
+//! ```
+//!
+//! Buckets without an online observation are left out, so gaps in the
+//! series are times the server was offline or not recorded.
+
+use crate::error::ApiError;
+use samp_query::storage::{Metric, SeriesPoint, SqliteStore};
+use samp_query::utils::{self, unix_timestamp_ms};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Most buckets a single request may ask for.
+const MAX_POINTS: u64 = 10_000;
+
+/// The query string of a history request.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct HistoryQuery {
+    pub metric: Metric,
+    /// How far back the series starts, e.g. `24h`.
+    pub since: String,
+    /// The width of each bucket, e.g. `5m`.
+    pub step: String,
+}
+
+impl Default for HistoryQuery {
+    fn default() -> Self {
+        Self {
+            metric: Metric::Players,
+            since: "24h".to_string(),
+            step: "5m".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize)]
+pub struct HistoryResponse {
+    pub address: SocketAddr,
+    pub metric: Metric,
+    /// Unix timestamp in milliseconds the series starts at.
+    pub since: u64,
+    /// Width of each bucket in milliseconds.
+    pub step_ms: u64,
+    pub points: Vec<SeriesPoint>,
+}
+
+/// Parses the duration parameter `name`, which must not be zero.
+fn parse_duration(name: &str, s: &str) -> Result<Duration, ApiError> {
+    match utils::parse_duration(s) {
+        Ok(duration) if !duration.is_zero() => Ok(duration),
+        Ok(_) => Err(ApiError::InvalidParameter(format!("{} must not be zero", name))),
+        Err(e) => Err(ApiError::InvalidParameter(format!("{}: {}", name, e))),
+    }
+}
+
+/// Reads the series `query` asks for from `store`.
+pub async fn series(
+    store: Arc<SqliteStore>,
+    addr: SocketAddr,
+    query: HistoryQuery,
+) -> Result<HistoryResponse, ApiError> {
+    let range = parse_duration("since", &query.since)?;
+    let step = parse_duration("step", &query.step)?;
+    if range.as_millis() / step.as_millis().max(1) > u128::from(MAX_POINTS) {
+        return Err(ApiError::InvalidParameter(format!(
+            "since {} with step {} asks for more than {} points",
+            query.since, query.step, MAX_POINTS
+        )));
+    }
+
+    let since = unix_timestamp_ms().saturating_sub(range.as_millis() as u64);
+    let step_ms = step.as_millis() as u64;
+    let metric = query.metric;
+    // SQLite blocks, so the query runs off the async workers.
+    let points = tokio::task::spawn_blocking(move || store.series(addr, metric, since, step_ms))
+        .await
+        .map_err(|e| ApiError::Internal(e.to_string()))??;
+
+    Ok(HistoryResponse {
+        address: addr,
+        metric,
+        since,
+        step_ms,
+        points,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_duration_units() {
+        assert_eq!(parse_duration("since", "90s").unwrap(), Duration::from_secs(90));
+        assert_eq!(parse_duration("since", "24h").unwrap(), Duration::from_secs(86_400));
+        assert_eq!(parse_duration("step", "1w").unwrap(), Duration::from_secs(604_800));
+    }
+
+    #[test]
+    fn parse_duration_rejects_zero_and_garbage() {
+        for value in ["0s", "0", "", "soon", "5y"] {
+            match parse_duration("step", value) {
+                Err(ApiError::InvalidParameter(message)) => assert!(message.starts_with("step"), "{}", message),
+                result => panic!("{:?} parsed as {:?}", value, result),
+            }
+        }
+    }
+}
@@ -0,0 +1,198 @@
+//! Live updates over WebSocket.
+//!
+//! `GET /api/v1/servers/:address/live` upgrades to a WebSocket that first
+//! receives a `snapshot` message with the server's current state and then a
+//! `diff` message after every poll, so dashboards do not have to poll the
+//! API. One [`Monitor`] runs per server however many clients watch it, and
+//! it stops when the last client disconnects.
+//!
+//! Messages are JSON objects tagged by `type`:
+//!
+//! ```text
+//! {"type":"snapshot","address":"127.0.0.1:7777","timestamp":...,"info":{...},"rules":{...},"players":{...},"ping":{...}}
+//! {"type":"diff","timestamp":...,"events":[{"type":"player_joined","name":"Sweet"}],"info":{...},"ping_ms":12}
+//! {"type":"error","message":"..."}
+//! ```
+//!
+//! A diff lists the monitor events since the previous one. After the server
+//! comes back from being offline, and whenever a client falls too far
+//! behind, a new snapshot is sent instead, since joins and leaves during
+//! the gap are unknown.
+
+use axum::extract::ws::{Message, WebSocket};
+use futures::StreamExt;
+use samp_query::cache::QueryCache;
+use samp_query::monitor::{Monitor, MonitorConfig, MonitorEventKind};
+use samp_query::{ServerInfo, ServerSnapshot};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::broadcast::{self, error::RecvError};
+
+/// Messages a slow client may fall behind by before it is sent a snapshot.
+const CHANNEL_CAPACITY: usize = 32;
+
+#[derive(Debug, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+enum LiveMessage {
+    Snapshot(ServerSnapshot),
+    Diff {
+        timestamp: u64,
+        events: Vec<MonitorEventKind>,
+        /// The latest information, or `None` if the server went offline.
+        info: Option<ServerInfo>,
+        ping_ms: Option<u64>,
+    },
+    Error {
+        message: String,
+    },
+}
+
+impl LiveMessage {
+    fn to_json(&self) -> Arc<str> {
+        serde_json::to_string(self)
+            .expect("live messages serialize to JSON")
+            .into()
+    }
+}
+
+/// The monitors of watched servers and the channels their diffs are sent on.
+#[derive(Debug, Clone)]
+pub struct LiveHub {
+    cache: QueryCache,
+    interval: Duration,
+    channels: Arc<Mutex<HashMap<SocketAddr, broadcast::Sender<Arc<str>>>>>,
+}
+
+impl LiveHub {
+    /// A hub polling watched servers every `interval` and reading snapshots
+    /// through `cache`.
+    pub fn new(cache: QueryCache, interval: Duration) -> Self {
+        Self {
+            cache,
+            interval,
+            channels: Arc::new(Mutex::new(HashMap::new())),
+        }
+    }
+
+    /// Subscribes to the diffs of `addr`, starting its monitor if nobody
+    /// watches it yet.
+    fn subscribe(&self, addr: SocketAddr) -> broadcast::Receiver<Arc<str>> {
+        let mut channels = self.channels.lock().unwrap();
+        if let Some(sender) = channels.get(&addr) {
+            return sender.subscribe();
+        }
+        let (sender, receiver) = broadcast::channel(CHANNEL_CAPACITY);
+        channels.insert(addr, sender.clone());
+        tokio::spawn(self.clone().watch(addr, sender));
+        receiver
+    }
+
+    /// Monitors `addr` and sends a message after every poll until no client
+    /// is left.
+    async fn watch(self, addr: SocketAddr, sender: broadcast::Sender<Arc<str>>) {
+        let config = MonitorConfig {
+            client: self.cache.config().client.clone(),
+            interval: self.interval,
+            ..MonitorConfig::default()
+        };
+        let mut events = Monitor::with_config(addr, config).stream();
+        let mut pending = Vec::new();
+        let (mut offline, mut resync) = (false, false);
+
+        loop {
+            let event = tokio::select! {
+                _ = sender.closed() => {
+                    // A client may have subscribed since the last one left.
+                    let mut channels = self.channels.lock().unwrap();
+                    if sender.receiver_count() == 0 {
+                        channels.remove(&addr);
+                        return;
+                    }
+                    continue;
+                }
+                event = events.next() => match event {
+                    Some(event) => event,
+                    None => return,
+                },
+            };
+
+            let message = match event.kind {
+                MonitorEventKind::Online => {
+                    resync = offline;
+                    offline = false;
+                    pending.push(MonitorEventKind::Online);
+                    None
+                }
+                MonitorEventKind::Offline { reason } => {
+                    offline = true;
+                    pending.push(MonitorEventKind::Offline { reason });
+                    Some(LiveMessage::Diff {
+                        timestamp: event.timestamp,
+                        events: std::mem::take(&mut pending),
+                        info: None,
+                        ping_ms: None,
+                    })
+                }
+                MonitorEventKind::Polled { .. } if resync => {
+                    resync = false;
+                    pending.clear();
+                    self.cache.invalidate(addr);
+                    Some(snapshot(&self.cache, addr).await)
+                }
+                MonitorEventKind::Polled { info, ping_ms, .. } => Some(LiveMessage::Diff {
+                    timestamp: event.timestamp,
+                    events: std::mem::take(&mut pending),
+                    info: Some(info),
+                    ping_ms,
+                }),
+                kind => {
+                    pending.push(kind);
+                    None
+                }
+            };
+            if let Some(message) = message {
+                // Failing only means every client has left, which is handled above.
+                let _ = sender.send(message.to_json());
+            }
+        }
+    }
+}
+
+/// The current state of `addr`, read through the cache, or an error message
+/// if the server does not answer.
+async fn snapshot(cache: &QueryCache, addr: SocketAddr) -> LiveMessage {
+    match cache.snapshot(addr).await {
+        Ok(snapshot) => LiveMessage::Snapshot(snapshot),
+        Err(e) => LiveMessage::Error {
+            message: e.to_string(),
+        },
+    }
+}
+
+/// Sends the updates of `addr` to `socket` until the client disconnects.
+pub async fn serve(mut socket: WebSocket, hub: LiveHub, addr: SocketAddr) {
+    let mut updates = hub.subscribe(addr);
+    let mut message = Some(snapshot(&hub.cache, addr).await.to_json());
+
+    loop {
+        if let Some(text) = message.take() {
+            if socket.send(Message::Text(text.to_string())).await.is_err() {
+                return;
+            }
+        }
+        tokio::select! {
+            update = updates.recv() => match update {
+                Ok(text) => message = Some(text),
+                Err(RecvError::Lagged(_)) => message = Some(snapshot(&hub.cache, addr).await.to_json()),
+                Err(RecvError::Closed) => return,
+            },
+            received = socket.recv() => match received {
+                Some(Ok(Message::Close(_))) | Some(Err(_)) | None => return,
+                Some(Ok(_)) => {}
+            },
+        }
+    }
+}
@@ -0,0 +1,148 @@
+//! Global concurrency limiting and load shedding.
+//!
+//! [`LoadShedLayer`] lets a fixed number of requests be handled at once.
+//! Further requests wait in a queue of limited length for up to a timeout;
+//! when the queue is full or the wait times out they are answered with
+//! `503 Service Unavailable`. Every handled request may query a game
+//! server over its own UDP socket, so this bounds the sockets a burst of
+//! requests can open, which the per-client rate limits alone do not.
+//! WebSocket upgrades only hold their slot until the upgrade is answered.
+
+use crate::error::ApiError;
+use axum::http::Request;
+use axum::response::{IntoResponse, Response};
+use std::future::Future;
+use std::pin::Pin;
+use std::sync::Arc;
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::Semaphore;
+use tower::{Layer, Service};
+
+/// Limits the requests in flight; see the module documentation.
+#[derive(Debug, Clone)]
+pub struct LoadShedLayer {
+    /// `None` when the limit is disabled.
+    limits: Option<Arc<Limits>>,
+}
+
+#[derive(Debug)]
+struct Limits {
+    /// Permits for requests being handled.
+    in_flight: Semaphore,
+    /// Permits for requests being handled or waiting.
+    admitted: Semaphore,
+    queue_timeout: Duration,
+}
+
+impl LoadShedLayer {
+    /// Handles up to `max_in_flight` requests at once, queueing up to
+    /// `max_queued` more for at most `queue_timeout` each. A limit of `0`
+    /// disables it.
+    pub fn new(max_in_flight: usize, max_queued: usize, queue_timeout: Duration) -> Self {
+        let limits = (max_in_flight > 0).then(|| {
+            Arc::new(Limits {
+                in_flight: Semaphore::new(max_in_flight),
+                admitted: Semaphore::new(max_in_flight + max_queued),
+                queue_timeout,
+            })
+        });
+        Self { limits }
+    }
+}
+
+impl<S> Layer<S> for LoadShedLayer {
+    type Service = LoadShed<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        LoadShed {
+            inner,
+            limits: self.limits.clone(),
+        }
+    }
+}
+
+/// The service built by [`LoadShedLayer`].
+#[derive(Debug, Clone)]
+pub struct LoadShed<S> {
+    inner: S,
+    limits: Option<Arc<Limits>>,
+}
+
+impl<S, B> Service<Request<B>> for LoadShed<S>
+where
+    S: Service<Request<B>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+    B: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        let Some(limits) = self.limits.clone() else {
+            return Box::pin(self.inner.call(request));
+        };
+        // The service that was polled ready handles the request once it gets
+        // a slot; the clone takes its place for the next request.
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+
+        Box::pin(async move {
+            let Ok(_admitted) = limits.admitted.try_acquire() else {
+                return Ok(ApiError::Overloaded.into_response());
+            };
+            let _in_flight = match tokio::time::timeout(limits.queue_timeout, limits.in_flight.acquire()).await {
+                Ok(Ok(permit)) => permit,
+                _ => return Ok(ApiError::Overloaded.into_response()),
+            };
+            inner.call(request).await
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::StatusCode;
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    /// A slow handler behind `layer`.
+    async fn call(layer: &LoadShedLayer) -> StatusCode {
+        let service = layer.layer(service_fn(|_: Request<()>| async {
+            tokio::time::sleep(Duration::from_millis(100)).await;
+            Ok::<_, Infallible>(Response::default())
+        }));
+        service.oneshot(Request::new(())).await.unwrap().status()
+    }
+
+    #[tokio::test]
+    async fn sheds_requests_beyond_the_queue() {
+        let layer = LoadShedLayer::new(1, 0, Duration::from_secs(1));
+        let (first, second) = tokio::join!(call(&layer), call(&layer));
+        assert_eq!((first, second), (StatusCode::OK, StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[tokio::test]
+    async fn queued_requests_wait_for_a_slot() {
+        let layer = LoadShedLayer::new(1, 1, Duration::from_secs(1));
+        let (first, second) = tokio::join!(call(&layer), call(&layer));
+        assert_eq!((first, second), (StatusCode::OK, StatusCode::OK));
+
+        let layer = LoadShedLayer::new(1, 1, Duration::from_millis(10));
+        let (first, second) = tokio::join!(call(&layer), call(&layer));
+        assert_eq!((first, second), (StatusCode::OK, StatusCode::SERVICE_UNAVAILABLE));
+    }
+
+    #[tokio::test]
+    async fn zero_limit_disables_it() {
+        let layer = LoadShedLayer::new(0, 0, Duration::ZERO);
+        let results = futures::future::join_all((0..4).map(|_| call(&layer))).await;
+        assert!(results.iter().all(|status| *status == StatusCode::OK));
+    }
+}
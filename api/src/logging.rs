@@ -0,0 +1,85 @@
+//! Structured request logging.
+//!
+//! Logs are written to stdout as one JSON object per line. Every request
+//! gets an ID, taken from its `X-Request-Id` header or generated, which is
+//! echoed in the response header of the same name. The request's span
+//! carries the ID, method and path, and the server address and query type
+//! once a handler knows them, so every line logged while handling the
+//! request can be correlated. A `request completed` line reports the
+//! status, latency and outcome:
+//!
+//! ```text
+//! {"timestamp":"...","level":"INFO","fields":{"message":"request completed","status":504,"latency_ms":3012,"outcome":"server_error"},"target":"samp_query_api::logging","span":{"request_id":"7f9c...","method":"GET","path":"/api/v1/servers/127.0.0.1:7777/info","address":"127.0.0.1:7777","query":"Information","error":"Query error: Connection timed out","name":"request"}}
+//! ```
+//!
+//! Levels are set with `RUST_LOG`, e.g. `RUST_LOG=samp_query=debug,info`.
+
+use axum::http::Request;
+use axum::response::Response;
+use std::fmt::Display;
+use std::time::Duration;
+use tower_http::request_id::RequestId;
+use tracing::{field, Span};
+use tracing_subscriber::EnvFilter;
+
+/// Installs the JSON subscriber, logging at `info` unless `RUST_LOG` says
+/// otherwise.
+pub fn init() {
+    tracing_subscriber::fmt()
+        .json()
+        .with_env_filter(EnvFilter::try_from_default_env().unwrap_or_else(|_| EnvFilter::new("info")))
+        .with_current_span(true)
+        .with_span_list(false)
+        .init();
+}
+
+/// The span every request is handled in.
+pub fn make_span<B>(request: &Request<B>) -> Span {
+    let request_id = request
+        .extensions()
+        .get::<RequestId>()
+        .and_then(|id| id.header_value().to_str().ok())
+        .unwrap_or_default();
+    tracing::info_span!(
+        "request",
+        request_id,
+        method = %request.method(),
+        path = %request.uri().path(),
+        address = field::Empty,
+        query = field::Empty,
+        error = field::Empty,
+    )
+}
+
+/// Logs the status and latency of a response.
+pub fn on_response(response: &Response, latency: Duration, _span: &Span) {
+    let status = response.status();
+    let outcome = if status.is_server_error() {
+        "server_error"
+    } else if status.is_client_error() {
+        "client_error"
+    } else {
+        "success"
+    };
+    tracing::info!(
+        status = status.as_u16(),
+        latency_ms = latency.as_millis() as u64,
+        outcome,
+        "request completed"
+    );
+}
+
+/// Records the server a request is about in its span.
+pub fn record_address(address: impl Display) {
+    Span::current().record("address", field::display(address));
+}
+
+/// Records the query a request makes in its span.
+pub fn record_query(query: impl Display) {
+    Span::current().record("query", field::display(query));
+}
+
+/// Records why a request failed in its span.
+pub fn record_error(error: impl Display) {
+    Span::current().record("error", field::display(error));
+}
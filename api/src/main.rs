@@ -1,40 +1,151 @@
 //! REST API for the SAMP Query library.
 
 use axum::{
-    extract::{Path, State},
+    extract::{ws::WebSocketUpgrade, Path, Query, State},
+    http::StatusCode,
+    middleware,
+    response::Response,
     routing::get,
-    Json, Router,
+    Extension, Json, Router,
 };
-use samp_query::Client;
+use samp_query::cache::{CacheConfig, CachedValue, QueryCache};
+use samp_query::protocol::{constants, QueryType};
+use samp_query::ratelimit::{RateLimiter, DEFAULT_PER_DESTINATION_QPS};
+use samp_query::resolver::Resolver;
+use samp_query::storage::SqliteStore;
+use samp_query::utils::split_host_port;
 use serde::Deserialize;
+use std::collections::HashSet;
 use std::net::SocketAddr;
 use std::sync::Arc;
+use std::time::Duration;
+use tower_http::request_id::{MakeRequestUuid, PropagateRequestIdLayer, SetRequestIdLayer};
 use tower_http::trace::TraceLayer;
-use tracing::info;
+use tracing::{info, warn};
 
+mod auth;
 mod error;
+mod history;
+mod live;
+mod loadshed;
+mod logging;
+mod ratelimit;
+mod registry;
+mod search;
+mod settings;
+mod telemetry;
+mod webhooks;
+use auth::{AuthLayer, Authenticated};
 use error::ApiError;
+use history::HistoryQuery;
+use live::LiveHub;
+use loadshed::LoadShedLayer;
+use metrics_exporter_prometheus::PrometheusHandle;
+use ratelimit::RateLimitLayer;
+use registry::{RegisteredServer, Registration, Registry};
+use search::SearchQuery;
+use settings::Settings;
+use webhooks::Notifier;
 
 #[derive(Clone)]
 struct AppState {
-    //add any shared state here
+    resolver: Resolver,
+    cache: QueryCache,
+    live: LiveHub,
+    metrics: PrometheusHandle,
+    /// `None` when no history database is configured.
+    history: Option<Arc<SqliteStore>>,
+    registry: Registry,
 }
 
+/// Queries per second the API sends across all servers.
+const GLOBAL_QPS: f64 = 200.0;
+
+/// How often cache entries too old to serve are dropped.
+const PURGE_INTERVAL: Duration = Duration::from_secs(60);
+
 #[derive(Deserialize)]
 struct ServerAddress {
     address: String,
 }
 
+/// `?fresh=true` queries the server even if a cached response is available.
+#[derive(Deserialize, Default)]
+#[serde(default)]
+struct Freshness {
+    fresh: bool,
+}
+
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
-    tracing_subscriber::fmt()
-        .with_max_level(tracing::Level::INFO)
-        .init();
+    logging::init();
+
+    let settings = Settings::load()?;
+    let metrics = telemetry::install()?;
+    let cache = QueryCache::new(CacheConfig {
+        limiter: Some(RateLimiter::new(GLOBAL_QPS).per_destination(DEFAULT_PER_DESTINATION_QPS)),
+        ..settings.cache_config()
+    });
+    let purged = cache.clone();
+    tokio::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+        loop {
+            interval.tick().await;
+            purged.purge_expired();
+        }
+    });
+
+    let history = match &settings.history_db {
+        Some(path) => {
+            info!("Serving history from {}", path.display());
+            Some(Arc::new(SqliteStore::open(path)?))
+        }
+        None => None,
+    };
 
-    let state = Arc::new(AppState {});
+    for url in &settings.webhooks {
+        webhooks::validate_url(url)?;
+    }
+    let registry = Registry::new(
+        cache.clone(),
+        settings.poll_interval(),
+        Notifier::new(settings.webhooks.clone()),
+        settings.registry_file.clone(),
+    );
+    let seeds = settings.servers.iter().map(|address| Registration {
+        name: address.clone(),
+        address: address.clone(),
+        interval: None,
+        webhooks: Vec::new(),
+        milestones: Vec::new(),
+    });
+    for registration in seeds.chain(registry.load()?) {
+        let result = match registration.validate() {
+            Ok(()) => resolve_address(Resolver::global(), &registration.address).await,
+            Err(e) => Err(e),
+        };
+        match result {
+            Ok(addr) => {
+                registry.insert(registration, addr, true)?;
+            }
+            Err(e) => warn!("Not registering {}: {}", registration.name, e),
+        }
+    }
 
+    let state = Arc::new(AppState {
+        resolver: Resolver::global().clone(),
+        live: LiveHub::new(cache.clone(), settings.live_interval()),
+        cache,
+        metrics,
+        history,
+        registry,
+    });
+
+    // Shared with the key check, so failed guesses use up the same budget.
+    let rate_limit = RateLimitLayer::new(settings.rate_limit, settings.rate_burst);
     let app = Router::new()
         .route("/", get(root))
+        .route("/metrics", get(get_metrics))
         .route("/api/v1/servers/:address/info", get(get_server_info))
         .route("/api/v1/servers/:address/rules", get(get_server_rules))
         .route("/api/v1/servers/:address/players", get(get_player_list))
@@ -43,68 +154,281 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
             get(get_detailed_player_list),
         )
         .route("/api/v1/servers/:address/ping", get(get_server_ping))
-        .layer(TraceLayer::new_for_http())
+        .route("/api/v1/servers/:address/full", get(get_server_snapshot))
+        .route("/api/v1/servers/:address/live", get(get_live_updates))
+        .route("/api/v1/servers/:address/history", get(get_server_history))
+        .route("/api/v1/players/search", get(search_players))
+        .route("/api/v1/registered", get(list_registered).post(register_server))
+        .route(
+            "/api/v1/registered/:name",
+            get(get_registered).put(update_registered).delete(unregister_server),
+        )
+        .route_layer(middleware::from_fn(telemetry::track_requests))
+        .layer(LoadShedLayer::new(
+            settings.max_concurrent_requests,
+            settings.max_queued_requests,
+            settings.queue_timeout(),
+        ))
+        .layer(rate_limit.clone())
+        .layer(AuthLayer::new(&settings.keys, settings.rate_limit, settings.rate_burst).limit_clients(&rate_limit))
+        .layer(
+            TraceLayer::new_for_http()
+                .make_span_with(logging::make_span)
+                .on_response(logging::on_response)
+                // Failures are reported by `on_response` already.
+                .on_failure(()),
+        )
+        .layer(PropagateRequestIdLayer::x_request_id())
+        .layer(SetRequestIdLayer::x_request_id(MakeRequestUuid))
         .with_state(state);
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], 3001));
+    let addr = settings.listen_addr();
     info!("Listening on {}", addr);
     axum::Server::bind(&addr)
-        .serve(app.into_make_service())
+        .serve(app.into_make_service_with_connect_info::<SocketAddr>())
         .await?;
 
     Ok(())
 }
 
+/// Parses `address` as `host[:port]` and resolves the host if it is a name.
+async fn parse_server_address(state: &AppState, address: &str) -> Result<SocketAddr, ApiError> {
+    logging::record_address(address);
+    resolve_address(&state.resolver, address).await
+}
+
+async fn resolve_address(resolver: &Resolver, address: &str) -> Result<SocketAddr, ApiError> {
+    let (host, port) = split_host_port(address, constants::DEFAULT_PORT).map_err(ApiError::InvalidAddress)?;
+    resolver.resolve(&host, port).await.map_err(|e| {
+        let reason = match e {
+            // The resolver's message starts with the host, which is reported separately.
+            samp_query::Error::Resolve(detail) => match detail.strip_prefix(&format!("{}: ", host)) {
+                Some(reason) => reason.to_string(),
+                None => detail,
+            },
+            e => e.to_string(),
+        };
+        ApiError::Resolve { host, reason }
+    })
+}
+
+/// Resolves `address` and answers `query_type` from the cache, or from the
+/// server if the rate limiter allows it.
+async fn query(
+    state: &AppState,
+    address: &str,
+    query_type: QueryType,
+    Freshness { fresh }: Freshness,
+) -> Result<CachedValue, ApiError> {
+    logging::record_query(query_type);
+    let addr = parse_server_address(state, address).await?;
+    let result = if fresh {
+        state.cache.fetch(addr, query_type).await
+    } else {
+        state.cache.get(addr, query_type).await
+    };
+    telemetry::record_query(addr, result.as_ref());
+    Ok(result?)
+}
+
+fn unexpected(value: CachedValue) -> ApiError {
+    ApiError::Internal(format!("Unexpected cached response: {:?}", value))
+}
+
 async fn root() -> &'static str {
     "SAMP Query API"
 }
 
+async fn get_metrics(State(state): State<Arc<AppState>>) -> String {
+    telemetry::render(&state.metrics)
+}
+
 async fn get_server_info(
     Path(ServerAddress { address }): Path<ServerAddress>,
-    State(_state): State<Arc<AppState>>,
+    Query(freshness): Query<Freshness>,
+    State(state): State<Arc<AppState>>,
 ) -> Result<Json<samp_query::ServerInfo>, ApiError> {
-    let addr: SocketAddr = address.parse()?;
-    let client = Client::connect(addr).await?;
-    let info = client.query_info().await?;
-    Ok(Json(info))
+    match query(&state, &address, QueryType::Information, freshness).await? {
+        CachedValue::Info(info) => Ok(Json(info)),
+        value => Err(unexpected(value)),
+    }
 }
 
 async fn get_server_rules(
     Path(ServerAddress { address }): Path<ServerAddress>,
-    State(_state): State<Arc<AppState>>,
+    Query(freshness): Query<Freshness>,
+    State(state): State<Arc<AppState>>,
 ) -> Result<Json<samp_query::ServerRules>, ApiError> {
-    let addr: SocketAddr = address.parse()?;
-    let client = Client::connect(addr).await?;
-    let rules = client.query_rules().await?;
-    Ok(Json(rules))
+    match query(&state, &address, QueryType::Rules, freshness).await? {
+        CachedValue::Rules(rules) => Ok(Json(rules)),
+        value => Err(unexpected(value)),
+    }
 }
 
 async fn get_player_list(
     Path(ServerAddress { address }): Path<ServerAddress>,
-    State(_state): State<Arc<AppState>>,
+    Query(freshness): Query<Freshness>,
+    State(state): State<Arc<AppState>>,
 ) -> Result<Json<samp_query::PlayerList>, ApiError> {
-    let addr: SocketAddr = address.parse()?;
-    let client = Client::connect(addr).await?;
-    let players = client.query_client_list().await?;
-    Ok(Json(players))
+    match query(&state, &address, QueryType::ClientList, freshness).await? {
+        CachedValue::ClientList(players) => Ok(Json(players)),
+        value => Err(unexpected(value)),
+    }
 }
 
 async fn get_detailed_player_list(
     Path(ServerAddress { address }): Path<ServerAddress>,
-    State(_state): State<Arc<AppState>>,
+    Query(freshness): Query<Freshness>,
+    State(state): State<Arc<AppState>>,
 ) -> Result<Json<samp_query::DetailedPlayerList>, ApiError> {
-    let addr: SocketAddr = address.parse()?;
-    let client = Client::connect(addr).await?;
-    let players = client.query_detailed_player_info().await?;
-    Ok(Json(players))
+    match query(&state, &address, QueryType::DetailedPlayerInfo, freshness).await? {
+        CachedValue::DetailedPlayers(players) => Ok(Json(players)),
+        value => Err(unexpected(value)),
+    }
 }
 
 async fn get_server_ping(
     Path(ServerAddress { address }): Path<ServerAddress>,
-    State(_state): State<Arc<AppState>>,
+    Query(freshness): Query<Freshness>,
+    State(state): State<Arc<AppState>>,
 ) -> Result<Json<samp_query::PingInfo>, ApiError> {
-    let addr: SocketAddr = address.parse()?;
-    let client = Client::connect(addr).await?;
-    let ping = client.query_ping().await?;
-    Ok(Json(ping))
+    match query(&state, &address, QueryType::Ping, freshness).await? {
+        CachedValue::Ping(ping) => Ok(Json(ping)),
+        value => Err(unexpected(value)),
+    }
+}
+
+async fn get_server_snapshot(
+    Path(ServerAddress { address }): Path<ServerAddress>,
+    Query(Freshness { fresh }): Query<Freshness>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<samp_query::ServerSnapshot>, ApiError> {
+    logging::record_query("Snapshot");
+    let addr = parse_server_address(&state, &address).await?;
+    let result = if fresh {
+        state.cache.fetch_snapshot(addr).await
+    } else {
+        state.cache.snapshot(addr).await
+    };
+    telemetry::record_snapshot(addr, result.as_ref());
+    Ok(Json(result?))
+}
+
+async fn get_live_updates(
+    Path(ServerAddress { address }): Path<ServerAddress>,
+    State(state): State<Arc<AppState>>,
+    upgrade: WebSocketUpgrade,
+) -> Result<Response, ApiError> {
+    let addr = parse_server_address(&state, &address).await?;
+    let hub = state.live.clone();
+    Ok(upgrade.on_upgrade(move |socket| live::serve(socket, hub, addr)))
+}
+
+async fn get_server_history(
+    Path(ServerAddress { address }): Path<ServerAddress>,
+    Query(query): Query<HistoryQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<history::HistoryResponse>, ApiError> {
+    let store = state.history.clone().ok_or(ApiError::HistoryUnavailable)?;
+    let addr = parse_server_address(&state, &address).await?;
+    Ok(Json(history::series(store, addr, query).await?))
+}
+
+async fn search_players(
+    Query(query): Query<SearchQuery>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<search::SearchResponse>, ApiError> {
+    let pattern = query.pattern()?;
+    let Some(servers) = query.servers() else {
+        let registered = state.registry.list();
+        if registered.is_empty() {
+            return Err(ApiError::InvalidParameter(
+                "servers must list the servers to search, as none are registered".to_string(),
+            ));
+        }
+        return Ok(Json(search::search_registered(&state.cache, query.name, pattern, registered).await));
+    };
+    if servers.len() > search::MAX_SERVERS {
+        return Err(ApiError::InvalidParameter(format!(
+            "At most {} servers may be searched at once",
+            search::MAX_SERVERS
+        )));
+    }
+    let mut addrs =
+        futures::future::try_join_all(servers.iter().map(|server| parse_server_address(&state, server))).await?;
+    if addrs.is_empty() {
+        return Err(ApiError::InvalidParameter("servers must list the servers to search".to_string()));
+    }
+    let mut seen = HashSet::new();
+    addrs.retain(|addr| seen.insert(*addr));
+    Ok(Json(search::search(&state.cache, query.name, pattern, addrs).await))
+}
+
+async fn list_registered(State(state): State<Arc<AppState>>) -> Json<Vec<RegisteredServer>> {
+    Json(state.registry.list())
+}
+
+async fn get_registered(
+    Path(name): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<Json<RegisteredServer>, ApiError> {
+    state.registry.get(&name).map(Json).ok_or(ApiError::NotRegistered(name))
+}
+
+/// Registrations make the API poll any address and POST to any URL, so
+/// only requests made with an API key may change them.
+fn require_key(authenticated: Option<Extension<Authenticated>>) -> Result<(), ApiError> {
+    match authenticated {
+        Some(_) => Ok(()),
+        None => Err(ApiError::Forbidden(
+            "Changing registrations requires an API key; configure keys to allow it".to_string(),
+        )),
+    }
+}
+
+async fn register_server(
+    authenticated: Option<Extension<Authenticated>>,
+    State(state): State<Arc<AppState>>,
+    Json(registration): Json<Registration>,
+) -> Result<(StatusCode, Json<RegisteredServer>), ApiError> {
+    require_key(authenticated)?;
+    let name = registration.name.clone();
+    registration.validate()?;
+    let addr = parse_server_address(&state, &registration.address).await?;
+    state.registry.insert(registration, addr, false)?;
+    state.registry.save().await?;
+    Ok((StatusCode::CREATED, get_registered(Path(name), State(state)).await?))
+}
+
+async fn update_registered(
+    authenticated: Option<Extension<Authenticated>>,
+    Path(name): Path<String>,
+    State(state): State<Arc<AppState>>,
+    Json(update): Json<registry::Update>,
+) -> Result<(StatusCode, Json<RegisteredServer>), ApiError> {
+    require_key(authenticated)?;
+    let registration = Registration {
+        name: name.clone(),
+        address: update.address,
+        interval: update.interval,
+        webhooks: update.webhooks,
+        milestones: update.milestones,
+    };
+    registration.validate()?;
+    let addr = parse_server_address(&state, &registration.address).await?;
+    let replaced = state.registry.insert(registration, addr, true)?;
+    state.registry.save().await?;
+    let status = if replaced { StatusCode::OK } else { StatusCode::CREATED };
+    Ok((status, get_registered(Path(name), State(state)).await?))
+}
+
+async fn unregister_server(
+    authenticated: Option<Extension<Authenticated>>,
+    Path(name): Path<String>,
+    State(state): State<Arc<AppState>>,
+) -> Result<StatusCode, ApiError> {
+    require_key(authenticated)?;
+    state.registry.remove(&name)?;
+    state.registry.save().await?;
+    Ok(StatusCode::NO_CONTENT)
 }
@@ -0,0 +1,144 @@
+//! Per-client request rate limiting.
+//!
+//! [`RateLimitLayer`] gives every client IP its own token bucket and answers
+//! requests over the rate with `429 Too Many Requests` and a `Retry-After`
+//! header. This protects the API host, and the game servers behind the
+//! cache, from scrapers. Clients are told apart by the peer address of the
+//! connection, so the router must be served with
+//! `into_make_service_with_connect_info::<SocketAddr>()`. Requests made
+//! with an API key are limited by [`AuthLayer`](crate::auth::AuthLayer)
+//! instead, which also counts requests it rejects for a missing or wrong
+//! key against this limit.
+
+use crate::auth::Authenticated;
+use crate::error::ApiError;
+use axum::extract::ConnectInfo;
+use axum::http::Request;
+use axum::response::{IntoResponse, Response};
+use samp_query::ratelimit::RateLimiter;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::pin::Pin;
+use std::task::{Context, Poll};
+use tower::{Layer, Service};
+
+/// Limits every client IP to a request rate; see the module documentation.
+#[derive(Debug, Clone)]
+pub struct RateLimitLayer {
+    /// `None` when rate limiting is disabled.
+    limiter: Option<RateLimiter>,
+}
+
+impl RateLimitLayer {
+    /// Allows each client `per_second` requests per second, with bursts of up
+    /// to `burst` requests. A rate of `0` disables the limit.
+    pub fn new(per_second: f64, burst: u32) -> Self {
+        let limiter =
+            (per_second > 0.0).then(|| RateLimiter::unlimited().per_destination_with_burst(per_second, burst));
+        Self { limiter }
+    }
+
+    /// Counts `request` against the limit of its client IP, returning the
+    /// `429` response to send instead once the client is over it.
+    pub fn check<B>(&self, request: &Request<B>) -> Option<Response> {
+        let (Some(limiter), Some(ConnectInfo(addr))) =
+            (&self.limiter, request.extensions().get::<ConnectInfo<SocketAddr>>())
+        else {
+            return None;
+        };
+        // Buckets are keyed by address; the port is dropped so every
+        // connection from one IP shares a bucket.
+        let retry_after = limiter.try_acquire(SocketAddr::new(addr.ip(), 0)).err()?;
+        Some(ApiError::RateLimited(retry_after).into_response())
+    }
+}
+
+impl<S> Layer<S> for RateLimitLayer {
+    type Service = RateLimit<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        RateLimit {
+            inner,
+            limit: self.clone(),
+        }
+    }
+}
+
+/// The service built by [`RateLimitLayer`].
+#[derive(Debug, Clone)]
+pub struct RateLimit<S> {
+    inner: S,
+    limit: RateLimitLayer,
+}
+
+impl<S, B> Service<Request<B>> for RateLimit<S>
+where
+    S: Service<Request<B>, Response = Response>,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<B>) -> Self::Future {
+        if request.extensions().get::<Authenticated>().is_none() {
+            if let Some(response) = self.limit.check(&request) {
+                return Box::pin(async move { Ok(response) });
+            }
+        }
+        Box::pin(self.inner.call(request))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::http::{header, StatusCode};
+    use std::convert::Infallible;
+    use tower::{service_fn, ServiceExt};
+
+    async fn call(layer: &RateLimitLayer, ip: Option<[u8; 4]>, authenticated: bool) -> Response {
+        let service = layer.layer(service_fn(|_: Request<()>| async { Ok::<_, Infallible>(Response::default()) }));
+        let mut request = Request::new(());
+        if let Some(ip) = ip {
+            request.extensions_mut().insert(ConnectInfo(SocketAddr::from((ip, 50000))));
+        }
+        if authenticated {
+            request.extensions_mut().insert(Authenticated);
+        }
+        service.oneshot(request).await.unwrap()
+    }
+
+    #[tokio::test]
+    async fn limits_each_client_ip() {
+        let layer = RateLimitLayer::new(1.0, 1);
+        assert_eq!(call(&layer, Some([192, 0, 2, 1]), false).await.status(), StatusCode::OK);
+
+        let response = call(&layer, Some([192, 0, 2, 1]), false).await;
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(response.headers()[header::RETRY_AFTER], "1");
+
+        assert_eq!(call(&layer, Some([192, 0, 2, 2]), false).await.status(), StatusCode::OK);
+    }
+
+    #[tokio::test]
+    async fn skips_authenticated_requests() {
+        let layer = RateLimitLayer::new(1.0, 1);
+        for _ in 0..3 {
+            assert_eq!(call(&layer, Some([192, 0, 2, 1]), true).await.status(), StatusCode::OK);
+            assert_eq!(call(&layer, None, false).await.status(), StatusCode::OK);
+        }
+    }
+
+    #[tokio::test]
+    async fn zero_rate_disables_the_limit() {
+        let layer = RateLimitLayer::new(0.0, 1);
+        for _ in 0..3 {
+            assert_eq!(call(&layer, Some([192, 0, 2, 1]), false).await.status(), StatusCode::OK);
+        }
+    }
+}
@@ -0,0 +1,301 @@
+//! Registered servers.
+//!
+//! Servers registered under a name through `/api/v1/registered` are polled
+//! in the background, each on its own interval, and their latest snapshot
+//! is answered without querying the server. Every poll takes one
+//! concurrent snapshot, which also refreshes the cache the other endpoints
+//! read, so `/info`, `/full` and the rest are usually cache hits for these
+//! servers. Servers that keep failing are polled less often, as by the
+//! library's [`Scheduler`](samp_query::scheduler::Scheduler).
+//!
+//! Changes seen by the polls are sent to webhooks; see
+//! [`webhooks`](crate::webhooks).
+//!
+//! Registrations are seeded from the `servers` setting, named after their
+//! address, and from the `registry_file` if one is configured, which is
+//! rewritten after every change so they survive a restart.
+
+use crate::error::ApiError;
+use crate::telemetry;
+use crate::webhooks::{self, Notifier, Previous};
+use samp_query::cache::QueryCache;
+use samp_query::scheduler::ScheduleConfig;
+use samp_query::utils::unix_timestamp_ms;
+use samp_query::ServerSnapshot;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::AbortHandle;
+use tracing::warn;
+
+/// Most servers that may be registered at once.
+pub const MAX_REGISTERED: usize = 1000;
+
+/// Longest name a server may be registered under.
+const MAX_NAME_LEN: usize = 64;
+
+/// A server to poll, as registered by a client.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Registration {
+    pub name: String,
+    /// The address as given, `host[:port]`.
+    pub address: String,
+    /// Seconds between polls, instead of the `poll_interval` setting.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub interval: Option<u64>,
+    /// URLs the changes of the server are POSTed to.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub webhooks: Vec<String>,
+    /// Player counts whose reaching is sent to the webhooks.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub milestones: Vec<u16>,
+}
+
+impl Registration {
+    /// Checks that the name can be used in a URL path unescaped.
+    pub fn validate(&self) -> Result<(), ApiError> {
+        let valid = !self.name.is_empty()
+            && self.name.len() <= MAX_NAME_LEN
+            && self
+                .name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.' | ':'));
+        if !valid {
+            return Err(ApiError::InvalidParameter(format!(
+                "name must be 1 to {} letters, digits, '-', '_', '.' or ':', got {:?}",
+                MAX_NAME_LEN, self.name
+            )));
+        }
+        if self.interval == Some(0) {
+            return Err(ApiError::InvalidParameter("interval must be at least 1 second".to_string()));
+        }
+        for url in &self.webhooks {
+            webhooks::validate_url(url).map_err(ApiError::InvalidParameter)?;
+        }
+        Ok(())
+    }
+}
+
+/// The body of `PUT /api/v1/registered/:name`, which takes the name from
+/// the path.
+#[derive(Debug, Deserialize)]
+pub struct Update {
+    pub address: String,
+    #[serde(default)]
+    pub interval: Option<u64>,
+    #[serde(default)]
+    pub webhooks: Vec<String>,
+    #[serde(default)]
+    pub milestones: Vec<u16>,
+}
+
+/// A registered server and what its last poll returned.
+#[derive(Debug, Clone, Serialize)]
+pub struct RegisteredServer {
+    #[serde(flatten)]
+    pub registration: Registration,
+    /// The address the server was resolved to.
+    pub resolved: SocketAddr,
+    /// Whether the last poll succeeded; `None` before the first poll.
+    pub online: Option<bool>,
+    /// Unix timestamp in milliseconds of the last poll.
+    pub polled_at: Option<u64>,
+    /// Why the last poll failed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+    /// The last successful snapshot, kept while the server is offline.
+    pub snapshot: Option<ServerSnapshot>,
+}
+
+struct Entry {
+    server: RegisteredServer,
+    /// Tells the poll task of this registration apart from one that replaced it.
+    id: u64,
+    task: AbortHandle,
+}
+
+#[derive(Default)]
+struct Inner {
+    entries: BTreeMap<String, Entry>,
+    next_id: u64,
+}
+
+/// The registered servers and their poll tasks.
+#[derive(Clone)]
+pub struct Registry {
+    cache: QueryCache,
+    schedule: ScheduleConfig,
+    interval: Duration,
+    notifier: Notifier,
+    file: Option<PathBuf>,
+    /// Held while the registry file is written.
+    saving: Arc<tokio::sync::Mutex<()>>,
+    inner: Arc<Mutex<Inner>>,
+}
+
+impl Registry {
+    /// An empty registry polling every `interval` by default, through
+    /// `cache`, sending changes through `notifier` and saving its
+    /// registrations to `file`.
+    pub fn new(cache: QueryCache, interval: Duration, notifier: Notifier, file: Option<PathBuf>) -> Self {
+        Self {
+            cache,
+            schedule: ScheduleConfig::default(),
+            interval,
+            notifier,
+            file,
+            saving: Arc::default(),
+            inner: Arc::new(Mutex::new(Inner::default())),
+        }
+    }
+
+    /// The registrations saved in the registry file, if there is one.
+    pub fn load(&self) -> std::io::Result<Vec<Registration>> {
+        let Some(file) = &self.file else {
+            return Ok(Vec::new());
+        };
+        match std::fs::read(file) {
+            Ok(data) => serde_json::from_slice(&data).map_err(std::io::Error::from),
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(Vec::new()),
+            Err(e) => Err(e),
+        }
+    }
+
+    /// Every registered server, by name.
+    pub fn list(&self) -> Vec<RegisteredServer> {
+        let inner = self.inner.lock().unwrap();
+        inner.entries.values().map(|entry| entry.server.clone()).collect()
+    }
+
+    pub fn get(&self, name: &str) -> Option<RegisteredServer> {
+        let inner = self.inner.lock().unwrap();
+        inner.entries.get(name).map(|entry| entry.server.clone())
+    }
+
+    /// Registers a server resolved to `addr` and starts polling it.
+    /// An existing registration of the name is replaced only if `replace`
+    /// is set; returns whether one was.
+    pub fn insert(&self, registration: Registration, addr: SocketAddr, replace: bool) -> Result<bool, ApiError> {
+        let mut inner = self.inner.lock().unwrap();
+        let existing = inner.entries.contains_key(&registration.name);
+        if existing && !replace {
+            return Err(ApiError::Conflict(format!(
+                "A server is already registered as {}",
+                registration.name
+            )));
+        }
+        if !existing && inner.entries.len() >= MAX_REGISTERED {
+            return Err(ApiError::InvalidParameter(format!(
+                "At most {} servers may be registered",
+                MAX_REGISTERED
+            )));
+        }
+
+        let id = inner.next_id;
+        inner.next_id += 1;
+        let interval = registration.interval.map_or(self.interval, Duration::from_secs);
+        let task = tokio::spawn(self.clone().poll(registration.name.clone(), id, addr, interval)).abort_handle();
+        let entry = Entry {
+            server: RegisteredServer {
+                registration,
+                resolved: addr,
+                online: None,
+                polled_at: None,
+                error: None,
+                snapshot: None,
+            },
+            id,
+            task,
+        };
+        if let Some(old) = inner.entries.insert(entry.server.registration.name.clone(), entry) {
+            old.task.abort();
+        }
+        Ok(existing)
+    }
+
+    /// Unregisters a server and stops polling it.
+    pub fn remove(&self, name: &str) -> Result<(), ApiError> {
+        let mut inner = self.inner.lock().unwrap();
+        let entry = inner
+            .entries
+            .remove(name)
+            .ok_or_else(|| ApiError::NotRegistered(name.to_string()))?;
+        entry.task.abort();
+        Ok(())
+    }
+
+    /// Writes the registrations to the registry file, if there is one.
+    pub async fn save(&self) -> Result<(), ApiError> {
+        let Some(file) = &self.file else {
+            return Ok(());
+        };
+        let _saving = self.saving.lock().await;
+        let registrations: Vec<Registration> = {
+            let inner = self.inner.lock().unwrap();
+            inner.entries.values().map(|entry| entry.server.registration.clone()).collect()
+        };
+        let data = serde_json::to_vec_pretty(&registrations).map_err(|e| ApiError::Internal(e.to_string()))?;
+
+        // Written next to the file and renamed, so a crash never leaves it
+        // half written.
+        let temp = file.with_extension("tmp");
+        let result = match tokio::fs::write(&temp, data).await {
+            Ok(()) => tokio::fs::rename(&temp, file).await,
+            Err(e) => Err(e),
+        };
+        result.map_err(|e| ApiError::Internal(format!("Failed to save {}: {}", file.display(), e)))
+    }
+
+    /// Polls `addr` until the registration `id` is removed or replaced.
+    async fn poll(self, name: String, id: u64, addr: SocketAddr, interval: Duration) {
+        let mut failures = 0;
+        loop {
+            let result = match self.cache.fetch_snapshot(addr).await {
+                // The server was just queried for another request; poll it
+                // once the limiter allows.
+                Err(samp_query::Error::RateLimited(retry_after)) => {
+                    tokio::time::sleep(retry_after).await;
+                    continue;
+                }
+                result => result,
+            };
+            telemetry::record_snapshot(addr, result.as_ref());
+            failures = if result.is_ok() { 0 } else { failures + 1 };
+
+            {
+                let mut inner = self.inner.lock().unwrap();
+                let Some(entry) = inner.entries.get_mut(&name).filter(|entry| entry.id == id) else {
+                    return;
+                };
+                let server = &mut entry.server;
+                let previous = Previous {
+                    online: server.online,
+                    snapshot: server.snapshot.as_ref(),
+                };
+                let changes = webhooks::changes(previous, result.as_ref(), &server.registration.milestones);
+                self.notifier.notify(&name, addr, &server.registration.webhooks, changes);
+
+                server.polled_at = Some(unix_timestamp_ms());
+                match result {
+                    Ok(snapshot) => {
+                        server.online = Some(true);
+                        server.error = None;
+                        server.snapshot = Some(snapshot);
+                    }
+                    Err(e) => {
+                        if server.online != Some(false) {
+                            warn!("Registered server {} ({}) is not answering: {}", name, addr, e);
+                        }
+                        server.online = Some(false);
+                        server.error = Some(e.to_string());
+                    }
+                }
+            }
+
+            tokio::time::sleep(self.schedule.next_delay(interval, failures)).await;
+        }
+    }
+}
@@ -0,0 +1,214 @@
+//! Cross-server player search.
+//!
+//! `GET /api/v1/players/search?name=Nick` queries the detailed player list
+//! of every server named in `servers`, separated by commas, or of the
+//! servers registered in the `servers` setting, and reports where a
+//! matching nickname is online:
+//!
+//! ```text
+//! GET /api/v1/players/search?name=Sweet&servers=127.0.0.1:7777,play.example.com
+//! {"name":"Sweet","searched":2,"unreachable":[],"matches":[{"address":"127.0.0.1:7777","id":0,"name":"Sweet","score":10,"ping":12}]}
+//! ```
+//!
+//! Player lists are read through the cache, so repeated searches do not
+//! query the servers again until the lists expire. Registered servers are
+//! searched in the snapshot of their latest poll instead, so a search of
+//! every registered server queries none that are offline.
+
+use crate::error::ApiError;
+use crate::registry::RegisteredServer;
+use crate::telemetry;
+use futures::stream::{self, StreamExt};
+use samp_query::cache::{CachedValue, QueryCache};
+use samp_query::finder::NamePattern;
+use samp_query::{DetailedPlayer, QueryType};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::net::SocketAddr;
+
+/// Most servers a single search may query.
+pub const MAX_SERVERS: usize = 100;
+
+/// Servers whose player lists are fetched at the same time.
+const CONCURRENCY: usize = 32;
+
+/// How `name` is compared with the nicknames online; always ignoring case.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchMode {
+    #[default]
+    Exact,
+    Contains,
+    Regex,
+}
+
+/// The query string of a search request.
+#[derive(Debug, Deserialize)]
+pub struct SearchQuery {
+    pub name: String,
+    #[serde(default, rename = "match")]
+    pub mode: MatchMode,
+    /// Servers to search, separated by commas, instead of the registered ones.
+    pub servers: Option<String>,
+}
+
+impl SearchQuery {
+    pub fn pattern(&self) -> Result<NamePattern, ApiError> {
+        if self.name.is_empty() {
+            return Err(ApiError::InvalidParameter("name must not be empty".to_string()));
+        }
+        Ok(match self.mode {
+            MatchMode::Exact => NamePattern::Exact(self.name.clone()),
+            MatchMode::Contains => NamePattern::Contains(self.name.clone()),
+            MatchMode::Regex => {
+                NamePattern::regex(&self.name).map_err(|e| ApiError::InvalidParameter(e.to_string()))?
+            }
+        })
+    }
+
+    /// The server addresses listed in `servers`, if given.
+    pub fn servers(&self) -> Option<Vec<&str>> {
+        self.servers.as_deref().map(|servers| {
+            servers
+                .split(',')
+                .map(str::trim)
+                .filter(|server| !server.is_empty())
+                .collect()
+        })
+    }
+}
+
+/// A player online on a searched server.
+#[derive(Debug, Serialize)]
+pub struct Match {
+    pub address: SocketAddr,
+    pub id: u8,
+    pub name: String,
+    pub score: i32,
+    pub ping: u32,
+}
+
+#[derive(Debug, Serialize)]
+pub struct SearchResponse {
+    pub name: String,
+    /// Number of servers searched.
+    pub searched: usize,
+    /// Servers that did not answer.
+    pub unreachable: Vec<SocketAddr>,
+    /// Matching players, in the order the servers were given.
+    pub matches: Vec<Match>,
+}
+
+/// Searches `servers` for players matching `pattern`.
+pub async fn search(cache: &QueryCache, name: String, pattern: NamePattern, servers: Vec<SocketAddr>) -> SearchResponse {
+    let results = stream::iter(servers)
+        .map(|addr| async move { (addr, players(cache, addr).await.ok()) })
+        .buffered(CONCURRENCY)
+        .collect()
+        .await;
+    respond(name, &pattern, results)
+}
+
+/// Searches the registered `servers` in the snapshot of their latest poll.
+/// Servers that were offline or not polled yet are reported unreachable
+/// without a query; only those online without a detailed player list are
+/// queried, for their client list.
+pub async fn search_registered(
+    cache: &QueryCache,
+    name: String,
+    pattern: NamePattern,
+    servers: Vec<RegisteredServer>,
+) -> SearchResponse {
+    let mut seen = HashSet::new();
+    let results = stream::iter(servers.into_iter().filter(|server| seen.insert(server.resolved)))
+        .map(|server| async move {
+            let addr = server.resolved;
+            let players = match (server.online, server.snapshot.and_then(|snapshot| snapshot.players)) {
+                (Some(true), Some(list)) => Some(list.players),
+                (Some(true), None) => client_list(cache, addr).await.ok(),
+                _ => None,
+            };
+            (addr, players)
+        })
+        .buffered(CONCURRENCY)
+        .collect()
+        .await;
+    respond(name, &pattern, results)
+}
+
+/// The matches among the players of every searched server, or `None` for
+/// the servers that did not answer.
+fn respond(
+    name: String,
+    pattern: &NamePattern,
+    results: Vec<(SocketAddr, Option<Vec<DetailedPlayer>>)>,
+) -> SearchResponse {
+    let mut response = SearchResponse {
+        name,
+        searched: results.len(),
+        unreachable: Vec::new(),
+        matches: Vec::new(),
+    };
+    for (addr, players) in results {
+        match players {
+            Some(players) => response.matches.extend(
+                players
+                    .into_iter()
+                    .filter(|player| pattern.matches(&player.name))
+                    .map(|player| Match {
+                        address: addr,
+                        id: player.id,
+                        name: player.name,
+                        score: player.score,
+                        ping: player.ping,
+                    }),
+            ),
+            None => response.unreachable.push(addr),
+        }
+    }
+    response
+}
+
+/// The players on `addr`. When the server refuses the detailed player
+/// query, its client list is used instead; a server that does not answer
+/// at all is not queried again.
+async fn players(cache: &QueryCache, addr: SocketAddr) -> samp_query::Result<Vec<DetailedPlayer>> {
+    match cache.get(addr, QueryType::DetailedPlayerInfo).await {
+        Err(e) if !e.is_timeout() && !matches!(e, samp_query::Error::RateLimited(_)) => {
+            client_list(cache, addr).await
+        }
+        result => {
+            telemetry::record_query(addr, result.as_ref());
+            to_players(result?)
+        }
+    }
+}
+
+/// The client list of `addr`, with an id and ping of zero.
+async fn client_list(cache: &QueryCache, addr: SocketAddr) -> samp_query::Result<Vec<DetailedPlayer>> {
+    let result = cache.get(addr, QueryType::ClientList).await;
+    telemetry::record_query(addr, result.as_ref());
+    to_players(result?)
+}
+
+fn to_players(value: CachedValue) -> samp_query::Result<Vec<DetailedPlayer>> {
+    Ok(match value {
+        CachedValue::DetailedPlayers(list) => list.players,
+        CachedValue::ClientList(list) => list
+            .players
+            .into_iter()
+            .map(|player| DetailedPlayer {
+                id: 0,
+                name: player.name,
+                score: player.score,
+                ping: 0,
+            })
+            .collect(),
+        value => {
+            return Err(samp_query::Error::Other(format!(
+                "Unexpected cached response: {:?}",
+                value
+            )))
+        }
+    })
+}
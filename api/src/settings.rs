@@ -0,0 +1,284 @@
+//! API configuration.
+//!
+//! Settings are read from a TOML file and from `SAMP_API_*` environment
+//! variables, which take precedence and may also be placed in a `.env` file
+//! next to the service. The file is `samp-query-api.toml` in the working
+//! directory if it exists, or the file named by `SAMP_API_CONFIG`:
+//!
+//! ```toml
+//! bind = "0.0.0.0"
+//! port = 3001
+//! timeout_ms = 1000
+//! max_retries = 3
+//! info_ttl = 5
+//! rules_ttl = 60
+//! players_ttl = 3
+//! ping_ttl = 5
+//! rate_limit = 10
+//! rate_burst = 20
+//! live_interval = 5
+//! max_concurrent_requests = 256
+//! max_queued_requests = 512
+//! queue_timeout_ms = 2000
+//! keys = ["dashboard-key:50", "ops-key"]
+//! history_db = "/var/lib/samp-query/history.db"
+//! servers = ["127.0.0.1:7777", "play.example.com"]
+//! poll_interval = 30
+//! registry_file = "/var/lib/samp-query/registered.json"
+//! webhooks = ["https://hooks.example.com/samp"]
+//! ```
+//!
+//! Each setting has a variable named after it, e.g. `SAMP_API_PORT=8080`;
+//! `SAMP_API_KEYS`, `SAMP_API_SERVERS` and `SAMP_API_WEBHOOKS` take lists
+//! separated by commas.
+//!
+//! TTLs and intervals are in seconds. A rate limit of `0` lets clients send
+//! requests as fast as they like, and a `max_concurrent_requests` of `0`
+//! handles any number of requests at once. Keys may be followed by `:` and their own
+//! rate limit.
+
+use samp_query::cache::CacheConfig;
+use samp_query::protocol::constants;
+use samp_query::ClientConfig;
+use serde::de::{self, Deserializer};
+use serde::Deserialize;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::path::PathBuf;
+use std::time::Duration;
+
+/// Config file read when `SAMP_API_CONFIG` is not set and the file exists.
+pub const DEFAULT_CONFIG_FILE: &str = "samp-query-api.toml";
+
+/// Environment variable naming the config file.
+pub const CONFIG_ENV: &str = "SAMP_API_CONFIG";
+
+/// A key accepted in the `Authorization` header.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+#[serde(try_from = "String")]
+pub struct ApiKey {
+    pub key: String,
+    /// Requests per second allowed with this key, instead of `rate_limit`.
+    pub rate_limit: Option<f64>,
+}
+
+impl TryFrom<String> for ApiKey {
+    type Error = String;
+
+    /// Parses `key` or `key:rate`. A key may itself contain `:`; only a
+    /// suffix that parses as a rate is taken for one.
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        let rate = value
+            .rsplit_once(':')
+            .and_then(|(key, rate)| Some((key, rate.trim().parse::<f64>().ok().filter(|rate| rate.is_finite())?)));
+        let (key, rate_limit) = match rate {
+            Some((key, rate)) => (key, Some(rate)),
+            None => (value.as_str(), None),
+        };
+        let key = key.trim();
+        if key.is_empty() {
+            return Err("API keys must not be empty".to_string());
+        }
+        Ok(Self {
+            key: key.to_string(),
+            rate_limit,
+        })
+    }
+}
+
+/// Reads a list, or a comma-separated string as environment variables give
+/// lists.
+fn comma_list<'de, D, T>(deserializer: D) -> Result<Vec<T>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de> + TryFrom<String>,
+    T::Error: std::fmt::Display,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum List<T> {
+        Items(Vec<T>),
+        Text(String),
+    }
+
+    match List::deserialize(deserializer)? {
+        List::Items(items) => Ok(items),
+        List::Text(text) => text
+            .split(',')
+            .filter(|item| !item.trim().is_empty())
+            .map(|item| T::try_from(item.trim().to_string()).map_err(de::Error::custom))
+            .collect(),
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    /// Address the API listens on.
+    pub bind: IpAddr,
+    pub port: u16,
+    /// Timeout of each query to a game server, in milliseconds.
+    pub timeout_ms: u64,
+    /// Attempts per query before a server counts as not answering.
+    pub max_retries: usize,
+    /// How long `/info` responses are served from the cache.
+    pub info_ttl: u64,
+    /// How long `/rules` responses are served from the cache.
+    pub rules_ttl: u64,
+    /// How long `/players` and `/players/detailed` responses are served from
+    /// the cache.
+    pub players_ttl: u64,
+    /// How long `/ping` responses are served from the cache.
+    pub ping_ttl: u64,
+    /// Requests per second each client IP, or API key without a rate of its
+    /// own, may send.
+    pub rate_limit: f64,
+    /// Requests a client may send at once after being idle.
+    pub rate_burst: u32,
+    /// Keys required on every request; authentication is off when empty.
+    #[serde(deserialize_with = "comma_list")]
+    pub keys: Vec<ApiKey>,
+    /// Seconds between the polls of servers watched over `/live`.
+    pub live_interval: u64,
+    /// Requests handled at the same time.
+    pub max_concurrent_requests: usize,
+    /// Requests waiting for a slot beyond which new ones are rejected.
+    pub max_queued_requests: usize,
+    /// How long a request waits for a slot, in milliseconds.
+    pub queue_timeout_ms: u64,
+    /// Database written by `samp-query-cli history record` or `daemon`,
+    /// served by `/history`; the endpoint is off when unset.
+    pub history_db: Option<PathBuf>,
+    /// Servers registered at startup, named after their address.
+    #[serde(deserialize_with = "comma_list")]
+    pub servers: Vec<String>,
+    /// Default seconds between the polls of registered servers.
+    pub poll_interval: u64,
+    /// JSON file the registered servers are kept in across restarts.
+    pub registry_file: Option<PathBuf>,
+    /// URLs the changes of every registered server are POSTed to.
+    #[serde(deserialize_with = "comma_list")]
+    pub webhooks: Vec<String>,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        let cache = CacheConfig::default();
+        Self {
+            bind: IpAddr::V4(Ipv4Addr::LOCALHOST),
+            port: 3001,
+            timeout_ms: constants::DEFAULT_TIMEOUT_MS,
+            max_retries: constants::MAX_RETRIES,
+            info_ttl: cache.info_ttl.as_secs(),
+            rules_ttl: cache.rules_ttl.as_secs(),
+            players_ttl: cache.players_ttl.as_secs(),
+            ping_ttl: cache.ping_ttl.as_secs(),
+            rate_limit: 10.0,
+            rate_burst: 20,
+            keys: Vec::new(),
+            live_interval: 5,
+            max_concurrent_requests: 256,
+            max_queued_requests: 512,
+            queue_timeout_ms: 2000,
+            history_db: None,
+            servers: Vec::new(),
+            poll_interval: 30,
+            registry_file: None,
+            webhooks: Vec::new(),
+        }
+    }
+}
+
+impl Settings {
+    /// Reads the settings from the config file, the environment and `.env`.
+    pub fn load() -> Result<Self, config::ConfigError> {
+        dotenv::dotenv().ok();
+        let file = match std::env::var(CONFIG_ENV) {
+            Ok(path) => config::File::new(&path, config::FileFormat::Toml),
+            Err(_) => config::File::new(DEFAULT_CONFIG_FILE, config::FileFormat::Toml).required(false),
+        };
+        config::Config::builder()
+            .add_source(file)
+            .add_source(config::Environment::with_prefix("SAMP_API"))
+            .build()?
+            .try_deserialize()
+    }
+
+    /// The address to listen on.
+    pub fn listen_addr(&self) -> SocketAddr {
+        SocketAddr::new(self.bind, self.port)
+    }
+
+    /// Time between the polls of servers watched over `/live`, at least a
+    /// second.
+    pub fn live_interval(&self) -> Duration {
+        Duration::from_secs(self.live_interval.max(1))
+    }
+
+    /// Default time between the polls of registered servers, at least a
+    /// second.
+    pub fn poll_interval(&self) -> Duration {
+        Duration::from_secs(self.poll_interval.max(1))
+    }
+
+    /// How long a request waits for a slot before it is rejected.
+    pub fn queue_timeout(&self) -> Duration {
+        Duration::from_millis(self.queue_timeout_ms)
+    }
+
+    /// The cache configuration for these TTLs and query settings, with the
+    /// library defaults for everything else.
+    pub fn cache_config(&self) -> CacheConfig {
+        CacheConfig {
+            client: ClientConfig {
+                timeout_ms: self.timeout_ms,
+                max_retries: self.max_retries,
+                retry: None,
+            },
+            info_ttl: Duration::from_secs(self.info_ttl),
+            rules_ttl: Duration::from_secs(self.rules_ttl),
+            players_ttl: Duration::from_secs(self.players_ttl),
+            ping_ttl: Duration::from_secs(self.ping_ttl),
+            ..CacheConfig::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn key(value: &str) -> Result<ApiKey, String> {
+        ApiKey::try_from(value.to_string())
+    }
+
+    #[test]
+    fn api_key_with_rate() {
+        assert_eq!(
+            key(" dashboard : 2.5").unwrap(),
+            ApiKey {
+                key: "dashboard".to_string(),
+                rate_limit: Some(2.5),
+            }
+        );
+        assert_eq!(key("secret").unwrap().rate_limit, None);
+    }
+
+    #[test]
+    fn api_key_containing_colons() {
+        let parsed = key("user:pass").unwrap();
+        assert_eq!(parsed.key, "user:pass");
+        assert_eq!(parsed.rate_limit, None);
+
+        let parsed = key("user:pass:10").unwrap();
+        assert_eq!(parsed.key, "user:pass");
+        assert_eq!(parsed.rate_limit, Some(10.0));
+
+        assert_eq!(key("key:inf").unwrap().key, "key:inf");
+    }
+
+    #[test]
+    fn empty_api_key() {
+        assert!(key("").is_err());
+        assert!(key(" :5").is_err());
+    }
+}
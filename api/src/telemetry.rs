@@ -0,0 +1,225 @@
+//! The `/metrics` endpoint.
+//!
+//! Metrics are recorded with the `metrics` crate and rendered in the
+//! Prometheus text format, so the API can be scraped like the standalone
+//! exporter:
+//!
+//! - `samp_up`, `samp_players`, `samp_max_players` and `samp_ping_ms`, per
+//!   server, as of the last answer the API gave about it. A server nobody
+//!   asked about for [`SERVER_IDLE_TIMEOUT`] is dropped.
+//! - `samp_api_request_duration_seconds`, a histogram of the handled
+//!   requests by method, route and status.
+//! - `process_*` metrics of the API itself, on Linux.
+
+use axum::extract::MatchedPath;
+use axum::http::Request;
+use axum::middleware::Next;
+use axum::response::Response;
+use metrics_exporter_prometheus::{BuildError, Matcher, PrometheusBuilder, PrometheusHandle};
+use metrics_util::MetricKindMask;
+use samp_query::cache::CachedValue;
+use samp_query::{ServerInfo, ServerSnapshot};
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// How long the gauges of a server are kept after the last request about it.
+pub const SERVER_IDLE_TIMEOUT: Duration = Duration::from_secs(600);
+
+const REQUEST_DURATION: &str = "samp_api_request_duration_seconds";
+
+/// Upper bounds in seconds of the request duration buckets.
+const REQUEST_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0];
+
+/// Installs the global recorder and returns the handle `/metrics` renders.
+pub fn install() -> Result<PrometheusHandle, BuildError> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full(REQUEST_DURATION.to_string()), REQUEST_BUCKETS)?
+        .idle_timeout(MetricKindMask::GAUGE, Some(SERVER_IDLE_TIMEOUT))
+        .install_recorder()?;
+
+    metrics::describe_gauge!("samp_up", "Whether the server answered the last query.");
+    metrics::describe_gauge!("samp_players", "Current number of players online.");
+    metrics::describe_gauge!("samp_max_players", "Maximum number of players.");
+    metrics::describe_gauge!("samp_ping_ms", "Ping of the last ping query in milliseconds.");
+    metrics::describe_histogram!(REQUEST_DURATION, metrics::Unit::Seconds, "Duration of API requests.");
+    Ok(handle)
+}
+
+/// Updates the gauges of `addr` with the answer to a request.
+pub fn record_query(addr: SocketAddr, result: Result<&CachedValue, &samp_query::Error>) {
+    let address = addr.to_string();
+    match result {
+        Ok(CachedValue::Info(info)) => record_info(address, info),
+        Ok(CachedValue::Ping(ping)) => record_ping(address, ping.ping_ms),
+        Ok(_) => metrics::gauge!("samp_up", 1.0, "address" => address),
+        // The server was not queried.
+        Err(samp_query::Error::RateLimited(_)) => {}
+        Err(_) => record_down(address),
+    }
+}
+
+/// Updates the gauges of `addr` with the answer to a `/full` request.
+pub fn record_snapshot(addr: SocketAddr, result: Result<&ServerSnapshot, &samp_query::Error>) {
+    let address = addr.to_string();
+    match result {
+        Ok(snapshot) => {
+            if let Some(ping) = &snapshot.ping {
+                record_ping(address.clone(), ping.ping_ms);
+            }
+            record_info(address, &snapshot.info);
+        }
+        Err(samp_query::Error::RateLimited(_)) => {}
+        Err(_) => record_down(address),
+    }
+}
+
+fn record_info(address: String, info: &ServerInfo) {
+    metrics::gauge!("samp_up", 1.0, "address" => address.clone());
+    metrics::gauge!("samp_players", info.players as f64, "address" => address.clone());
+    metrics::gauge!("samp_max_players", info.max_players as f64, "address" => address);
+}
+
+fn record_ping(address: String, ping_ms: u64) {
+    metrics::gauge!("samp_up", 1.0, "address" => address.clone());
+    metrics::gauge!("samp_ping_ms", ping_ms as f64, "address" => address);
+}
+
+fn record_down(address: String) {
+    metrics::gauge!("samp_up", 0.0, "address" => address.clone());
+    metrics::gauge!("samp_players", 0.0, "address" => address);
+}
+
+/// Middleware recording the duration of every routed request.
+pub async fn track_requests<B>(request: Request<B>, next: Next<B>) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map_or_else(|| request.uri().path().to_string(), |path| path.as_str().to_string());
+    let method = request.method().to_string();
+
+    let started = Instant::now();
+    let response = next.run(request).await;
+    metrics::histogram!(
+        REQUEST_DURATION,
+        started.elapsed().as_secs_f64(),
+        "method" => method,
+        "route" => route,
+        "status" => response.status().as_u16().to_string()
+    );
+    response
+}
+
+/// Everything recorded so far, followed by the process metrics.
+pub fn render(handle: &PrometheusHandle) -> String {
+    let mut output = handle.render();
+    render_process(&mut output);
+    output
+}
+
+/// Appends the standard `process_*` metrics read from `/proc`. Metrics that
+/// cannot be read, e.g. on other systems, are left out.
+fn render_process(output: &mut String) {
+    let mut metric = |name: &str, kind: &str, help: &str, value: Option<f64>| {
+        if let Some(value) = value {
+            let _ = writeln!(output, "# HELP {} {}", name, help);
+            let _ = writeln!(output, "# TYPE {} {}", name, kind);
+            let _ = writeln!(output, "{} {}", name, value);
+        }
+    };
+
+    // Fields of /proc/self/stat after the parenthesized command name, which
+    // may itself contain spaces.
+    let stat = std::fs::read_to_string("/proc/self/stat").ok();
+    let fields: Vec<&str> = stat
+        .as_deref()
+        .and_then(|stat| stat.rsplit_once(") "))
+        .map(|(_, rest)| rest.split_whitespace().collect())
+        .unwrap_or_default();
+    let field = |index: usize| fields.get(index).and_then(|value| value.parse::<f64>().ok());
+    let status = std::fs::read_to_string("/proc/self/status").unwrap_or_default();
+    let status_kb = |key: &str| {
+        status
+            .lines()
+            .find_map(|line| line.strip_prefix(key))
+            .and_then(|value| value.trim().trim_end_matches("kB").trim().parse::<f64>().ok())
+    };
+    let ticks = ticks_per_second();
+    let boot_time = std::fs::read_to_string("/proc/stat").ok().and_then(|stat| {
+        stat.lines()
+            .find_map(|line| line.strip_prefix("btime "))
+            .and_then(|value| value.trim().parse::<f64>().ok())
+    });
+
+    metric(
+        "process_cpu_seconds_total",
+        "counter",
+        "Total user and system CPU time spent in seconds.",
+        field(11).zip(field(12)).zip(ticks).map(|((user, system), ticks)| (user + system) / ticks),
+    );
+    metric(
+        "process_resident_memory_bytes",
+        "gauge",
+        "Resident memory size in bytes.",
+        status_kb("VmRSS:").map(|kb| kb * 1024.0),
+    );
+    metric(
+        "process_virtual_memory_bytes",
+        "gauge",
+        "Virtual memory size in bytes.",
+        status_kb("VmSize:").map(|kb| kb * 1024.0),
+    );
+    metric(
+        "process_threads",
+        "gauge",
+        "Number of OS threads in the process.",
+        field(17),
+    );
+    metric(
+        "process_open_fds",
+        "gauge",
+        "Number of open file descriptors.",
+        std::fs::read_dir("/proc/self/fd").ok().map(|entries| entries.count() as f64),
+    );
+    metric(
+        "process_start_time_seconds",
+        "gauge",
+        "Start time of the process since the Unix epoch in seconds.",
+        boot_time.zip(field(19)).zip(ticks).map(|((boot, start), ticks)| boot + start / ticks),
+    );
+}
+
+/// The clock ticks per second `/proc` reports CPU times in, which depends
+/// on the kernel.
+#[cfg(unix)]
+fn ticks_per_second() -> Option<f64> {
+    // SAFETY: `sysconf` only reads a configuration value.
+    let ticks = unsafe { libc::sysconf(libc::_SC_CLK_TCK) };
+    (ticks > 0).then_some(ticks as f64)
+}
+
+#[cfg(not(unix))]
+fn ticks_per_second() -> Option<f64> {
+    None
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn process_start_time_is_recent() {
+        let mut output = String::new();
+        render_process(&mut output);
+        let start = output
+            .lines()
+            .find_map(|line| line.strip_prefix("process_start_time_seconds "))
+            .and_then(|value| value.parse::<f64>().ok())
+            .unwrap();
+        let now = std::time::SystemTime::now()
+            .duration_since(std::time::UNIX_EPOCH)
+            .unwrap()
+            .as_secs_f64();
+        assert!(start <= now + 1.0 && start > now - 3600.0, "started at {}, now {}", start, now);
+    }
+}
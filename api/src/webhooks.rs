@@ -0,0 +1,281 @@
+//! Webhook notifications about registered servers.
+//!
+//! After every poll of a registered server, [`changes`] compares it with the
+//! previous one, and each change is POSTed as JSON to the webhooks of the
+//! registration and to those of the `webhooks` setting:
+//!
+//! ```text
+//! {"name":"lv","address":"203.0.113.7:7777","timestamp":...,"kind":{"type":"offline","reason":"Connection timed out"}}
+//! {"name":"lv","address":"203.0.113.7:7777","timestamp":...,"kind":{"type":"online"}}
+//! {"name":"lv","address":"203.0.113.7:7777","timestamp":...,"kind":{"type":"hostname_changed","old":"...","new":"..."}}
+//! {"name":"lv","address":"203.0.113.7:7777","timestamp":...,"kind":{"type":"player_milestone","milestone":100,"players":102}}
+//! ```
+//!
+//! The first poll after a server is registered only sets the baseline.
+//! Every webhook has one delivery queue, so it receives changes in the
+//! order the polls saw them, even across servers and slow deliveries.
+//! Deliveries are not retried; failures are logged.
+
+use samp_query::utils::unix_timestamp_ms;
+use samp_query::ServerSnapshot;
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tracing::warn;
+
+/// How long a webhook may take to answer.
+const DELIVERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Polls whose changes may wait for delivery to one webhook; the changes of
+/// further polls are dropped until it catches up.
+const QUEUE_LEN: usize = 64;
+
+/// How long the delivery task of a webhook outlives its last delivery.
+const IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+type Queues = Arc<Mutex<HashMap<String, mpsc::Sender<Arc<[Change]>>>>>;
+
+/// Checks that `url` can be POSTed to.
+pub fn validate_url(url: &str) -> Result<(), String> {
+    if !(url.starts_with("http://") || url.starts_with("https://")) {
+        return Err(format!("webhook URL must start with http:// or https://, got {:?}", url));
+    }
+    Ok(())
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ChangeKind {
+    /// The server answered after failing to.
+    Online,
+    /// The server stopped answering.
+    Offline { reason: String },
+    HostnameChanged { old: String, new: String },
+    /// The player count rose to or past a milestone of the registration.
+    PlayerMilestone { milestone: u16, players: u16 },
+}
+
+/// A change of a registered server, as delivered to webhooks.
+#[derive(Debug, Clone, Serialize)]
+pub struct Change {
+    /// The name the server is registered under.
+    pub name: String,
+    pub address: SocketAddr,
+    /// Unix timestamp in milliseconds of the poll that saw the change.
+    pub timestamp: u64,
+    pub kind: ChangeKind,
+}
+
+/// The last poll of a server, as far as changes are concerned.
+#[derive(Debug, Clone, Copy)]
+pub struct Previous<'a> {
+    /// `None` before the first poll.
+    pub online: Option<bool>,
+    /// The last successful snapshot.
+    pub snapshot: Option<&'a ServerSnapshot>,
+}
+
+/// The changes between the previous poll and the result of the latest one.
+/// Only the highest milestone crossed is reported.
+pub fn changes(
+    previous: Previous<'_>,
+    result: Result<&ServerSnapshot, &samp_query::Error>,
+    milestones: &[u16],
+) -> Vec<ChangeKind> {
+    let mut changes = Vec::new();
+    let Some(was_online) = previous.online else {
+        return changes;
+    };
+    let snapshot = match result {
+        Ok(snapshot) => snapshot,
+        Err(e) => {
+            if was_online {
+                changes.push(ChangeKind::Offline { reason: e.to_string() });
+            }
+            return changes;
+        }
+    };
+
+    if !was_online {
+        changes.push(ChangeKind::Online);
+    }
+    if let Some(old) = previous.snapshot {
+        if old.info.hostname != snapshot.info.hostname {
+            changes.push(ChangeKind::HostnameChanged {
+                old: old.info.hostname.clone(),
+                new: snapshot.info.hostname.clone(),
+            });
+        }
+        let (before, now) = (old.info.players, snapshot.info.players);
+        let crossed = milestones
+            .iter()
+            .copied()
+            .filter(|&milestone| before < milestone && milestone <= now)
+            .max();
+        if let Some(milestone) = crossed {
+            changes.push(ChangeKind::PlayerMilestone { milestone, players: now });
+        }
+    }
+    changes
+}
+
+/// Delivers changes to webhooks.
+#[derive(Debug, Clone)]
+pub struct Notifier {
+    http: reqwest::Client,
+    /// Webhooks notified about every registered server.
+    global: Arc<[String]>,
+    /// The delivery queue of every webhook with a delivery task.
+    queues: Queues,
+}
+
+impl Notifier {
+    pub fn new(global: Vec<String>) -> Self {
+        Self {
+            http: reqwest::Client::builder()
+                .timeout(DELIVERY_TIMEOUT)
+                .build()
+                .expect("the HTTP client configuration is valid"),
+            global: global.into(),
+            queues: Arc::default(),
+        }
+    }
+
+    /// Queues the changes of the server registered as `name` for `webhooks`
+    /// and the global webhooks, which receive them in the background.
+    pub fn notify(&self, name: &str, address: SocketAddr, webhooks: &[String], changes: Vec<ChangeKind>) {
+        if changes.is_empty() {
+            return;
+        }
+        let timestamp = unix_timestamp_ms();
+        let changes: Arc<[Change]> = changes
+            .into_iter()
+            .map(|kind| Change {
+                name: name.to_string(),
+                address,
+                timestamp,
+                kind,
+            })
+            .collect();
+
+        // Sent with the lock held, so an idle delivery task never exits with
+        // changes left in its queue; see `deliver`.
+        let mut queues = self.queues.lock().unwrap();
+        let mut seen = HashSet::new();
+        for url in webhooks.iter().chain(self.global.iter()).filter(|url| seen.insert(*url)) {
+            let queue = queues.entry(url.clone()).or_insert_with(|| {
+                let (sender, receiver) = mpsc::channel(QUEUE_LEN);
+                tokio::spawn(deliver(self.http.clone(), self.queues.clone(), url.clone(), receiver));
+                sender
+            });
+            if queue.try_send(changes.clone()).is_err() {
+                warn!("Dropping changes of {} for webhook {}: too many deliveries pending", name, url);
+            }
+        }
+    }
+}
+
+/// POSTs the changes queued for `url` one at a time, until the queue has
+/// been idle for [`IDLE_TIMEOUT`].
+async fn deliver(http: reqwest::Client, queues: Queues, url: String, mut receiver: mpsc::Receiver<Arc<[Change]>>) {
+    loop {
+        let changes = match tokio::time::timeout(IDLE_TIMEOUT, receiver.recv()).await {
+            Ok(Some(changes)) => changes,
+            Ok(None) => return,
+            Err(_) => {
+                let mut queues = queues.lock().unwrap();
+                if receiver.is_empty() {
+                    queues.remove(&url);
+                    return;
+                }
+                continue;
+            }
+        };
+        for change in changes.iter() {
+            let result = http
+                .post(&url)
+                .json(change)
+                .send()
+                .await
+                .and_then(|response| response.error_for_status());
+            if let Err(e) = result {
+                warn!("Failed to deliver webhook to {}: {}", url, e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use samp_query::ServerInfo;
+
+    fn snapshot(hostname: &str, players: u16) -> ServerSnapshot {
+        ServerSnapshot {
+            address: "127.0.0.1:7777".parse().unwrap(),
+            timestamp: 0,
+            info: ServerInfo {
+                password: false,
+                players,
+                max_players: 500,
+                hostname: hostname.to_string(),
+                gamemode: String::new(),
+                language: String::new(),
+            },
+            rules: None,
+            players: None,
+            ping: None,
+        }
+    }
+
+    fn previous(online: Option<bool>, snapshot: Option<&ServerSnapshot>) -> Previous<'_> {
+        Previous { online, snapshot }
+    }
+
+    #[test]
+    fn first_poll_sets_the_baseline() {
+        let now = snapshot("lv", 10);
+        assert!(changes(previous(None, None), Ok(&now), &[5]).is_empty());
+        assert!(changes(previous(None, None), Err(&samp_query::Error::Timeout), &[]).is_empty());
+    }
+
+    #[test]
+    fn online_and_offline() {
+        let old = snapshot("lv", 10);
+        assert_eq!(
+            changes(previous(Some(true), Some(&old)), Err(&samp_query::Error::Timeout), &[]),
+            [ChangeKind::Offline {
+                reason: samp_query::Error::Timeout.to_string()
+            }]
+        );
+        assert!(changes(previous(Some(false), Some(&old)), Err(&samp_query::Error::Timeout), &[]).is_empty());
+        assert_eq!(
+            changes(previous(Some(false), Some(&old)), Ok(&old), &[]),
+            [ChangeKind::Online]
+        );
+    }
+
+    #[test]
+    fn hostname_and_highest_milestone() {
+        let old = snapshot("lv", 40);
+        let now = snapshot("ls", 120);
+        assert_eq!(
+            changes(previous(Some(true), Some(&old)), Ok(&now), &[50, 100, 200]),
+            [
+                ChangeKind::HostnameChanged {
+                    old: "lv".to_string(),
+                    new: "ls".to_string(),
+                },
+                ChangeKind::PlayerMilestone {
+                    milestone: 100,
+                    players: 120,
+                },
+            ]
+        );
+        // Already past the milestone: not reported again.
+        assert!(changes(previous(Some(true), Some(&now)), Ok(&now), &[100]).is_empty());
+    }
+}
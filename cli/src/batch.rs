@@ -0,0 +1,233 @@
+//! Batch mode: `info`, `ping` and `all` over a list of servers, and the
+//! one-line-per-server output of `--quiet`.
+
+use crate::exit;
+use crate::output::OutputFormat;
+use crate::targets::Target;
+use anyhow::Result;
+use colored::Colorize;
+use futures::stream::{self, Stream, StreamExt};
+use samp_query::pipeline::{self, PipelineConfig};
+use samp_query::{Client, Error, ServerInfo};
+use serde::Serialize;
+use std::future::Future;
+use std::io::Write;
+use std::process::ExitCode;
+
+/// Servers queried at the same time unless `--parallel` says otherwise.
+pub const DEFAULT_PARALLEL: usize = 32;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Query {
+    Info,
+    Ping,
+    All,
+}
+
+impl Query {
+    /// The key holding the response in JSON and YAML output.
+    fn key(self) -> &'static str {
+        match self {
+            Query::Info => "info",
+            Query::Ping => "ping",
+            Query::All => "snapshot",
+        }
+    }
+}
+
+/// What one server answered.
+struct Answer {
+    info: Option<ServerInfo>,
+    ping_ms: Option<u64>,
+    /// The full response, for JSON and YAML.
+    value: serde_json::Value,
+}
+
+struct Outcome {
+    /// The resolved address, or the line that did not resolve.
+    address: String,
+    /// The answer, or why there is none.
+    result: samp_query::Result<Answer>,
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    address: &'a str,
+    hostname: Option<&'a str>,
+    gamemode: Option<&'a str>,
+    players: Option<u16>,
+    max_players: Option<u16>,
+    password: Option<bool>,
+    ping_ms: Option<u64>,
+    error: Option<String>,
+}
+
+fn to_value<T: Serialize>(value: &T) -> samp_query::Result<serde_json::Value> {
+    serde_json::to_value(value).map_err(|e| Error::Other(e.to_string()))
+}
+
+async fn answer(query: Query, client: Client) -> samp_query::Result<Answer> {
+    Ok(match query {
+        Query::Info => {
+            let info = client.query_info().await?;
+            Answer {
+                value: to_value(&info)?,
+                info: Some(info),
+                ping_ms: None,
+            }
+        }
+        Query::Ping => {
+            let ping = client.query_ping().await?;
+            Answer {
+                value: to_value(&ping)?,
+                info: None,
+                ping_ms: Some(ping.ping_ms),
+            }
+        }
+        Query::All => {
+            let snapshot = client.snapshot_concurrent().await?;
+            Answer {
+                value: to_value(&snapshot)?,
+                ping_ms: snapshot.ping.map(|ping| ping.ping_ms),
+                info: Some(snapshot.info),
+            }
+        }
+    })
+}
+
+/// Runs `query` against every target that resolved and yields a result per
+/// target in the order given, with the resolution error for the others.
+fn query_targets<'a, F, Fut, T>(
+    targets: Vec<Target>,
+    config: PipelineConfig,
+    query: F,
+) -> impl Stream<Item = (String, samp_query::Result<T>)> + 'a
+where
+    F: Fn(Client) -> Fut + Send + Sync + 'a,
+    Fut: Future<Output = samp_query::Result<T>> + Send + 'a,
+    T: Send + 'a,
+{
+    let config = PipelineConfig { ordered: true, ..config };
+    let addresses: Vec<_> = targets.iter().filter_map(|target| target.addr.as_ref().ok().copied()).collect();
+    let results = pipeline::query(stream::iter(addresses), config, query);
+
+    stream::unfold((targets.into_iter(), results), |(mut targets, mut results)| async move {
+        let target = targets.next()?;
+        let item = match target.addr {
+            Ok(_) => {
+                let (address, result) = results.next().await?;
+                (address.to_string(), result)
+            }
+            Err(e) => (target.line, Err(e)),
+        };
+        Some((item, (targets, results)))
+    })
+}
+
+/// Queries every server and prints one line, object or record per server in
+/// the order given. Servers that fail or do not resolve are reported in
+/// place of their line.
+///
+/// `config` limits how many servers are queried at once and how fast; its
+/// results are always kept in order. When no server answers, the exit code
+/// is that of the first failure.
+pub async fn run(query: Query, targets: Vec<Target>, config: PipelineConfig, format: OutputFormat) -> Result<ExitCode> {
+    let outcomes = query_targets(targets, config, move |client| answer(query, client))
+        .map(|(address, result)| Outcome { address, result });
+    let mut outcomes = std::pin::pin!(outcomes);
+    let mut csv = csv::Writer::from_writer(std::io::stdout());
+    let (mut answered, mut failed) = (0, 0);
+    let mut first_error = None;
+
+    while let Some(outcome) = outcomes.next().await {
+        match &outcome.result {
+            Ok(_) => answered += 1,
+            Err(e) => {
+                failed += 1;
+                first_error.get_or_insert(exit::query_code(e));
+            }
+        }
+        match format {
+            OutputFormat::Table => println!("{}", format_line(&outcome)),
+            OutputFormat::Json => println!("{}", serde_json::to_string(&document(query, &outcome))?),
+            OutputFormat::Yaml => print!("---\n{}", serde_yaml::to_string(&document(query, &outcome))?),
+            OutputFormat::Csv => {
+                let answer = outcome.result.as_ref().ok();
+                let info = answer.and_then(|answer| answer.info.as_ref());
+                csv.serialize(Record {
+                    address: &outcome.address,
+                    hostname: info.map(|info| info.hostname.as_str()),
+                    gamemode: info.map(|info| info.gamemode.as_str()),
+                    players: info.map(|info| info.players),
+                    max_players: info.map(|info| info.max_players),
+                    password: info.map(|info| info.password),
+                    ping_ms: answer.and_then(|answer| answer.ping_ms),
+                    error: outcome.result.as_ref().err().map(ToString::to_string),
+                })?;
+                csv.flush()?;
+            }
+        }
+        std::io::stdout().flush()?;
+    }
+
+    eprintln!("{} of {} servers answered", answered, answered + failed);
+    Ok(match first_error {
+        Some(code) if answered == 0 => ExitCode::from(code),
+        _ => ExitCode::SUCCESS,
+    })
+}
+
+/// Prints `address|online|players|max_players|ping_ms` for every server in
+/// the order given, or `address|offline|||` for servers that do not answer
+/// or resolve.
+pub async fn quiet(targets: Vec<Target>, config: PipelineConfig) -> Result<()> {
+    let results = query_targets(targets, config, |client| async move {
+        let info = client.query_info().await?;
+        let ping = client.query_ping().await?;
+        Ok((info, ping))
+    });
+    let mut results = std::pin::pin!(results);
+
+    while let Some((address, result)) = results.next().await {
+        match result {
+            Ok((info, ping)) => println!(
+                "{}|online|{}|{}|{}",
+                address, info.players, info.max_players, ping.ping_ms
+            ),
+            Err(_) => println!("{}|offline|||", address),
+        }
+        std::io::stdout().flush()?;
+    }
+    Ok(())
+}
+
+fn document(query: Query, outcome: &Outcome) -> serde_json::Value {
+    let mut document = serde_json::Map::new();
+    document.insert("address".to_string(), outcome.address.clone().into());
+    match &outcome.result {
+        Ok(answer) => document.insert(query.key().to_string(), answer.value.clone()),
+        Err(error) => document.insert("error".to_string(), error.to_string().into()),
+    };
+    document.into()
+}
+
+fn format_line(outcome: &Outcome) -> String {
+    let address = format!("{:<21}", outcome.address).blue().bold();
+    let answer = match &outcome.result {
+        Ok(answer) => answer,
+        Err(error) => return format!("{} {}", address, error.to_string().red()),
+    };
+
+    let mut line = address.to_string();
+    if let Some(info) = &answer.info {
+        line.push_str(&format!(
+            " {} {}",
+            info.hostname,
+            format!("{}/{}", info.players, info.max_players).green()
+        ));
+    }
+    if let Some(ping_ms) = answer.ping_ms {
+        line.push_str(&format!(" {}", format!("{} ms", ping_ms).dimmed()));
+    }
+    line
+}
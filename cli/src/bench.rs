@@ -0,0 +1,170 @@
+//! The `bench` command: how many complete info queries per second the tool
+//! achieves over a server list.
+
+use crate::output::{render, OutputFormat};
+use anyhow::Result;
+use colored::Colorize;
+use futures::StreamExt;
+use samp_query::pipeline::{self, PipelineConfig};
+use samp_query::Error;
+use serde::Serialize;
+use std::io::ErrorKind;
+use std::net::SocketAddr;
+use std::time::{Duration, Instant};
+
+/// Why queries did not complete, by where the problem lies.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct Failures {
+    /// No answer within the timeout.
+    pub timeouts: u64,
+    /// The server refused the query, e.g. nothing listens on the port.
+    pub refused: u64,
+    /// The answer could not be parsed.
+    pub parse: u64,
+    /// Local limits such as open sockets or send buffers.
+    pub local: u64,
+    pub other: u64,
+}
+
+impl Failures {
+    fn count(&mut self, error: &Error) {
+        let counter = match error {
+            Error::Timeout => &mut self.timeouts,
+            Error::InvalidResponse(_) | Error::Utf8(_) | Error::ServerError(_) => &mut self.parse,
+            Error::Bind(_) => &mut self.local,
+            Error::Send(e) | Error::Receive(e) | Error::Connect(e) | Error::Io(e) => {
+                if e.kind() == ErrorKind::ConnectionRefused {
+                    &mut self.refused
+                } else {
+                    &mut self.local
+                }
+            }
+            _ => &mut self.other,
+        };
+        *counter += 1;
+    }
+
+    fn total(&self) -> u64 {
+        self.timeouts + self.refused + self.parse + self.local + self.other
+    }
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct BenchReport {
+    pub queries: u64,
+    pub answered: u64,
+    pub parallel: usize,
+    /// Wall time of the whole run, in milliseconds.
+    pub elapsed_ms: u64,
+    /// Answered queries per second of wall time.
+    pub answered_per_second: f64,
+    /// Mean time to an answer, in milliseconds.
+    pub mean_latency_ms: Option<f64>,
+    pub failures: Failures,
+    /// What limited the run most.
+    pub bottleneck: String,
+}
+
+/// Names what held the throughput back: the dominant failure if failures
+/// are common, otherwise the parallelism or rate limit.
+fn bottleneck(report: &BenchReport, rate: Option<u32>) -> String {
+    let failures = &report.failures;
+    if failures.total() * 10 >= report.queries.max(1) {
+        let worst = [
+            (failures.timeouts, "timeouts: servers or the network are slow; raise --timeout or lower --parallel"),
+            (failures.refused, "refused queries: servers in the list are not running"),
+            (failures.parse, "malformed answers: servers reply with packets that do not parse"),
+            (failures.local, "local limits: raise the open file limit (ulimit -n) or lower --parallel"),
+            (failures.other, "other errors"),
+        ];
+        if let Some((_, reason)) = worst.iter().max_by_key(|(count, _)| *count) {
+            return reason.to_string();
+        }
+    }
+    if let Some(rate) = rate {
+        if report.answered_per_second >= rate as f64 * 0.9 {
+            return format!("the --rate limit of {} queries per second", rate);
+        }
+    }
+    match report.mean_latency_ms {
+        Some(latency) if latency > 0.0 => {
+            let ceiling = report.parallel as f64 * 1000.0 / latency;
+            if report.answered_per_second >= ceiling * 0.8 {
+                "parallelism: every slot is waiting on a server; raise --parallel".to_string()
+            } else {
+                "local processing: the tool is not keeping its slots busy".to_string()
+            }
+        }
+        _ => "nothing answered".to_string(),
+    }
+}
+
+fn format_report(report: &BenchReport) -> String {
+    let failures = &report.failures;
+    let latency = report
+        .mean_latency_ms
+        .map_or_else(|| "-".to_string(), |latency| format!("{:.1} ms", latency));
+    format!(
+        "{}\nQueries: {} ({} at a time)\nAnswered: {}\nElapsed: {:.2}s\nThroughput: {} answered queries/s\nMean latency: {}\n\
+         Failures: {} timeouts, {} refused, {} parse, {} local, {} other\nBottleneck: {}",
+        "Benchmark".green().bold(),
+        report.queries,
+        report.parallel,
+        report.answered,
+        report.elapsed_ms as f64 / 1000.0,
+        format!("{:.1}", report.answered_per_second).green(),
+        latency,
+        failures.timeouts,
+        failures.refused,
+        failures.parse,
+        failures.local,
+        failures.other,
+        report.bottleneck.yellow()
+    )
+}
+
+/// Queries the info of every server `repeat` times, `config.max_in_flight`
+/// at a time, and prints the throughput with a breakdown of the failures.
+pub async fn run(
+    servers: Vec<SocketAddr>,
+    repeat: usize,
+    config: PipelineConfig,
+    rate: Option<u32>,
+    format: OutputFormat,
+) -> Result<()> {
+    let parallel = config.max_in_flight;
+    let addresses = futures::stream::iter(std::iter::repeat_n(servers, repeat).flatten());
+    let started = Instant::now();
+    let mut results = pipeline::query(addresses, config, |client| async move {
+        let sent = Instant::now();
+        client.query_info().await.map(|_| sent.elapsed())
+    });
+
+    let (mut queries, mut answered, mut latency) = (0, 0, Duration::ZERO);
+    let mut failures = Failures::default();
+    while let Some((_, result)) = results.next().await {
+        queries += 1;
+        match result {
+            Ok(elapsed) => {
+                answered += 1;
+                latency += elapsed;
+            }
+            Err(e) => failures.count(&e),
+        }
+    }
+    let elapsed = started.elapsed();
+
+    let mut report = BenchReport {
+        queries,
+        answered,
+        parallel,
+        elapsed_ms: elapsed.as_millis() as u64,
+        answered_per_second: answered as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+        mean_latency_ms: (answered > 0).then(|| latency.as_secs_f64() * 1000.0 / answered as f64),
+        failures,
+        bottleneck: String::new(),
+    };
+    report.bottleneck = bottleneck(&report, rate);
+    println!("{}", render(format, &report, format_report)?);
+    Ok(())
+}
@@ -0,0 +1,132 @@
+//! The `check` command: a Nagios/Icinga check plugin.
+//!
+//! Prints one status line with performance data and exits with the plugin
+//! status, e.g.
+//!
+//! ```text
+//! SAMP WARNING - Mock Freeroam: 2/50 players, ping 180 ms | ping=180ms;150;400;0; players=2;48;;0;50
+//! ```
+
+use crate::settings::Settings;
+use samp_query::{Client, ClientConfig, PingInfo, ServerInfo};
+use std::fmt;
+
+/// Plugin statuses.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Status {
+    Ok,
+    Warning,
+    Critical,
+    Unknown,
+}
+
+impl Status {
+    /// The exit code monitoring systems expect for the status.
+    pub fn code(self) -> i32 {
+        match self {
+            Status::Ok => 0,
+            Status::Warning => 1,
+            Status::Critical => 2,
+            Status::Unknown => 3,
+        }
+    }
+}
+
+impl fmt::Display for Status {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let label = match self {
+            Status::Ok => "OK",
+            Status::Warning => "WARNING",
+            Status::Critical => "CRITICAL",
+            Status::Unknown => "UNKNOWN",
+        };
+        f.write_str(label)
+    }
+}
+
+/// The limits a server is checked against. Unset limits are not checked.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct Thresholds {
+    /// Warn when the ping exceeds this many milliseconds.
+    pub warn_ping: Option<u64>,
+    /// Critical when the ping exceeds this many milliseconds.
+    pub crit_ping: Option<u64>,
+    /// Warn when fewer player slots than this are free.
+    pub warn_players_free: Option<u32>,
+    /// Critical when fewer player slots than this are free.
+    pub crit_players_free: Option<u32>,
+}
+
+impl Thresholds {
+    fn status(&self, info: &ServerInfo, ping: &PingInfo) -> Status {
+        let free = info.max_players.saturating_sub(info.players) as u32;
+        let exceeds = |limit: Option<u64>| limit.is_some_and(|limit| ping.ping_ms > limit);
+        let full = |limit: Option<u32>| limit.is_some_and(|limit| free < limit);
+
+        if exceeds(self.crit_ping) || full(self.crit_players_free) {
+            Status::Critical
+        } else if exceeds(self.warn_ping) || full(self.warn_players_free) {
+            Status::Warning
+        } else {
+            Status::Ok
+        }
+    }
+
+    /// Performance data in the `label=value[unit];warn;crit;min;max` format.
+    fn perfdata(&self, info: &ServerInfo, ping: &PingInfo) -> String {
+        let limit = |limit: Option<u64>| limit.map(|limit| limit.to_string()).unwrap_or_default();
+        let players = |free: Option<u32>| {
+            free.map(|free| (info.max_players as u32).saturating_sub(free).to_string())
+                .unwrap_or_default()
+        };
+        format!(
+            "ping={}ms;{};{};0; players={};{};{};0;{}",
+            ping.ping_ms,
+            limit(self.warn_ping),
+            limit(self.crit_ping),
+            info.players,
+            players(self.warn_players_free),
+            players(self.crit_players_free),
+            info.max_players
+        )
+    }
+}
+
+/// Queries the server, prints the check result and exits with its status.
+///
+/// A server that does not answer is critical; an address that does not
+/// resolve is unknown.
+pub async fn run(settings: &Settings, address: &str, thresholds: Thresholds, config: ClientConfig) -> ! {
+    let (status, output) = check(settings, address, thresholds, config).await;
+    println!("SAMP {} - {}", status, output);
+    std::process::exit(status.code())
+}
+
+async fn check(settings: &Settings, address: &str, thresholds: Thresholds, config: ClientConfig) -> (Status, String) {
+    let addr = match settings.server_address(address).await {
+        Ok(addr) => addr,
+        Err(e) => return (Status::Unknown, format!("{}: {:#}", address, e)),
+    };
+    let result = async {
+        let client = Client::connect_with_config(addr, config).await?;
+        let info = client.query_info().await?;
+        let ping = client.query_ping().await?;
+        Ok::<_, samp_query::Error>((info, ping))
+    }
+    .await;
+
+    match result {
+        Ok((info, ping)) => (
+            thresholds.status(&info, &ping),
+            format!(
+                "{}: {}/{} players, ping {} ms | {}",
+                info.hostname,
+                info.players,
+                info.max_players,
+                ping.ping_ms,
+                thresholds.perfdata(&info, &ping)
+            ),
+        ),
+        Err(e) => (Status::Critical, format!("{}: {}", addr, e)),
+    }
+}
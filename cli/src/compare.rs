@@ -0,0 +1,106 @@
+//! The `compare` command: two servers side by side.
+
+use crate::output::{draw, highlight, render_rows, CsvRecord, OutputFormat};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use samp_query::{Client, ClientConfig, ServerSnapshot};
+use serde::Serialize;
+use std::net::SocketAddr;
+use tabled::builder::Builder;
+
+/// Rules shown in the comparison, when at least one server has them.
+const KEY_RULES: &[&str] = &["version", "mapname", "weburl", "lagcomp", "worldtime", "weather"];
+
+/// Reads one compared value from a snapshot.
+type Field = fn(&ServerSnapshot) -> Option<String>;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct Difference {
+    field: String,
+    first: String,
+    second: String,
+    differs: bool,
+}
+
+impl CsvRecord for Difference {
+    const HEADER: &'static [&'static str] = &["field", "first", "second", "differs"];
+}
+
+fn rows(first: &ServerSnapshot, second: &ServerSnapshot) -> Vec<Difference> {
+    let fields: [(&str, Field); 6] = [
+        ("hostname", |s| Some(s.info.hostname.clone())),
+        ("gamemode", |s| Some(s.info.gamemode.clone())),
+        ("language", |s| Some(s.info.language.clone())),
+        ("players", |s| Some(format!("{}/{}", s.info.players, s.info.max_players))),
+        ("password", |s| Some(if s.info.password { "yes" } else { "no" }.to_string())),
+        ("ping", |s| s.ping.as_ref().map(|ping| format!("{} ms", ping.ping_ms))),
+    ];
+    let mut rows: Vec<Difference> = fields
+        .iter()
+        .map(|(field, value)| difference(field.to_string(), value(first), value(second)))
+        .collect();
+
+    let rule = |snapshot: &ServerSnapshot, name: &str| snapshot.rules.as_ref()?.rules.get(name).cloned();
+    for name in KEY_RULES {
+        let (a, b) = (rule(first, name), rule(second, name));
+        if a.is_some() || b.is_some() {
+            rows.push(difference(format!("rule.{}", name), a, b));
+        }
+    }
+    rows
+}
+
+fn difference(field: String, first: Option<String>, second: Option<String>) -> Difference {
+    Difference {
+        differs: first != second,
+        first: first.unwrap_or_default(),
+        second: second.unwrap_or_default(),
+        field,
+    }
+}
+
+fn format_differences(addresses: (SocketAddr, SocketAddr), rows: &[Difference]) -> String {
+    let mut builder = Builder::default();
+    builder.set_header(["Field".to_string(), addresses.0.to_string(), addresses.1.to_string()]);
+    for row in rows {
+        builder.push_record([
+            row.field.clone(),
+            highlight(row.first.clone(), row.differs),
+            highlight(row.second.clone(), row.differs),
+        ]);
+    }
+
+    let differing = rows.iter().filter(|row| row.differs).count();
+    format!(
+        "{}\n{}\n{} of {} fields differ",
+        "Server Comparison".green().bold(),
+        draw(builder.build()),
+        differing,
+        rows.len()
+    )
+}
+
+/// Queries both servers at once and prints the fields next to each other,
+/// highlighting those that differ.
+pub async fn run(first: SocketAddr, second: SocketAddr, config: ClientConfig, format: OutputFormat) -> Result<()> {
+    let query = |addr: SocketAddr| {
+        let config = config.clone();
+        async move {
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+            client
+                .snapshot_concurrent()
+                .await
+                .with_context(|| format!("Failed to query {}", addr))
+        }
+    };
+    let (a, b) = tokio::try_join!(query(first), query(second))?;
+
+    let rows = rows(&a, &b);
+    println!(
+        "{}",
+        render_rows(format, &rows, |rows| format_differences((first, second), rows), |rows| rows.clone())?
+    );
+    Ok(())
+}
@@ -0,0 +1,122 @@
+//! The `daemon` command: monitors every server of the `[[monitors]]` table,
+//! records them to the history database and sends their notifications.
+//!
+//! Events are logged to stderr, so under systemd they end up in the journal.
+//! The daemon runs until interrupted or sent SIGTERM.
+
+use crate::history;
+use crate::monitor::Webhook;
+use crate::settings::Settings;
+use anyhow::{bail, Context, Result};
+use futures::StreamExt;
+use samp_query::monitor::MonitorConfig;
+use samp_query::notify::discord::DiscordNotifier;
+use samp_query::notify::webhook::WebhookSink;
+use samp_query::notify::{EventSink, LogSink};
+use samp_query::scheduler::{ScheduleConfig, Scheduler};
+use samp_query::ClientConfig;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+use tracing::{info, warn};
+
+/// Resolves when the process is asked to stop.
+async fn shutdown() {
+    #[cfg(unix)]
+    {
+        use tokio::signal::unix::{signal, SignalKind};
+        match signal(SignalKind::terminate()) {
+            Ok(mut terminate) => {
+                tokio::select! {
+                    _ = tokio::signal::ctrl_c() => {}
+                    _ = terminate.recv() => {}
+                }
+            }
+            Err(_) => {
+                let _ = tokio::signal::ctrl_c().await;
+            }
+        }
+    }
+    #[cfg(not(unix))]
+    {
+        let _ = tokio::signal::ctrl_c().await;
+    }
+}
+
+/// Polls the configured servers until stopped. Servers without their own
+/// interval are polled every `interval`. The change events of every server
+/// go to `webhooks`, besides the server's own notifications.
+pub async fn run(
+    settings: &Settings,
+    db: &Path,
+    interval: Duration,
+    config: ClientConfig,
+    parallel: Option<usize>,
+    webhooks: Vec<Webhook>,
+) -> Result<()> {
+    if settings.monitors.is_empty() {
+        bail!("No [[monitors]] in the configuration file; the daemon has nothing to watch");
+    }
+
+    let schedule = ScheduleConfig {
+        monitor: MonitorConfig {
+            client: config,
+            interval,
+            ..MonitorConfig::default()
+        },
+        concurrency: parallel.unwrap_or(ScheduleConfig::default().concurrency),
+        ..ScheduleConfig::default()
+    };
+    let mut scheduler = Scheduler::new(schedule);
+    let mut sinks: HashMap<SocketAddr, Vec<Box<dyn EventSink>>> = HashMap::new();
+    for monitor in &settings.monitors {
+        let addr = settings
+            .server_address(&monitor.server)
+            .await
+            .with_context(|| format!("Invalid monitor {:?}", monitor.server))?;
+        match monitor.interval {
+            Some(seconds) => scheduler.add_with_interval(addr, Duration::from_secs(seconds)),
+            None => scheduler.add(addr),
+        }
+
+        let server_sinks = sinks.entry(addr).or_default();
+        if let Some(webhook) = &monitor.webhook {
+            server_sinks.push(Box::new(WebhookSink::new(settings.webhook(webhook)?)));
+        }
+        if let Some(discord) = &monitor.discord {
+            server_sinks.push(Box::new(DiscordNotifier::new(settings.webhook(discord)?)));
+        }
+    }
+
+    let webhooks: Vec<_> = webhooks.into_iter().map(Webhook::into_sink).collect();
+
+    let store = history::open(db)?;
+    info!("Monitoring {} servers, recording to {}", scheduler.len(), db.display());
+
+    let mut events = scheduler.stream();
+    let stop = shutdown();
+    tokio::pin!(stop);
+    loop {
+        let event = tokio::select! {
+            event = events.next() => event,
+            _ = &mut stop => None,
+        };
+        let Some(event) = event else {
+            break;
+        };
+
+        if let Err(e) = store.record_event(&event) {
+            warn!("Failed to record event for {}: {}", event.address, e);
+        }
+        let _ = LogSink.publish(&event).await;
+        for sink in sinks.get(&event.address).into_iter().flatten().chain(&webhooks) {
+            if let Err(e) = sink.publish(&event).await {
+                warn!("Failed to notify about {}: {}", event.address, e);
+            }
+        }
+    }
+
+    info!("Stopping");
+    Ok(())
+}
@@ -0,0 +1,78 @@
+//! The `diff` command: what changed since a saved snapshot.
+
+use crate::output::{clock, render, OutputFormat};
+use crate::settings::Settings;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use samp_query::diff::{Change, SnapshotDiff};
+use samp_query::{Client, ClientConfig, ServerSnapshot};
+use std::path::Path;
+
+/// Reads a snapshot saved with `export` or `all --format json`.
+fn read(path: &Path) -> Result<ServerSnapshot> {
+    let text = std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?;
+    let toml = path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("toml"));
+    let snapshot = if toml {
+        toml::from_str(&text).map_err(anyhow::Error::from)
+    } else {
+        serde_json::from_str(&text).map_err(anyhow::Error::from)
+    };
+    snapshot.with_context(|| format!("{} is not a saved snapshot", path.display()))
+}
+
+/// The snapshot saved in `target` if it names a file, otherwise a fresh
+/// snapshot of the server it names.
+async fn load(target: &str, settings: &Settings, config: ClientConfig) -> Result<ServerSnapshot> {
+    let path = Path::new(target);
+    if path.is_file() {
+        return read(path);
+    }
+    let addr = settings.server_address(target).await?;
+    let client = Client::connect_with_config(addr, config)
+        .await
+        .context("Failed to connect to server")?;
+    client
+        .snapshot_concurrent()
+        .await
+        .with_context(|| format!("Failed to query {}", addr))
+}
+
+fn format_change(change: &Change, prefix: &str) -> String {
+    let field = format!("{}{}", prefix, change.field);
+    match (&change.old, &change.new) {
+        (Some(old), Some(new)) => format!("{} {}: {} -> {}", "~".yellow(), field, old.red(), new.green()),
+        (None, Some(new)) => format!("{} {}: {}", "+".green(), field, new.green()),
+        (Some(old), None) => format!("{} {}: {}", "-".red(), field, old.red()),
+        (None, None) => field,
+    }
+}
+
+fn format_diff(old: &ServerSnapshot, new: &ServerSnapshot, diff: &SnapshotDiff) -> String {
+    let mut lines = vec![format!(
+        "{} {} ({}) -> {} ({})",
+        "Changes".green().bold(),
+        old.address,
+        clock(old.timestamp),
+        new.address,
+        clock(new.timestamp)
+    )];
+    if diff.is_empty() {
+        lines.push("No differences".dimmed().to_string());
+    }
+    lines.extend(diff.joined.iter().map(|name| format!("{} {} joined", "+".green(), name.green())));
+    lines.extend(diff.left.iter().map(|name| format!("{} {} left", "-".red(), name.red())));
+    lines.extend(diff.info.iter().map(|change| format_change(change, "")));
+    lines.extend(diff.rules.iter().map(|change| format_change(change, "rule.")));
+    lines.join("\n")
+}
+
+/// Prints what changed between the snapshot saved in `against` and
+/// `target`, a server to query now or a second saved snapshot.
+pub async fn run(target: &str, against: &Path, settings: &Settings, config: ClientConfig, format: OutputFormat) -> Result<()> {
+    let old = read(against)?;
+    let new = load(target, settings, config).await?;
+
+    let diff = SnapshotDiff::between(&old, &new);
+    println!("{}", render(format, &diff, |diff| format_diff(&old, &new, diff))?);
+    Ok(())
+}
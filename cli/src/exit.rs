@@ -0,0 +1,52 @@
+//! Exit codes, so scripts can tell failures apart without parsing messages.
+
+use samp_query::Error;
+use std::process::ExitCode;
+
+/// Any failure without a more specific code.
+pub const FAILURE: u8 = 1;
+/// A server did not answer in time.
+pub const TIMEOUT: u8 = 2;
+/// The server rejected the RCON password.
+pub const AUTH_FAILED: u8 = 3;
+/// A server answered with a packet that does not parse.
+pub const MALFORMED_RESPONSE: u8 = 4;
+/// A server address could not be parsed or resolved.
+pub const RESOLUTION: u8 = 5;
+/// `monitor` stopped because an alert triggered.
+pub const ALERT: u8 = 6;
+
+/// The exit code for `error`, from the first query error in its chain.
+pub fn code(error: &anyhow::Error) -> ExitCode {
+    ExitCode::from(error.chain().find_map(|cause| cause.downcast_ref::<Error>()).map_or(FAILURE, query_code))
+}
+
+/// The exit code for a query error.
+pub fn query_code(error: &Error) -> u8 {
+    match error {
+        Error::Timeout => TIMEOUT,
+        Error::RconAuthFailed => AUTH_FAILED,
+        Error::InvalidResponse(_) | Error::Utf8(_) => MALFORMED_RESPONSE,
+        Error::Resolve(_) | Error::AddrParse(_) => RESOLUTION,
+        _ => FAILURE,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use anyhow::Context;
+
+    #[test]
+    fn code_from_the_query_error_in_the_chain() {
+        let error = Err::<(), _>(Error::Timeout).context("Failed to query server").unwrap_err();
+        assert_eq!(code(&error), ExitCode::from(TIMEOUT));
+        assert_eq!(code(&Error::RconAuthFailed.into()), ExitCode::from(AUTH_FAILED));
+        assert_eq!(
+            code(&Error::InvalidResponse("short".to_string()).into()),
+            ExitCode::from(MALFORMED_RESPONSE)
+        );
+        assert_eq!(code(&Error::Resolve("nowhere".to_string()).into()), ExitCode::from(RESOLUTION));
+        assert_eq!(code(&anyhow::anyhow!("bad argument")), ExitCode::from(FAILURE));
+    }
+}
@@ -0,0 +1,103 @@
+//! The `export` command: saves a full snapshot of a server to a file.
+//!
+//! JSON and TOML exports are the snapshot's fields plus `generator` and
+//! `exported_at`, so `diff --against` reads them like any saved snapshot.
+//! CSV exports flatten the snapshot to `section,key,value` rows for
+//! spreadsheets.
+
+use crate::output::datetime;
+use anyhow::{bail, Context, Result};
+use samp_query::utils::unix_timestamp_ms;
+use samp_query::{Client, ClientConfig, ServerSnapshot};
+use serde::Serialize;
+use std::net::SocketAddr;
+use std::path::Path;
+
+#[derive(Serialize)]
+struct Export<'a> {
+    /// The program and version that wrote the file.
+    generator: &'static str,
+    /// When the file was written, as an RFC 3339 UTC time.
+    exported_at: String,
+    #[serde(flatten)]
+    snapshot: &'a ServerSnapshot,
+}
+
+#[derive(Serialize)]
+struct Row {
+    section: &'static str,
+    key: String,
+    value: String,
+}
+
+fn rows(export: &Export) -> Vec<Row> {
+    let snapshot = export.snapshot;
+    let info = &snapshot.info;
+    let row = |section, key: &str, value: String| Row {
+        section,
+        key: key.to_string(),
+        value,
+    };
+
+    let mut rows = vec![
+        row("meta", "generator", export.generator.to_string()),
+        row("meta", "exported_at", export.exported_at.clone()),
+        row("meta", "address", snapshot.address.to_string()),
+        row("meta", "timestamp", snapshot.timestamp.to_string()),
+        row("info", "hostname", info.hostname.clone()),
+        row("info", "gamemode", info.gamemode.clone()),
+        row("info", "language", info.language.clone()),
+        row("info", "players", info.players.to_string()),
+        row("info", "max_players", info.max_players.to_string()),
+        row("info", "password", info.password.to_string()),
+    ];
+    if let Some(ping) = &snapshot.ping {
+        rows.push(row("ping", "ping_ms", ping.ping_ms.to_string()));
+    }
+    if let Some(rules) = &snapshot.rules {
+        let mut names: Vec<_> = rules.rules.keys().collect();
+        names.sort();
+        rows.extend(names.into_iter().map(|name| row("rule", name, rules.rules[name].clone())));
+    }
+    for player in snapshot.players.iter().flat_map(|players| &players.players) {
+        rows.push(row("player", &format!("{}.name", player.id), player.name.clone()));
+        rows.push(row("player", &format!("{}.score", player.id), player.score.to_string()));
+        rows.push(row("player", &format!("{}.ping", player.id), player.ping.to_string()));
+    }
+    rows
+}
+
+/// Writes `snapshot` to `path` in the format its extension names: `.json`,
+/// `.toml` or `.csv`.
+fn write(snapshot: &ServerSnapshot, path: &Path) -> Result<()> {
+    let export = Export {
+        generator: concat!("samp-query-cli ", env!("CARGO_PKG_VERSION")),
+        exported_at: datetime(unix_timestamp_ms()),
+        snapshot,
+    };
+    let extension = path.extension().and_then(|extension| extension.to_str()).unwrap_or_default();
+    let text = match extension.to_ascii_lowercase().as_str() {
+        "json" => serde_json::to_string_pretty(&export)? + "\n",
+        "toml" => toml::to_string(&export).context("Failed to write the snapshot as TOML")?,
+        "csv" => {
+            let mut writer = csv::Writer::from_writer(Vec::new());
+            for row in rows(&export) {
+                writer.serialize(row)?;
+            }
+            String::from_utf8(writer.into_inner()?)?
+        }
+        _ => bail!("Cannot tell the format of {}; name it .json, .toml or .csv", path.display()),
+    };
+    std::fs::write(path, text).with_context(|| format!("Failed to write {}", path.display()))
+}
+
+/// Takes a snapshot of every query the server answers and writes it to `out`.
+pub async fn run(addr: SocketAddr, config: ClientConfig, out: &Path) -> Result<()> {
+    let client = Client::connect_with_config(addr, config)
+        .await
+        .context("Failed to connect to server")?;
+    let snapshot = client.snapshot_concurrent().await.context("Failed to query server")?;
+    write(&snapshot, out)?;
+    eprintln!("Saved {} to {}", addr, out.display());
+    Ok(())
+}
@@ -0,0 +1,61 @@
+//! The `find-player` command: where matching nicknames are online.
+
+use crate::output::{draw, render_rows, CsvRecord, OutputFormat};
+use anyhow::Result;
+use colored::Colorize;
+use samp_query::finder::{find_player_with_config, FindConfig, NamePattern};
+use samp_query::DetailedPlayer;
+use serde::Serialize;
+use std::net::SocketAddr;
+use tabled::{Table, Tabled};
+
+#[derive(Debug, Clone, Serialize, Tabled)]
+pub struct Found {
+    #[tabled(rename = "Server")]
+    address: SocketAddr,
+    #[tabled(rename = "ID")]
+    id: u8,
+    #[tabled(rename = "Name")]
+    name: String,
+    #[tabled(rename = "Score")]
+    score: i32,
+    #[tabled(rename = "Ping")]
+    ping: u32,
+}
+
+impl CsvRecord for Found {
+    const HEADER: &'static [&'static str] = &["address", "id", "name", "score", "ping"];
+}
+
+impl Found {
+    fn new(address: SocketAddr, player: DetailedPlayer) -> Self {
+        Self {
+            address,
+            id: player.id,
+            name: player.name,
+            score: player.score,
+            ping: player.ping,
+        }
+    }
+}
+
+fn format_found(found: &[Found]) -> String {
+    if found.is_empty() {
+        return "No matching players online".yellow().to_string();
+    }
+    format!("{} ({})\n{}", "Players Found".green().bold(), found.len(), draw(Table::new(found)))
+}
+
+/// Queries the servers concurrently and prints the players whose nickname
+/// matches `pattern`, a case-insensitive regular expression.
+pub async fn run(pattern: &str, servers: &[SocketAddr], config: FindConfig, format: OutputFormat) -> Result<()> {
+    let pattern = NamePattern::regex(pattern)?;
+
+    let found: Vec<Found> = find_player_with_config(&pattern, servers, config)
+        .await
+        .into_iter()
+        .map(|(addr, player)| Found::new(addr, player))
+        .collect();
+    println!("{}", render_rows(format, &found, |found| format_found(found), |found| found.clone())?);
+    Ok(())
+}
@@ -0,0 +1,28 @@
+//! The `completions` and `man` commands: shell completion scripts and
+//! manual pages. Distribution packages generate both at build time with
+//! `cargo xtask assets`.
+
+use anyhow::{Context, Result};
+use clap::Command;
+use clap_complete::Shell;
+use std::io::Write;
+use std::path::Path;
+
+/// Prints the completion script of `shell` for `command`.
+pub fn completions(shell: Shell, mut command: Command) -> Result<()> {
+    // Generated into a buffer because the generator panics on write errors.
+    let mut script = Vec::new();
+    let name = command.get_name().to_string();
+    clap_complete::generate(shell, &mut command, name, &mut script);
+    std::io::stdout().write_all(&script).context("Failed to print the completion script")
+}
+
+/// Writes a manual page for `command` and one for each of its subcommands
+/// to `dir`, creating it if needed.
+pub fn man(command: Command, dir: &Path) -> Result<()> {
+    std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    clap_mangen::generate_to(command, dir)
+        .with_context(|| format!("Failed to write the manual pages to {}", dir.display()))?;
+    eprintln!("Wrote the manual pages to {}", dir.display());
+    Ok(())
+}
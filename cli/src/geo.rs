@@ -0,0 +1,17 @@
+//! The country and network shown next to servers with `--geo`.
+
+use colored::Colorize;
+use samp_query::geoip::GeoInfo;
+use serde::Serialize;
+
+/// A server's data with its location, as printed in JSON and YAML.
+#[derive(Serialize)]
+pub struct Located<'a, T> {
+    #[serde(flatten)]
+    pub server: &'a T,
+    pub geo: &'a GeoInfo,
+}
+
+pub fn format_location(geo: &GeoInfo) -> String {
+    format!("{}: {}", "Location".blue().bold(), geo)
+}
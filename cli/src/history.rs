@@ -0,0 +1,112 @@
+//! The `history` command: records servers to SQLite and reports on them.
+
+use crate::output::{clock, render, OutputFormat};
+use anyhow::{bail, Context, Result};
+use colored::Colorize;
+use samp_query::history::HistoryEntry;
+use samp_query::storage::{HistoryStats, SqliteStore};
+use samp_query::utils::unix_timestamp_ms;
+use samp_query::{Client, ClientConfig};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+/// Where the database is kept when neither `--db` nor the configuration
+/// file names one.
+pub fn default_db() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_DATA_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".local").join("share"),
+    };
+    Some(base.join("samp-query").join("history.db"))
+}
+
+/// Opens the database, creating its directory if needed.
+pub fn open(path: &Path) -> Result<SqliteStore> {
+    if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir).with_context(|| format!("Failed to create {}", dir.display()))?;
+    }
+    SqliteStore::open(path).with_context(|| format!("Failed to open {}", path.display()))
+}
+
+/// Polls `addr` every `interval` and stores each observation, including
+/// failed polls as offline, until interrupted.
+pub async fn record(addr: SocketAddr, interval: Duration, config: ClientConfig, db: &Path) -> Result<()> {
+    let store = open(db)?;
+    let client = Client::connect_with_config(addr, config)
+        .await
+        .context("Failed to connect to server")?;
+    eprintln!("Recording {} to {} every {:?}", addr, db.display(), interval);
+
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    loop {
+        ticker.tick().await;
+        match client.snapshot_concurrent().await {
+            Ok(snapshot) => {
+                store.record_snapshot(&snapshot)?;
+                let ping = snapshot.ping.map_or_else(String::new, |ping| format!(", {} ms", ping.ping_ms));
+                println!(
+                    "{} {}/{} players{}",
+                    clock(snapshot.timestamp).dimmed(),
+                    snapshot.info.players,
+                    snapshot.info.max_players,
+                    ping
+                );
+            }
+            Err(e) => {
+                let timestamp = unix_timestamp_ms();
+                store.record_entry(addr, &HistoryEntry::offline(timestamp), None, None)?;
+                println!("{} {}", clock(timestamp).dimmed(), format!("offline: {}", e).red());
+            }
+        }
+    }
+}
+
+fn format_stats(addr: SocketAddr, since: Duration, stats: &HistoryStats) -> String {
+    let mut output = format!(
+        "{} for {} over the last {}\n",
+        "History".green().bold(),
+        addr,
+        humanize(since)
+    );
+    output.push_str(&format!("Samples: {}\n", stats.samples));
+    match (stats.peak_players, stats.peak_at) {
+        (Some(peak), Some(at)) => output.push_str(&format!("Peak players: {} at {}\n", peak.to_string().green(), clock(at))),
+        _ => output.push_str("Peak players: -\n"),
+    }
+    match stats.average_ping_ms {
+        Some(ping) => output.push_str(&format!("Average ping: {:.1} ms\n", ping)),
+        None => output.push_str("Average ping: -\n"),
+    }
+    match stats.uptime_percentage {
+        Some(uptime) => output.push_str(&format!("Uptime: {:.1}%", uptime)),
+        None => output.push_str("Uptime: -"),
+    }
+    output
+}
+
+fn humanize(duration: Duration) -> String {
+    let seconds = duration.as_secs();
+    match seconds {
+        s if s % 604_800 == 0 && s >= 2 * 604_800 => format!("{}w", s / 604_800),
+        s if s % 86_400 == 0 && s >= 2 * 86_400 => format!("{}d", s / 86_400),
+        s if s % 3600 == 0 && s > 0 => format!("{}h", s / 3600),
+        s if s % 60 == 0 && s > 0 => format!("{}m", s / 60),
+        s => format!("{}s", s),
+    }
+}
+
+/// Prints the peak player count, average ping and uptime recorded for
+/// `addr` within `since`.
+pub fn stats(addr: SocketAddr, since: Duration, db: &Path, format: OutputFormat) -> Result<()> {
+    if !db.exists() {
+        bail!("No history database at {}; run history record first", db.display());
+    }
+    let store = open(db)?;
+    let from = unix_timestamp_ms().saturating_sub(since.as_millis() as u64);
+    let stats = store.stats(addr, from)?;
+
+    println!("{}", render(format, &stats, |stats| format_stats(addr, since, stats))?);
+    Ok(())
+}
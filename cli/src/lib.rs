@@ -0,0 +1,1082 @@
+//! Command-line interface for the SAMP Query library.
+
+use anyhow::{anyhow, bail, Context, Result};
+use clap::{Args, CommandFactory, Parser, Subcommand, ValueEnum};
+use clap_complete::Shell;
+use colored::Colorize;
+use regex::RegexBuilder;
+use samp_query::exporter::prometheus::{ExporterConfig, PrometheusExporter};
+use samp_query::finder::FindConfig;
+use samp_query::monitor::MonitorConfig;
+use samp_query::packet::explain;
+use samp_query::pipeline::PipelineConfig;
+use samp_query::players::{PlayerFilter, PlayerSort};
+use samp_query::protocol::constants;
+use samp_query::ratelimit::RateLimiter;
+use samp_query::scanner::{Ipv4Cidr, PortRange, ScanConfig};
+use samp_query::utils::{parse_duration, Backoff, RetryPolicy};
+use samp_query::{Client, ClientConfig};
+use std::io::IsTerminal;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::Duration;
+use tracing_subscriber::EnvFilter;
+
+mod batch;
+mod bench;
+mod check;
+mod compare;
+mod daemon;
+mod diff;
+mod exit;
+mod export;
+mod find;
+pub mod generate;
+mod geo;
+mod history;
+mod monitor;
+mod omp;
+mod output;
+mod rcon;
+mod rcon_shell;
+mod resolve;
+mod scan;
+mod settings;
+mod stress;
+mod targets;
+mod template;
+mod top;
+mod tui;
+mod watch;
+use output::{
+    format_detailed_player_list, format_player_list, format_player_list_changes, format_rules, format_server_info,
+    format_server_info_changes, format_snapshot, highlight, render, render_rows, rule_records, ColorChoice, OutputFormat,
+    TableStyle,
+};
+use geo::{format_location, Located};
+use settings::{RetryBackoff, Settings};
+use template::Template;
+use stress::StressQuery;
+use watch::PlayerAlerts;
+
+#[derive(Parser)]
+#[command(author, version, about, long_about = None)]
+struct Cli {
+    ///subcommand
+    #[command(subcommand)]
+    command: Commands,
+
+    ///timeout in milliseconds [default: 1000]
+    #[arg(short, long)]
+    timeout: Option<u64>,
+
+    ///number of retries [default: 3]
+    #[arg(short, long)]
+    retries: Option<usize>,
+
+    ///pause between query attempts: none, fixed or exponential [default: none, or exponential with --retry-delay or --retry-max-elapsed]
+    #[arg(long, value_enum, global = true, value_name = "BACKOFF")]
+    retry_backoff: Option<RetryBackoff>,
+
+    ///milliseconds to wait before the first retry [default: 100]
+    #[arg(long, global = true, value_name = "MS")]
+    retry_delay: Option<u64>,
+
+    ///stop retrying a query after this long, e.g. 5s; without --retries, retries are then unlimited
+    #[arg(long, global = true, value_name = "DURATION", value_parser = parse_duration)]
+    retry_max_elapsed: Option<Duration>,
+
+    ///output format [default: table]
+    #[arg(short, long, value_enum, global = true)]
+    format: Option<OutputFormat>,
+
+    ///when to color output; auto colors terminals unless NO_COLOR is set [default: auto]
+    #[arg(long, value_enum, global = true)]
+    color: Option<ColorChoice>,
+
+    ///how tables are drawn; long values are truncated to fit the terminal [default: ascii]
+    #[arg(long, value_enum, global = true, value_name = "STYLE")]
+    table_style: Option<TableStyle>,
+
+    ///print one line rendered from the server snapshot instead, e.g. "{hostname} {players}/{max_players} ({ping}ms)"
+    #[arg(long, global = true, conflicts_with = "format")]
+    template: Option<String>,
+
+    ///print one address|online|players|max|ping line per server, for info, ping and all
+    #[arg(short, long, global = true, conflicts_with_all = ["format", "template"])]
+    quiet: bool,
+
+    ///servers queried at the same time by --targets, find-player and favorites [default: 32]
+    #[arg(long, global = true, value_name = "N")]
+    parallel: Option<usize>,
+
+    ///queries per second sent by --targets, find-player and scan, e.g. 500pps [default for scan: 100pps]
+    #[arg(long, global = true, value_parser = scan::parse_rate)]
+    rate: Option<u32>,
+
+    ///print an annotated hexdump of every query packet sent and received to stderr
+    #[arg(long, global = true)]
+    debug_packets: bool,
+
+    ///configuration file [default: ~/.config/samp-query/config.toml]
+    #[arg(long, global = true, value_name = "PATH")]
+    config: Option<PathBuf>,
+}
+
+#[derive(Subcommand)]
+enum Commands {
+    ///query server information
+    Info {
+        ///server address (HOST[:PORT])
+        #[arg(required_unless_present = "targets")]
+        address: Option<String>,
+        ///refresh every SECONDS, highlighting changed values
+        #[arg(long, value_name = "SECONDS")]
+        watch: Option<f64>,
+        ///query every server listed in FILE (one per line, - for stdin) instead
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["address", "watch"])]
+        targets: Option<PathBuf>,
+        ///show the server's country and network from the GeoIP databases in the configuration file
+        #[arg(long, conflicts_with_all = ["watch", "targets"])]
+        geo: bool,
+    },
+    ///query server rules
+    Rules {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///print only the value of this rule, failing if the server does not have it
+        #[arg(long, value_name = "NAME")]
+        key: Option<String>,
+        ///only list rules whose name or value matches this case-insensitive regular expression
+        #[arg(long, value_name = "REGEX", conflicts_with = "key")]
+        grep: Option<String>,
+    },
+    ///query player list
+    Players {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///refresh every SECONDS, highlighting changed values and who joined or left
+        #[arg(long, value_name = "SECONDS")]
+        watch: Option<f64>,
+        ///ring the terminal bell when players join or leave
+        #[arg(long, requires = "watch")]
+        bell: bool,
+        ///send a desktop notification when players join or leave
+        #[arg(long, requires = "watch")]
+        notify: bool,
+        ///only alert when NAME joins or leaves (repeatable; implies --bell without --notify)
+        #[arg(long, value_name = "NAME", requires = "watch")]
+        notify_on: Vec<String>,
+        #[command(flatten)]
+        filter: PlayerFilterArgs,
+    },
+    ///qery detailed player information
+    PlayersDetailed {
+        ///server address (HOST[:PORT])
+        address: String,
+        #[command(flatten)]
+        filter: PlayerFilterArgs,
+    },
+    ///query server ping
+    Ping {
+        ///server address (HOST[:PORT])
+        #[arg(required_unless_present = "targets")]
+        address: Option<String>,
+        ///refresh every SECONDS, highlighting changed values
+        #[arg(long, value_name = "SECONDS")]
+        watch: Option<f64>,
+        ///query every server listed in FILE (one per line, - for stdin) instead
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["address", "watch"])]
+        targets: Option<PathBuf>,
+    },
+    ///query info, rules, detailed players and ping at once
+    All {
+        ///server address (HOST[:PORT])
+        #[arg(required_unless_present = "targets")]
+        address: Option<String>,
+        ///query every server listed in FILE (one per line, - for stdin) instead
+        #[arg(long, value_name = "FILE", conflicts_with_all = ["address"])]
+        targets: Option<PathBuf>,
+    },
+    ///show whether a server runs open.mp, with its open.mp rules
+    OmpInfo {
+        ///server address (HOST[:PORT])
+        address: String,
+    },
+    ///compare the info, key rules and ping of two servers
+    Compare {
+        ///first server address (HOST[:PORT])
+        first: String,
+        ///second server address (HOST[:PORT])
+        second: String,
+    },
+    ///show joined and left players and changed info and rules since a saved snapshot
+    Diff {
+        ///server address (HOST[:PORT]), or a second saved snapshot file
+        target: String,
+        ///snapshot saved with `all --format json` to compare against
+        #[arg(long, value_name = "FILE")]
+        against: PathBuf,
+    },
+    ///show what a server address resolves to and which of its addresses answer, to debug unreachable servers
+    Resolve {
+        ///server address (HOST[:PORT])
+        address: String,
+    },
+    ///save a full snapshot of a server to a file, for archiving and for diff --against
+    Export {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///file to write; .json, .toml or .csv picks the format
+        #[arg(short, long, value_name = "FILE")]
+        out: PathBuf,
+    },
+    ///execute RCON command
+    #[command(override_usage = "samp-query-cli rcon [OPTIONS] <ADDRESS> [PASSWORD] <COMMAND>\n       samp-query-cli rcon [OPTIONS] --file <PATH> <ADDRESS> [PASSWORD]")]
+    Rcon {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///the RCON command, optionally preceded by the password; without it the password is read from
+        ///--password-file or SAMP_RCON_PASSWORD, or prompted for. With --file, only the password
+        #[arg(required_unless_present = "file", num_args = 1..=2, value_name = "ARGS")]
+        args: Vec<String>,
+        ///read the RCON password from the first line of PATH
+        #[arg(long, value_name = "PATH")]
+        password_file: Option<PathBuf>,
+        ///run the commands in PATH one after another, one per line
+        #[arg(long, value_name = "PATH")]
+        file: Option<PathBuf>,
+        ///with --file, stop at the first failing command instead of reporting it and going on
+        #[arg(long, requires = "file")]
+        stop_on_error: bool,
+        ///with --file, milliseconds to wait after each command
+        #[arg(long, value_name = "MS", default_value = "0", requires = "file")]
+        delay: u64,
+    },
+    ///interactive RCON console
+    RconShell {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///read the RCON password from the first line of PATH instead of SAMP_RCON_PASSWORD or a prompt
+        #[arg(long, value_name = "PATH")]
+        password_file: Option<PathBuf>,
+    },
+    ///monitor a server, printing changes and alerting on conditions
+    Monitor {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///seconds between polls
+        #[arg(short, long, default_value = "30", value_parser = parse_interval)]
+        interval: Duration,
+        ///alert when the server stops answering
+        #[arg(long)]
+        alert_offline: bool,
+        ///alert when the player count drops by this many players, or by a percentage like 50%
+        #[arg(long, value_name = "DROP")]
+        alert_player_drop: Option<monitor::PlayerDrop>,
+        #[command(flatten)]
+        webhooks: WebhookArgs,
+    },
+    ///discover servers in CIDR and port ranges
+    Scan {
+        ///networks to scan, e.g. 203.0.113.0/24
+        #[arg(required = true)]
+        ranges: Vec<Ipv4Cidr>,
+        ///ports and port ranges to probe, e.g. 7777-7787,8000
+        #[arg(short, long, default_value = "7777", value_delimiter = ',')]
+        ports: Vec<PortRange>,
+        ///show each server's country and network from the GeoIP databases in the configuration file
+        #[arg(long)]
+        geo: bool,
+    },
+    ///find where players matching a nickname pattern are online
+    FindPlayer {
+        ///case-insensitive regular expression, e.g. "Nick.*"
+        pattern: String,
+        ///file listing one server address per line; all favorite servers if omitted
+        #[arg(long, value_name = "FILE")]
+        servers: Option<PathBuf>,
+    },
+    ///record servers to a SQLite database and report on them
+    History {
+        #[command(subcommand)]
+        command: HistoryCommand,
+        ///database file [default: ~/.local/share/samp-query/history.db]
+        #[arg(long, global = true, value_name = "PATH")]
+        db: Option<PathBuf>,
+    },
+    ///monitor every server in the [[monitors]] table of the configuration file, recording history and sending notifications
+    Daemon {
+        ///default seconds between polls of a server
+        #[arg(short, long, default_value = "30", value_parser = parse_interval)]
+        interval: Duration,
+        ///history database [default: ~/.local/share/samp-query/history.db]
+        #[arg(long, value_name = "PATH")]
+        db: Option<PathBuf>,
+        #[command(flatten)]
+        webhooks: WebhookArgs,
+    },
+    ///flood a server you run with queries at a fixed rate and report loss and latency
+    Stress {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///queries sent per second
+        #[arg(long, default_value = "100")]
+        qps: u32,
+        ///how long to send for, e.g. 60s or 5m
+        #[arg(long, default_value = "10s", value_parser = parse_duration)]
+        duration: Duration,
+        ///query type to send
+        #[arg(long, value_enum, default_value_t)]
+        query: StressQuery,
+    },
+    ///measure how many complete info queries per second are achieved over a server list
+    Bench {
+        ///servers to query, one per line (- for stdin)
+        #[arg(long, value_name = "FILE")]
+        targets: PathBuf,
+        ///query the whole list this many times
+        #[arg(long, default_value = "1", value_name = "N")]
+        repeat: usize,
+    },
+    ///check a server as a Nagios/Icinga plugin, exiting 0 (OK), 1 (WARNING), 2 (CRITICAL) or 3 (UNKNOWN)
+    Check {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///warn when the ping exceeds MS milliseconds
+        #[arg(long, value_name = "MS")]
+        warn_ping: Option<u64>,
+        ///critical when the ping exceeds MS milliseconds
+        #[arg(long, value_name = "MS")]
+        crit_ping: Option<u64>,
+        ///warn when fewer than N player slots are free
+        #[arg(long, value_name = "N")]
+        warn_players_free: Option<u32>,
+        ///critical when fewer than N player slots are free
+        #[arg(long, value_name = "N")]
+        crit_players_free: Option<u32>,
+    },
+    ///serve Prometheus metrics for a list of servers
+    ServeMetrics {
+        ///file listing one server address per line
+        #[arg(long, value_name = "FILE")]
+        targets: PathBuf,
+        ///address the /metrics endpoint listens on
+        #[arg(long, default_value = "0.0.0.0:9777")]
+        listen: SocketAddr,
+        ///seconds between polls
+        #[arg(short, long, default_value = "15", value_parser = parse_interval)]
+        interval: Duration,
+    },
+    ///live dashboard with info, players and a ping graph
+    Tui {
+        ///server addresses (HOST[:PORT]); all favorite servers if omitted
+        addresses: Vec<String>,
+        ///seconds between refreshes
+        #[arg(short, long, default_value = "5", value_parser = parse_interval)]
+        interval: Duration,
+    },
+    ///full-screen player table of one server, sorted by score or ping, with joins and leaves marked
+    Top {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///seconds between refreshes
+        #[arg(short, long, default_value = "2", value_parser = parse_interval)]
+        interval: Duration,
+        ///column to sort players by; press s to switch
+        #[arg(short, long, value_enum, default_value_t)]
+        sort: top::TopSort,
+    },
+    ///print a shell completion script for bash, zsh, fish, elvish or powershell
+    Completions {
+        ///shell to complete for
+        #[arg(value_enum)]
+        shell: Shell,
+    },
+    ///write manual pages for the tool and every command
+    Man {
+        ///directory to write the pages to
+        #[arg(short, long, value_name = "DIR", default_value = "man")]
+        out: PathBuf,
+    },
+}
+
+#[derive(Subcommand)]
+enum HistoryCommand {
+    ///poll a server periodically and record every observation
+    Record {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///seconds between polls
+        #[arg(short, long, default_value = "60", value_parser = parse_interval)]
+        interval: Duration,
+    },
+    ///print peak players, average ping and uptime from the recorded history
+    Stats {
+        ///server address (HOST[:PORT])
+        address: String,
+        ///how far back to look, e.g. 30m, 24h or 7d
+        #[arg(long, default_value = "24h", value_parser = parse_duration)]
+        since: Duration,
+    },
+}
+
+#[derive(Debug, Clone, Copy, ValueEnum)]
+enum SortKey {
+    Score,
+    Name,
+    Ping,
+}
+
+#[derive(Args)]
+struct WebhookArgs {
+    ///POST notifications to this URL or to a webhook named in the configuration file: alerts for monitor, every change for daemon; may be repeated
+    #[arg(long = "webhook-url", alias = "webhook", value_name = "URL")]
+    webhook_urls: Vec<String>,
+    ///payload to post: the event as JSON, a Discord message, or auto to pick Discord for Discord webhook URLs
+    #[arg(long, value_enum, default_value_t, value_name = "FORMAT")]
+    webhook_format: monitor::WebhookFormat,
+}
+
+impl WebhookArgs {
+    fn webhooks(&self, settings: &Settings) -> Result<Vec<monitor::Webhook>> {
+        self.webhook_urls
+            .iter()
+            .map(|webhook| Ok(monitor::Webhook::new(settings.webhook(webhook)?, self.webhook_format)))
+            .collect()
+    }
+}
+
+#[derive(Args)]
+struct PlayerFilterArgs {
+    ///order players by score, name or ping (ascending unless --desc)
+    #[arg(long, value_enum)]
+    sort: Option<SortKey>,
+    ///reverse the order given by --sort
+    #[arg(long, requires = "sort")]
+    desc: bool,
+    ///only list players with at least this score
+    #[arg(long, value_name = "N", allow_negative_numbers = true)]
+    min_score: Option<i64>,
+    ///only list players whose name matches this case-insensitive regular expression
+    #[arg(long, value_name = "REGEX")]
+    name_filter: Option<String>,
+    ///only list the first N players, after sorting
+    #[arg(long, value_name = "N")]
+    top: Option<usize>,
+}
+
+impl PlayerFilterArgs {
+    fn filter(self) -> Result<PlayerFilter> {
+        let filter = PlayerFilter {
+            sort: self.sort.map(|sort| match sort {
+                SortKey::Score => PlayerSort::Score,
+                SortKey::Name => PlayerSort::Name,
+                SortKey::Ping => PlayerSort::Ping,
+            }),
+            descending: self.desc,
+            min_score: self.min_score,
+            name: None,
+            top: self.top,
+        };
+        Ok(match &self.name_filter {
+            Some(pattern) => filter.name_pattern(pattern)?,
+            None => filter,
+        })
+    }
+}
+
+impl Commands {
+    /// The queried server, for the commands `--template` applies to.
+    fn template_address(&self) -> Option<&str> {
+        match self {
+            Commands::Info { address, .. } | Commands::Ping { address, .. } | Commands::All { address, .. } => {
+                address.as_deref()
+            }
+            Commands::Rules { address, .. }
+            | Commands::Players { address, .. }
+            | Commands::PlayersDetailed { address, .. } => Some(address),
+            Commands::OmpInfo { .. }
+            | Commands::Compare { .. }
+            | Commands::Diff { .. }
+            | Commands::Export { .. }
+            | Commands::Resolve { .. }
+            | Commands::Rcon { .. }
+            | Commands::RconShell { .. }
+            | Commands::Monitor { .. }
+            | Commands::Scan { .. }
+            | Commands::FindPlayer { .. }
+            | Commands::History { .. }
+            | Commands::Daemon { .. }
+            | Commands::Stress { .. }
+            | Commands::Bench { .. }
+            | Commands::Check { .. }
+            | Commands::ServeMetrics { .. }
+            | Commands::Tui { .. }
+            | Commands::Top { .. }
+            | Commands::Completions { .. }
+            | Commands::Man { .. } => None,
+        }
+    }
+
+    /// The query and server list of the commands run with `--targets`.
+    fn batch(&self) -> Option<(batch::Query, &Path)> {
+        match self {
+            Commands::Info { targets: Some(path), .. } => Some((batch::Query::Info, path)),
+            Commands::Ping { targets: Some(path), .. } => Some((batch::Query::Ping, path)),
+            Commands::All { targets: Some(path), .. } => Some((batch::Query::All, path)),
+            _ => None,
+        }
+    }
+}
+
+/// The filter for `players`, whose client list has no pings to sort by.
+fn client_list_filter(args: PlayerFilterArgs) -> Result<PlayerFilter> {
+    if matches!(args.sort, Some(SortKey::Ping)) {
+        bail!("The player list has no pings; use players-detailed --sort ping");
+    }
+    args.filter()
+}
+
+/// The retry policy given by the `--retry-*` flags or settings, or `None`
+/// for back-to-back attempts.
+fn retry_policy(cli: &Cli, settings: &Settings) -> Result<Option<RetryPolicy>> {
+    let max_elapsed = match (cli.retry_max_elapsed, &settings.retry_max_elapsed) {
+        (Some(max_elapsed), _) => Some(max_elapsed),
+        (None, Some(max_elapsed)) => {
+            Some(parse_duration(max_elapsed).map_err(|e| anyhow!("Invalid retry_max_elapsed: {}", e))?)
+        }
+        (None, None) => None,
+    };
+    let backoff = cli.retry_backoff.or(settings.retry_backoff);
+    let delay = cli.retry_delay.or(settings.retry_delay);
+    if backoff.is_none() && delay.is_none() && max_elapsed.is_none() {
+        return Ok(None);
+    }
+
+    let max_attempts = match cli.retries.or(settings.retries) {
+        Some(retries) => retries,
+        None if max_elapsed.is_some() => usize::MAX,
+        None => constants::MAX_RETRIES,
+    };
+    let default = RetryPolicy::default();
+    Ok(Some(RetryPolicy {
+        max_attempts,
+        backoff: backoff.map_or(default.backoff, Backoff::from),
+        initial_delay: delay.map_or(default.initial_delay, Duration::from_millis),
+        max_elapsed,
+        ..default
+    }))
+}
+
+/// Parses a positive number of seconds between polls or refreshes; a zero
+/// interval would make the tokio ticker panic.
+fn parse_interval(s: &str) -> Result<Duration, String> {
+    match s.trim().parse::<f64>().ok().and_then(|seconds| Duration::try_from_secs_f64(seconds).ok()) {
+        Some(interval) if !interval.is_zero() => Ok(interval),
+        _ => Err(format!("expected a positive number of seconds, got {}", s)),
+    }
+}
+
+/// The refresh interval of `--watch`, which only renders tables.
+fn watch_interval(seconds: f64, format: OutputFormat) -> Result<Duration> {
+    if format != OutputFormat::Table {
+        bail!("--watch only supports table output");
+    }
+    Duration::try_from_secs_f64(seconds).context("Invalid watch interval")
+}
+
+/// The command line, for generating completion scripts and manual pages.
+pub fn command() -> clap::Command {
+    Cli::command()
+}
+
+/// Runs the command given on the command line, printing any error.
+pub async fn main() -> ExitCode {
+    match run().await {
+        Ok(code) => code,
+        Err(e) => {
+            eprintln!("Error: {:?}", e);
+            exit::code(&e)
+        }
+    }
+}
+
+async fn run() -> Result<ExitCode> {
+    let cli = match Cli::try_parse() {
+        Ok(cli) => cli,
+        Err(e) => {
+            // clap would exit with 2 on usage errors, the code of a timeout.
+            let _ = e.print();
+            return Ok(if e.use_stderr() {
+                ExitCode::from(exit::FAILURE)
+            } else {
+                ExitCode::SUCCESS
+            });
+        }
+    };
+    let mut filter = EnvFilter::new("info");
+    if cli.debug_packets {
+        filter = filter.add_directive(format!("{}=trace", explain::TRACE_TARGET).parse()?);
+    }
+    tracing_subscriber::fmt()
+        .with_env_filter(filter)
+        .with_writer(std::io::stderr)
+        .with_ansi(std::io::stderr().is_terminal())
+        .init();
+
+    let settings = Settings::load(cli.config.as_deref())?;
+    cli.color.or(settings.color).unwrap_or_default().apply();
+    cli.table_style.or(settings.table_style).unwrap_or_default().apply();
+
+    let timeout_ms = cli.timeout.or(settings.timeout).unwrap_or(constants::DEFAULT_TIMEOUT_MS);
+    let config = ClientConfig {
+        timeout_ms,
+        max_retries: cli.retries.or(settings.retries).unwrap_or(constants::MAX_RETRIES),
+        retry: retry_policy(&cli, &settings)?,
+    };
+
+    let format = cli.format.or(settings.format).unwrap_or_default();
+    let parallel = cli.parallel.or(settings.parallel);
+    let rate = cli.rate.or(settings.rate);
+    let limiter = rate.map(|rate| RateLimiter::new(rate as f64));
+
+    if let Some(template) = &cli.template {
+        let Some(address) = cli.command.template_address() else {
+            bail!("--template needs a single server and is only supported by info, rules, players, players-detailed, ping and all");
+        };
+        let template = Template::parse(template)?;
+        let addr = settings.server_address(address).await?;
+        let client = Client::connect_with_config(addr, config)
+            .await
+            .context("Failed to connect to server")?;
+
+        let snapshot = client.snapshot_concurrent().await.context("Failed to query server")?;
+        println!("{}", template.render(&snapshot));
+        return Ok(ExitCode::SUCCESS);
+    }
+
+    let batch_query = cli.command.batch();
+    if cli.quiet || batch_query.is_some() {
+        let config = PipelineConfig {
+            client: config,
+            max_in_flight: parallel.unwrap_or(batch::DEFAULT_PARALLEL),
+            limiter,
+            ..PipelineConfig::default()
+        };
+        if let (Some((query, path)), false) = (batch_query, cli.quiet) {
+            let servers = targets::read(path, &settings).await?;
+            return batch::run(query, servers, config, format).await;
+        }
+
+        let servers = match &cli.command {
+            Commands::Info { targets: Some(path), .. }
+            | Commands::Ping { targets: Some(path), .. }
+            | Commands::All { targets: Some(path), .. } => targets::read(path, &settings).await?,
+            Commands::Info { address: Some(address), .. }
+            | Commands::Ping { address: Some(address), .. }
+            | Commands::All { address: Some(address), .. } => vec![settings.server_address(address).await?.into()],
+            _ => bail!("--quiet is only supported by info, ping and all"),
+        };
+        return batch::quiet(servers, config).await.map(|()| ExitCode::SUCCESS);
+    }
+
+    match cli.command {
+        Commands::Info {
+            address: Some(address),
+            watch: Some(seconds),
+            ..
+        } => {
+            let interval = watch_interval(seconds, format)?;
+            let addr = settings.server_address(&address).await?;
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+
+            watch::watch(
+                &address,
+                interval,
+                || async { client.query_info().await.context("Failed to query server info") },
+                format_server_info_changes,
+            )
+            .await?;
+        }
+        Commands::Info {
+            address: Some(address),
+            watch: None,
+            geo,
+            ..
+        } => {
+            let geoip = geo.then(|| settings.geoip()).transpose()?;
+            let addr = settings.server_address(&address).await?;
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+
+            let info = client.query_info().await.context("Failed to query server info")?;
+            match geoip {
+                Some(geoip) => {
+                    let located = Located {
+                        server: &info,
+                        geo: &geoip.lookup(addr.ip()),
+                    };
+                    println!(
+                        "{}",
+                        render(format, &located, |located| format!(
+                            "{}\n{}",
+                            format_server_info(located.server).trim_end(),
+                            format_location(located.geo)
+                        ))?
+                    );
+                }
+                None => println!("{}", render(format, &info, format_server_info)?),
+            }
+        }
+        Commands::Rules { address, key, grep } => {
+            let grep = grep
+                .map(|pattern| RegexBuilder::new(&pattern).case_insensitive(true).build())
+                .transpose()
+                .context("Invalid --grep pattern")?;
+            let addr = settings.server_address(&address).await?;
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+
+            let mut rules = client.query_rules().await.context("Failed to query server rules")?;
+            if let Some(key) = key {
+                let Some(value) = rules.rules.remove(&key) else {
+                    bail!("The server has no {:?} rule", key);
+                };
+                println!("{}", render(format, &value, String::clone)?);
+                return Ok(ExitCode::SUCCESS);
+            }
+            if let Some(grep) = grep {
+                rules.rules.retain(|name, value| grep.is_match(name) || grep.is_match(value));
+            }
+            println!("{}", render_rows(format, &rules, format_rules, rule_records)?);
+        }
+        Commands::Players {
+            address,
+            watch: Some(seconds),
+            bell,
+            notify,
+            notify_on,
+            filter,
+        } => {
+            let interval = watch_interval(seconds, format)?;
+            let filter = client_list_filter(filter)?;
+            let addr = settings.server_address(&address).await?;
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+            let alerts = PlayerAlerts {
+                bell: bell || (!notify && !notify_on.is_empty()),
+                notify,
+                names: notify_on,
+            };
+
+            watch::watch(
+                &address,
+                interval,
+                || async {
+                    let mut players = client.query_client_list().await.context("Failed to query player list")?;
+                    filter.apply(&mut players.players);
+                    Ok(players)
+                },
+                |players, previous| {
+                    let screen = format_player_list_changes(players, previous);
+                    format!("{}{}", screen, alerts.alert(&address, players, previous))
+                },
+            )
+            .await?;
+        }
+        Commands::Players {
+            address,
+            watch: None,
+            filter,
+            ..
+        } => {
+            let filter = client_list_filter(filter)?;
+            let addr = settings.server_address(&address).await?;
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+
+            let mut players = client
+                .query_client_list()
+                .await
+                .context("Failed to query player list")?;
+            filter.apply(&mut players.players);
+            println!(
+                "{}",
+                render_rows(format, &players, format_player_list, |list| list.players.clone())?
+            );
+        }
+        Commands::PlayersDetailed { address, filter } => {
+            let filter = filter.filter()?;
+            let addr = settings.server_address(&address).await?;
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+
+            let mut players = client
+                .query_detailed_player_info()
+                .await
+                .context("Failed to query detailed player info")?;
+            filter.apply(&mut players.players);
+            println!(
+                "{}",
+                render_rows(format, &players, format_detailed_player_list, |list| list.players.clone())?
+            );
+        }
+        Commands::Ping {
+            address: Some(address),
+            watch: Some(seconds),
+            ..
+        } => {
+            let interval = watch_interval(seconds, format)?;
+            let addr = settings.server_address(&address).await?;
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+
+            watch::watch(
+                &address,
+                interval,
+                || async { client.query_ping().await.context("Failed to query server ping") },
+                |ping, previous| {
+                    let changed = previous.is_some_and(|previous| previous.ping_ms != ping.ping_ms);
+                    format!("Ping: {}", highlight(format!("{} ms", ping.ping_ms), changed))
+                },
+            )
+            .await?;
+        }
+        Commands::Ping {
+            address: Some(address),
+            watch: None,
+            ..
+        } => {
+            let addr = settings.server_address(&address).await?;
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+
+            let ping = client.query_ping().await.context("Failed to query server ping")?;
+            println!(
+                "{}",
+                render(format, &ping, |ping| format!("Ping: {} ms", ping.ping_ms).green().to_string())?
+            );
+        }
+        Commands::All {
+            address: Some(address), ..
+        } => {
+            let addr = settings.server_address(&address).await?;
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+
+            let snapshot = client.snapshot_concurrent().await.context("Failed to query server")?;
+            println!("{}", render(format, &snapshot, format_snapshot)?);
+        }
+        Commands::Info { address: None, .. } | Commands::Ping { address: None, .. } | Commands::All { address: None, .. } => {
+            unreachable!("clap requires an address unless --targets is given")
+        }
+        Commands::OmpInfo { address } => {
+            let addr = settings.server_address(&address).await?;
+            omp::run(addr, config, format).await?;
+        }
+        Commands::Compare { first, second } => {
+            let (first, second) = tokio::try_join!(settings.server_address(&first), settings.server_address(&second))?;
+            compare::run(first, second, config, format).await?;
+        }
+        Commands::Diff { target, against } => {
+            diff::run(&target, &against, &settings, config, format).await?;
+        }
+        Commands::Resolve { address } => {
+            resolve::run(settings.server_host(&address), config.timeout_ms, format).await?;
+        }
+        Commands::Export { address, out } => {
+            let addr = settings.server_address(&address).await?;
+            export::run(addr, config, &out).await?;
+        }
+        Commands::Rcon {
+            address,
+            mut args,
+            password_file,
+            file,
+            stop_on_error,
+            delay,
+        } => {
+            let addr = settings.server_address(&address).await?;
+            let command = match file {
+                Some(_) if args.len() > 1 => bail!("Give either an RCON command or --file"),
+                Some(_) => None,
+                None => args.pop(),
+            };
+            let password = match args.pop() {
+                Some(_) if password_file.is_some() => {
+                    bail!("Give the RCON password either as an argument or with --password-file")
+                }
+                Some(password) => password,
+                None => rcon::password(addr, password_file.as_deref())?,
+            };
+
+            if let Some(file) = file {
+                let delay = Duration::from_millis(delay);
+                rcon::run_file(addr, password, config, &file, stop_on_error, delay, format).await?;
+                return Ok(ExitCode::SUCCESS);
+            }
+            let client = Client::connect_with_config(addr, config)
+                .await
+                .context("Failed to connect to server")?;
+
+            let response = client
+                .rcon_command(&password, &command.unwrap_or_default())
+                .await
+                .context("Failed to execute RCON command")?;
+            println!("{}", render(format, &response, |response| response.message.clone())?);
+        }
+        Commands::RconShell { address, password_file } => {
+            let addr = settings.server_address(&address).await?;
+            let password = rcon::password(addr, password_file.as_deref())?;
+            rcon_shell::run(addr, password, config).await?;
+        }
+        Commands::Monitor {
+            address,
+            interval,
+            alert_offline,
+            alert_player_drop,
+            webhooks,
+        } => {
+            let addr = settings.server_address(&address).await?;
+            let webhooks = webhooks.webhooks(&settings)?;
+            let config = MonitorConfig {
+                client: config,
+                interval,
+                ..MonitorConfig::default()
+            };
+            let alerts = monitor::Alerts {
+                offline: alert_offline,
+                player_drop: alert_player_drop,
+            };
+
+            return monitor::run(addr, config, alerts, webhooks, format).await;
+        }
+        Commands::Scan { ranges, ports, geo } => {
+            let geoip = geo.then(|| settings.geoip()).transpose()?;
+            let config = ScanConfig {
+                ranges,
+                ports,
+                rate_pps: rate.unwrap_or(ScanConfig::default().rate_pps),
+                timeout: Duration::from_millis(timeout_ms),
+                ..ScanConfig::default()
+            };
+
+            scan::run(config, format, geoip).await?;
+        }
+        Commands::FindPlayer { pattern, servers } => {
+            let servers = match servers {
+                Some(path) => targets::read_addresses(&path, &settings).await?,
+                None => settings.favorites().await?,
+            };
+            let config = FindConfig {
+                client: config,
+                concurrency: parallel.unwrap_or(batch::DEFAULT_PARALLEL),
+                limiter,
+            };
+            find::run(&pattern, &servers, config, format).await?;
+        }
+        Commands::Daemon { interval, db, webhooks } => {
+            let Some(db) = db.or(settings.history_db.clone()).or_else(history::default_db) else {
+                bail!("No history database given; pass --db");
+            };
+            let webhooks = webhooks.webhooks(&settings)?;
+            daemon::run(&settings, &db, interval, config, parallel, webhooks).await?;
+        }
+        Commands::History { command, db } => {
+            let Some(db) = db.or(settings.history_db.clone()).or_else(history::default_db) else {
+                bail!("No history database given; pass --db");
+            };
+            match command {
+                HistoryCommand::Record { address, interval } => {
+                    let addr = settings.server_address(&address).await?;
+                    history::record(addr, interval, config, &db).await?;
+                }
+                HistoryCommand::Stats { address, since } => {
+                    let addr = settings.server_address(&address).await?;
+                    history::stats(addr, since, &db, format)?;
+                }
+            }
+        }
+        Commands::Stress {
+            address,
+            qps,
+            duration,
+            query,
+        } => {
+            let addr = settings.server_address(&address).await?;
+            stress::run(addr, qps, duration, query, Duration::from_millis(timeout_ms), format).await?;
+        }
+        Commands::Bench { targets, repeat } => {
+            let servers = targets::read_addresses(&targets, &settings).await?;
+            let config = PipelineConfig {
+                client: config,
+                max_in_flight: parallel.unwrap_or(batch::DEFAULT_PARALLEL),
+                limiter,
+                ..PipelineConfig::default()
+            };
+            bench::run(servers, repeat, config, rate, format).await?;
+        }
+        Commands::Check {
+            address,
+            warn_ping,
+            crit_ping,
+            warn_players_free,
+            crit_players_free,
+        } => {
+            let thresholds = check::Thresholds {
+                warn_ping,
+                crit_ping,
+                warn_players_free,
+                crit_players_free,
+            };
+            check::run(&settings, &address, thresholds, config).await;
+        }
+        Commands::ServeMetrics {
+            targets,
+            listen,
+            interval,
+        } => {
+            let config = ExporterConfig {
+                servers: targets::read_addresses(&targets, &settings).await?,
+                interval,
+                client: config,
+                bind: listen,
+                ..ExporterConfig::default()
+            };
+
+            eprintln!("Serving metrics for {} servers on http://{}/metrics", config.servers.len(), listen);
+            PrometheusExporter::new(config)
+                .run()
+                .await
+                .context("Failed to serve metrics")?;
+        }
+        Commands::Tui { addresses, interval } => {
+            let servers = if addresses.is_empty() {
+                settings.favorites().await?
+            } else {
+                settings.server_addresses(addresses.iter().map(String::as_str)).await?
+            };
+            tui::run(servers, interval, config).await?;
+        }
+        Commands::Completions { shell } => generate::completions(shell, Cli::command())?,
+        Commands::Man { out } => generate::man(Cli::command(), &out)?,
+        Commands::Top { address, interval, sort } => {
+            let addr = settings.server_address(&address).await?;
+            top::run(addr, interval, sort, config).await?;
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
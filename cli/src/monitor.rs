@@ -0,0 +1,227 @@
+//! The `monitor` command: streams monitor events and raises alerts.
+
+use crate::exit;
+use crate::output::{clock, OutputFormat};
+use anyhow::{bail, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use futures::StreamExt;
+use samp_query::monitor::{Monitor, MonitorConfig, MonitorEvent, MonitorEventKind};
+use samp_query::notify::discord::{DiscordNotifier, Embed, WebhookMessage};
+use samp_query::notify::webhook::WebhookSink;
+use samp_query::notify::EventSink;
+use std::net::SocketAddr;
+use std::process::ExitCode;
+use std::str::FromStr;
+
+/// How large a drop in the player count between two polls raises an alert.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PlayerDrop {
+    /// At least this many players.
+    Count(u16),
+    /// At least this percentage of the previous count.
+    Percent(f64),
+}
+
+impl FromStr for PlayerDrop {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim().strip_suffix('%') {
+            Some(percent) => match percent.trim().parse::<f64>() {
+                Ok(percent) if percent > 0.0 && percent <= 100.0 => Ok(PlayerDrop::Percent(percent)),
+                _ => Err(format!("invalid percentage: {}", s)),
+            },
+            None => match s.trim().parse::<u16>() {
+                Ok(count) if count > 0 => Ok(PlayerDrop::Count(count)),
+                _ => Err(format!("expected a player count or a percentage like 50%, got {}", s)),
+            },
+        }
+    }
+}
+
+impl PlayerDrop {
+    fn triggered(&self, old: u16, new: u16) -> bool {
+        let drop = old.saturating_sub(new);
+        match *self {
+            PlayerDrop::Count(count) => drop >= count,
+            PlayerDrop::Percent(percent) => drop > 0 && drop as f64 * 100.0 >= old as f64 * percent,
+        }
+    }
+}
+
+#[derive(Debug, Default)]
+pub struct Alerts {
+    pub offline: bool,
+    pub player_drop: Option<PlayerDrop>,
+}
+
+impl Alerts {
+    /// Why `event` raises an alert, if it does.
+    fn check(&self, event: &MonitorEvent) -> Option<String> {
+        match &event.kind {
+            MonitorEventKind::Offline { reason } if self.offline => Some(format!("server went offline: {}", reason)),
+            MonitorEventKind::PlayerCountChanged { old, new }
+                if self.player_drop.is_some_and(|drop| drop.triggered(*old, *new)) =>
+            {
+                Some(format!("player count dropped from {} to {}", old, new))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Color of the Discord embed of an alert.
+const ALERT_COLOR: u32 = 0xE74C3C;
+
+/// The payload posted to `--webhook-url`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum WebhookFormat {
+    /// Discord messages for Discord webhook URLs, JSON otherwise.
+    #[default]
+    Auto,
+    /// The monitor event as JSON.
+    Json,
+    /// A Discord message with one embed.
+    Discord,
+}
+
+/// A webhook given with `--webhook-url`.
+pub enum Webhook {
+    Json(WebhookSink),
+    Discord(DiscordNotifier),
+}
+
+impl Webhook {
+    pub fn new(url: String, format: WebhookFormat) -> Self {
+        let discord = match format {
+            WebhookFormat::Auto => ["https://discord.com/api/webhooks/", "https://discordapp.com/api/webhooks/"]
+                .iter()
+                .any(|prefix| url.starts_with(prefix)),
+            WebhookFormat::Json => false,
+            WebhookFormat::Discord => true,
+        };
+        if discord {
+            Webhook::Discord(DiscordNotifier::new(url))
+        } else {
+            Webhook::Json(WebhookSink::new(url))
+        }
+    }
+
+    /// Posts the alert `event` raised: the event itself as JSON, or a
+    /// Discord message giving `reason`.
+    async fn alert(&self, event: &MonitorEvent, reason: &str) -> samp_query::Result<()> {
+        match self {
+            Webhook::Json(sink) => sink.publish(event).await,
+            Webhook::Discord(notifier) => {
+                let message = WebhookMessage {
+                    username: None,
+                    embeds: vec![Embed {
+                        title: "Alert".to_string(),
+                        description: format!("**{}**: {}", event.address, reason),
+                        color: ALERT_COLOR,
+                    }],
+                };
+                notifier.send(&message).await
+            }
+        }
+    }
+
+    /// The webhook as a sink for every change event, as the daemon uses it.
+    pub fn into_sink(self) -> Box<dyn EventSink> {
+        match self {
+            Webhook::Json(sink) => Box::new(sink),
+            Webhook::Discord(notifier) => Box::new(notifier),
+        }
+    }
+}
+
+/// Prints change events until an alert triggers.
+///
+/// Without webhooks the first alert ends the command with
+/// [`exit::ALERT`]; with them, every alert is posted to each and
+/// monitoring continues.
+pub async fn run(
+    addr: SocketAddr,
+    config: MonitorConfig,
+    alerts: Alerts,
+    webhooks: Vec<Webhook>,
+    format: OutputFormat,
+) -> Result<ExitCode> {
+    if format == OutputFormat::Csv {
+        bail!("monitor does not support CSV output");
+    }
+    let mut events = Monitor::with_config(addr, config).stream();
+
+    while let Some(event) = events.next().await {
+        if !event.is_change() {
+            continue;
+        }
+        print_event(&event, format)?;
+
+        let Some(reason) = alerts.check(&event) else {
+            continue;
+        };
+        eprintln!("{} {}", "Alert:".red().bold(), reason);
+        if webhooks.is_empty() {
+            return Ok(ExitCode::from(exit::ALERT));
+        }
+        for webhook in &webhooks {
+            if let Err(e) = webhook.alert(&event, &reason).await {
+                eprintln!("{} {}", "Failed to call webhook:".red(), e);
+            }
+        }
+    }
+
+    Ok(ExitCode::SUCCESS)
+}
+
+fn print_event(event: &MonitorEvent, format: OutputFormat) -> Result<()> {
+    match format {
+        OutputFormat::Json => println!("{}", serde_json::to_string(event)?),
+        OutputFormat::Yaml => print!("---\n{}", serde_yaml::to_string(event)?),
+        _ => {
+            let kind = event.kind.to_string();
+            let kind = match event.kind {
+                MonitorEventKind::Online | MonitorEventKind::PlayerJoined { .. } => kind.green(),
+                MonitorEventKind::Offline { .. } | MonitorEventKind::PingSpike { .. } => kind.red(),
+                MonitorEventKind::PlayerLeft { .. } => kind.yellow(),
+                _ => kind.normal(),
+            };
+            println!("{} [{}] {}", clock(event.timestamp).dimmed(), event.address, kind);
+        }
+    }
+    Ok(())
+}
+
+/// Checks the webhook URL before monitoring starts instead of on the first alert.
+pub fn parse_webhook(s: &str) -> Result<String, String> {
+    if !(s.starts_with("http://") || s.starts_with("https://")) {
+        return Err("webhook URL must start with http:// or https://".to_string());
+    }
+    Ok(s.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn player_drop_from_str() {
+        assert_eq!("10".parse(), Ok(PlayerDrop::Count(10)));
+        assert_eq!(" 25% ".parse(), Ok(PlayerDrop::Percent(25.0)));
+        assert_eq!("100%".parse(), Ok(PlayerDrop::Percent(100.0)));
+        for invalid in ["0", "-5", "0%", "101%", "many", "%"] {
+            assert!(invalid.parse::<PlayerDrop>().is_err(), "{:?} parsed", invalid);
+        }
+    }
+
+    #[test]
+    fn player_drop_triggers() {
+        assert!(PlayerDrop::Count(10).triggered(50, 40));
+        assert!(!PlayerDrop::Count(10).triggered(50, 41));
+        assert!(PlayerDrop::Percent(50.0).triggered(10, 5));
+        assert!(!PlayerDrop::Percent(50.0).triggered(10, 6));
+        assert!(!PlayerDrop::Percent(50.0).triggered(0, 0));
+    }
+}
@@ -0,0 +1,75 @@
+//! The `omp-info` command: what a server reveals about being open.mp.
+//!
+//! open.mp answers an extra `o` query with its Discord link, banners and
+//! lite flag. The library does not send that query yet, so this command
+//! reports what the `i` and `r` queries show: the detected platform and the
+//! open.mp-specific rules. The extended fields are left empty until it does.
+
+use crate::output::{render, OutputFormat};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use samp_query::fingerprint::{detect_platform, Platform};
+use samp_query::{Client, ClientConfig};
+use serde::Serialize;
+use std::net::SocketAddr;
+
+#[derive(Debug, Clone, Serialize)]
+pub struct OmpInfo {
+    address: SocketAddr,
+    hostname: String,
+    /// `samp`, `openmp`, or `None` if the rules do not tell.
+    platform: Option<&'static str>,
+    version: Option<String>,
+    allowed_clients: Option<String>,
+    weburl: Option<String>,
+}
+
+fn format_omp_info(info: &OmpInfo) -> String {
+    if info.platform != Some(Platform::OpenMp.as_str()) {
+        let detail = match (&info.platform, &info.version) {
+            (Some(_), Some(version)) => format!("it runs SA-MP {}", version),
+            _ => "its rules do not identify the platform".to_string(),
+        };
+        return format!("{} is not an open.mp server; {}", info.address, detail)
+            .yellow()
+            .to_string();
+    }
+
+    let mut output = format!("{}\n", "open.mp Server".green().bold());
+    output.push_str(&format!("{}: {}\n", "Hostname".blue().bold(), info.hostname));
+    let fields = [
+        ("Version", &info.version),
+        ("Allowed clients", &info.allowed_clients),
+        ("Website", &info.weburl),
+    ];
+    for (label, value) in fields {
+        if let Some(value) = value {
+            output.push_str(&format!("{}: {}\n", label.blue().bold(), value));
+        }
+    }
+    let pending = "Discord link, banners and lite flag need the open.mp `o` query, which is not supported yet";
+    output.push_str(&pending.dimmed().to_string());
+    output
+}
+
+/// Queries the server's info and rules and prints whether it runs open.mp,
+/// with the rules only open.mp servers report.
+pub async fn run(addr: SocketAddr, config: ClientConfig, format: OutputFormat) -> Result<()> {
+    let client = Client::connect_with_config(addr, config)
+        .await
+        .context("Failed to connect to server")?;
+    let snapshot = client.snapshot_concurrent().await.context("Failed to query server")?;
+
+    let rules = snapshot.rules.unwrap_or_default();
+    let rule = |name: &str| rules.rules.get(name).cloned();
+    let omp = OmpInfo {
+        address: addr,
+        hostname: snapshot.info.hostname,
+        platform: detect_platform(&rules).map(|platform| platform.as_str()),
+        version: rule("version"),
+        allowed_clients: rule("allowed_clients"),
+        weburl: rule("weburl"),
+    };
+    println!("{}", render(format, &omp, format_omp_info)?);
+    Ok(())
+}
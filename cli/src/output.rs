@@ -1,22 +1,206 @@
 //! Output formatting for the CLI.
 
+use anyhow::{bail, Result};
+use clap::ValueEnum;
 use colored::Colorize;
-use samp_query::{DetailedPlayerList, PlayerList, ServerInfo, ServerRules};
+use samp_query::{DetailedPlayer, DetailedPlayerList, Player, PlayerList, ServerInfo, ServerRules, ServerSnapshot};
+use serde::{Deserialize, Serialize};
+use std::io::IsTerminal;
+use std::sync::OnceLock;
+use tabled::settings::peaker::PriorityMax;
+use tabled::settings::{Style, Width};
 use tabled::{Table, Tabled};
 
+/// How command results are printed.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum OutputFormat {
+    /// Colored text and tables
+    #[default]
+    Table,
+    /// The response as pretty-printed JSON
+    Json,
+    /// The response as YAML
+    Yaml,
+    /// Comma-separated rows with a header (players, players-detailed, rules, compare, find-player and scan only)
+    Csv,
+}
+
+/// When output is colored.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ColorChoice {
+    /// Color when stdout is a terminal and NO_COLOR is not set
+    #[default]
+    Auto,
+    /// Always color, even when piped
+    Always,
+    /// Never color
+    Never,
+}
+
+impl ColorChoice {
+    /// Applies the choice to everything printed, tables included.
+    pub fn apply(self) {
+        let enabled = match self {
+            ColorChoice::Always => true,
+            ColorChoice::Never => false,
+            ColorChoice::Auto => {
+                let no_color = std::env::var_os("NO_COLOR").is_some_and(|value| !value.is_empty());
+                !no_color && std::io::stdout().is_terminal()
+            }
+        };
+        colored::control::set_override(enabled);
+    }
+}
+
+/// How tables are drawn.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TableStyle {
+    /// Columns separated by spaces, without borders
+    Plain,
+    /// Borders drawn with +, - and |
+    #[default]
+    Ascii,
+    /// A GitHub-flavored Markdown table
+    Markdown,
+    /// Borders drawn with rounded box characters
+    Rounded,
+}
+
+static TABLE_STYLE: OnceLock<TableStyle> = OnceLock::new();
+
+impl TableStyle {
+    /// Uses this style for every table drawn afterwards.
+    pub fn apply(self) {
+        let _ = TABLE_STYLE.set(self);
+    }
+}
+
+/// Draws `table` in the chosen style. On a terminal, the widest columns are
+/// truncated so the table fits its width.
+pub fn draw(mut table: Table) -> String {
+    match TABLE_STYLE.get().copied().unwrap_or_default() {
+        TableStyle::Plain => table.with(Style::blank()),
+        TableStyle::Ascii => table.with(Style::ascii()),
+        TableStyle::Markdown => table.with(Style::markdown()),
+        TableStyle::Rounded => table.with(Style::rounded()),
+    };
+    if let Some(width) = terminal_width() {
+        table.with(Width::truncate(width).suffix("...").priority::<PriorityMax>());
+    }
+    table.to_string()
+}
+
+fn terminal_width() -> Option<usize> {
+    if !std::io::stdout().is_terminal() {
+        return None;
+    }
+    crossterm::terminal::size().ok().map(|(columns, _)| columns as usize)
+}
+
+/// Renders `value` in `format`, using `table` for the human-readable form.
+/// Fails for CSV, which needs rows; see [`render_rows`].
+pub fn render<T: Serialize>(format: OutputFormat, value: &T, table: impl FnOnce(&T) -> String) -> Result<String> {
+    Ok(match format {
+        OutputFormat::Table => table(value),
+        OutputFormat::Json => serde_json::to_string_pretty(value)?,
+        OutputFormat::Yaml => serde_yaml::to_string(value)?.trim_end().to_string(),
+        OutputFormat::Csv => bail!("CSV output is only supported by players, players-detailed, rules, compare, find-player and scan"),
+    })
+}
+
+/// A record of CSV output.
+pub trait CsvRecord: Serialize {
+    /// The column names, in field order, written even when there are no
+    /// records.
+    const HEADER: &'static [&'static str];
+}
+
+/// Like [`render`], with `rows` giving the records written as CSV.
+pub fn render_rows<T: Serialize, R: CsvRecord>(
+    format: OutputFormat,
+    value: &T,
+    table: impl FnOnce(&T) -> String,
+    rows: impl FnOnce(&T) -> Vec<R>,
+) -> Result<String> {
+    if format != OutputFormat::Csv {
+        return render(format, value, table);
+    }
+    let mut writer = csv::WriterBuilder::new().has_headers(false).from_writer(Vec::new());
+    writer.write_record(R::HEADER)?;
+    for row in rows(value) {
+        writer.serialize(row)?;
+    }
+    Ok(String::from_utf8(writer.into_inner()?)?.trim_end().to_string())
+}
+
+impl CsvRecord for Player {
+    const HEADER: &'static [&'static str] = &["name", "score"];
+}
+
+impl CsvRecord for DetailedPlayer {
+    const HEADER: &'static [&'static str] = &["id", "name", "score", "ping"];
+}
+
+#[derive(Serialize)]
+pub struct RuleRecord {
+    rule: String,
+    value: String,
+}
+
+impl CsvRecord for RuleRecord {
+    const HEADER: &'static [&'static str] = &["rule", "value"];
+}
+
+/// The rules as CSV records, sorted by name.
+pub fn rule_records(rules: &ServerRules) -> Vec<RuleRecord> {
+    let mut records: Vec<_> = rules
+        .rules
+        .iter()
+        .map(|(rule, value)| RuleRecord {
+            rule: rule.clone(),
+            value: value.clone(),
+        })
+        .collect();
+    records.sort_by(|a, b| a.rule.cmp(&b.rule));
+    records
+}
+
 pub fn format_server_info(info: &ServerInfo) -> String {
+    format_server_info_changes(info, None)
+}
+
+/// Like [`format_server_info`], highlighting the values that differ from `previous`.
+pub fn format_server_info_changes(info: &ServerInfo, previous: Option<&ServerInfo>) -> String {
+    let changed = |f: fn(&ServerInfo) -> String| previous.is_some_and(|previous| f(previous) != f(info));
     let mut output = String::new();
 
     output.push_str(&format!("{}\n", "Server Information".green().bold()));
-    output.push_str(&format!("{}: {}\n", "Hostname".blue().bold(), info.hostname));
     output.push_str(&format!(
-        "{}: {}/{}\n",
+        "{}: {}\n",
+        "Hostname".blue().bold(),
+        highlight(info.hostname.clone(), changed(|i| i.hostname.clone()))
+    ));
+    output.push_str(&format!(
+        "{}: {}\n",
         "Players".blue().bold(),
-        info.players,
-        info.max_players
+        highlight(
+            format!("{}/{}", info.players, info.max_players),
+            changed(|i| format!("{}/{}", i.players, i.max_players))
+        )
+    ));
+    output.push_str(&format!(
+        "{}: {}\n",
+        "Gamemode".blue().bold(),
+        highlight(info.gamemode.clone(), changed(|i| i.gamemode.clone()))
+    ));
+    output.push_str(&format!(
+        "{}: {}\n",
+        "Language".blue().bold(),
+        highlight(info.language.clone(), changed(|i| i.language.clone()))
     ));
-    output.push_str(&format!("{}: {}\n", "Gamemode".blue().bold(), info.gamemode));
-    output.push_str(&format!("{}: {}\n", "Language".blue().bold(), info.language));
     output.push_str(&format!(
         "{}: {}\n",
         "Password".blue().bold(),
@@ -26,6 +210,47 @@ pub fn format_server_info(info: &ServerInfo) -> String {
     output
 }
 
+/// Formats a Unix timestamp in milliseconds as a UTC `HH:MM:SS` time.
+pub fn clock(timestamp_ms: u64) -> String {
+    let seconds = timestamp_ms / 1000 % 86_400;
+    format!("{:02}:{:02}:{:02} UTC", seconds / 3600, seconds / 60 % 60, seconds % 60)
+}
+
+/// Formats a Unix timestamp in milliseconds as an RFC 3339 UTC time, e.g.
+/// `2024-05-01T12:30:00Z`.
+pub fn datetime(timestamp_ms: u64) -> String {
+    // Civil date from days since the epoch, after Howard Hinnant's algorithm.
+    let days = (timestamp_ms / 1000 / 86_400) as i64 + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days.rem_euclid(146_097);
+    let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 { month_index + 3 } else { month_index - 9 };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+
+    let seconds = timestamp_ms / 1000 % 86_400;
+    format!(
+        "{:04}-{:02}-{:02}T{:02}:{:02}:{:02}Z",
+        year,
+        month,
+        day,
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Marks a value that changed since the previous refresh.
+pub fn highlight(value: String, changed: bool) -> String {
+    if changed {
+        value.black().on_yellow().to_string()
+    } else {
+        value
+    }
+}
+
 pub fn format_rules(rules: &ServerRules) -> String {
     let mut output = String::new();
 
@@ -49,13 +274,28 @@ pub fn format_rules(rules: &ServerRules) -> String {
     }
 
     let table = Table::new(rule_rows);
-    let formatted_table = table.to_string();
+    let formatted_table = draw(table);
     output.push_str(&formatted_table);
 
     output
 }
 
 pub fn format_player_list(players: &PlayerList) -> String {
+    format_player_list_changes(players, None)
+}
+
+/// The names in `players` but not `previous`, and those in `previous` but
+/// no longer in `players`.
+pub fn player_changes<'a>(players: &'a PlayerList, previous: &'a PlayerList) -> (Vec<&'a str>, Vec<&'a str>) {
+    let missing_from = |list: &PlayerList, name: &str| !list.players.iter().any(|p| p.name == name);
+    let joined = players.players.iter().map(|p| p.name.as_str()).filter(|name| missing_from(previous, name));
+    let left = previous.players.iter().map(|p| p.name.as_str()).filter(|name| missing_from(players, name));
+    (joined.collect(), left.collect())
+}
+
+/// Like [`format_player_list`], highlighting players missing from `previous`
+/// and scores that changed, and listing who joined and left.
+pub fn format_player_list_changes(players: &PlayerList, previous: Option<&PlayerList>) -> String {
     let mut output = String::new();
 
     output.push_str(&format!(
@@ -69,21 +309,35 @@ pub fn format_player_list(players: &PlayerList) -> String {
         #[tabled(rename = "Name")]
         name: String,
         #[tabled(rename = "Score")]
-        score: i32,
+        score: String,
     }
 
     let mut player_rows = Vec::new();
     for player in &players.players {
+        let before = previous.map(|previous| previous.players.iter().find(|p| p.name == player.name));
         player_rows.push(PlayerRow {
-            name: player.name.clone(),
-            score: player.score,
+            name: highlight(player.name.clone(), matches!(before, Some(None))),
+            score: highlight(
+                player.score.to_string(),
+                matches!(before, Some(Some(p)) if p.score != player.score),
+            ),
         });
     }
 
     let table = Table::new(player_rows);
-    let formatted_table = table.to_string();
+    let formatted_table = draw(table);
     output.push_str(&formatted_table);
 
+    if let Some(previous) = previous {
+        let (joined, left) = player_changes(players, previous);
+        if !joined.is_empty() {
+            output.push_str(&format!("\n{} {}", "Joined:".green().bold(), joined.join(", ").green()));
+        }
+        if !left.is_empty() {
+            output.push_str(&format!("\n{} {}", "Left:".red().bold(), left.join(", ").red()));
+        }
+    }
+
     output
 }
 
@@ -119,8 +373,34 @@ pub fn format_detailed_player_list(players: &DetailedPlayerList) -> String {
     }
 
     let table = Table::new(player_rows);
-    let formatted_table = table.to_string();
+    let formatted_table = draw(table);
     output.push_str(&formatted_table);
 
     output
 }
+
+/// Formats every part of a snapshot, noting the queries that went unanswered.
+pub fn format_snapshot(snapshot: &ServerSnapshot) -> String {
+    let missing = |what: &str| format!("{}\n", format!("No {} (the server did not answer)", what).dimmed());
+    let mut output = format_server_info(&snapshot.info);
+
+    output.push('\n');
+    match &snapshot.ping {
+        Some(ping) => output.push_str(&format!("{} {} ms\n", "Ping:".bold(), ping.ping_ms.to_string().green())),
+        None => output.push_str(&missing("ping")),
+    }
+
+    output.push('\n');
+    match &snapshot.rules {
+        Some(rules) => output.push_str(&format_rules(rules)),
+        None => output.push_str(&missing("rules")),
+    }
+
+    output.push('\n');
+    match &snapshot.players {
+        Some(players) => output.push_str(&format_detailed_player_list(players)),
+        None => output.push_str(&missing("detailed player list")),
+    }
+
+    output.trim_end().to_string()
+}
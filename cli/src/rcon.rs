@@ -0,0 +1,133 @@
+//! RCON passwords for the `rcon` and `rcon-shell` commands, and the command
+//! files run by `rcon --file`.
+
+use crate::output::{render, OutputFormat};
+use anyhow::{anyhow, bail, Context, Result};
+use colored::Colorize;
+use samp_query::rcon::{RconSession, Script, Step};
+use samp_query::{Client, ClientConfig};
+use serde::Serialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+/// Environment variable holding the RCON password.
+pub const PASSWORD_ENV: &str = "SAMP_RCON_PASSWORD";
+
+/// The password read from `file`, otherwise from [`PASSWORD_ENV`],
+/// otherwise prompted for without echoing it.
+pub fn password(addr: SocketAddr, file: Option<&Path>) -> Result<String> {
+    if let Some(file) = file {
+        let text = std::fs::read_to_string(file).with_context(|| format!("Failed to read {}", file.display()))?;
+        let password = text.lines().next().unwrap_or_default();
+        if password.is_empty() {
+            bail!("{} does not contain a password", file.display());
+        }
+        return Ok(password.to_string());
+    }
+    if let Some(password) = std::env::var_os(PASSWORD_ENV).filter(|password| !password.is_empty()) {
+        return password
+            .into_string()
+            .map_err(|_| anyhow!("{} is not valid UTF-8", PASSWORD_ENV));
+    }
+    rpassword::prompt_password(format!("RCON password for {}: ", addr)).with_context(|| {
+        format!(
+            "Failed to prompt for the RCON password; set {} or pass --password-file",
+            PASSWORD_ENV
+        )
+    })
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct CommandResult {
+    command: String,
+    lines: Vec<String>,
+    error: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct FileReport {
+    commands: Vec<CommandResult>,
+    /// Whether a failing command stopped the file early.
+    stopped: bool,
+}
+
+fn format_file_report(report: &FileReport) -> String {
+    let mut output = Vec::new();
+    for result in &report.commands {
+        output.push(format!("{} {}", ">".blue().bold(), result.command.bold()));
+        match &result.error {
+            Some(error) => output.push(error.red().to_string()),
+            None => output.extend(result.lines.iter().cloned()),
+        }
+    }
+    if report.stopped {
+        output.push("Stopped at the first failing command".yellow().to_string());
+    }
+    output.join("\n")
+}
+
+/// Runs the commands in `file` one after another and prints the output of
+/// each. The file uses the script format of [`samp_query::rcon`].
+///
+/// Failing commands are reported and skipped unless `stop_on_error` is set,
+/// in which case only commands marked with `-` are skipped. `delay` is the
+/// pause after every command the file does not give one with `@delay`.
+pub async fn run_file(
+    addr: SocketAddr,
+    password: String,
+    config: ClientConfig,
+    file: &Path,
+    stop_on_error: bool,
+    delay: Duration,
+    format: OutputFormat,
+) -> Result<()> {
+    let script = Script::load(file).with_context(|| format!("Failed to load {}", file.display()))?;
+    let steps = script
+        .steps()
+        .iter()
+        .cloned()
+        .map(|step| match step {
+            Step::Command {
+                command,
+                ignore_errors,
+                delay: file_delay,
+            } => Step::Command {
+                command,
+                ignore_errors: ignore_errors || !stop_on_error,
+                delay: if file_delay.is_zero() { delay } else { file_delay },
+            },
+            wait => wait,
+        })
+        .collect();
+
+    let client = Client::connect_with_config(addr, config)
+        .await
+        .context("Failed to connect to server")?;
+    let session = RconSession::new(client, &password);
+    let report = Script::new(steps)
+        .run(&session, &HashMap::new())
+        .await
+        .with_context(|| format!("Failed to run {}", file.display()))?;
+
+    let failed = report.outputs.iter().filter(|output| output.error.is_some()).count();
+    let total = report.outputs.len();
+    let report = FileReport {
+        commands: report
+            .outputs
+            .into_iter()
+            .map(|output| CommandResult {
+                command: output.command,
+                lines: output.lines,
+                error: output.error,
+            })
+            .collect(),
+        stopped: report.aborted,
+    };
+    println!("{}", render(format, &report, format_file_report)?);
+    if failed > 0 {
+        bail!("{} of {} RCON commands failed", failed, total);
+    }
+    Ok(())
+}
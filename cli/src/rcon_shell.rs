@@ -0,0 +1,120 @@
+//! The `rcon-shell` command: an interactive RCON console.
+
+use anyhow::{Context, Result};
+use colored::Colorize;
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
+use samp_query::rcon::RconSession;
+use samp_query::{Client, ClientConfig, Error};
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+/// Where the command history is kept between sessions.
+fn history_path() -> Option<PathBuf> {
+    std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".samp_query_rcon_history"))
+}
+
+/// Commands whose argument is a password, kept out of the history file.
+const SECRET_COMMANDS: &[&str] = &["rcon_password", "password", "rcon.password", "game.password"];
+
+/// Whether `command` sets a password, so it must not be saved.
+fn is_secret(command: &str) -> bool {
+    let name = command.split_whitespace().next().unwrap_or_default();
+    SECRET_COMMANDS.iter().any(|secret| name.eq_ignore_ascii_case(secret))
+}
+
+/// Makes an existing history file readable by its owner only, as rustyline
+/// creates new ones, since it holds the commands run on the server.
+fn restrict_history(path: &Path) -> std::io::Result<()> {
+    #[cfg(unix)]
+    if path.exists() {
+        use std::os::unix::fs::PermissionsExt;
+        std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600))?;
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+    Ok(())
+}
+
+/// Sends every entered line as a command until `exit`, `quit` or end of
+/// input.
+pub async fn run(addr: SocketAddr, password: String, config: ClientConfig) -> Result<()> {
+    let client = Client::connect_with_config(addr, config)
+        .await
+        .context("Failed to connect to server")?;
+    let session = RconSession::new(client, &password);
+
+    let mut editor = DefaultEditor::new()?;
+    let history = history_path();
+    if let Some(history) = &history {
+        if let Err(e) = restrict_history(history) {
+            eprintln!("{}", format!("Failed to restrict {}: {}", history.display(), e).red());
+        }
+        let _ = editor.load_history(history);
+    }
+    println!("Connected to {}. Type exit or press Ctrl-D to leave.", addr);
+
+    let prompt = format!("{}> ", addr);
+    let mut result = Ok(());
+    loop {
+        // Reading blocks, so it runs off the async runtime.
+        let prompt = prompt.clone();
+        let (returned, line) = tokio::task::spawn_blocking(move || {
+            let line = editor.readline(&prompt);
+            (editor, line)
+        })
+        .await?;
+        editor = returned;
+
+        let line = match line {
+            Ok(line) => line,
+            Err(ReadlineError::Interrupted) => continue,
+            Err(ReadlineError::Eof) => break,
+            Err(e) => return Err(e.into()),
+        };
+        let command = line.trim();
+        if command.is_empty() {
+            continue;
+        }
+        if command == "exit" || command == "quit" {
+            break;
+        }
+        if !is_secret(command) {
+            let _ = editor.add_history_entry(command);
+        }
+
+        match session.command(command).await {
+            Ok(lines) => {
+                for line in lines {
+                    println!("{}", line);
+                }
+            }
+            Err(Error::RconAuthFailed) => {
+                // Returned, so the shell exits with the same code as `rcon`.
+                result = Err(Error::RconAuthFailed.into());
+                break;
+            }
+            Err(e) => eprintln!("{}", e.to_string().red()),
+        }
+    }
+
+    if let Some(history) = &history {
+        let _ = editor.save_history(history);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn password_commands_are_secret() {
+        assert!(is_secret("rcon_password hunter2"));
+        assert!(is_secret("  PASSWORD 1234"));
+        assert!(is_secret("rcon.password hunter2"));
+        assert!(!is_secret("passwordless"));
+        assert!(!is_secret("say password is hunter2"));
+        assert!(!is_secret("players"));
+    }
+}
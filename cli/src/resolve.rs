@@ -0,0 +1,168 @@
+//! The `resolve` command: where a server address leads and whether anything
+//! answers there, for "server not responding" reports.
+
+use crate::output::{render, OutputFormat};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use samp_query::protocol::constants;
+use samp_query::resolver::Resolver;
+use samp_query::utils::split_host_port;
+use samp_query::{Client, ClientConfig};
+use serde::Serialize;
+use std::net::{IpAddr, SocketAddr};
+use std::time::Instant;
+
+/// Multiples of the base timeout tried on the selected address.
+const TIMEOUT_FACTORS: [u64; 3] = [1, 2, 4];
+
+/// One address the hostname resolved to.
+#[derive(Debug, Clone, Serialize)]
+pub struct Candidate {
+    address: SocketAddr,
+    /// Whether queries go to this address.
+    selected: bool,
+    /// Round trip of a ping query, if one was answered.
+    ping_ms: Option<u64>,
+    error: Option<String>,
+}
+
+/// An info query sent with a longer timeout than the last.
+#[derive(Debug, Clone, Serialize)]
+pub struct TimeoutProbe {
+    timeout_ms: u64,
+    /// Time to the answer, if one came.
+    answered_ms: Option<u64>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct ResolveReport {
+    host: String,
+    port: u16,
+    /// Time the DNS lookup took; zero for IP addresses.
+    lookup_ms: f64,
+    candidates: Vec<Candidate>,
+    selected: SocketAddr,
+    probes: Vec<TimeoutProbe>,
+    diagnosis: String,
+}
+
+fn diagnose(report: &ResolveReport) -> String {
+    let reachable = report.candidates.iter().any(|candidate| candidate.ping_ms.is_some());
+    let answered = report.probes.iter().find(|probe| probe.answered_ms.is_some());
+    match (answered, report.probes.first()) {
+        (Some(probe), Some(first)) if probe.timeout_ms == first.timeout_ms => {
+            "The selected address answers within the timeout".to_string()
+        }
+        (Some(probe), _) => format!(
+            "The server answers slowly; raise --timeout to at least {} ms",
+            probe.timeout_ms
+        ),
+        (None, _) if reachable => {
+            "Only another address of the host answers; query it by IP address".to_string()
+        }
+        (None, _) => "Nothing answers: the server is down, the port is wrong, or a firewall drops UDP".to_string(),
+    }
+}
+
+fn format_report(report: &ResolveReport) -> String {
+    let mut output = vec![format!("{} {}:{}", "Resolve".green().bold(), report.host, report.port)];
+    output.push(format!("{}: {:.1} ms", "DNS lookup".blue().bold(), report.lookup_ms));
+    output.push(format!("{}:", "Addresses".blue().bold()));
+    for candidate in &report.candidates {
+        let marker = if candidate.selected { "*" } else { " " };
+        let status = match (&candidate.ping_ms, &candidate.error) {
+            (Some(ping), _) => format!("answers in {} ms", ping).green(),
+            (None, Some(error)) => error.red(),
+            (None, None) => "no answer".red(),
+        };
+        output.push(format!("  {} {:<24} {}", marker, candidate.address.to_string(), status));
+    }
+    output.push(format!("{}: {}", "Selected".blue().bold(), report.selected));
+    output.push(format!("{}:", "Timeouts".blue().bold()));
+    for probe in &report.probes {
+        let status = match probe.answered_ms {
+            Some(answered) => format!("answered in {} ms", answered).green(),
+            None => "timed out".red(),
+        };
+        output.push(format!("  {:>6} ms  {}", probe.timeout_ms, status));
+    }
+    output.push(report.diagnosis.yellow().to_string());
+    output.join("\n")
+}
+
+/// Sends one query to `addr` with `timeout_ms`, without retries.
+async fn probe(addr: SocketAddr, timeout_ms: u64, ping: bool) -> samp_query::Result<u64> {
+    let config = ClientConfig {
+        timeout_ms,
+        max_retries: 1,
+        retry: None,
+    };
+    let client = Client::connect_with_config(addr, config).await?;
+    let sent = Instant::now();
+    if ping {
+        client.query_ping().await.map(|ping| ping.ping_ms)
+    } else {
+        client.query_info().await.map(|_| sent.elapsed().as_millis() as u64)
+    }
+}
+
+/// Resolves `address`, pings every address it resolves to, and queries the
+/// selected one with growing multiples of `timeout_ms`, then prints what it
+/// found. Probes are not retried, so each shows what a single query does.
+pub async fn run(address: &str, timeout_ms: u64, format: OutputFormat) -> Result<()> {
+    let (host, port) = split_host_port(address, constants::DEFAULT_PORT)
+        .map_err(samp_query::Error::Resolve)
+        .context("Invalid server address")?;
+    let resolver = Resolver::global();
+    let started = Instant::now();
+    let ips: Vec<IpAddr> = resolver
+        .lookup(&host)
+        .await
+        .with_context(|| format!("Failed to resolve {}", host))?;
+    let lookup_ms = started.elapsed().as_secs_f64() * 1000.0;
+    let selected = resolver.resolve(&host, port).await?;
+
+    let candidates = futures::future::join_all(ips.into_iter().map(|ip| {
+        let address = SocketAddr::new(ip, port);
+        async move {
+            let result = if ip.is_ipv4() {
+                probe(address, timeout_ms, true).await.map_err(|e| e.to_string())
+            } else {
+                Err("skipped: the query protocol only supports IPv4".to_string())
+            };
+            Candidate {
+                address,
+                selected: address == selected,
+                ping_ms: result.as_ref().ok().copied(),
+                error: result.err(),
+            }
+        }
+    }))
+    .await;
+
+    let mut probes = Vec::new();
+    for factor in TIMEOUT_FACTORS {
+        let timeout_ms = timeout_ms * factor;
+        let answered_ms = probe(selected, timeout_ms, false).await.ok();
+        probes.push(TimeoutProbe { timeout_ms, answered_ms });
+        if answered_ms.is_some() {
+            break;
+        }
+    }
+
+    let mut report = ResolveReport {
+        host,
+        port,
+        lookup_ms,
+        candidates,
+        selected,
+        probes,
+        diagnosis: String::new(),
+    };
+    report.diagnosis = diagnose(&report);
+    println!("{}", render(format, &report, format_report)?);
+    if report.probes.iter().all(|probe| probe.answered_ms.is_none()) {
+        return Err(samp_query::Error::Timeout).with_context(|| format!("{} did not answer", selected));
+    }
+    Ok(())
+}
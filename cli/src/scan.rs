@@ -0,0 +1,90 @@
+//! The `scan` command: discovers servers in address and port ranges.
+
+use crate::geo::Located;
+use crate::output::OutputFormat;
+use anyhow::{Context, Result};
+use colored::Colorize;
+use futures::StreamExt;
+use samp_query::geoip::GeoIp;
+use samp_query::scanner::{Discovered, ScanConfig, Scanner};
+use serde::Serialize;
+use std::io::Write;
+
+/// Parses a probe rate such as `500pps` or `500`.
+pub fn parse_rate(s: &str) -> Result<u32, String> {
+    let number = s.trim().strip_suffix("pps").unwrap_or(s.trim());
+    match number.parse::<u32>() {
+        Ok(rate) if rate > 0 => Ok(rate),
+        _ => Err(format!("expected a packet rate like 500pps, got {}", s)),
+    }
+}
+
+#[derive(Serialize)]
+struct Record<'a> {
+    address: String,
+    hostname: &'a str,
+    gamemode: &'a str,
+    players: u16,
+    max_players: u16,
+    password: bool,
+    ping_ms: u64,
+}
+
+impl<'a> From<&'a Discovered> for Record<'a> {
+    fn from(server: &'a Discovered) -> Self {
+        Self {
+            address: server.address.to_string(),
+            hostname: &server.info.hostname,
+            gamemode: &server.info.gamemode,
+            players: server.info.players,
+            max_players: server.info.max_players,
+            password: server.info.password,
+            ping_ms: server.ping_ms,
+        }
+    }
+}
+
+/// Scans and prints every server as soon as it answers, then a summary on
+/// stderr. With `geoip`, each server's country and network are added.
+pub async fn run(config: ScanConfig, format: OutputFormat, geoip: Option<GeoIp>) -> Result<()> {
+    let targets = config.target_count();
+    let mut servers = Scanner::new(config).scan().await.context("Failed to start scan")?;
+    let mut csv = csv::Writer::from_writer(std::io::stdout());
+    let mut found = 0;
+
+    while let Some(server) = servers.next().await {
+        found += 1;
+        let geo = geoip.as_ref().map(|geoip| geoip.lookup(server.address.ip()));
+        match (format, &geo) {
+            (OutputFormat::Table, _) => {
+                let mut line = format!(
+                    "{:<21} {} {} {}",
+                    server.address.to_string().blue().bold(),
+                    server.info.hostname,
+                    format!("{}/{}", server.info.players, server.info.max_players).green(),
+                    format!("{} ms", server.ping_ms).dimmed()
+                );
+                if let Some(geo) = &geo {
+                    line.push_str(&format!(" {}", geo.to_string().cyan()));
+                }
+                println!("{}", line);
+            }
+            (OutputFormat::Json, None) => println!("{}", serde_json::to_string(&server)?),
+            (OutputFormat::Json, Some(geo)) => println!("{}", serde_json::to_string(&Located { server: &server, geo })?),
+            (OutputFormat::Yaml, None) => print!("---\n{}", serde_yaml::to_string(&server)?),
+            (OutputFormat::Yaml, Some(geo)) => print!("---\n{}", serde_yaml::to_string(&Located { server: &server, geo })?),
+            (OutputFormat::Csv, None) => {
+                csv.serialize(Record::from(&server))?;
+                csv.flush()?;
+            }
+            (OutputFormat::Csv, Some(geo)) => {
+                csv.serialize((Record::from(&server), geo))?;
+                csv.flush()?;
+            }
+        }
+        std::io::stdout().flush()?;
+    }
+
+    eprintln!("Found {} servers in {} addresses", found, targets);
+    Ok(())
+}
@@ -0,0 +1,226 @@
+//! Defaults read from the configuration file.
+//!
+//! The file is `~/.config/samp-query/config.toml` (or under
+//! `$XDG_CONFIG_HOME`) unless `--config` names another one:
+//!
+//! ```toml
+//! timeout = 2000
+//! retries = 2
+//! retry_backoff = "exponential"
+//! retry_delay = 200
+//! retry_max_elapsed = "5s"
+//! format = "json"
+//! color = "never"
+//! table_style = "rounded"
+//! parallel = 16
+//! rate = 200
+//! history_db = "/var/lib/samp-query/history.db"
+//! geoip_db = "/usr/share/GeoIP/GeoLite2-Country.mmdb"
+//! geoip_asn_db = "/usr/share/GeoIP/GeoLite2-ASN.mmdb"
+//!
+//! [servers]
+//! lv = "203.0.113.7:7777"
+//!
+//! [webhooks]
+//! ops = "https://hooks.example.com/samp"
+//!
+//! [[monitors]]
+//! server = "lv"
+//! interval = 60
+//! webhook = "ops"
+//! ```
+//!
+//! Flags given on the command line take precedence over the file.
+
+use crate::monitor::parse_webhook;
+use crate::output::{ColorChoice, OutputFormat, TableStyle};
+use anyhow::{anyhow, bail, Context, Result};
+use clap::ValueEnum;
+use samp_query::geoip::GeoIp;
+use samp_query::protocol::constants;
+use samp_query::resolver::Resolver;
+use samp_query::utils::Backoff;
+use serde::{de, Deserialize, Deserializer};
+use std::collections::BTreeMap;
+use std::net::SocketAddr;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Default, Deserialize)]
+#[serde(default, deny_unknown_fields)]
+pub struct Settings {
+    /// Query timeout in milliseconds.
+    pub timeout: Option<u64>,
+    /// Number of query attempts.
+    pub retries: Option<usize>,
+    pub retry_backoff: Option<RetryBackoff>,
+    /// Pause before the first retry, in milliseconds.
+    pub retry_delay: Option<u64>,
+    /// Give up retrying after this long, e.g. `5s`.
+    pub retry_max_elapsed: Option<String>,
+    pub format: Option<OutputFormat>,
+    pub color: Option<ColorChoice>,
+    pub table_style: Option<TableStyle>,
+    /// Servers queried at the same time by batch commands.
+    pub parallel: Option<usize>,
+    /// Queries per second sent by batch commands and scans.
+    pub rate: Option<u32>,
+    /// Favorite servers by name, usable wherever an address is expected.
+    pub servers: BTreeMap<String, String>,
+    /// Database used by the `history` commands.
+    pub history_db: Option<PathBuf>,
+    /// MaxMind country database used by `--geo`.
+    pub geoip_db: Option<PathBuf>,
+    /// MaxMind ASN database used by `--geo`.
+    pub geoip_asn_db: Option<PathBuf>,
+    /// Webhook URLs by name, usable with `--webhook-url` and in `[[monitors]]`.
+    pub webhooks: BTreeMap<String, String>,
+    /// Servers watched by the `daemon` command.
+    pub monitors: Vec<MonitorSettings>,
+}
+
+/// One server watched by the `daemon` command.
+#[derive(Debug, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct MonitorSettings {
+    /// Address or favorite name.
+    pub server: String,
+    /// Seconds between polls, instead of the daemon's `--interval`.
+    #[serde(default, deserialize_with = "interval")]
+    pub interval: Option<u64>,
+    /// Webhook URL or name receiving the server's change events as JSON.
+    pub webhook: Option<String>,
+    /// Discord webhook URL or name receiving the server's change events.
+    pub discord: Option<String>,
+}
+
+/// A monitor's `interval`, which must not be zero: the daemon would poll
+/// the server back to back.
+fn interval<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<u64>, D::Error> {
+    match Option::<u64>::deserialize(deserializer)? {
+        Some(0) => Err(de::Error::custom("interval must be at least 1 second")),
+        interval => Ok(interval),
+    }
+}
+
+/// The pause between query attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, ValueEnum, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RetryBackoff {
+    /// Retry at once.
+    None,
+    /// Wait the retry delay before every attempt.
+    Fixed,
+    /// Double the delay after every attempt.
+    Exponential,
+}
+
+impl From<RetryBackoff> for Backoff {
+    fn from(backoff: RetryBackoff) -> Self {
+        match backoff {
+            RetryBackoff::None => Backoff::None,
+            RetryBackoff::Fixed => Backoff::Fixed,
+            RetryBackoff::Exponential => Backoff::Exponential,
+        }
+    }
+}
+
+/// Where the configuration file is looked for when `--config` is not given.
+fn default_path() -> Option<PathBuf> {
+    let base = match std::env::var_os("XDG_CONFIG_HOME") {
+        Some(dir) if !dir.is_empty() => PathBuf::from(dir),
+        _ => PathBuf::from(std::env::var_os("HOME")?).join(".config"),
+    };
+    Some(base.join("samp-query").join("config.toml"))
+}
+
+/// The address inside a `samp://host:port/` link, as opened from server
+/// list websites; other input is returned unchanged.
+fn strip_url(address: &str) -> &str {
+    let address = address.trim();
+    match address.get(..7) {
+        Some(scheme) if scheme.eq_ignore_ascii_case("samp://") => address[7..].trim_end_matches('/'),
+        _ => address,
+    }
+}
+
+impl Settings {
+    /// Reads `path`, or the default file if there is one.
+    pub fn load(path: Option<&Path>) -> Result<Self> {
+        let (path, required) = match path {
+            Some(path) => (path.to_path_buf(), true),
+            None => match default_path() {
+                Some(path) => (path, false),
+                None => return Ok(Self::default()),
+            },
+        };
+
+        let text = match std::fs::read_to_string(&path) {
+            Ok(text) => text,
+            Err(e) if !required && e.kind() == std::io::ErrorKind::NotFound => return Ok(Self::default()),
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        toml::from_str(&text).with_context(|| format!("Invalid configuration file {}", path.display()))
+    }
+
+    /// The `host[:port]` that `address` stands for: the address of the
+    /// favorite of that name, or `address` without any `samp://` prefix.
+    pub fn server_host<'a>(&'a self, address: &'a str) -> &'a str {
+        let address = strip_url(address);
+        self.servers.get(address).map_or(address, String::as_str)
+    }
+
+    /// Resolves `address`, a favorite's name, `IP:PORT` or a hostname with
+    /// an optional port. Any of these may be written as a `samp://` URL.
+    pub async fn server_address(&self, address: &str) -> Result<SocketAddr> {
+        let address = self.server_host(address);
+        let (_, addr) = Resolver::global()
+            .resolve_address(address, constants::DEFAULT_PORT)
+            .await
+            .context("Invalid server address")?;
+        Ok(addr)
+    }
+
+    /// Resolves several addresses at once.
+    pub async fn server_addresses(&self, addresses: impl IntoIterator<Item = &str>) -> Result<Vec<SocketAddr>> {
+        futures::future::try_join_all(addresses.into_iter().map(|address| self.server_address(address))).await
+    }
+
+    /// The addresses of every favorite server, failing if there are none.
+    pub async fn favorites(&self) -> Result<Vec<SocketAddr>> {
+        if self.servers.is_empty() {
+            bail!("No servers given and no favorite servers in the configuration file");
+        }
+        self.server_addresses(self.servers.keys().map(String::as_str)).await
+    }
+
+    /// Opens the GeoIP databases for `--geo`.
+    pub fn geoip(&self) -> Result<GeoIp> {
+        let paths: Vec<_> = self.geoip_db.iter().chain(&self.geoip_asn_db).collect();
+        if paths.is_empty() {
+            bail!("--geo needs geoip_db or geoip_asn_db in the configuration file");
+        }
+        Ok(GeoIp::open(paths)?)
+    }
+
+    /// `webhook` itself if it is a URL, otherwise the webhook of that name.
+    pub fn webhook(&self, webhook: &str) -> Result<String> {
+        if let Some(url) = self.webhooks.get(webhook) {
+            return parse_webhook(url).map_err(|e| anyhow!("webhook {}: {}", webhook, e));
+        }
+        parse_webhook(webhook).map_err(|_| anyhow!("{:?} is neither a webhook URL nor a configured webhook", webhook))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn monitor_interval_must_not_be_zero() {
+        let settings: Settings = toml::from_str("[[monitors]]\nserver = \"lv\"\ninterval = 60\n").unwrap();
+        assert_eq!(settings.monitors[0].interval, Some(60));
+
+        let error = toml::from_str::<Settings>("[[monitors]]\nserver = \"lv\"\ninterval = 0\n").unwrap_err();
+        assert!(error.to_string().contains("interval must be at least 1 second"), "{}", error);
+    }
+}
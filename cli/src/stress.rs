@@ -0,0 +1,102 @@
+//! The `stress` command: a fixed-rate query flood with a latency report.
+
+use crate::output::{render, OutputFormat};
+use anyhow::{Context, Result};
+use clap::ValueEnum;
+use colored::Colorize;
+use samp_query::stress::{StressConfig, StressReport, StressTest};
+use samp_query::QueryType;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Width of the longest histogram bar, in characters.
+const BAR_WIDTH: usize = 40;
+
+/// The query sent by `stress`.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum StressQuery {
+    #[default]
+    Ping,
+    Info,
+    Rules,
+    Players,
+    PlayersDetailed,
+}
+
+impl From<StressQuery> for QueryType {
+    fn from(query: StressQuery) -> Self {
+        match query {
+            StressQuery::Ping => QueryType::Ping,
+            StressQuery::Info => QueryType::Information,
+            StressQuery::Rules => QueryType::Rules,
+            StressQuery::Players => QueryType::ClientList,
+            StressQuery::PlayersDetailed => QueryType::DetailedPlayerInfo,
+        }
+    }
+}
+
+fn format_report(report: &StressReport) -> String {
+    let loss = format!("{:.2}%", report.loss_percentage);
+    let loss = if report.lost == 0 { loss.green() } else { loss.red() };
+    let mut output = format!(
+        "{}\nTarget: {}\nSent: {} ({:.1} qps over {:.1}s)\nReceived: {}\nLost: {} ({})",
+        "Stress Test".green().bold(),
+        report.target,
+        report.sent,
+        report.achieved_qps,
+        report.elapsed_ms as f64 / 1000.0,
+        report.received,
+        report.lost,
+        loss
+    );
+
+    let Some(latency) = &report.latency else {
+        return output;
+    };
+    output.push_str(&format!(
+        "\nLatency: min {:.2} / mean {:.2} / p50 {:.2} / p90 {:.2} / p99 {:.2} / max {:.2} ms\n",
+        latency.min_ms, latency.mean_ms, latency.p50_ms, latency.p90_ms, latency.p99_ms, latency.max_ms
+    ));
+
+    // The buckets are cumulative; the bars show the answers within each one.
+    let mut rows = Vec::new();
+    let mut below = 0;
+    for bucket in &latency.histogram {
+        rows.push((format!("<= {} ms", bucket.le_ms), bucket.count - below));
+        below = bucket.count;
+    }
+    if let Some(last) = latency.histogram.last() {
+        rows.push((format!(" > {} ms", last.le_ms), report.received.saturating_sub(below)));
+    }
+    let widest = rows.iter().map(|(_, count)| *count).max().unwrap_or(0).max(1);
+    for (label, count) in rows {
+        let bar = "#".repeat((count as usize * BAR_WIDTH).div_ceil(widest as usize));
+        let bar = format!("{:<width$}", bar, width = BAR_WIDTH);
+        output.push_str(&format!("\n{:>10} {} {}", label, bar.cyan(), count));
+    }
+    output
+}
+
+/// Sends `query` to `target` at `qps` for `duration` and prints the loss
+/// and latency of the answers.
+pub async fn run(
+    target: SocketAddr,
+    qps: u32,
+    duration: Duration,
+    query: StressQuery,
+    timeout: Duration,
+    format: OutputFormat,
+) -> Result<()> {
+    let config = StressConfig {
+        qps,
+        duration,
+        query_type: query.into(),
+        timeout,
+        ..StressConfig::new(target)
+    };
+    eprintln!("Sending {} qps to {} for {:?}", qps, target, duration);
+
+    let report = StressTest::new(config).run().await.context("Stress test failed")?;
+    println!("{}", render(format, &report, format_report)?);
+    Ok(())
+}
@@ -0,0 +1,97 @@
+//! Server lists read from files, for the commands that query many servers.
+
+use crate::settings::Settings;
+use anyhow::{bail, Context, Result};
+use futures::stream::{self, StreamExt};
+use samp_query::protocol::constants;
+use samp_query::resolver::Resolver;
+use std::collections::HashSet;
+use std::net::SocketAddr;
+use std::path::Path;
+
+/// Lines resolved at the same time.
+const RESOLVE_CONCURRENCY: usize = 16;
+
+/// A line of a server list.
+#[derive(Debug)]
+pub struct Target {
+    /// The line number, from 1.
+    pub number: usize,
+    /// The line as written, without its comment.
+    pub line: String,
+    /// The address the line resolved to, or why it did not.
+    pub addr: samp_query::Result<SocketAddr>,
+}
+
+impl From<SocketAddr> for Target {
+    fn from(addr: SocketAddr) -> Self {
+        Self {
+            number: 1,
+            line: addr.to_string(),
+            addr: Ok(addr),
+        }
+    }
+}
+
+/// Resolves one address or favorite name per line, in order. Blank lines
+/// and text after `#` are ignored, and so are lines resolving to an address
+/// listed before. Lines that do not resolve are kept with the error.
+pub async fn parse(text: &str, settings: &Settings) -> Vec<Target> {
+    let lines = text
+        .lines()
+        .enumerate()
+        .map(|(number, line)| (number + 1, line.split('#').next().unwrap_or_default().trim()))
+        .filter(|(_, line)| !line.is_empty());
+    let resolved: Vec<Target> = stream::iter(lines)
+        .map(|(number, line)| async move {
+            let addr = Resolver::global()
+                .resolve_address(settings.server_host(line), constants::DEFAULT_PORT)
+                .await
+                .map(|(_, addr)| addr);
+            Target {
+                number,
+                line: line.to_string(),
+                addr,
+            }
+        })
+        .buffered(RESOLVE_CONCURRENCY)
+        .collect()
+        .await;
+
+    let mut seen = HashSet::new();
+    resolved
+        .into_iter()
+        .filter(|target| target.addr.as_ref().map_or(true, |addr| seen.insert(*addr)))
+        .collect()
+}
+
+/// Reads the servers listed in `path`, or on stdin if it is `-`, failing if
+/// there are none.
+pub async fn read(path: &Path, settings: &Settings) -> Result<Vec<Target>> {
+    let text = if path == Path::new("-") {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read the server list from stdin")?
+    } else {
+        std::fs::read_to_string(path).with_context(|| format!("Failed to read {}", path.display()))?
+    };
+    let targets = parse(&text, settings).await;
+    if targets.is_empty() {
+        bail!("No servers listed in {}", path.display());
+    }
+    Ok(targets)
+}
+
+/// Like [`read`], for the commands that need every line resolved: fails on
+/// the first one that is not.
+pub async fn read_addresses(path: &Path, settings: &Settings) -> Result<Vec<SocketAddr>> {
+    read(path, settings)
+        .await?
+        .into_iter()
+        .map(|target| {
+            target
+                .addr
+                .context("Invalid server address")
+                .with_context(|| format!("line {}: {}", target.number, target.line))
+                .with_context(|| format!("Invalid server list {}", path.display()))
+        })
+        .collect()
+}
@@ -0,0 +1,125 @@
+//! Output templates over server snapshots.
+//!
+//! A template is text with `{field}` placeholders, e.g.
+//! `{hostname} {players}/{max_players} ({ping}ms)`, rendered with
+//! [`render_template_with`]. Fields without a value, such as `ping` when the
+//! ping query failed, render as nothing unless a fallback is given with
+//! `{field|fallback}`. `{{` and `}}` are literal braces; `{rule.<name>}` is
+//! the value of a server rule.
+
+use anyhow::{bail, Result};
+use samp_query::utils::{render_template_with, template_placeholders};
+use samp_query::ServerSnapshot;
+
+/// Field names accepted in placeholders, besides `rule.<name>`.
+const FIELDS: &[&str] = &[
+    "address",
+    "hostname",
+    "gamemode",
+    "language",
+    "players",
+    "max_players",
+    "password",
+    "ping",
+    "player_names",
+    "timestamp",
+];
+
+#[derive(Debug)]
+pub struct Template {
+    text: String,
+}
+
+impl Template {
+    /// Checks that every placeholder names a known field.
+    pub fn parse(text: &str) -> Result<Self> {
+        for placeholder in template_placeholders(text) {
+            let name = placeholder.name;
+            let is_rule = name.strip_prefix("rule.").is_some_and(|rule| !rule.is_empty());
+            if !FIELDS.contains(&name) && !is_rule {
+                bail!(
+                    "Unknown template field {:?}; expected one of {} or rule.<name>",
+                    name,
+                    FIELDS.join(", ")
+                );
+            }
+        }
+
+        Ok(Self { text: text.to_string() })
+    }
+
+    pub fn render(&self, snapshot: &ServerSnapshot) -> String {
+        render_template_with(&self.text, |placeholder| {
+            field(snapshot, placeholder.name).or_else(|| Some(placeholder.fallback.unwrap_or_default().to_string()))
+        })
+    }
+}
+
+fn field(snapshot: &ServerSnapshot, name: &str) -> Option<String> {
+    let info = &snapshot.info;
+    if let Some(rule) = name.strip_prefix("rule.") {
+        return snapshot.rules.as_ref()?.rules.get(rule).cloned();
+    }
+
+    Some(match name {
+        "address" => snapshot.address.to_string(),
+        "hostname" => info.hostname.clone(),
+        "gamemode" => info.gamemode.clone(),
+        "language" => info.language.clone(),
+        "players" => info.players.to_string(),
+        "max_players" => info.max_players.to_string(),
+        "password" => if info.password { "yes" } else { "no" }.to_string(),
+        "ping" => snapshot.ping.as_ref()?.ping_ms.to_string(),
+        "player_names" => snapshot
+            .players
+            .as_ref()?
+            .players
+            .iter()
+            .map(|player| player.name.as_str())
+            .collect::<Vec<_>>()
+            .join(", "),
+        "timestamp" => snapshot.timestamp.to_string(),
+        _ => return None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use samp_query::ServerInfo;
+
+    fn snapshot() -> ServerSnapshot {
+        ServerSnapshot {
+            address: "127.0.0.1:7777".parse().unwrap(),
+            timestamp: 1,
+            info: ServerInfo {
+                password: false,
+                players: 12,
+                max_players: 50,
+                hostname: "Los Santos".to_string(),
+                gamemode: "Freeroam".to_string(),
+                language: "English".to_string(),
+            },
+            rules: None,
+            players: None,
+            ping: None,
+        }
+    }
+
+    #[test]
+    fn parse_rejects_unknown_fields() {
+        assert!(Template::parse("{hostname} {rule.version|?}").is_ok());
+        assert!(Template::parse("{{hostname}} and {{nothing}}").is_ok());
+        assert!(Template::parse("{hostnam}").is_err());
+        assert!(Template::parse("{rule.}").is_err());
+    }
+
+    #[test]
+    fn render_uses_fallbacks_for_missing_fields() {
+        let template = Template::parse("{hostname} {players}/{max_players} ({ping|?}ms){ping}").unwrap();
+        assert_eq!(template.render(&snapshot()), "Los Santos 12/50 (?ms)");
+
+        let template = Template::parse("{{hostname}} {rule.weather|clear}").unwrap();
+        assert_eq!(template.render(&snapshot()), "{hostname} clear");
+    }
+}
@@ -0,0 +1,292 @@
+//! The `top` command: a full-screen player table for one server, refreshed
+//! in place like `htop`.
+//!
+//! Players who joined since a recent refresh are marked in the table, and
+//! players who left stay in it, marked, for a few refreshes before they are
+//! dropped.
+
+use crate::output::clock;
+use anyhow::Result;
+use clap::ValueEnum;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Color, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Table};
+use ratatui::{DefaultTerminal, Frame};
+use samp_query::game::{GamePlayer, GameServerClient};
+use samp_query::utils::unix_timestamp_ms;
+use samp_query::{Client, ClientConfig, ServerInfo};
+use std::cmp::Reverse;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Number of refreshes a join or leave stays marked.
+const MARKED_REFRESHES: u32 = 3;
+
+/// The column `top` sorts players by.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, ValueEnum)]
+pub enum TopSort {
+    /// Highest score first.
+    #[default]
+    Score,
+    /// Lowest ping first.
+    Ping,
+}
+
+impl TopSort {
+    fn toggle(self) -> Self {
+        match self {
+            TopSort::Score => TopSort::Ping,
+            TopSort::Ping => TopSort::Score,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            TopSort::Score => "score",
+            TopSort::Ping => "ping",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Mark {
+    Joined,
+    Left,
+}
+
+struct Entry {
+    player: GamePlayer,
+    /// The join or leave shown next to the player, and the refreshes it
+    /// stays shown for.
+    mark: Option<(Mark, u32)>,
+}
+
+struct Update {
+    info: ServerInfo,
+    players: Vec<GamePlayer>,
+    ping_ms: Option<u64>,
+}
+
+struct Top {
+    addr: SocketAddr,
+    config: ClientConfig,
+    sort: TopSort,
+    info: Option<ServerInfo>,
+    ping_ms: Option<u64>,
+    entries: Vec<Entry>,
+    /// Whether a refresh has been applied, so the first player list is not
+    /// shown as everyone joining.
+    seeded: bool,
+    error: Option<String>,
+    updated: Option<u64>,
+    refreshing: bool,
+    updates: mpsc::UnboundedSender<samp_query::Result<Update>>,
+}
+
+impl Top {
+    fn refresh(&mut self) {
+        if self.refreshing {
+            return;
+        }
+        self.refreshing = true;
+
+        let (addr, config, updates) = (self.addr, self.config.clone(), self.updates.clone());
+        tokio::spawn(async move {
+            let _ = updates.send(poll(addr, config).await);
+        });
+    }
+
+    fn apply(&mut self, result: samp_query::Result<Update>) {
+        self.refreshing = false;
+        self.updated = Some(unix_timestamp_ms());
+        let update = match result {
+            Ok(update) => update,
+            Err(e) => {
+                self.error = Some(e.to_string());
+                return;
+            }
+        };
+        self.error = None;
+        self.info = Some(update.info);
+        self.ping_ms = update.ping_ms;
+
+        let mut previous: HashMap<String, Entry> = std::mem::take(&mut self.entries)
+            .into_iter()
+            .map(|entry| (entry.player.name.clone(), entry))
+            .collect();
+        for player in update.players {
+            let mark = match previous.remove(&player.name) {
+                Some(entry) => match entry.mark {
+                    Some((Mark::Joined, refreshes)) => countdown(Mark::Joined, refreshes),
+                    // A player who left and came back joined again.
+                    Some((Mark::Left, _)) => Some((Mark::Joined, MARKED_REFRESHES)),
+                    None => None,
+                },
+                None if self.seeded => Some((Mark::Joined, MARKED_REFRESHES)),
+                None => None,
+            };
+            self.entries.push(Entry { player, mark });
+        }
+        // Players who left keep their last score and ping until the mark runs out.
+        for entry in previous.into_values() {
+            let mark = match entry.mark {
+                Some((Mark::Left, refreshes)) => countdown(Mark::Left, refreshes),
+                _ => Some((Mark::Left, MARKED_REFRESHES)),
+            };
+            if mark.is_some() {
+                self.entries.push(Entry { mark, ..entry });
+            }
+        }
+        self.seeded = true;
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let [header, table, help] =
+            Layout::vertical([Constraint::Length(3), Constraint::Min(3), Constraint::Length(1)]).areas(frame.area());
+
+        let mut lines = Vec::new();
+        match &self.info {
+            Some(info) => {
+                let ping = self.ping_ms.map_or_else(|| "-".to_string(), |ping| format!("{} ms", ping));
+                lines.push(Line::from(vec![
+                    info.hostname.clone().bold(),
+                    "  ".into(),
+                    format!("{}/{} players", info.players, info.max_players).green(),
+                    format!("  ping {}", ping).into(),
+                ]));
+            }
+            None => lines.push(Line::from("Waiting for the first answer...".dark_gray())),
+        }
+        match (&self.error, self.updated) {
+            (Some(error), _) => lines.push(Line::from(error.clone().red())),
+            (None, Some(updated)) => lines.push(Line::from(format!("Updated {}", clock(updated)).dark_gray())),
+            (None, None) => lines.push(Line::default()),
+        }
+        frame.render_widget(Paragraph::new(lines).block(Block::new().title(format!("top {}", self.addr))), header);
+
+        let online = self.entries.iter().filter(|entry| entry.mark.map(|(mark, _)| mark) != Some(Mark::Left)).count();
+        let rows = self.sorted().into_iter().map(|entry| {
+            let (marker, style) = match entry.mark {
+                Some((Mark::Joined, _)) => ("joined", Style::new().fg(Color::Green)),
+                Some((Mark::Left, _)) => ("left", Style::new().fg(Color::Red).dim()),
+                None => ("", Style::new()),
+            };
+            let player = &entry.player;
+            Row::new(vec![
+                player.name.clone(),
+                player.score.map_or_else(String::new, |score| score.to_string()),
+                player.ping.map_or_else(String::new, |ping| format!("{} ms", ping.as_millis())),
+                marker.to_string(),
+            ])
+            .style(style)
+        });
+        let widths = [
+            Constraint::Min(24),
+            Constraint::Length(10),
+            Constraint::Length(10),
+            Constraint::Length(8),
+        ];
+        frame.render_widget(
+            Table::new(rows, widths)
+                .header(Row::new(vec!["Name", "Score", "Ping", ""]).bold().reversed())
+                .block(Block::new().title(format!("Players ({}) by {}", online, self.sort.label()))),
+            table,
+        );
+
+        let help_text = format!(" q quit  r refresh  s sort by {}", self.sort.toggle().label());
+        frame.render_widget(Paragraph::new(help_text).dark_gray(), help);
+    }
+
+    /// The entries in the chosen order, by name among equals; players
+    /// without a score or ping sort last.
+    fn sorted(&self) -> Vec<&Entry> {
+        let mut entries: Vec<&Entry> = self.entries.iter().collect();
+        entries.sort_by_key(|entry| entry.player.name.to_lowercase());
+        match self.sort {
+            TopSort::Score => entries.sort_by_key(|entry| Reverse(entry.player.score)),
+            TopSort::Ping => entries.sort_by_key(|entry| entry.player.ping.unwrap_or(Duration::MAX)),
+        }
+        entries
+    }
+}
+
+/// The mark after one more refresh, or `None` once it has been shown for
+/// [`MARKED_REFRESHES`] refreshes.
+fn countdown(mark: Mark, refreshes: u32) -> Option<(Mark, u32)> {
+    (refreshes > 1).then(|| (mark, refreshes - 1))
+}
+
+async fn poll(addr: SocketAddr, config: ClientConfig) -> samp_query::Result<Update> {
+    let client = Client::connect_with_config(addr, config).await?;
+    let info = client.query_info().await?;
+    let players = GameServerClient::players(&client).await.unwrap_or_default();
+    let ping_ms = client.query_ping().await.ok().map(|ping| ping.ping_ms);
+
+    Ok(Update { info, players, ping_ms })
+}
+
+/// Shows the players of `addr` until the user quits.
+pub async fn run(addr: SocketAddr, interval: Duration, sort: TopSort, config: ClientConfig) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_top(&mut terminal, addr, interval, sort, config).await;
+    ratatui::restore();
+    result
+}
+
+async fn run_top(
+    terminal: &mut DefaultTerminal,
+    addr: SocketAddr,
+    interval: Duration,
+    sort: TopSort,
+    config: ClientConfig,
+) -> Result<()> {
+    let (updates, mut results) = mpsc::unbounded_channel();
+    let mut top = Top {
+        addr,
+        config,
+        sort,
+        info: None,
+        ping_ms: None,
+        entries: Vec::new(),
+        seeded: false,
+        error: None,
+        updated: None,
+        refreshing: false,
+        updates,
+    };
+    let mut events = EventStream::new();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        terminal.draw(|frame| top.draw(frame))?;
+
+        tokio::select! {
+            _ = ticker.tick() => top.refresh(),
+            Some(result) = results.recv() => top.apply(result),
+            event = events.next() => {
+                let key = match event {
+                    Some(Ok(Event::Key(key))) => key,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                    None => return Ok(()),
+                };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                    KeyCode::Char('r') => top.refresh(),
+                    KeyCode::Char('s') => top.sort = top.sort.toggle(),
+                    _ => {}
+                }
+            }
+        }
+    }
+}
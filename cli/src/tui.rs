@@ -0,0 +1,294 @@
+//! The `tui` command: a live dashboard for one or more servers.
+
+use crate::output::clock;
+use anyhow::Result;
+use crossterm::event::{Event, EventStream, KeyCode, KeyEventKind, KeyModifiers};
+use futures::StreamExt;
+use ratatui::layout::{Constraint, Layout, Rect};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Paragraph, Row, Sparkline, Table, Tabs};
+use ratatui::{DefaultTerminal, Frame};
+use samp_query::game::{GamePlayer, GameServerClient};
+use samp_query::utils::unix_timestamp_ms;
+use samp_query::{Client, ClientConfig, ServerInfo};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+/// Number of pings kept for the graph.
+const PING_HISTORY: usize = 120;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum SortBy {
+    Score,
+    Name,
+    Ping,
+}
+
+impl SortBy {
+    fn next(self) -> Self {
+        match self {
+            SortBy::Score => SortBy::Name,
+            SortBy::Name => SortBy::Ping,
+            SortBy::Ping => SortBy::Score,
+        }
+    }
+
+    fn label(self) -> &'static str {
+        match self {
+            SortBy::Score => "score",
+            SortBy::Name => "name",
+            SortBy::Ping => "ping",
+        }
+    }
+
+    fn sort(self, players: &mut [GamePlayer]) {
+        match self {
+            SortBy::Score => players.sort_by_key(|player| std::cmp::Reverse(player.score)),
+            SortBy::Name => players.sort_by_key(|player| player.name.to_lowercase()),
+            SortBy::Ping => players.sort_by_key(|player| player.ping.unwrap_or(Duration::MAX)),
+        }
+    }
+}
+
+struct Update {
+    info: ServerInfo,
+    players: Vec<GamePlayer>,
+    ping_ms: Option<u64>,
+}
+
+#[derive(Default)]
+struct ServerView {
+    info: Option<ServerInfo>,
+    players: Vec<GamePlayer>,
+    pings: VecDeque<u64>,
+    error: Option<String>,
+    updated: Option<u64>,
+    refreshing: bool,
+}
+
+struct App {
+    servers: Vec<(SocketAddr, ServerView)>,
+    config: ClientConfig,
+    selected: usize,
+    sort: SortBy,
+    updates: mpsc::UnboundedSender<(usize, samp_query::Result<Update>)>,
+}
+
+impl App {
+    /// Starts a poll of every server that is not already being polled.
+    fn refresh(&mut self) {
+        for (index, (addr, view)) in self.servers.iter_mut().enumerate() {
+            if view.refreshing {
+                continue;
+            }
+            view.refreshing = true;
+
+            let (addr, config, updates) = (*addr, self.config.clone(), self.updates.clone());
+            tokio::spawn(async move {
+                let _ = updates.send((index, poll(addr, config).await));
+            });
+        }
+    }
+
+    fn apply(&mut self, index: usize, result: samp_query::Result<Update>) {
+        let Some((_, view)) = self.servers.get_mut(index) else {
+            return;
+        };
+        view.refreshing = false;
+        view.updated = Some(unix_timestamp_ms());
+
+        match result {
+            Ok(update) => {
+                view.info = Some(update.info);
+                view.players = update.players;
+                view.error = None;
+                if let Some(ping) = update.ping_ms {
+                    if view.pings.len() == PING_HISTORY {
+                        view.pings.pop_front();
+                    }
+                    view.pings.push_back(ping);
+                }
+            }
+            Err(e) => view.error = Some(e.to_string()),
+        }
+    }
+
+    fn draw(&self, frame: &mut Frame) {
+        let tabs_height = if self.servers.len() > 1 { 3 } else { 0 };
+        let [tabs, info, players, pings, help] = Layout::vertical([
+            Constraint::Length(tabs_height),
+            Constraint::Length(8),
+            Constraint::Min(5),
+            Constraint::Length(8),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+
+        if tabs_height > 0 {
+            let titles = self.servers.iter().map(|(addr, view)| match &view.info {
+                Some(info) => format!("{} ({})", addr, info.players),
+                None => addr.to_string(),
+            });
+            frame.render_widget(
+                Tabs::new(titles)
+                    .select(self.selected)
+                    .highlight_style(Style::new().fg(Color::Yellow).add_modifier(Modifier::BOLD))
+                    .block(Block::bordered().title("Servers")),
+                tabs,
+            );
+        }
+
+        let (addr, view) = &self.servers[self.selected];
+        self.draw_info(frame, info, *addr, view);
+        self.draw_players(frame, players, view);
+        draw_pings(frame, pings, view);
+
+        let help_text = format!(
+            " q quit  r refresh  s sort ({}){}",
+            self.sort.label(),
+            if self.servers.len() > 1 { "  ←/→ server" } else { "" }
+        );
+        frame.render_widget(Paragraph::new(help_text).dark_gray(), help);
+    }
+
+    fn draw_info(&self, frame: &mut Frame, area: Rect, addr: SocketAddr, view: &ServerView) {
+        let mut lines = Vec::new();
+        match &view.info {
+            Some(info) => {
+                lines.push(Line::from(vec!["Hostname: ".bold(), info.hostname.clone().into()]));
+                lines.push(Line::from(vec![
+                    "Players:  ".bold(),
+                    format!("{}/{}", info.players, info.max_players).green(),
+                ]));
+                lines.push(Line::from(vec!["Gamemode: ".bold(), info.gamemode.clone().into()]));
+                lines.push(Line::from(vec!["Language: ".bold(), info.language.clone().into()]));
+                lines.push(Line::from(vec![
+                    "Password: ".bold(),
+                    if info.password { "Yes".red() } else { "No".green() },
+                ]));
+            }
+            None => lines.push(Line::from("Waiting for the first answer...".dark_gray())),
+        }
+        match (&view.error, view.updated) {
+            (Some(error), _) => lines.push(Line::from(error.clone().red())),
+            (None, Some(updated)) => lines.push(Line::from(format!("Updated {}", clock(updated)).dark_gray())),
+            (None, None) => {}
+        }
+
+        frame.render_widget(
+            Paragraph::new(lines).block(Block::bordered().title(format!("Server {}", addr))),
+            area,
+        );
+    }
+
+    fn draw_players(&self, frame: &mut Frame, area: Rect, view: &ServerView) {
+        let mut players = view.players.clone();
+        self.sort.sort(&mut players);
+
+        let rows = players.iter().map(|player| {
+            Row::new(vec![
+                player.name.clone(),
+                player.score.map_or_else(String::new, |score| score.to_string()),
+                player
+                    .ping
+                    .map_or_else(String::new, |ping| format!("{} ms", ping.as_millis())),
+            ])
+        });
+        let table = Table::new(
+            rows,
+            [Constraint::Min(24), Constraint::Length(10), Constraint::Length(10)],
+        )
+        .header(Row::new(vec!["Name", "Score", "Ping"]).bold())
+        .block(Block::bordered().title(format!("Players ({})", players.len())));
+
+        frame.render_widget(table, area);
+    }
+}
+
+fn draw_pings(frame: &mut Frame, area: Rect, view: &ServerView) {
+    // Show the most recent pings that fit in the panel.
+    let width = area.width.saturating_sub(2) as usize;
+    let pings: Vec<u64> = view.pings.iter().skip(view.pings.len().saturating_sub(width)).copied().collect();
+    let title = match (pings.last(), pings.iter().max()) {
+        (Some(last), Some(max)) => format!("Ping {} ms (max {} ms)", last, max),
+        _ => "Ping".to_string(),
+    };
+
+    frame.render_widget(
+        Sparkline::default()
+            .data(&pings)
+            .style(Style::new().fg(Color::Cyan))
+            .block(Block::bordered().title(title)),
+        area,
+    );
+}
+
+async fn poll(addr: SocketAddr, config: ClientConfig) -> samp_query::Result<Update> {
+    let client = Client::connect_with_config(addr, config).await?;
+    let info = client.query_info().await?;
+    let players = GameServerClient::players(&client).await.unwrap_or_default();
+    let ping_ms = client.query_ping().await.ok().map(|ping| ping.ping_ms);
+
+    Ok(Update { info, players, ping_ms })
+}
+
+/// Runs the dashboard until the user quits.
+pub async fn run(servers: Vec<SocketAddr>, interval: Duration, config: ClientConfig) -> Result<()> {
+    let mut terminal = ratatui::init();
+    let result = run_app(&mut terminal, servers, interval, config).await;
+    ratatui::restore();
+    result
+}
+
+async fn run_app(
+    terminal: &mut DefaultTerminal,
+    servers: Vec<SocketAddr>,
+    interval: Duration,
+    config: ClientConfig,
+) -> Result<()> {
+    let (updates, mut results) = mpsc::unbounded_channel();
+    let mut app = App {
+        servers: servers.into_iter().map(|addr| (addr, ServerView::default())).collect(),
+        config,
+        selected: 0,
+        sort: SortBy::Score,
+        updates,
+    };
+    let mut events = EventStream::new();
+    let mut ticker = tokio::time::interval(interval);
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+
+    loop {
+        terminal.draw(|frame| app.draw(frame))?;
+
+        tokio::select! {
+            _ = ticker.tick() => app.refresh(),
+            Some((index, result)) = results.recv() => app.apply(index, result),
+            event = events.next() => {
+                let key = match event {
+                    Some(Ok(Event::Key(key))) => key,
+                    Some(Ok(_)) => continue,
+                    Some(Err(e)) => return Err(e.into()),
+                    None => return Ok(()),
+                };
+                if key.kind != KeyEventKind::Press {
+                    continue;
+                }
+                match key.code {
+                    KeyCode::Char('q') | KeyCode::Esc => return Ok(()),
+                    KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => return Ok(()),
+                    KeyCode::Char('r') => app.refresh(),
+                    KeyCode::Char('s') => app.sort = app.sort.next(),
+                    KeyCode::Right | KeyCode::Tab => app.selected = (app.selected + 1) % app.servers.len(),
+                    KeyCode::Left | KeyCode::BackTab => {
+                        app.selected = (app.selected + app.servers.len() - 1) % app.servers.len();
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,122 @@
+//! Re-running a query on an interval.
+
+use crate::output::{clock, player_changes};
+use anyhow::{Context, Result};
+use colored::Colorize;
+use samp_query::PlayerList;
+use samp_query::utils::unix_timestamp_ms;
+use std::future::Future;
+use std::io::Write;
+use std::time::Duration;
+
+/// Clears the terminal and moves the cursor to the top left.
+const CLEAR_SCREEN: &str = "\x1B[2J\x1B[H";
+
+/// Runs `query` every `interval` and redraws the screen with `render`, which
+/// also receives the previous successful result to highlight changes.
+///
+/// Failed queries are shown in place of the output rather than ending the
+/// watch, so a server can be watched through a restart. Runs until interrupted.
+pub async fn watch<T, F, Fut>(
+    title: &str,
+    interval: Duration,
+    mut query: F,
+    render: impl Fn(&T, Option<&T>) -> String,
+) -> Result<()>
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = Result<T>>,
+{
+    let mut ticker = tokio::time::interval(interval.max(Duration::from_millis(100)));
+    ticker.set_missed_tick_behavior(tokio::time::MissedTickBehavior::Delay);
+    let mut previous: Option<T> = None;
+
+    loop {
+        ticker.tick().await;
+        let screen = match query().await {
+            Ok(value) => {
+                let screen = render(&value, previous.as_ref());
+                previous = Some(value);
+                screen
+            }
+            Err(e) => format!("{:#}", e).red().to_string(),
+        };
+
+        let header = format!(
+            "Every {}s: {}    {}",
+            interval.as_secs_f64(),
+            title,
+            clock(unix_timestamp_ms())
+        );
+        print!("{}{}\n\n{}\n", CLEAR_SCREEN, header.dimmed(), screen);
+        std::io::stdout().flush()?;
+    }
+}
+
+/// What `players --watch` does when players join or leave.
+#[derive(Debug, Clone, Default)]
+pub struct PlayerAlerts {
+    /// Ring the terminal bell.
+    pub bell: bool,
+    /// Send a desktop notification.
+    pub notify: bool,
+    /// Only alert on these nicknames, compared case-insensitively. Empty
+    /// means every player.
+    pub names: Vec<String>,
+}
+
+impl PlayerAlerts {
+    fn watches(&self, name: &str) -> bool {
+        self.names.is_empty() || self.names.iter().any(|watched| watched.eq_ignore_ascii_case(name))
+    }
+
+    /// Alerts on the watched players who joined or left `server` since
+    /// `previous`, returning what to append to the screen: the bell, or why
+    /// the notification could not be sent.
+    pub fn alert(&self, server: &str, players: &PlayerList, previous: Option<&PlayerList>) -> String {
+        let Some(previous) = previous else {
+            return String::new();
+        };
+        let (joined, left) = player_changes(players, previous);
+        let changes: Vec<String> = joined
+            .into_iter()
+            .filter(|name| self.watches(name))
+            .map(|name| format!("{} joined", name))
+            .chain(left.into_iter().filter(|name| self.watches(name)).map(|name| format!("{} left", name)))
+            .collect();
+        if changes.is_empty() {
+            return String::new();
+        }
+
+        let mut output = String::new();
+        if self.notify {
+            if let Err(e) = desktop_notification(server, &changes.join("\n")) {
+                output.push_str(&format!("\n{}", format!("Failed to send notification: {:#}", e).red()));
+            }
+        }
+        if self.bell {
+            output.push('\x07');
+        }
+        output
+    }
+}
+
+/// Shows a notification through `notify-send`, or `osascript` on macOS,
+/// without waiting for it.
+fn desktop_notification(title: &str, body: &str) -> Result<()> {
+    let program = if cfg!(target_os = "macos") { "osascript" } else { "notify-send" };
+    let mut command = tokio::process::Command::new(program);
+    if cfg!(target_os = "macos") {
+        command
+            .arg("-e")
+            .arg(format!("display notification {:?} with title {:?}", body, title));
+    } else {
+        command.arg(title).arg(body);
+    }
+    command
+        .stdout(std::process::Stdio::null())
+        .stderr(std::process::Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {}", program))?;
+    Ok(())
+}
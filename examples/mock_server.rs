@@ -0,0 +1,165 @@
+//! Serves a fake SA-MP server described by a TOML file, for developing
+//! against the query protocol without a real server install.
+//!
+//! Usage: `cargo run --example mock_server -- [config] [listen]`
+//!
+//! The config defaults to `examples/mock_server.toml`, which documents the
+//! format; `listen` overrides the address from the file.
+
+use samp_query::server::{DataSource, Server};
+use samp_query::{DetailedPlayer, DetailedPlayerList, Error, Result, ServerInfo, ServerRules};
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct Config {
+    listen: SocketAddr,
+    info: InfoConfig,
+    rules: HashMap<String, String>,
+    players: Vec<PlayerConfig>,
+    rcon: Option<RconConfig>,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            listen: SocketAddr::from(([127, 0, 0, 1], 7777)),
+            info: InfoConfig::default(),
+            rules: HashMap::new(),
+            players: Vec::new(),
+            rcon: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+struct InfoConfig {
+    hostname: String,
+    gamemode: String,
+    language: String,
+    max_players: u16,
+    password: bool,
+    /// Reported player count; the length of the player list when unset.
+    players: Option<u16>,
+}
+
+impl Default for InfoConfig {
+    fn default() -> Self {
+        Self {
+            hostname: "Mock Server".to_string(),
+            gamemode: "Freeroam".to_string(),
+            language: "English".to_string(),
+            max_players: 50,
+            password: false,
+            players: None,
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+struct PlayerConfig {
+    name: String,
+    #[serde(default)]
+    score: i32,
+    #[serde(default)]
+    ping: u32,
+}
+
+#[derive(Debug, Deserialize)]
+struct RconConfig {
+    password: String,
+    /// Output lines per command.
+    #[serde(default)]
+    commands: HashMap<String, Vec<String>>,
+}
+
+struct MockSource {
+    info: ServerInfo,
+    rules: ServerRules,
+    players: DetailedPlayerList,
+    rcon: Option<RconConfig>,
+}
+
+impl From<Config> for MockSource {
+    fn from(config: Config) -> Self {
+        let players = config
+            .players
+            .into_iter()
+            .enumerate()
+            .map(|(id, player)| DetailedPlayer {
+                id: id as u8,
+                name: player.name,
+                score: player.score,
+                ping: player.ping,
+            })
+            .collect::<Vec<_>>();
+
+        Self {
+            info: ServerInfo {
+                password: config.info.password,
+                players: config.info.players.unwrap_or(players.len() as u16),
+                max_players: config.info.max_players,
+                hostname: config.info.hostname,
+                gamemode: config.info.gamemode,
+                language: config.info.language,
+            },
+            rules: ServerRules { rules: config.rules },
+            players: DetailedPlayerList { players },
+            rcon: config.rcon,
+        }
+    }
+}
+
+impl DataSource for MockSource {
+    fn info(&self) -> ServerInfo {
+        self.info.clone()
+    }
+
+    fn rules(&self) -> ServerRules {
+        self.rules.clone()
+    }
+
+    fn players(&self) -> DetailedPlayerList {
+        self.players.clone()
+    }
+
+    /// Answers configured commands like a real server, including the
+    /// response to unknown ones.
+    fn rcon(&self, password: &str, command: &str) -> Option<Vec<String>> {
+        let rcon = self.rcon.as_ref().filter(|rcon| rcon.password == password)?;
+        let name = command.split_whitespace().next().unwrap_or_default();
+
+        Some(match rcon.commands.get(name) {
+            Some(lines) => lines.clone(),
+            None => vec!["Unknown command or variable:".to_string(), command.to_string()],
+        })
+    }
+}
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let path = args
+        .next()
+        .unwrap_or_else(|| concat!(env!("CARGO_MANIFEST_DIR"), "/examples/mock_server.toml").to_string());
+    let mut config: Config = toml::from_str(&std::fs::read_to_string(&path)?)
+        .map_err(|e| Error::Other(format!("{}: {}", path, e)))?;
+    if let Some(listen) = args.next() {
+        config.listen = listen.parse().map_err(Error::AddrParse)?;
+    }
+
+    let listen = config.listen;
+    let source = MockSource::from(config);
+    let summary = format!(
+        "\"{}\" ({}/{} players)",
+        source.info.hostname, source.info.players, source.info.max_players
+    );
+
+    let server = Server::bind(listen, source).await?;
+    println!("Serving {} on {}", summary, server.local_addr()?);
+
+    server.run().await
+}
@@ -0,0 +1,27 @@
+//! Runs a caching query proxy in front of a server.
+//!
+//! Usage: `cargo run --example query_proxy -- <upstream> [listen]`
+
+use samp_query::proxy::{ProxyConfig, QueryProxy};
+use samp_query::{Error, Result};
+use std::net::SocketAddr;
+
+#[tokio::main]
+async fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    let upstream: SocketAddr = args
+        .next()
+        .unwrap_or_else(|| "127.0.0.1:7777".to_string())
+        .parse()
+        .map_err(Error::AddrParse)?;
+    let listen: SocketAddr = args
+        .next()
+        .unwrap_or_else(|| "0.0.0.0:7778".to_string())
+        .parse()
+        .map_err(Error::AddrParse)?;
+
+    let proxy = QueryProxy::bind(ProxyConfig::new(upstream, listen)).await?;
+    println!("Relaying queries for {} on {}", upstream, proxy.local_addr()?);
+
+    proxy.run().await
+}
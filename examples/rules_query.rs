@@ -5,7 +5,7 @@ use std::net::SocketAddr;
 
 #[tokio::main]
 async fn main() -> Result<()> {
-    let addr: SocketAddr = "127.0.0.1:7777".parse().map_err(|e| Error::AddrParse(e))?;
+    let addr: SocketAddr = "127.0.0.1:7777".parse().map_err(Error::AddrParse)?;
     
     println!("Connecting to server at {}...", addr);
     
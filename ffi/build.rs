@@ -0,0 +1,21 @@
+use std::env;
+use std::path::PathBuf;
+
+/// Generates the C header into `OUT_DIR`. The checked-in copy in `include/`
+/// is regenerated explicitly with the cbindgen CLI, so building never writes
+/// to the source tree.
+fn main() {
+    let crate_dir = PathBuf::from(env::var("CARGO_MANIFEST_DIR").unwrap());
+    let out_dir = PathBuf::from(env::var("OUT_DIR").unwrap());
+    let config = cbindgen::Config::from_file(crate_dir.join("cbindgen.toml")).unwrap();
+
+    println!("cargo:rerun-if-changed=src/lib.rs");
+    println!("cargo:rerun-if-changed=cbindgen.toml");
+
+    match cbindgen::generate_with_config(&crate_dir, config) {
+        Ok(bindings) => {
+            bindings.write_to_file(out_dir.join("samp_query.h"));
+        }
+        Err(e) => println!("cargo:warning=Failed to generate C header: {}", e),
+    }
+}
@@ -0,0 +1,157 @@
+#ifndef SAMP_QUERY_H
+#define SAMP_QUERY_H
+
+/* This file is generated by cbindgen from ffi/src/lib.rs. Do not edit it by hand. */
+
+#include <stdarg.h>
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+#include <stdlib.h>
+
+/**
+ * Result of an FFI call.
+ */
+typedef enum SampStatus {
+  SAMP_STATUS_OK = 0,
+  /**
+   * A pointer was null or the address could not be parsed.
+   */
+  SAMP_STATUS_INVALID_ARGUMENT = 1,
+  /**
+   * The server did not answer in time.
+   */
+  SAMP_STATUS_TIMEOUT = 2,
+  /**
+   * The server answered with a malformed response.
+   */
+  SAMP_STATUS_INVALID_RESPONSE = 3,
+  /**
+   * A socket could not be opened or used.
+   */
+  SAMP_STATUS_NETWORK = 4,
+  /**
+   * Any other failure.
+   */
+  SAMP_STATUS_OTHER = 5,
+} SampStatus;
+
+/**
+ * Server information. Strings are NUL-terminated UTF-8.
+ */
+typedef struct SampServerInfo {
+  bool password;
+  uint16_t players;
+  uint16_t max_players;
+  char *hostname;
+  char *gamemode;
+  char *language;
+} SampServerInfo;
+
+typedef struct SampPlayer {
+  char *name;
+  int32_t score;
+} SampPlayer;
+
+typedef struct SampPlayerList {
+  struct SampPlayer *players;
+  size_t len;
+} SampPlayerList;
+
+typedef struct SampRule {
+  char *name;
+  char *value;
+} SampRule;
+
+typedef struct SampRuleList {
+  struct SampRule *rules;
+  size_t len;
+} SampRuleList;
+
+#ifdef __cplusplus
+extern "C" {
+#endif // __cplusplus
+
+/**
+ * Queries server information.
+ *
+ * # Safety
+ *
+ * `addr` must be a valid NUL-terminated string and `out` must point to
+ * writable memory for a `SampServerInfo`.
+ */
+enum SampStatus samp_query_info(const char *addr, uint32_t timeout_ms, struct SampServerInfo *out);
+
+/**
+ * Releases the strings of a `SampServerInfo` filled by [`samp_query_info`].
+ *
+ * # Safety
+ *
+ * `info` must be null or point to a value written by `samp_query_info` that
+ * has not been freed yet.
+ */
+void samp_server_info_free(struct SampServerInfo *info);
+
+/**
+ * Queries the client list (names and scores).
+ *
+ * # Safety
+ *
+ * `addr` must be a valid NUL-terminated string and `out` must point to
+ * writable memory for a `SampPlayerList`.
+ */
+enum SampStatus samp_query_players(const char *addr,
+                                   uint32_t timeout_ms,
+                                   struct SampPlayerList *out);
+
+/**
+ * Releases a `SampPlayerList` filled by [`samp_query_players`].
+ *
+ * # Safety
+ *
+ * `list` must be null or point to a value written by `samp_query_players`
+ * that has not been freed yet.
+ */
+void samp_player_list_free(struct SampPlayerList *list);
+
+/**
+ * Queries the server rules, sorted by name.
+ *
+ * # Safety
+ *
+ * `addr` must be a valid NUL-terminated string and `out` must point to
+ * writable memory for a `SampRuleList`.
+ */
+enum SampStatus samp_query_rules(const char *addr, uint32_t timeout_ms, struct SampRuleList *out);
+
+/**
+ * Releases a `SampRuleList` filled by [`samp_query_rules`].
+ *
+ * # Safety
+ *
+ * `list` must be null or point to a value written by `samp_query_rules`
+ * that has not been freed yet.
+ */
+void samp_rule_list_free(struct SampRuleList *list);
+
+/**
+ * Measures the round-trip time of a ping query in milliseconds.
+ *
+ * # Safety
+ *
+ * `addr` must be a valid NUL-terminated string and `out_ms` must point to
+ * a writable `uint32_t`.
+ */
+enum SampStatus samp_query_ping(const char *addr, uint32_t timeout_ms, uint32_t *out_ms);
+
+/**
+ * Returns a static, human-readable description of a status, or of an
+ * unknown status code.
+ */
+const char *samp_status_message(int status);
+
+#ifdef __cplusplus
+}  // extern "C"
+#endif  // __cplusplus
+
+#endif  /* SAMP_QUERY_H */
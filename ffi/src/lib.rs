@@ -0,0 +1,437 @@
+//! C bindings for the SAMP Query library.
+//!
+//! Every query function takes a NUL-terminated server address (`host`,
+//! `host:port` or `host,port`), a timeout in milliseconds and an output
+//! pointer, blocks until the query completes and returns a [`SampStatus`].
+//! Data written to an output pointer is owned by the caller and must be
+//! released with the matching `*_free` function.
+//!
+//! Panics never unwind into the caller: a call that panics returns
+//! [`SampStatus::Other`]. The functions may be called from any thread,
+//! including one driving a Tokio runtime.
+//!
+//! `include/samp_query.h` is generated by cbindgen and checked in; the build
+//! script writes a fresh copy to `OUT_DIR`. After changing the bindings,
+//! regenerate it with `cbindgen --config cbindgen.toml --output include/samp_query.h`
+//! from the crate directory.
+//!
+//! ```c
+//! SampServerInfo info;
+//! if (samp_query_info("127.0.0.1:7777", 1000, &info) == SAMP_STATUS_OK) {
+//!     printf("%s (%u/%u)\n", info.hostname, info.players, info.max_players);
+//!     samp_server_info_free(&info);
+//! }
+//! ```
+
+use samp_query::protocol::constants;
+use samp_query::utils::normalize_address;
+use samp_query::{Client, ClientConfig, Error};
+use std::ffi::{c_char, c_int, CStr, CString};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::sync::{mpsc, OnceLock};
+use tokio::runtime::Runtime;
+
+/// Result of an FFI call.
+#[repr(C)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampStatus {
+    Ok = 0,
+    /// A pointer was null or the address could not be parsed.
+    InvalidArgument = 1,
+    /// The server did not answer in time.
+    Timeout = 2,
+    /// The server answered with a malformed response.
+    InvalidResponse = 3,
+    /// A socket could not be opened or used.
+    Network = 4,
+    /// Any other failure.
+    Other = 5,
+}
+
+impl SampStatus {
+    fn from_code(code: c_int) -> Option<Self> {
+        Some(match code {
+            0 => SampStatus::Ok,
+            1 => SampStatus::InvalidArgument,
+            2 => SampStatus::Timeout,
+            3 => SampStatus::InvalidResponse,
+            4 => SampStatus::Network,
+            5 => SampStatus::Other,
+            _ => return None,
+        })
+    }
+}
+
+impl From<&Error> for SampStatus {
+    fn from(e: &Error) -> Self {
+        match e {
+            Error::Timeout => SampStatus::Timeout,
+            Error::InvalidResponse(_) | Error::Utf8(_) => SampStatus::InvalidResponse,
+            Error::Bind(_) | Error::Connect(_) | Error::Send(_) | Error::Receive(_) | Error::Io(_) => {
+                SampStatus::Network
+            }
+            Error::AddrParse(_) => SampStatus::InvalidArgument,
+            _ => SampStatus::Other,
+        }
+    }
+}
+
+/// Server information. Strings are NUL-terminated UTF-8.
+#[repr(C)]
+#[derive(Debug)]
+pub struct SampServerInfo {
+    pub password: bool,
+    pub players: u16,
+    pub max_players: u16,
+    pub hostname: *mut c_char,
+    pub gamemode: *mut c_char,
+    pub language: *mut c_char,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SampPlayer {
+    pub name: *mut c_char,
+    pub score: i32,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SampPlayerList {
+    pub players: *mut SampPlayer,
+    pub len: usize,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SampRule {
+    pub name: *mut c_char,
+    pub value: *mut c_char,
+}
+
+#[repr(C)]
+#[derive(Debug)]
+pub struct SampRuleList {
+    pub rules: *mut SampRule,
+    pub len: usize,
+}
+
+fn runtime() -> &'static Runtime {
+    static RUNTIME: OnceLock<Runtime> = OnceLock::new();
+    RUNTIME.get_or_init(|| {
+        tokio::runtime::Builder::new_multi_thread()
+            .worker_threads(1)
+            .enable_all()
+            .build()
+            .expect("Failed to start the query runtime")
+    })
+}
+
+/// Runs `future` on the query runtime and waits for it. The future is
+/// spawned rather than driven with `block_on`, which panics on a thread
+/// that drives another runtime.
+fn block_on<F>(future: F) -> F::Output
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    let (sender, receiver) = mpsc::sync_channel(1);
+    runtime().spawn(async move {
+        let _ = sender.send(future.await);
+    });
+    receiver.recv().expect("the query task panicked")
+}
+
+/// Calls `f`, returning `fallback` if it panics instead of unwinding into C.
+fn catch<T>(fallback: T, f: impl FnOnce() -> T) -> T {
+    panic::catch_unwind(AssertUnwindSafe(f)).unwrap_or(fallback)
+}
+
+unsafe fn parse_addr(addr: *const c_char) -> Option<SocketAddr> {
+    if addr.is_null() {
+        return None;
+    }
+
+    let addr = CStr::from_ptr(addr).to_str().ok()?;
+    normalize_address(addr, constants::DEFAULT_PORT)
+        .ok()
+        .map(|(_, addr)| addr)
+}
+
+fn to_c_string(value: String) -> *mut c_char {
+    CString::new(value.replace('\0', ""))
+        .map(CString::into_raw)
+        .unwrap_or(ptr::null_mut())
+}
+
+unsafe fn free_c_string(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+/// Runs a query against `addr` and passes the result to `write`.
+unsafe fn run<T, F, Fut>(addr: *const c_char, timeout_ms: u32, query: F, write: impl FnOnce(T)) -> SampStatus
+where
+    F: FnOnce(Client) -> Fut + Send + 'static,
+    Fut: Future<Output = samp_query::Result<T>> + Send,
+    T: Send + 'static,
+{
+    catch(SampStatus::Other, || run_inner(addr, timeout_ms, query, write))
+}
+
+unsafe fn run_inner<T, F, Fut>(addr: *const c_char, timeout_ms: u32, query: F, write: impl FnOnce(T)) -> SampStatus
+where
+    F: FnOnce(Client) -> Fut + Send + 'static,
+    Fut: Future<Output = samp_query::Result<T>> + Send,
+    T: Send + 'static,
+{
+    let Some(addr) = parse_addr(addr) else {
+        return SampStatus::InvalidArgument;
+    };
+    let config = ClientConfig {
+        timeout_ms: timeout_ms as u64,
+        max_retries: constants::MAX_RETRIES,
+        ..Default::default()
+    };
+
+    let result = block_on(async move {
+        let client = Client::connect_with_config(addr, config).await?;
+        query(client).await
+    });
+
+    match result {
+        Ok(value) => {
+            write(value);
+            SampStatus::Ok
+        }
+        Err(e) => SampStatus::from(&e),
+    }
+}
+
+/// Queries server information.
+///
+/// # Safety
+///
+/// `addr` must be a valid NUL-terminated string and `out` must point to
+/// writable memory for a `SampServerInfo`.
+#[no_mangle]
+pub unsafe extern "C" fn samp_query_info(
+    addr: *const c_char,
+    timeout_ms: u32,
+    out: *mut SampServerInfo,
+) -> SampStatus {
+    if out.is_null() {
+        return SampStatus::InvalidArgument;
+    }
+
+    run(
+        addr,
+        timeout_ms,
+        |client| async move { client.query_info().await },
+        |info| {
+            out.write(SampServerInfo {
+                password: info.password,
+                players: info.players,
+                max_players: info.max_players,
+                hostname: to_c_string(info.hostname),
+                gamemode: to_c_string(info.gamemode),
+                language: to_c_string(info.language),
+            })
+        },
+    )
+}
+
+/// Releases the strings of a `SampServerInfo` filled by [`samp_query_info`].
+///
+/// # Safety
+///
+/// `info` must be null or point to a value written by `samp_query_info` that
+/// has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn samp_server_info_free(info: *mut SampServerInfo) {
+    catch((), || {
+        let Some(info) = info.as_mut() else {
+            return;
+        };
+
+        free_c_string(info.hostname);
+        free_c_string(info.gamemode);
+        free_c_string(info.language);
+        info.hostname = ptr::null_mut();
+        info.gamemode = ptr::null_mut();
+        info.language = ptr::null_mut();
+    })
+}
+
+/// Queries the client list (names and scores).
+///
+/// # Safety
+///
+/// `addr` must be a valid NUL-terminated string and `out` must point to
+/// writable memory for a `SampPlayerList`.
+#[no_mangle]
+pub unsafe extern "C" fn samp_query_players(
+    addr: *const c_char,
+    timeout_ms: u32,
+    out: *mut SampPlayerList,
+) -> SampStatus {
+    if out.is_null() {
+        return SampStatus::InvalidArgument;
+    }
+
+    run(
+        addr,
+        timeout_ms,
+        |client| async move { client.query_client_list().await },
+        |list| {
+            let players: Box<[SampPlayer]> = list
+                .players
+                .into_iter()
+                .map(|player| SampPlayer {
+                    name: to_c_string(player.name),
+                    score: player.score,
+                })
+                .collect();
+            let len = players.len();
+            out.write(SampPlayerList {
+                players: Box::into_raw(players) as *mut SampPlayer,
+                len,
+            })
+        },
+    )
+}
+
+/// Releases a `SampPlayerList` filled by [`samp_query_players`].
+///
+/// # Safety
+///
+/// `list` must be null or point to a value written by `samp_query_players`
+/// that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn samp_player_list_free(list: *mut SampPlayerList) {
+    catch((), || {
+        let Some(list) = list.as_mut() else {
+            return;
+        };
+        if list.players.is_null() {
+            return;
+        }
+
+        let players = Box::from_raw(ptr::slice_from_raw_parts_mut(list.players, list.len));
+        for player in players.iter() {
+            free_c_string(player.name);
+        }
+        list.players = ptr::null_mut();
+        list.len = 0;
+    })
+}
+
+/// Queries the server rules, sorted by name.
+///
+/// # Safety
+///
+/// `addr` must be a valid NUL-terminated string and `out` must point to
+/// writable memory for a `SampRuleList`.
+#[no_mangle]
+pub unsafe extern "C" fn samp_query_rules(
+    addr: *const c_char,
+    timeout_ms: u32,
+    out: *mut SampRuleList,
+) -> SampStatus {
+    if out.is_null() {
+        return SampStatus::InvalidArgument;
+    }
+
+    run(
+        addr,
+        timeout_ms,
+        |client| async move { client.query_rules().await },
+        |rules| {
+            let mut rules: Vec<(String, String)> = rules.rules.into_iter().collect();
+            rules.sort();
+            let rules: Box<[SampRule]> = rules
+                .into_iter()
+                .map(|(name, value)| SampRule {
+                    name: to_c_string(name),
+                    value: to_c_string(value),
+                })
+                .collect();
+            let len = rules.len();
+            out.write(SampRuleList {
+                rules: Box::into_raw(rules) as *mut SampRule,
+                len,
+            })
+        },
+    )
+}
+
+/// Releases a `SampRuleList` filled by [`samp_query_rules`].
+///
+/// # Safety
+///
+/// `list` must be null or point to a value written by `samp_query_rules`
+/// that has not been freed yet.
+#[no_mangle]
+pub unsafe extern "C" fn samp_rule_list_free(list: *mut SampRuleList) {
+    catch((), || {
+        let Some(list) = list.as_mut() else {
+            return;
+        };
+        if list.rules.is_null() {
+            return;
+        }
+
+        let rules = Box::from_raw(ptr::slice_from_raw_parts_mut(list.rules, list.len));
+        for rule in rules.iter() {
+            free_c_string(rule.name);
+            free_c_string(rule.value);
+        }
+        list.rules = ptr::null_mut();
+        list.len = 0;
+    })
+}
+
+/// Measures the round-trip time of a ping query in milliseconds.
+///
+/// # Safety
+///
+/// `addr` must be a valid NUL-terminated string and `out_ms` must point to
+/// a writable `uint32_t`.
+#[no_mangle]
+pub unsafe extern "C" fn samp_query_ping(
+    addr: *const c_char,
+    timeout_ms: u32,
+    out_ms: *mut u32,
+) -> SampStatus {
+    if out_ms.is_null() {
+        return SampStatus::InvalidArgument;
+    }
+
+    run(
+        addr,
+        timeout_ms,
+        |client| async move { client.query_ping().await },
+        |ping| out_ms.write(ping.ping_ms.min(u32::MAX as u64) as u32),
+    )
+}
+
+/// Returns a static, human-readable description of a status, or of an
+/// unknown status code.
+#[no_mangle]
+pub extern "C" fn samp_status_message(status: c_int) -> *const c_char {
+    let message: &'static CStr = match SampStatus::from_code(status) {
+        Some(SampStatus::Ok) => c"OK",
+        Some(SampStatus::InvalidArgument) => c"Invalid argument",
+        Some(SampStatus::Timeout) => c"Connection timed out",
+        Some(SampStatus::InvalidResponse) => c"Invalid response",
+        Some(SampStatus::Network) => c"Network error",
+        Some(SampStatus::Other) => c"Query failed",
+        None => c"Unknown status",
+    };
+
+    message.as_ptr()
+}
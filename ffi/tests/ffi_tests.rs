@@ -0,0 +1,103 @@
+//! Tests of the C bindings, called as C would call them.
+
+use samp_query::mock::{MockResponse, MockResponses, MockServer};
+use samp_query::{Player, PlayerList, QueryType, ServerInfo};
+use samp_query_ffi::*;
+use std::ffi::{c_int, CStr, CString};
+use std::mem::MaybeUninit;
+use std::ptr;
+
+fn address(server: &MockServer) -> CString {
+    CString::new(server.addr().to_string()).unwrap()
+}
+
+// Also checks that the blocking calls work from a thread driving a runtime.
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_query_info_and_players() {
+    let info = ServerInfo {
+        password: true,
+        players: 1,
+        max_players: 50,
+        hostname: "Mock Server".to_string(),
+        gamemode: "Freeroam".to_string(),
+        language: "English".to_string(),
+    };
+    let players = PlayerList {
+        players: vec![Player {
+            name: "Sweet".to_string(),
+            score: 10,
+        }],
+    };
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(info))
+            .on(QueryType::ClientList, MockResponse::Players(players)),
+    )
+    .await
+    .unwrap();
+    let addr = address(&server);
+
+    let mut info = MaybeUninit::<SampServerInfo>::uninit();
+    let status = unsafe { samp_query_info(addr.as_ptr(), 500, info.as_mut_ptr()) };
+    assert_eq!(status, SampStatus::Ok);
+    let mut info = unsafe { info.assume_init() };
+    assert!(info.password);
+    assert_eq!((info.players, info.max_players), (1, 50));
+    assert_eq!(unsafe { CStr::from_ptr(info.hostname) }.to_str().unwrap(), "Mock Server");
+    unsafe { samp_server_info_free(&mut info) };
+    assert!(info.hostname.is_null());
+
+    let mut list = MaybeUninit::<SampPlayerList>::uninit();
+    let status = unsafe { samp_query_players(addr.as_ptr(), 500, list.as_mut_ptr()) };
+    assert_eq!(status, SampStatus::Ok);
+    let mut list = unsafe { list.assume_init() };
+    assert_eq!(list.len, 1);
+    let player = unsafe { &*list.players };
+    assert_eq!(unsafe { CStr::from_ptr(player.name) }.to_str().unwrap(), "Sweet");
+    assert_eq!(player.score, 10);
+    unsafe { samp_player_list_free(&mut list) };
+    assert!(list.players.is_null());
+    // Freeing twice is harmless once the pointers are cleared.
+    unsafe { samp_player_list_free(&mut list) };
+}
+
+#[tokio::test(flavor = "multi_thread", worker_threads = 2)]
+async fn test_query_timeout() {
+    let server = MockServer::start(MockResponses::new().on(QueryType::Ping, MockResponse::Drop))
+        .await
+        .unwrap();
+    let addr = address(&server);
+
+    let mut ping = 0;
+    let status = unsafe { samp_query_ping(addr.as_ptr(), 50, &mut ping) };
+    assert_eq!(status, SampStatus::Timeout);
+}
+
+#[test]
+fn test_invalid_arguments() {
+    let addr = CString::new("127.0.0.1:7777").unwrap();
+    let mut ping = 0;
+    assert_eq!(unsafe { samp_query_ping(ptr::null(), 50, &mut ping) }, SampStatus::InvalidArgument);
+    assert_eq!(
+        unsafe { samp_query_ping(addr.as_ptr(), 50, ptr::null_mut()) },
+        SampStatus::InvalidArgument
+    );
+
+    let invalid = CString::new("127.0.0.1:notaport").unwrap();
+    assert_eq!(unsafe { samp_query_ping(invalid.as_ptr(), 50, &mut ping) }, SampStatus::InvalidArgument);
+
+    unsafe {
+        samp_server_info_free(ptr::null_mut());
+        samp_player_list_free(ptr::null_mut());
+        samp_rule_list_free(ptr::null_mut());
+    }
+}
+
+#[test]
+fn test_status_message() {
+    let message = |status: c_int| unsafe { CStr::from_ptr(samp_status_message(status)) }.to_str().unwrap();
+    assert_eq!(message(SampStatus::Ok as c_int), "OK");
+    assert_eq!(message(SampStatus::Timeout as c_int), "Connection timed out");
+    assert_eq!(message(42), "Unknown status");
+    assert_eq!(message(-1), "Unknown status");
+}
@@ -0,0 +1,30 @@
+//! Runs every body decoder on the same input and checks that whatever
+//! decodes also survives an encode/decode round trip. Whatever decoded fits
+//! the length prefixes, so encoding it never fails.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use samp_query::packet::{decode, encode};
+
+fuzz_target!(|data: &[u8]| {
+    if let Ok(info) = decode::info(data) {
+        assert_eq!(decode::info(&encode::info(&info)).unwrap(), info);
+    }
+    if let Ok(rules) = decode::rules(data) {
+        assert_eq!(decode::rules(&encode::rules(&rules).unwrap()).unwrap(), rules);
+    }
+    if let Ok(players) = decode::client_list(data) {
+        assert_eq!(decode::client_list(&encode::client_list(&players).unwrap()).unwrap(), players);
+    }
+    if let Ok(players) = decode::detailed_players(data) {
+        assert_eq!(
+            decode::detailed_players(&encode::detailed_players(&players).unwrap()).unwrap(),
+            players
+        );
+    }
+    if let Ok(line) = decode::rcon_line(data) {
+        // A line that just decoded always fits its u16 length prefix.
+        assert_eq!(decode::rcon_line(&encode::rcon_line(&line).unwrap()).unwrap(), line);
+    }
+});
@@ -0,0 +1,27 @@
+//! Feeds arbitrary datagrams through the client-side response path.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use samp_query::packet::{decode, Packet};
+use samp_query::QueryType;
+
+fuzz_target!(|data: &[u8]| {
+    let packet = Packet::from_bytes(data);
+    let Some(query_type) = packet.query_type() else {
+        let _ = packet.validate_response();
+        return;
+    };
+    let Ok(body) = packet.parse_response(query_type) else {
+        return;
+    };
+
+    match query_type {
+        QueryType::Information => drop(decode::info(&body)),
+        QueryType::Rules => drop(decode::rules(&body)),
+        QueryType::ClientList => drop(decode::client_list(&body)),
+        QueryType::DetailedPlayerInfo => drop(decode::detailed_players(&body)),
+        QueryType::Rcon => drop(decode::rcon_line(&body)),
+        QueryType::Ping => {}
+    }
+});
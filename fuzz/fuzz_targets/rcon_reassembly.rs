@@ -0,0 +1,55 @@
+//! Reassembles RCON output from a structured sequence of datagrams, as the
+//! server sends one datagram per output line.
+
+#![no_main]
+
+use arbitrary::Arbitrary;
+use libfuzzer_sys::fuzz_target;
+use samp_query::packet::{decode, encode, Packet};
+use samp_query::protocol::constants::{HEADER_SIZE, MAX_PACKET_SIZE};
+use samp_query::QueryType;
+
+#[derive(Debug, Arbitrary)]
+enum Datagram {
+    /// A well-formed line, so the fuzzer reaches long sequences quickly.
+    Line(String),
+    /// Arbitrary bytes after a valid RCON header.
+    Body(Vec<u8>),
+    /// Arbitrary bytes, header included.
+    Raw(Vec<u8>),
+}
+
+fuzz_target!(|datagrams: Vec<Datagram>| {
+    let request = Packet::create_query("127.0.0.1:7777".parse().unwrap(), QueryType::Rcon).unwrap();
+    let header = &request.as_bytes()[..HEADER_SIZE];
+
+    let mut lines = Vec::new();
+    for datagram in datagrams {
+        let (bytes, expected) = match datagram {
+            Datagram::Line(line) => match encode::rcon_line(&line) {
+                Ok(body) => ([header, &body].concat(), Some(line)),
+                // Too long for its u16 length prefix, so not a line a server sends.
+                Err(_) => continue,
+            },
+            Datagram::Body(body) => ([header, &body].concat(), None),
+            Datagram::Raw(raw) => (raw, None),
+        };
+
+        let packet = Packet::from_bytes(&bytes);
+        if packet.query_type() != Some(QueryType::Rcon) {
+            continue;
+        }
+        let decoded = packet
+            .parse_response(QueryType::Rcon)
+            .and_then(|body| decode::rcon_line(&body));
+
+        if let Some(line) = expected.filter(|line| line.len() <= MAX_PACKET_SIZE) {
+            assert_eq!(decoded.as_ref().ok(), Some(&line));
+        }
+        if let Ok(line) = decoded {
+            lines.push(line);
+        }
+    }
+
+    let _output = lines.join("\n");
+});
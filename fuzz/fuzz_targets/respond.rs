@@ -0,0 +1,19 @@
+//! Feeds arbitrary requests to the server-side responder.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use samp_query::server::{respond, StaticSource};
+
+fuzz_target!(|data: &[u8]| {
+    let source = StaticSource {
+        info: Default::default(),
+        rules: Default::default(),
+        players: Default::default(),
+    };
+
+    for response in respond(&source, data) {
+        assert!(response.len() >= 11);
+        assert_eq!(&response[..4], b"SAMP");
+    }
+});
@@ -0,0 +1,140 @@
+//! Writes seed corpora for the fuzz targets.
+//!
+//! Every seed is a valid packet built with the library's own encoders,
+//! followed by mutated copies (bit flips, truncations, inflated length
+//! fields, spliced bytes) that sit just off the happy path.
+//!
+//! ```text
+//! cargo run --bin generate_corpus -- [output dir] [mutations per seed]
+//! cargo fuzz run parse_response corpus/parse_response
+//! ```
+
+use rand::Rng;
+use samp_query::packet::{encode, Packet};
+use samp_query::protocol::constants::HEADER_SIZE;
+use samp_query::{DetailedPlayer, DetailedPlayerList, Player, PlayerList, QueryType, ServerInfo, ServerRules};
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::path::Path;
+
+fn bodies() -> Vec<(QueryType, Vec<u8>)> {
+    let info = ServerInfo {
+        password: true,
+        players: 42,
+        max_players: 100,
+        hostname: "Fuzz {FF0000}Server".to_string(),
+        gamemode: "Freeroam".to_string(),
+        language: "English".to_string(),
+    };
+    let mut rules = HashMap::new();
+    rules.insert("version".to_string(), "0.3.7-R2".to_string());
+    rules.insert("weburl".to_string(), "www.sa-mp.com".to_string());
+    let players: Vec<DetailedPlayer> = (0..4)
+        .map(|id| DetailedPlayer {
+            id,
+            name: format!("Player_{}", id),
+            score: id as i32 * 10 - 5,
+            ping: 40 + id as u32,
+        })
+        .collect();
+    let list = PlayerList {
+        players: players
+            .iter()
+            .map(|p| Player {
+                name: p.name.clone(),
+                score: p.score,
+            })
+            .collect(),
+    };
+
+    vec![
+        (QueryType::Information, encode::info(&info)),
+        (QueryType::Rules, encode::rules(&ServerRules { rules }).unwrap()),
+        (QueryType::ClientList, encode::client_list(&list).unwrap()),
+        (QueryType::ClientList, encode::client_list(&PlayerList::default()).unwrap()),
+        (QueryType::DetailedPlayerInfo, encode::detailed_players(&DetailedPlayerList { players }).unwrap()),
+        (QueryType::Rcon, encode::rcon_line("Unknown command or variable:").unwrap()),
+        (QueryType::Rcon, Vec::new()),
+        (QueryType::Ping, vec![1, 2, 3, 4]),
+    ]
+}
+
+fn mutate(seed: &[u8], rng: &mut impl Rng) -> Vec<u8> {
+    let mut data = seed.to_vec();
+    if data.is_empty() {
+        return vec![rng.gen()];
+    }
+
+    match rng.gen_range(0..5) {
+        0 => {
+            let i = rng.gen_range(0..data.len());
+            data[i] ^= 1 << rng.gen_range(0..8);
+        }
+        1 => data.truncate(rng.gen_range(0..data.len())),
+        // Length and count fields are where forged values do the most damage.
+        2 => {
+            let i = rng.gen_range(0..data.len());
+            data[i] = 0xff;
+        }
+        3 => {
+            let i = rng.gen_range(0..=data.len());
+            let extra: Vec<u8> = (0..rng.gen_range(1..16)).map(|_| rng.gen()).collect();
+            data.splice(i..i, extra);
+        }
+        _ => {
+            let start = rng.gen_range(0..data.len());
+            let tail = data[start..].to_vec();
+            data.extend_from_slice(&tail);
+        }
+    }
+
+    data
+}
+
+fn write(dir: &Path, name: &str, data: &[u8]) -> std::io::Result<()> {
+    std::fs::create_dir_all(dir)?;
+    std::fs::write(dir.join(name), data)
+}
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let mut args = std::env::args().skip(1);
+    let out = args.next().unwrap_or_else(|| "corpus".to_string());
+    let mutations: usize = args.next().map(|n| n.parse()).transpose()?.unwrap_or(32);
+    let out = Path::new(&out);
+
+    let addr: SocketAddr = "127.0.0.1:7777".parse()?;
+    let mut rng = rand::thread_rng();
+    let mut written = 0;
+
+    for (i, (query_type, body)) in bodies().into_iter().enumerate() {
+        let request = match query_type {
+            QueryType::Rcon => Packet::create_rcon_query(addr, "changeme", "cmdlist")?,
+            _ => Packet::create_query(addr, query_type)?,
+        };
+        let response = Packet::create_response(&request, &body)?;
+        let name = format!("{}-{}", query_type.opcode_char(), i);
+
+        write(&out.join("parse_response"), &name, response.as_bytes())?;
+        write(&out.join("decode_bodies"), &name, &body)?;
+        write(&out.join("respond"), &name, request.as_bytes())?;
+        written += 3;
+
+        for m in 0..mutations {
+            let suffix = format!("{}-m{}", name, m);
+            // Every other mutation keeps the header intact, so the decoders
+            // behind the signature check are reached from the start.
+            let packet = if m % 2 == 0 {
+                mutate(response.as_bytes(), &mut rng)
+            } else {
+                [&response.as_bytes()[..HEADER_SIZE], &mutate(&body, &mut rng)].concat()
+            };
+            write(&out.join("parse_response"), &suffix, &packet)?;
+            write(&out.join("decode_bodies"), &suffix, &mutate(&body, &mut rng))?;
+            write(&out.join("respond"), &suffix, &mutate(request.as_bytes(), &mut rng))?;
+            written += 3;
+        }
+    }
+
+    println!("Wrote {} inputs to {}", written, out.display());
+    Ok(())
+}
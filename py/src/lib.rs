@@ -0,0 +1,213 @@
+//! Python bindings for the SAMP Query library.
+//!
+//! Build with [maturin](https://www.maturin.rs) (`maturin develop` in this
+//! directory). The module exposes an asyncio `Client` and a `BlockingClient`
+//! with the same methods; responses are returned as plain dicts and lists.
+//!
+//! ```python
+//! import asyncio
+//! import samp_query
+//!
+//! async def main():
+//!     client = await samp_query.Client.connect("127.0.0.1:7777")
+//!     info = await client.info()
+//!     print(info["hostname"], info["players"], info["max_players"])
+//!
+//! asyncio.run(main())
+//!
+//! client = samp_query.BlockingClient("127.0.0.1:7777", timeout_ms=500)
+//! print(client.players())
+//! ```
+
+use pyo3::create_exception;
+use pyo3::exceptions::{PyException, PyValueError};
+use pyo3::prelude::*;
+use pythonize::pythonize;
+use samp_query::protocol::constants;
+use samp_query::resolver::Resolver;
+use samp_query::{Client as QueryClient, ClientConfig, Error};
+use serde::Serialize;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+create_exception!(samp_query, QueryError, PyException, "A query failed.");
+create_exception!(samp_query, QueryTimeout, QueryError, "The server did not answer in time.");
+
+fn to_py_err(e: Error) -> PyErr {
+    if e.is_timeout() {
+        QueryTimeout::new_err(e.to_string())
+    } else {
+        QueryError::new_err(e.to_string())
+    }
+}
+
+fn to_python<T: Serialize>(py: Python<'_>, value: &T) -> PyResult<PyObject> {
+    pythonize(py, value)
+        .map(|value| value.unbind())
+        .map_err(|e| QueryError::new_err(e.to_string()))
+}
+
+/// Resolves `address` on the runtime, so neither the GIL nor the event loop
+/// is held during the lookup.
+async fn resolve(address: &str) -> PyResult<SocketAddr> {
+    Resolver::global()
+        .resolve_address(address, constants::DEFAULT_PORT)
+        .await
+        .map(|(_, addr)| addr)
+        .map_err(|e| PyValueError::new_err(e.to_string()))
+}
+
+fn config(timeout_ms: u64, retries: usize) -> ClientConfig {
+    ClientConfig {
+        timeout_ms,
+        max_retries: retries,
+        ..Default::default()
+    }
+}
+
+/// An asyncio client bound to one server.
+#[pyclass(module = "samp_query")]
+struct Client {
+    inner: Arc<QueryClient>,
+}
+
+impl Client {
+    fn query<'py, T, F, Fut>(&self, py: Python<'py>, query: F) -> PyResult<Bound<'py, PyAny>>
+    where
+        T: Serialize + Send + 'static,
+        F: FnOnce(Arc<QueryClient>) -> Fut,
+        Fut: Future<Output = samp_query::Result<T>> + Send + 'static,
+    {
+        let future = query(self.inner.clone());
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let value = future.await.map_err(to_py_err)?;
+            Python::with_gil(|py| to_python(py, &value))
+        })
+    }
+}
+
+#[pymethods]
+impl Client {
+    /// Connects to a server. Accepts `host`, `host:port` or `host,port`.
+    #[staticmethod]
+    #[pyo3(signature = (address, timeout_ms = constants::DEFAULT_TIMEOUT_MS, retries = constants::MAX_RETRIES))]
+    fn connect<'py>(
+        py: Python<'py>,
+        address: &str,
+        timeout_ms: u64,
+        retries: usize,
+    ) -> PyResult<Bound<'py, PyAny>> {
+        let address = address.to_string();
+        pyo3_async_runtimes::tokio::future_into_py(py, async move {
+            let addr = resolve(&address).await?;
+            let inner = QueryClient::connect_with_config(addr, config(timeout_ms, retries))
+                .await
+                .map_err(to_py_err)?;
+            Ok(Client {
+                inner: Arc::new(inner),
+            })
+        })
+    }
+
+    fn info<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.query(py, |client| async move { client.query_info().await })
+    }
+
+    fn rules<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.query(py, |client| async move { client.query_rules().await })
+    }
+
+    fn players<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.query(py, |client| async move { client.query_client_list().await })
+    }
+
+    fn detailed_players<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.query(py, |client| async move { client.query_detailed_player_info().await })
+    }
+
+    fn ping<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.query(py, |client| async move { client.query_ping().await })
+    }
+
+    fn snapshot<'py>(&self, py: Python<'py>) -> PyResult<Bound<'py, PyAny>> {
+        self.query(py, |client| async move { client.snapshot().await })
+    }
+}
+
+/// A blocking client for scripts that do not use asyncio.
+#[pyclass(module = "samp_query")]
+struct BlockingClient {
+    inner: Arc<QueryClient>,
+}
+
+impl BlockingClient {
+    fn query<T, F, Fut>(&self, py: Python<'_>, query: F) -> PyResult<PyObject>
+    where
+        T: Serialize + Send,
+        F: FnOnce(Arc<QueryClient>) -> Fut + Send,
+        Fut: Future<Output = samp_query::Result<T>> + Send,
+    {
+        let client = self.inner.clone();
+        let value = py
+            .allow_threads(|| pyo3_async_runtimes::tokio::get_runtime().block_on(query(client)))
+            .map_err(to_py_err)?;
+
+        to_python(py, &value)
+    }
+}
+
+#[pymethods]
+impl BlockingClient {
+    #[new]
+    #[pyo3(signature = (address, timeout_ms = constants::DEFAULT_TIMEOUT_MS, retries = constants::MAX_RETRIES))]
+    fn new(py: Python<'_>, address: &str, timeout_ms: u64, retries: usize) -> PyResult<Self> {
+        let inner = py.allow_threads(|| {
+            pyo3_async_runtimes::tokio::get_runtime().block_on(async {
+                let addr = resolve(address).await?;
+                QueryClient::connect_with_config(addr, config(timeout_ms, retries))
+                    .await
+                    .map_err(to_py_err)
+            })
+        })?;
+
+        Ok(Self {
+            inner: Arc::new(inner),
+        })
+    }
+
+    fn info(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.query(py, |client| async move { client.query_info().await })
+    }
+
+    fn rules(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.query(py, |client| async move { client.query_rules().await })
+    }
+
+    fn players(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.query(py, |client| async move { client.query_client_list().await })
+    }
+
+    fn detailed_players(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.query(py, |client| async move { client.query_detailed_player_info().await })
+    }
+
+    fn ping(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.query(py, |client| async move { client.query_ping().await })
+    }
+
+    fn snapshot(&self, py: Python<'_>) -> PyResult<PyObject> {
+        self.query(py, |client| async move { client.snapshot().await })
+    }
+}
+
+#[pymodule]
+#[pyo3(name = "samp_query")]
+fn samp_query_module(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<Client>()?;
+    m.add_class::<BlockingClient>()?;
+    m.add("QueryError", m.py().get_type::<QueryError>())?;
+    m.add("QueryTimeout", m.py().get_type::<QueryTimeout>())?;
+
+    Ok(())
+}
@@ -0,0 +1,228 @@
+//! Bulk querying and ranking.
+//!
+//! [`Aggregator`] queries a list of servers with bounded concurrency and a
+//! start rate limit, then returns the servers that answered as a ranked
+//! table and the ones that did not as a separate failure list. It keeps an
+//! [`AvailabilityTracker`] across runs, so repeated runs can also rank by
+//! uptime. Servers rejected by the configured [`ServerFilter`] are left out
+//! of both.
+//!
+//! ```rust,no_run
+//! use samp_query::aggregate::{AggregateConfig, Aggregator, RankBy};
+//!
+//! # async fn run() {
+//! let servers = vec!["127.0.0.1:7777".parse().unwrap(), "127.0.0.1:7778".parse().unwrap()];
+//! let mut aggregator = Aggregator::new(AggregateConfig::default());
+//!
+//! let mut table = aggregator.run(&servers).await;
+//! table.rank(RankBy::Ping);
+//! for server in &table.servers {
+//!     println!("#{} {} {}", server.rank, server.address, server.info.hostname);
+//! }
+//! # }
+//! ```
+
+use crate::availability::AvailabilityTracker;
+use crate::client::{Client, ClientConfig};
+use crate::error::Result;
+use crate::filter::ServerFilter;
+use crate::ratelimit::RateLimiter;
+use crate::types::{ServerInfo, ServerRules};
+use crate::utils::unix_timestamp_ms;
+use futures::stream::{self, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+use std::net::SocketAddr;
+use std::time::Duration;
+
+#[derive(Debug, Clone)]
+pub struct AggregateConfig {
+    pub client: ClientConfig,
+    /// Maximum number of servers queried at the same time.
+    pub concurrency: usize,
+    /// Maximum number of servers whose queries start per second.
+    pub rate_per_sec: u32,
+    /// A limiter shared with other components, used instead of `rate_per_sec`.
+    pub limiter: Option<RateLimiter>,
+    /// Window over which uptime is computed.
+    pub uptime_window: Duration,
+    /// Servers to leave out of the table. Blocked addresses are not queried;
+    /// rules are only queried when the filter has rule predicates.
+    pub filter: ServerFilter,
+}
+
+impl Default for AggregateConfig {
+    fn default() -> Self {
+        Self {
+            client: ClientConfig::default(),
+            concurrency: 32,
+            rate_per_sec: 50,
+            limiter: None,
+            uptime_window: Duration::from_secs(24 * 60 * 60),
+            filter: ServerFilter::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RankBy {
+    /// Most players first.
+    Players,
+    /// Lowest ping first; servers without a ping come last.
+    Ping,
+    /// Highest uptime first.
+    Uptime,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RankedServer {
+    /// Position in the table, starting at 1.
+    pub rank: usize,
+    pub address: SocketAddr,
+    pub info: ServerInfo,
+    pub ping_ms: Option<u64>,
+    /// Uptime over the configured window, as tracked across runs.
+    pub uptime_percentage: f64,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct AggregateFailure {
+    pub address: SocketAddr,
+    pub error: String,
+    pub timeout: bool,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct AggregateTable {
+    pub servers: Vec<RankedServer>,
+    /// Servers that did not answer, in input order.
+    pub failures: Vec<AggregateFailure>,
+    /// Servers rejected by the filter, in input order.
+    #[serde(default)]
+    pub filtered: Vec<SocketAddr>,
+}
+
+impl AggregateTable {
+    /// Sorts the servers and renumbers their ranks.
+    ///
+    /// Ties are broken by player count, then ping, then address, so the order
+    /// is deterministic.
+    pub fn rank(&mut self, by: RankBy) {
+        let ping = |server: &RankedServer| server.ping_ms.unwrap_or(u64::MAX);
+        self.servers.sort_by(|a, b| {
+            let primary = match by {
+                RankBy::Players => Ordering::Equal,
+                RankBy::Ping => ping(a).cmp(&ping(b)),
+                RankBy::Uptime => b.uptime_percentage.total_cmp(&a.uptime_percentage),
+            };
+            primary
+                .then_with(|| b.info.players.cmp(&a.info.players))
+                .then_with(|| ping(a).cmp(&ping(b)))
+                .then_with(|| a.address.cmp(&b.address))
+        });
+
+        for (i, server) in self.servers.iter_mut().enumerate() {
+            server.rank = i + 1;
+        }
+    }
+
+    /// Total players across all servers that answered.
+    pub fn total_players(&self) -> u64 {
+        self.servers.iter().map(|server| server.info.players as u64).sum()
+    }
+}
+
+#[derive(Debug)]
+pub struct Aggregator {
+    config: AggregateConfig,
+    availability: AvailabilityTracker,
+}
+
+impl Aggregator {
+    pub fn new(config: AggregateConfig) -> Self {
+        let availability = AvailabilityTracker::new(config.uptime_window);
+        Self { config, availability }
+    }
+
+    pub fn config(&self) -> &AggregateConfig {
+        &self.config
+    }
+
+    pub fn availability(&self) -> &AvailabilityTracker {
+        &self.availability
+    }
+
+    /// Queries every server once and returns the table ranked by players.
+    pub async fn run(&mut self, servers: &[SocketAddr]) -> AggregateTable {
+        let config = &self.config;
+        let limiter = config
+            .limiter
+            .clone()
+            .unwrap_or_else(|| RateLimiter::new(config.rate_per_sec.max(1) as f64));
+        let mut table = AggregateTable::default();
+
+        let (allowed, blocked): (Vec<_>, Vec<_>) =
+            servers.iter().copied().partition(|addr| config.filter.allows_addr(addr));
+        table.filtered = blocked;
+
+        let mut results: Vec<_> = stream::iter(allowed.into_iter().enumerate())
+            .map(|(i, addr)| {
+                let limiter = limiter.clone();
+                async move {
+                    limiter.acquire(addr).await;
+                    (i, addr, query(addr, config).await)
+                }
+            })
+            .buffer_unordered(config.concurrency.max(1))
+            .collect()
+            .await;
+        results.sort_by_key(|(i, _, _)| *i);
+
+        let now = unix_timestamp_ms();
+        for (_, address, result) in results {
+            self.availability.record(address, now, result.is_ok());
+            match result {
+                Ok((info, rules, _)) if !config.filter.allows(&address, &info, rules.as_ref()) => {
+                    table.filtered.push(address);
+                }
+                Ok((info, _, ping_ms)) => table.servers.push(RankedServer {
+                    rank: 0,
+                    address,
+                    info,
+                    ping_ms,
+                    uptime_percentage: self
+                        .availability
+                        .get(&address)
+                        .map_or(100.0, |availability| availability.uptime_percentage()),
+                }),
+                Err(e) => table.failures.push(AggregateFailure {
+                    address,
+                    timeout: e.is_timeout(),
+                    error: e.to_string(),
+                }),
+            }
+        }
+
+        table.rank(RankBy::Players);
+        table
+    }
+}
+
+async fn query(addr: SocketAddr, config: &AggregateConfig) -> Result<(ServerInfo, Option<ServerRules>, Option<u64>)> {
+    let client = Client::connect_with_config(addr, config.client.clone()).await?;
+    let info = client.query_info().await?;
+    let rules = if config.filter.checks_rules() {
+        client.query_rules().await.ok()
+    } else {
+        None
+    };
+    let ping = client.query_ping().await.ok().map(|ping| ping.ping_ms);
+
+    Ok((info, rules, ping))
+}
+
+/// Queries `servers` once with a fresh [`Aggregator`].
+pub async fn aggregate(servers: &[SocketAddr], config: AggregateConfig) -> AggregateTable {
+    Aggregator::new(config).run(servers).await
+}
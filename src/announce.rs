@@ -0,0 +1,249 @@
+//! Periodic announcing to public server lists.
+//!
+//! Available with the `http` feature. SA-MP servers announce themselves by
+//! calling `<list>/<version>/announce/<port>`; the list then queries the
+//! server back on that port. [`Announcer`] performs that call on an interval,
+//! retrying failures with backoff.
+//!
+//! SA-MP and open.mp servers announce slightly differently: open.mp always
+//! uses the legacy `0.3.7` path, whatever client version it accepts, and
+//! identifies itself with its own user agent. Unless a [`Platform`] is set
+//! in the config, the announcer queries the game server's rules once and
+//! picks the matching format, so the same wrapper works in front of both.
+//!
+//! ```rust,no_run
+//! use samp_query::announce::Announcer;
+//!
+//! # async fn run() {
+//! let announcer = Announcer::new(7777);
+//! announcer.run().await;
+//! # }
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::error::{Error, Result};
+use crate::fingerprint::{detect_platform, Platform};
+use crate::protocol::constants;
+use crate::utils::{retry, Backoff, RetryPolicy};
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::task::JoinHandle;
+use tracing::{debug, warn};
+
+/// Announce endpoint of the classic SA-MP list.
+pub const SAMP_ENDPOINT: &str = "http://server.sa-mp.com";
+
+/// Legacy-compatible announce endpoint of the open.mp list.
+pub const OMP_ENDPOINT: &str = "https://api.open.mp";
+
+/// Interval between announcements used by the SA-MP server.
+pub const DEFAULT_ANNOUNCE_INTERVAL: Duration = Duration::from_secs(5 * 60);
+
+/// Version path segment open.mp servers announce with.
+pub const OMP_LEGACY_VERSION: &str = "0.3.7";
+
+#[derive(Debug, Clone)]
+pub struct AnnounceConfig {
+    /// Port the game server listens on.
+    pub port: u16,
+    /// Version path segment, e.g. `0.3.7`.
+    pub version: String,
+    /// List base URLs to announce to.
+    pub endpoints: Vec<String>,
+    /// Interval between announcements.
+    pub interval: Duration,
+    /// Retry policy for a single announcement.
+    pub retry: RetryPolicy,
+    /// Announce format to use; detected from the server's rules when `None`.
+    pub flavor: Option<Platform>,
+    /// Address the game server is queried on for detection.
+    pub host: IpAddr,
+}
+
+impl AnnounceConfig {
+    pub fn new(port: u16) -> Self {
+        Self {
+            port,
+            version: "0.3.7".to_string(),
+            endpoints: vec![SAMP_ENDPOINT.to_string(), OMP_ENDPOINT.to_string()],
+            interval: DEFAULT_ANNOUNCE_INTERVAL,
+            retry: RetryPolicy {
+                max_attempts: 5,
+                backoff: Backoff::Exponential,
+                initial_delay: Duration::from_secs(1),
+                max_delay: Duration::from_secs(60),
+                max_elapsed: None,
+            },
+            flavor: None,
+            host: IpAddr::V4(Ipv4Addr::LOCALHOST),
+        }
+    }
+
+    /// The request announcing this server to `endpoint` in the given format.
+    ///
+    /// `server_version` is the server's `version` rule, used in the open.mp
+    /// user agent when known.
+    pub fn request(&self, endpoint: &str, flavor: Platform, server_version: Option<&str>) -> AnnounceRequest {
+        let (version, user_agent) = match flavor {
+            Platform::Samp => (self.version.as_str(), format!("SAMP/{}", self.version)),
+            Platform::OpenMp => {
+                let server_version = server_version
+                    .map(|v| v.trim_start_matches("open.mp").trim_start_matches("omp").trim())
+                    .filter(|v| !v.is_empty());
+                let user_agent = match server_version {
+                    Some(v) => format!("open.mp/{}", v),
+                    None => "open.mp".to_string(),
+                };
+                (OMP_LEGACY_VERSION, user_agent)
+            }
+        };
+
+        AnnounceRequest {
+            url: format!("{}/{}/announce/{}", endpoint.trim_end_matches('/'), version, self.port),
+            user_agent,
+        }
+    }
+}
+
+/// The HTTP request of one announcement.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AnnounceRequest {
+    pub url: String,
+    pub user_agent: String,
+}
+
+#[derive(Debug, Clone)]
+struct Detected {
+    flavor: Platform,
+    version: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Announcer {
+    http: reqwest::Client,
+    config: AnnounceConfig,
+    detected: Arc<Mutex<Option<Detected>>>,
+}
+
+impl Announcer {
+    pub fn new(port: u16) -> Self {
+        Self::with_config(AnnounceConfig::new(port))
+    }
+
+    pub fn with_config(mut config: AnnounceConfig) -> Self {
+        // A zero period makes the tokio ticker panic.
+        config.interval = config.interval.max(Duration::from_millis(constants::MIN_POLL_INTERVAL_MS));
+        Self {
+            http: crate::resolver::http_client(),
+            config,
+            detected: Arc::new(Mutex::new(None)),
+        }
+    }
+
+    pub fn config(&self) -> &AnnounceConfig {
+        &self.config
+    }
+
+    /// The announce format in use.
+    ///
+    /// Without a configured flavor, the game server's rules are queried
+    /// until it answers; the result is kept for later announcements. A
+    /// server that cannot be detected is announced in the SA-MP format.
+    pub async fn flavor(&self) -> Platform {
+        self.detect().await.flavor
+    }
+
+    async fn detect(&self) -> Detected {
+        if let Some(detected) = self.detected.lock().unwrap().clone() {
+            return detected;
+        }
+        if self.config.flavor == Some(Platform::Samp) {
+            return Detected {
+                flavor: Platform::Samp,
+                version: None,
+            };
+        }
+
+        let addr = SocketAddr::new(self.config.host, self.config.port);
+        let config = ClientConfig {
+            timeout_ms: 1000,
+            max_retries: 2,
+            ..Default::default()
+        };
+        let rules = match Client::connect_with_config(addr, config).await {
+            Ok(client) => client.query_rules().await.ok(),
+            Err(_) => None,
+        };
+        let Some(rules) = rules else {
+            return Detected {
+                flavor: self.config.flavor.unwrap_or(Platform::Samp),
+                version: None,
+            };
+        };
+
+        let detected = Detected {
+            flavor: self
+                .config
+                .flavor
+                .or_else(|| detect_platform(&rules))
+                .unwrap_or(Platform::Samp),
+            version: rules.rules.get("version").cloned(),
+        };
+        debug!("Announcing {} as {}", addr, detected.flavor.as_str());
+        *self.detected.lock().unwrap() = Some(detected.clone());
+        detected
+    }
+
+    /// Announces to a single endpoint, retrying according to the policy.
+    pub async fn announce_to(&self, endpoint: &str) -> Result<()> {
+        let detected = self.detect().await;
+        let request = self
+            .config
+            .request(endpoint, detected.flavor, detected.version.as_deref());
+
+        retry(&self.config.retry, |attempt| {
+            let request = request.clone();
+            async move {
+                debug!("Announcing to {} (attempt {})", request.url, attempt + 1);
+                self.http
+                    .get(&request.url)
+                    .header(reqwest::header::USER_AGENT, &request.user_agent)
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map(|_| ())
+                    .map_err(|e| Error::Http(e.to_string()))
+            }
+        })
+        .await
+    }
+
+    /// Announces to every configured endpoint once.
+    pub async fn announce_once(&self) -> Vec<(String, Result<()>)> {
+        let announcements = self.config.endpoints.iter().map(|endpoint| async move {
+            (endpoint.clone(), self.announce_to(endpoint).await)
+        });
+
+        futures::future::join_all(announcements).await
+    }
+
+    /// Announces on the configured interval forever.
+    pub async fn run(&self) {
+        let mut ticker = tokio::time::interval(self.config.interval);
+
+        loop {
+            ticker.tick().await;
+            for (endpoint, result) in self.announce_once().await {
+                if let Err(e) = result {
+                    warn!("Failed to announce to {}: {}", endpoint, e);
+                }
+            }
+        }
+    }
+
+    /// Runs the announcer in a background task.
+    pub fn spawn(self) -> JoinHandle<()> {
+        tokio::spawn(async move { self.run().await })
+    }
+}
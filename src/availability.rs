@@ -0,0 +1,222 @@
+//! Uptime and availability tracking.
+//!
+//! [`Availability`] keeps the online/offline history of a single server over a
+//! rolling window; [`AvailabilityTracker`] keeps one per server and can be fed
+//! directly with [`MonitorEvent`]s.
+//!
+//! ```
+//! use samp_query::availability::Availability;
+//! use std::time::Duration;
+//!
+//! let mut availability = Availability::new(Duration::from_secs(3600));
+//! availability.record(0, true);
+//! availability.record(30_000, false);
+//! availability.record(40_000, true);
+//!
+//! let report = availability.report();
+//! assert!(report.online);
+//! assert_eq!(report.uptime_percentage, 75.0);
+//! assert_eq!(report.last_downtime.unwrap().duration_ms(), Some(10_000));
+//! ```
+
+use crate::monitor::{MonitorEvent, MonitorEventKind};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// A period during which a server was offline.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Downtime {
+    /// Unix timestamp in milliseconds at which the server went offline.
+    pub started: u64,
+    /// Unix timestamp in milliseconds at which it came back, if it has.
+    pub ended: Option<u64>,
+}
+
+impl Downtime {
+    /// Duration of the downtime, if it has ended.
+    pub fn duration_ms(&self) -> Option<u64> {
+        self.ended.map(|ended| ended.saturating_sub(self.started))
+    }
+}
+
+/// Serializable summary of a server's availability.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AvailabilityReport {
+    /// Whether the server was online at the last observation.
+    pub online: bool,
+    /// Percentage of the window during which the server was online.
+    pub uptime_percentage: f64,
+    /// Unix timestamp in milliseconds at which the current streak started.
+    pub streak_since: u64,
+    /// Length of the current online or offline streak in milliseconds.
+    pub streak_ms: u64,
+    /// The most recent downtime, if any.
+    pub last_downtime: Option<Downtime>,
+    /// Unix timestamp in milliseconds of the last observation.
+    pub last_seen: u64,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Availability {
+    window_ms: u64,
+    /// State changes as `(timestamp, online)`, oldest first.
+    transitions: VecDeque<(u64, bool)>,
+    downtimes: VecDeque<Downtime>,
+    last_seen: u64,
+}
+
+impl Availability {
+    /// Number of downtimes kept for reporting.
+    const MAX_DOWNTIMES: usize = 16;
+
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window_ms: window.as_millis() as u64,
+            transitions: VecDeque::new(),
+            downtimes: VecDeque::new(),
+            last_seen: 0,
+        }
+    }
+
+    /// Records whether the server was online at `timestamp` (Unix milliseconds).
+    pub fn record(&mut self, timestamp: u64, online: bool) {
+        self.last_seen = self.last_seen.max(timestamp);
+
+        if self.transitions.back().map(|(_, state)| *state) != Some(online) {
+            self.transitions.push_back((timestamp, online));
+
+            if online {
+                if let Some(downtime) = self.downtimes.back_mut() {
+                    if downtime.ended.is_none() {
+                        downtime.ended = Some(timestamp);
+                    }
+                }
+            } else {
+                self.downtimes.push_back(Downtime {
+                    started: timestamp,
+                    ended: None,
+                });
+                if self.downtimes.len() > Self::MAX_DOWNTIMES {
+                    self.downtimes.pop_front();
+                }
+            }
+        }
+
+        // Drop transitions that ended before the window, keeping the one that
+        // straddles the window start.
+        let window_start = self.last_seen.saturating_sub(self.window_ms);
+        while self.transitions.len() > 1 && self.transitions[1].0 <= window_start {
+            self.transitions.pop_front();
+        }
+    }
+
+    /// Updates the tracker from a monitor event.
+    ///
+    /// `Online`, `Offline` and `Polled` events are recorded; other events are ignored.
+    pub fn observe(&mut self, event: &MonitorEvent) {
+        match event.kind {
+            MonitorEventKind::Online | MonitorEventKind::Polled { .. } => {
+                self.record(event.timestamp, true)
+            }
+            MonitorEventKind::Offline { .. } => self.record(event.timestamp, false),
+            _ => {}
+        }
+    }
+
+    /// Whether the server was online at the last observation.
+    pub fn is_online(&self) -> bool {
+        self.transitions.back().is_some_and(|(_, online)| *online)
+    }
+
+    /// Percentage of the window (up to the last observation) the server was online.
+    pub fn uptime_percentage(&self) -> f64 {
+        let Some(&(first, _)) = self.transitions.front() else {
+            return 0.0;
+        };
+
+        let now = self.last_seen;
+        let window_start = now.saturating_sub(self.window_ms).max(first);
+        let total = now.saturating_sub(window_start);
+        if total == 0 {
+            return if self.is_online() { 100.0 } else { 0.0 };
+        }
+
+        let mut online_ms = 0;
+        for (i, &(start, online)) in self.transitions.iter().enumerate() {
+            let end = self.transitions.get(i + 1).map_or(now, |(next, _)| *next);
+            if online {
+                online_ms += end.saturating_sub(start.max(window_start));
+            }
+        }
+
+        online_ms as f64 / total as f64 * 100.0
+    }
+
+    /// The most recent downtime, if any.
+    pub fn last_downtime(&self) -> Option<Downtime> {
+        self.downtimes.back().copied()
+    }
+
+    /// Recent downtimes, oldest first.
+    pub fn downtimes(&self) -> impl Iterator<Item = &Downtime> {
+        self.downtimes.iter()
+    }
+
+    pub fn report(&self) -> AvailabilityReport {
+        let streak_since = self.transitions.back().map_or(0, |(start, _)| *start);
+
+        AvailabilityReport {
+            online: self.is_online(),
+            uptime_percentage: self.uptime_percentage(),
+            streak_since,
+            streak_ms: self.last_seen.saturating_sub(streak_since),
+            last_downtime: self.last_downtime(),
+            last_seen: self.last_seen,
+        }
+    }
+}
+
+/// Tracks [`Availability`] for many servers.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AvailabilityTracker {
+    window_ms: u64,
+    servers: HashMap<SocketAddr, Availability>,
+}
+
+impl AvailabilityTracker {
+    pub fn new(window: Duration) -> Self {
+        Self {
+            window_ms: window.as_millis() as u64,
+            servers: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, addr: SocketAddr, timestamp: u64, online: bool) {
+        self.entry(addr).record(timestamp, online);
+    }
+
+    pub fn observe(&mut self, event: &MonitorEvent) {
+        self.entry(event.address).observe(event);
+    }
+
+    pub fn get(&self, addr: &SocketAddr) -> Option<&Availability> {
+        self.servers.get(addr)
+    }
+
+    /// Reports for every tracked server.
+    pub fn reports(&self) -> HashMap<SocketAddr, AvailabilityReport> {
+        self.servers
+            .iter()
+            .map(|(addr, availability)| (*addr, availability.report()))
+            .collect()
+    }
+
+    fn entry(&mut self, addr: SocketAddr) -> &mut Availability {
+        let window = Duration::from_millis(self.window_ms);
+        self.servers
+            .entry(addr)
+            .or_insert_with(|| Availability::new(window))
+    }
+}
@@ -0,0 +1,513 @@
+//! Query result cache.
+//!
+//! [`QueryCache`] caches responses keyed by `(address, query type)` with a TTL
+//! per query type. Failures are cached for a shorter negative TTL so dead
+//! servers are not queried on every request, and expired values are served
+//! for a while longer (stale-while-revalidate) while a background refresh
+//! runs. [`QueryCache::snapshot`] combines the cached parts into a
+//! [`ServerSnapshot`], querying all of them in one pass when any is missing.
+//!
+//! Concurrent requests for the same entry share one query: a fetch started
+//! while another is in flight for the same address and query type, or the
+//! same snapshot, waits for its result instead of querying the server
+//! again. With a [`limiter`](CacheConfig::limiter), a fetch that would
+//! exceed it fails at once with [`Error::RateLimited`] rather than waiting.
+//!
+//! ```rust,no_run
+//! use samp_query::cache::{CacheConfig, QueryCache};
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let cache = QueryCache::new(CacheConfig::default());
+//! let addr = "127.0.0.1:7777".parse().unwrap();
+//!
+//! // Only the first call reaches the server while the entry is fresh.
+//! let info = cache.info(addr).await?;
+//! let again = cache.info(addr).await?;
+//! assert_eq!(info, again);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::error::{Error, Result};
+use crate::protocol::QueryType;
+use crate::ratelimit::RateLimiter;
+use crate::types::*;
+use crate::utils::unix_timestamp_ms;
+use futures::future::{BoxFuture, FutureExt, Shared};
+use std::collections::HashMap;
+use std::fmt;
+use std::future::Future;
+use std::io;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tracing::debug;
+
+#[derive(Debug, Clone)]
+pub struct CacheConfig {
+    /// Client configuration used to fetch missing entries.
+    pub client: ClientConfig,
+    pub info_ttl: Duration,
+    pub rules_ttl: Duration,
+    /// TTL for both client list and detailed player queries.
+    pub players_ttl: Duration,
+    pub ping_ttl: Duration,
+    /// How long failures are cached.
+    pub negative_ttl: Duration,
+    /// How long after expiry a value may still be served while it is refreshed.
+    pub stale_ttl: Duration,
+    /// Limits the queries sent for missing and expired entries, e.g. a
+    /// limiter shared with other components. Fetches over the limit fail
+    /// with [`Error::RateLimited`].
+    pub limiter: Option<RateLimiter>,
+}
+
+impl Default for CacheConfig {
+    fn default() -> Self {
+        Self {
+            client: ClientConfig::default(),
+            info_ttl: Duration::from_secs(5),
+            rules_ttl: Duration::from_secs(60),
+            players_ttl: Duration::from_secs(3),
+            ping_ttl: Duration::from_secs(5),
+            negative_ttl: Duration::from_secs(10),
+            stale_ttl: Duration::from_secs(30),
+            limiter: None,
+        }
+    }
+}
+
+impl CacheConfig {
+    /// TTL of successful responses for the given query type.
+    pub fn ttl(&self, query_type: QueryType) -> Duration {
+        match query_type {
+            QueryType::Information => self.info_ttl,
+            QueryType::Rules => self.rules_ttl,
+            QueryType::ClientList | QueryType::DetailedPlayerInfo => self.players_ttl,
+            QueryType::Ping => self.ping_ttl,
+            QueryType::Rcon => Duration::ZERO,
+        }
+    }
+}
+
+/// A cached response.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CachedValue {
+    Info(ServerInfo),
+    Rules(ServerRules),
+    ClientList(PlayerList),
+    DetailedPlayers(DetailedPlayerList),
+    Ping(PingInfo),
+}
+
+/// A cached failure. [`Error`] is not `Clone`, so the variant is kept with
+/// its message and rebuilt whenever the failure is served.
+#[derive(Debug, Clone)]
+enum CachedError {
+    Bind(io::ErrorKind, String),
+    Connect(io::ErrorKind, String),
+    Send(io::ErrorKind, String),
+    Receive(io::ErrorKind, String),
+    Io(io::ErrorKind, String),
+    Resolve(String),
+    Timeout,
+    InvalidResponse(String),
+    ServerError(String),
+    RconAuthFailed,
+    InvalidQueryType(String),
+    Http(String),
+    Storage(String),
+    RateLimited(Duration),
+    Other(String),
+}
+
+impl From<&Error> for CachedError {
+    fn from(e: &Error) -> Self {
+        match e {
+            Error::Bind(e) => Self::Bind(e.kind(), e.to_string()),
+            Error::Connect(e) => Self::Connect(e.kind(), e.to_string()),
+            Error::Send(e) => Self::Send(e.kind(), e.to_string()),
+            Error::Receive(e) => Self::Receive(e.kind(), e.to_string()),
+            Error::Io(e) => Self::Io(e.kind(), e.to_string()),
+            Error::Resolve(host) => Self::Resolve(host.clone()),
+            Error::Timeout => Self::Timeout,
+            Error::InvalidResponse(message) => Self::InvalidResponse(message.clone()),
+            // The UTF-8 error cannot be rebuilt; it came from the response.
+            Error::Utf8(e) => Self::InvalidResponse(e.to_string()),
+            Error::ServerError(message) => Self::ServerError(message.clone()),
+            Error::RconAuthFailed => Self::RconAuthFailed,
+            Error::InvalidQueryType(message) => Self::InvalidQueryType(message.clone()),
+            Error::Http(message) => Self::Http(message.clone()),
+            Error::Storage(message) => Self::Storage(message.clone()),
+            Error::RateLimited(retry_after) => Self::RateLimited(*retry_after),
+            Error::AddrParse(_) | Error::Other(_) => Self::Other(e.to_string()),
+        }
+    }
+}
+
+impl From<CachedError> for Error {
+    fn from(e: CachedError) -> Self {
+        match e {
+            CachedError::Bind(kind, message) => Error::Bind(io::Error::new(kind, message)),
+            CachedError::Connect(kind, message) => Error::Connect(io::Error::new(kind, message)),
+            CachedError::Send(kind, message) => Error::Send(io::Error::new(kind, message)),
+            CachedError::Receive(kind, message) => Error::Receive(io::Error::new(kind, message)),
+            CachedError::Io(kind, message) => Error::Io(io::Error::new(kind, message)),
+            CachedError::Resolve(host) => Error::Resolve(host),
+            CachedError::Timeout => Error::Timeout,
+            CachedError::InvalidResponse(message) => Error::InvalidResponse(message),
+            CachedError::ServerError(message) => Error::ServerError(message),
+            CachedError::RconAuthFailed => Error::RconAuthFailed,
+            CachedError::InvalidQueryType(message) => Error::InvalidQueryType(message),
+            CachedError::Http(message) => Error::Http(message),
+            CachedError::Storage(message) => Error::Storage(message),
+            CachedError::RateLimited(retry_after) => Error::RateLimited(retry_after),
+            CachedError::Other(message) => Error::Other(message),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Entry {
+    value: std::result::Result<CachedValue, CachedError>,
+    fetched_at: Instant,
+}
+
+type Key = (SocketAddr, QueryType);
+
+/// A query in flight, awaited by every fetch of its entry.
+type Flight<T> = Shared<BoxFuture<'static, std::result::Result<T, CachedError>>>;
+
+struct Inner {
+    config: CacheConfig,
+    entries: Mutex<HashMap<Key, Entry>>,
+    queries: Mutex<HashMap<Key, Flight<CachedValue>>>,
+    snapshots: Mutex<HashMap<SocketAddr, Flight<ServerSnapshot>>>,
+}
+
+impl fmt::Debug for Inner {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Inner")
+            .field("config", &self.config)
+            .field("entries", &self.entries)
+            .finish_non_exhaustive()
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct QueryCache {
+    inner: Arc<Inner>,
+}
+
+enum Lookup {
+    Hit(std::result::Result<CachedValue, CachedError>),
+    Stale(CachedValue),
+    Miss,
+}
+
+impl QueryCache {
+    pub fn new(config: CacheConfig) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                entries: Mutex::new(HashMap::new()),
+                queries: Mutex::new(HashMap::new()),
+                snapshots: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    pub fn config(&self) -> &CacheConfig {
+        &self.inner.config
+    }
+
+    /// Returns the response for `query_type`, from the cache when possible.
+    pub async fn get(&self, addr: SocketAddr, query_type: QueryType) -> Result<CachedValue> {
+        if query_type == QueryType::Rcon {
+            return Err(Error::InvalidQueryType(
+                "RCON responses are never cached".to_string(),
+            ));
+        }
+
+        match self.lookup((addr, query_type)) {
+            Lookup::Hit(value) => value.map_err(Error::from),
+            Lookup::Stale(value) => {
+                // The refresh completes in the background; while the rate
+                // limiter does not allow one, the stale value is served alone.
+                debug!("Revalidating {} {}", addr, query_type);
+                let _ = self.query_flight(addr, query_type);
+                Ok(value)
+            }
+            Lookup::Miss => self.fetch(addr, query_type).await,
+        }
+    }
+
+    /// Queries the server, bypassing the cache, and stores the result. Joins
+    /// the query already in flight for the same entry, if there is one.
+    pub async fn fetch(&self, addr: SocketAddr, query_type: QueryType) -> Result<CachedValue> {
+        Ok(self.query_flight(addr, query_type)?.await?)
+    }
+
+    /// The query in flight for `(addr, query_type)`, started if there is none.
+    fn query_flight(&self, addr: SocketAddr, query_type: QueryType) -> Result<Flight<CachedValue>> {
+        let key = (addr, query_type);
+        let mut queries = self.inner.queries.lock().unwrap();
+        if let Some(flight) = queries.get(&key) {
+            return Ok(flight.clone());
+        }
+        self.check_limit(addr)?;
+
+        let cache = self.clone();
+        let flight = start(async move {
+            let result = query(addr, query_type, cache.inner.config.client.clone()).await;
+            let value = result.map_err(|e| CachedError::from(&e));
+            cache.inner.entries.lock().unwrap().insert(
+                key,
+                Entry {
+                    value: value.clone(),
+                    fetched_at: Instant::now(),
+                },
+            );
+            cache.inner.queries.lock().unwrap().remove(&key);
+            value
+        });
+        queries.insert(key, flight.clone());
+        Ok(flight)
+    }
+
+    /// Accounts for a query to `addr`, or fails if the limiter does not
+    /// allow one yet.
+    fn check_limit(&self, addr: SocketAddr) -> Result<()> {
+        match &self.inner.config.limiter {
+            Some(limiter) => limiter.try_acquire(addr).map_err(Error::RateLimited),
+            None => Ok(()),
+        }
+    }
+
+    /// The parts of a [`ServerSnapshot`], in the order they are stored.
+    const SNAPSHOT_PARTS: [QueryType; 4] = [
+        QueryType::Information,
+        QueryType::Rules,
+        QueryType::DetailedPlayerInfo,
+        QueryType::Ping,
+    ];
+
+    /// Returns the information, rules, detailed players and ping of a server
+    /// as a snapshot. Unless all four are cached and fresh, they are queried
+    /// at once with [`Client::snapshot_concurrent`] and stored.
+    ///
+    /// As with [`Client::snapshot`], only the information is required; parts
+    /// the server did not answer are `None`.
+    pub async fn snapshot(&self, addr: SocketAddr) -> Result<ServerSnapshot> {
+        let mut parts = Vec::with_capacity(Self::SNAPSHOT_PARTS.len());
+        for query_type in Self::SNAPSHOT_PARTS {
+            match self.peek((addr, query_type)) {
+                // A server known not to answer is not asked again.
+                Some((Err(e), _)) if query_type == QueryType::Information => return Err(e.into()),
+                Some(part) => parts.push(part),
+                None => return self.fetch_snapshot(addr).await,
+            }
+        }
+        let [(info, age), (rules, _), (players, _), (ping, _)]: [_; 4] = parts.try_into().map_err(|_| unexpected())?;
+        let Ok(CachedValue::Info(info)) = info else {
+            return Err(unexpected());
+        };
+
+        Ok(ServerSnapshot {
+            address: addr,
+            timestamp: unix_timestamp_ms().saturating_sub(age.as_millis() as u64),
+            info,
+            rules: match rules {
+                Ok(CachedValue::Rules(rules)) => Some(rules),
+                _ => None,
+            },
+            players: match players {
+                Ok(CachedValue::DetailedPlayers(players)) => Some(players),
+                _ => None,
+            },
+            ping: match ping {
+                Ok(CachedValue::Ping(ping)) => Some(ping),
+                _ => None,
+            },
+        })
+    }
+
+    /// Queries all parts of a snapshot in one pass, bypassing the cache, and
+    /// stores them. Parts the server did not answer are cached as failures.
+    /// Joins the snapshot already being taken of `addr`, if there is one.
+    pub async fn fetch_snapshot(&self, addr: SocketAddr) -> Result<ServerSnapshot> {
+        let flight = {
+            let mut snapshots = self.inner.snapshots.lock().unwrap();
+            match snapshots.get(&addr) {
+                Some(flight) => flight.clone(),
+                None => {
+                    self.check_limit(addr)?;
+                    let flight = start(self.clone().take_snapshot(addr));
+                    snapshots.insert(addr, flight.clone());
+                    flight
+                }
+            }
+        };
+        Ok(flight.await?)
+    }
+
+    async fn take_snapshot(self, addr: SocketAddr) -> std::result::Result<ServerSnapshot, CachedError> {
+        let result = match Client::connect_with_config(addr, self.inner.config.client.clone()).await {
+            Ok(client) => client.snapshot_concurrent().await,
+            Err(e) => Err(e),
+        };
+
+        let unanswered = || CachedError::Timeout;
+        let values = match &result {
+            Ok(snapshot) => vec![
+                Ok(CachedValue::Info(snapshot.info.clone())),
+                snapshot.rules.clone().map(CachedValue::Rules).ok_or_else(unanswered),
+                snapshot.players.clone().map(CachedValue::DetailedPlayers).ok_or_else(unanswered),
+                snapshot.ping.clone().map(CachedValue::Ping).ok_or_else(unanswered),
+            ],
+            Err(e) => vec![Err(CachedError::from(e))],
+        };
+        let fetched_at = Instant::now();
+        {
+            let mut entries = self.inner.entries.lock().unwrap();
+            for (query_type, value) in Self::SNAPSHOT_PARTS.into_iter().zip(values) {
+                entries.insert((addr, query_type), Entry { value, fetched_at });
+            }
+        }
+        self.inner.snapshots.lock().unwrap().remove(&addr);
+
+        result.map_err(|e| CachedError::from(&e))
+    }
+
+    /// The entry for `key` and its age, if it is still fresh.
+    fn peek(&self, key: Key) -> Option<(std::result::Result<CachedValue, CachedError>, Duration)> {
+        let config = &self.inner.config;
+        let entries = self.inner.entries.lock().unwrap();
+        let entry = entries.get(&key)?;
+        let age = entry.fetched_at.elapsed();
+        let ttl = match entry.value {
+            Ok(_) => config.ttl(key.1),
+            Err(_) => config.negative_ttl,
+        };
+        (age < ttl).then(|| (entry.value.clone(), age))
+    }
+
+    fn lookup(&self, key: Key) -> Lookup {
+        let config = &self.inner.config;
+        let entries = self.inner.entries.lock().unwrap();
+        let Some(entry) = entries.get(&key) else {
+            return Lookup::Miss;
+        };
+
+        let age = entry.fetched_at.elapsed();
+        match &entry.value {
+            Ok(_) if age < config.ttl(key.1) => Lookup::Hit(entry.value.clone()),
+            Err(_) if age < config.negative_ttl => Lookup::Hit(entry.value.clone()),
+            Ok(value) if age < config.ttl(key.1) + config.stale_ttl => Lookup::Stale(value.clone()),
+            _ => Lookup::Miss,
+        }
+    }
+
+    /// Drops every cached entry for a server.
+    pub fn invalidate(&self, addr: SocketAddr) {
+        self.inner
+            .entries
+            .lock()
+            .unwrap()
+            .retain(|(entry_addr, _), _| *entry_addr != addr);
+    }
+
+    /// Drops entries that can no longer be served, even stale.
+    pub fn purge_expired(&self) {
+        let config = &self.inner.config;
+        self.inner.entries.lock().unwrap().retain(|(_, query_type), entry| {
+            let max_age = match entry.value {
+                Ok(_) => config.ttl(*query_type) + config.stale_ttl,
+                Err(_) => config.negative_ttl,
+            };
+            entry.fetched_at.elapsed() < max_age
+        });
+    }
+
+    pub fn len(&self) -> usize {
+        self.inner.entries.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub async fn info(&self, addr: SocketAddr) -> Result<ServerInfo> {
+        match self.get(addr, QueryType::Information).await? {
+            CachedValue::Info(info) => Ok(info),
+            _ => Err(unexpected()),
+        }
+    }
+
+    pub async fn rules(&self, addr: SocketAddr) -> Result<ServerRules> {
+        match self.get(addr, QueryType::Rules).await? {
+            CachedValue::Rules(rules) => Ok(rules),
+            _ => Err(unexpected()),
+        }
+    }
+
+    pub async fn client_list(&self, addr: SocketAddr) -> Result<PlayerList> {
+        match self.get(addr, QueryType::ClientList).await? {
+            CachedValue::ClientList(players) => Ok(players),
+            _ => Err(unexpected()),
+        }
+    }
+
+    pub async fn detailed_players(&self, addr: SocketAddr) -> Result<DetailedPlayerList> {
+        match self.get(addr, QueryType::DetailedPlayerInfo).await? {
+            CachedValue::DetailedPlayers(players) => Ok(players),
+            _ => Err(unexpected()),
+        }
+    }
+
+    pub async fn ping(&self, addr: SocketAddr) -> Result<PingInfo> {
+        match self.get(addr, QueryType::Ping).await? {
+            CachedValue::Ping(ping) => Ok(ping),
+            _ => Err(unexpected()),
+        }
+    }
+}
+
+/// Runs `query` to completion in the background, even if every fetch
+/// awaiting it is dropped, so its result is still cached.
+fn start<T>(query: impl Future<Output = std::result::Result<T, CachedError>> + Send + 'static) -> Flight<T>
+where
+    T: Clone + Send + Sync + 'static,
+{
+    let task = tokio::spawn(query);
+    async move {
+        task.await
+            .unwrap_or_else(|e| Err(CachedError::Other(format!("Query task failed: {}", e))))
+    }
+    .boxed()
+    .shared()
+}
+
+fn unexpected() -> Error {
+    Error::Other("Cached value has an unexpected type".to_string())
+}
+
+async fn query(addr: SocketAddr, query_type: QueryType, config: ClientConfig) -> Result<CachedValue> {
+    let client = Client::connect_with_config(addr, config).await?;
+
+    Ok(match query_type {
+        QueryType::Information => CachedValue::Info(client.query_info().await?),
+        QueryType::Rules => CachedValue::Rules(client.query_rules().await?),
+        QueryType::ClientList => CachedValue::ClientList(client.query_client_list().await?),
+        QueryType::DetailedPlayerInfo => {
+            CachedValue::DetailedPlayers(client.query_detailed_player_info().await?)
+        }
+        QueryType::Ping => CachedValue::Ping(client.query_ping().await?),
+        QueryType::Rcon => {
+            return Err(Error::InvalidQueryType(
+                "RCON responses are never cached".to_string(),
+            ))
+        }
+    })
+}
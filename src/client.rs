@@ -1,21 +1,27 @@
 //! Client implementation for the SAMP Query protocol.
 
 use crate::error::{Error, Result};
-use crate::packet::{utils as packet_utils, Packet};
+use crate::packet::explain::{self, Direction};
+use crate::packet::{decode, Packet};
 use crate::protocol::{constants, QueryType};
+#[cfg(feature = "net")]
+use crate::transport::UdpTransport;
+use crate::transport::Transport;
 use crate::types::*;
-use bytes::Buf;
-use std::collections::HashMap;
-use std::io::Cursor;
+use crate::utils::{retry_if, unix_timestamp_ms, RetryPolicy};
+use std::fmt;
 use std::net::SocketAddr;
-use std::time::{Duration, Instant};
-use tokio::net::UdpSocket;
-use tokio::time::timeout;
+use std::time::Duration;
+use tokio::time::{timeout, Instant};
 
 #[derive(Debug, Clone)]
 pub struct ClientConfig {
     pub timeout_ms: u64,
+    /// Attempts per query, including the first; `0` is treated as `1`.
     pub max_retries: usize,
+    /// How timed-out queries are retried. `None` sends up to `max_retries`
+    /// attempts back to back.
+    pub retry: Option<RetryPolicy>,
 }
 
 impl Default for ClientConfig {
@@ -23,65 +29,137 @@ impl Default for ClientConfig {
         Self {
             timeout_ms: constants::DEFAULT_TIMEOUT_MS,
             max_retries: constants::MAX_RETRIES,
+            retry: None,
         }
     }
 }
 
-#[derive(Debug)]
 pub struct Client {
     server_addr: SocketAddr,
-    socket: UdpSocket,
+    transport: Box<dyn Transport>,
     config: ClientConfig,
 }
 
+impl fmt::Debug for Client {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Client")
+            .field("server_addr", &self.server_addr)
+            .field("config", &self.config)
+            .finish_non_exhaustive()
+    }
+}
+
 impl Client {
+    #[cfg(feature = "net")]
     pub async fn connect(server_addr: SocketAddr) -> Result<Self> {
         Self::connect_with_config(server_addr, ClientConfig::default()).await
     }
 
+    #[cfg(feature = "net")]
     pub async fn connect_with_config(
         server_addr: SocketAddr,
         config: ClientConfig,
     ) -> Result<Self> {
-        let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(Error::Bind)?;
+        let transport = UdpTransport::connect(server_addr).await?;
+
+        Ok(Self::with_transport(server_addr, transport, config))
+    }
 
-        socket
-            .connect(server_addr)
-            .await
-            .map_err(Error::Connect)?;
+    /// Connects to a `host[:port]` address, resolving hostnames through the
+    /// shared caching [`Resolver`](crate::resolver::Resolver).
+    #[cfg(feature = "net")]
+    pub async fn connect_host(host: &str, config: ClientConfig) -> Result<Self> {
+        let (_, server_addr) = crate::resolver::Resolver::global()
+            .resolve_address(host, constants::DEFAULT_PORT)
+            .await?;
 
-        Ok(Self {
+        Self::connect_with_config(server_addr, config).await
+    }
+
+    /// Creates a client that talks to `server_addr` over a custom transport.
+    ///
+    /// The address is still needed because it is embedded in every query packet.
+    pub fn with_transport<T: Transport + 'static>(
+        server_addr: SocketAddr,
+        transport: T,
+        config: ClientConfig,
+    ) -> Self {
+        Self {
             server_addr,
-            socket,
+            transport: Box::new(transport),
             config,
-        })
+        }
     }
 
-    async fn send_query(&self, packet: &Packet) -> Result<Vec<u8>> {
-        let mut retries = 0;
-        let timeout_duration = Duration::from_millis(self.config.timeout_ms);
+    /// The address of the server this client queries.
+    pub fn server_addr(&self) -> SocketAddr {
+        self.server_addr
+    }
 
-        while retries < self.config.max_retries {
-            self.socket
-                .send(packet.as_bytes())
-                .await
-                .map_err(Error::Send)?;
-
-            let mut buf = vec![0u8; constants::MAX_PACKET_SIZE];
-            match timeout(timeout_duration, self.socket.recv(&mut buf)).await {
-                Ok(Ok(size)) => {
-                    buf.truncate(size);
-                    return Ok(buf);
-                }
-                Ok(Err(e)) => return Err(Error::Receive(e)),
-                Err(_) => {
-                    retries += 1;
-                    continue;
-                }
-            }
+    pub fn config(&self) -> &ClientConfig {
+        &self.config
+    }
+
+    /// Changes the per-attempt timeout of subsequent queries.
+    pub fn set_timeout_ms(&mut self, timeout_ms: u64) {
+        self.config.timeout_ms = timeout_ms;
+    }
+
+    async fn send(&self, data: &[u8]) -> Result<()> {
+        self.trace(data, Direction::Request);
+        self.transport.send(data).await
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        let size = self.transport.recv(buf).await?;
+        self.trace(&buf[..size], Direction::Response);
+        Ok(size)
+    }
+
+    /// Logs an annotated hexdump of `data` when [`explain::TRACE_TARGET`] is
+    /// enabled at the `trace` level.
+    fn trace(&self, data: &[u8], direction: Direction) {
+        if tracing::enabled!(target: explain::TRACE_TARGET, tracing::Level::TRACE) {
+            let action = match direction {
+                Direction::Request => "sent to",
+                Direction::Response => "received from",
+            };
+            tracing::trace!(
+                target: explain::TRACE_TARGET,
+                "{} bytes {} {}\n{}",
+                data.len(),
+                action,
+                self.server_addr,
+                explain::hexdump(data, direction).trim_end()
+            );
         }
+    }
 
-        Err(Error::Timeout)
+    async fn send_query(&self, packet: &Packet) -> Result<Vec<u8>> {
+        let timeout_duration = Duration::from_millis(self.config.timeout_ms);
+        let policy = match &self.config.retry {
+            Some(policy) => policy.clone(),
+            None => RetryPolicy::immediate(self.config.max_retries),
+        };
+
+        retry_if(
+            &policy,
+            |_| async {
+                self.send(packet.as_bytes()).await?;
+
+                let mut buf = vec![0u8; constants::MAX_PACKET_SIZE];
+                match timeout(timeout_duration, self.recv(&mut buf)).await {
+                    Ok(Ok(size)) => {
+                        buf.truncate(size);
+                        Ok(buf)
+                    }
+                    Ok(Err(e)) => Err(e),
+                    Err(_) => Err(Error::Timeout),
+                }
+            },
+            Error::is_timeout,
+        )
+        .await
     }
 
     pub async fn query_info(&self) -> Result<ServerInfo> {
@@ -90,24 +168,7 @@ impl Client {
         let response_packet = Packet::from_bytes(&response);
         let data = response_packet.parse_response(QueryType::Information)?;
 
-        let mut cursor = Cursor::new(&data);
-
-        let password = cursor.get_u8() != 0;
-        let players = cursor.get_u16_le();
-        let max_players = cursor.get_u16_le();
-
-        let hostname = packet_utils::read_length_prefixed_string_32(&mut cursor)?;
-        let gamemode = packet_utils::read_length_prefixed_string_32(&mut cursor)?;
-        let language = packet_utils::read_length_prefixed_string_32(&mut cursor)?;
-
-        Ok(ServerInfo {
-            password,
-            players,
-            max_players,
-            hostname,
-            gamemode,
-            language,
-        })
+        decode::info(&data)
     }
 
     pub async fn query_rules(&self) -> Result<ServerRules> {
@@ -116,18 +177,7 @@ impl Client {
         let response_packet = Packet::from_bytes(&response);
         let data = response_packet.parse_response(QueryType::Rules)?;
 
-        let mut cursor = Cursor::new(&data);
-
-        let rule_count = cursor.get_u16_le() as usize;
-        let mut rules = HashMap::with_capacity(rule_count);
-
-        for _ in 0..rule_count {
-            let name = packet_utils::read_length_prefixed_string(&mut cursor)?;
-            let value = packet_utils::read_length_prefixed_string(&mut cursor)?;
-            rules.insert(name, value);
-        }
-
-        Ok(ServerRules { rules })
+        decode::rules(&data)
     }
 
     pub async fn query_client_list(&self) -> Result<PlayerList> {
@@ -136,19 +186,7 @@ impl Client {
         let response_packet = Packet::from_bytes(&response);
         let data = response_packet.parse_response(QueryType::ClientList)?;
 
-        let mut cursor = Cursor::new(&data);
-
-        let player_count = cursor.get_u16_le() as usize;
-        let mut players = Vec::with_capacity(player_count);
-
-        for _ in 0..player_count {
-            let name = packet_utils::read_length_prefixed_string(&mut cursor)?;
-            let score = cursor.get_i32_le();
-
-            players.push(Player { name, score });
-        }
-
-        Ok(PlayerList { players })
+        decode::client_list(&data)
     }
 
     pub async fn query_detailed_player_info(&self) -> Result<DetailedPlayerList> {
@@ -157,26 +195,7 @@ impl Client {
         let response_packet = Packet::from_bytes(&response);
         let data = response_packet.parse_response(QueryType::DetailedPlayerInfo)?;
 
-        let mut cursor = Cursor::new(&data);
-
-        let player_count = cursor.get_u16_le() as usize;
-        let mut players = Vec::with_capacity(player_count);
-
-        for _ in 0..player_count {
-            let id = cursor.get_u8();
-            let name = packet_utils::read_length_prefixed_string(&mut cursor)?;
-            let score = cursor.get_i32_le();
-            let ping = cursor.get_u32_le();
-
-            players.push(DetailedPlayer {
-                id,
-                name,
-                score,
-                ping,
-            });
-        }
-
-        Ok(DetailedPlayerList { players })
+        decode::detailed_players(&data)
     }
 
     pub async fn query_ping(&self) -> Result<PingInfo> {
@@ -189,7 +208,7 @@ impl Client {
         let response_packet = Packet::from_bytes(&response);
         let data = response_packet.parse_response(QueryType::Ping)?;
 
-        if data.len() < 4 || &data[0..4] != &random_bytes {
+        if data.len() < 4 || data[0..4] != random_bytes {
             return Err(Error::InvalidResponse(
                 "Invalid ping response".to_string(),
             ));
@@ -200,19 +219,161 @@ impl Client {
         })
     }
 
+    /// Runs an RCON command and returns its output lines joined by newlines.
+    ///
+    /// Fails with [`Error::Timeout`] when the command produced no output.
+    /// Use [`RconSession`](crate::rcon::RconSession) to run several commands
+    /// or to get the lines separately.
     pub async fn rcon_command(&self, password: &str, command: &str) -> Result<RconResponse> {
+        let lines = self
+            .rcon_lines(password, command, Duration::from_millis(constants::RCON_LINE_TIMEOUT_MS))
+            .await?;
+        if lines.is_empty() {
+            return Err(Error::Timeout);
+        }
+
+        Ok(RconResponse {
+            message: lines.join("\n"),
+        })
+    }
+
+    /// Sends an RCON command once and collects its output.
+    ///
+    /// The server sends one datagram per output line with no end marker, so
+    /// lines are read until `line_timeout` passes without a new one. The
+    /// first line is awaited for the configured timeout; a command without
+    /// output returns no lines. Commands are not retried, since running one
+    /// twice is rarely harmless.
+    pub(crate) async fn rcon_lines(&self, password: &str, command: &str, line_timeout: Duration) -> Result<Vec<String>> {
         let packet = Packet::create_rcon_query(self.server_addr, password, command)?;
-        let response = self.send_query(&packet).await?;
-        let response_packet = Packet::from_bytes(&response);
-        let data = response_packet.parse_response(QueryType::Rcon)?;
+        self.send(packet.as_bytes()).await?;
+
+        let mut lines = Vec::new();
+        let mut buf = vec![0u8; constants::MAX_PACKET_SIZE];
+        loop {
+            let wait = if lines.is_empty() {
+                Duration::from_millis(self.config.timeout_ms)
+            } else {
+                line_timeout
+            };
+            let size = match timeout(wait, self.recv(&mut buf)).await {
+                Ok(result) => result?,
+                Err(_) => return Ok(lines),
+            };
+
+            let response = Packet::from_bytes(&buf[..size]);
+            if response.query_type() != Some(QueryType::Rcon) {
+                continue;
+            }
+            let data = response.parse_response(QueryType::Rcon)?;
+            if data.is_empty() {
+                return Err(Error::RconAuthFailed);
+            }
 
-        if data.is_empty() {
-            return Err(Error::RconAuthFailed);
+            let line = decode::rcon_line(&data)?;
+            if lines.is_empty() && line == "Invalid RCON password." {
+                return Err(Error::RconAuthFailed);
+            }
+            lines.push(line);
         }
+    }
+
+    /// Queries information, rules, detailed players and ping in one pass.
+    ///
+    /// Only the information query is required to succeed; the other parts are
+    /// left empty when the server does not answer them (e.g. the detailed
+    /// player query on servers with more than 100 players).
+    pub async fn snapshot(&self) -> Result<ServerSnapshot> {
+        let info = self.query_info().await?;
+        let rules = self.query_rules().await.ok();
+        let players = self.query_detailed_player_info().await.ok();
+        let ping = self.query_ping().await.ok();
+
+        Ok(ServerSnapshot {
+            address: self.server_addr,
+            timestamp: unix_timestamp_ms(),
+            info,
+            rules,
+            players,
+            ping,
+        })
+    }
 
-        let message = String::from_utf8(data).map_err(Error::from)?;
+    /// Like [`snapshot`](Self::snapshot), but sends the four queries at once.
+    ///
+    /// Responses are told apart by the opcode in their header, so the whole
+    /// snapshot takes about one round trip. Queries still unanswered when the
+    /// timeout passes are sent again, up to the configured number of attempts.
+    pub async fn snapshot_concurrent(&self) -> Result<ServerSnapshot> {
+        let timeout_duration = Duration::from_millis(self.config.timeout_ms);
+        let (ping_packet, random_bytes) = Packet::create_ping_query(self.server_addr)?;
+        let mut pending = vec![
+            (QueryType::Information, Packet::create_query(self.server_addr, QueryType::Information)?),
+            (QueryType::Rules, Packet::create_query(self.server_addr, QueryType::Rules)?),
+            (
+                QueryType::DetailedPlayerInfo,
+                Packet::create_query(self.server_addr, QueryType::DetailedPlayerInfo)?,
+            ),
+            (QueryType::Ping, ping_packet),
+        ];
+
+        let (mut info, mut rules, mut players, mut ping) = (None, None, None, None);
+        let mut buf = vec![0u8; constants::MAX_PACKET_SIZE];
+        for _ in 0..self.config.max_retries.max(1) {
+            let start = Instant::now();
+            for (_, packet) in &pending {
+                self.send(packet.as_bytes()).await?;
+            }
 
-        Ok(RconResponse { message })
+            while !pending.is_empty() {
+                let size = match tokio::time::timeout_at(start + timeout_duration, self.recv(&mut buf)).await {
+                    Ok(result) => result?,
+                    Err(_) => break,
+                };
+                let response = Packet::from_bytes(&buf[..size]);
+                let Some(query_type) = response.query_type() else {
+                    continue;
+                };
+                let Some(index) = pending.iter().position(|(pending, _)| *pending == query_type) else {
+                    continue;
+                };
+                let Ok(data) = response.parse_response(query_type) else {
+                    continue;
+                };
+
+                match query_type {
+                    QueryType::Information => info = Some(decode::info(&data)?),
+                    QueryType::Rules => rules = decode::rules(&data).ok(),
+                    QueryType::DetailedPlayerInfo => players = decode::detailed_players(&data).ok(),
+                    QueryType::Ping => {
+                        // A reply to an earlier attempt carries the same bytes,
+                        // so the ping may include a lost round trip; that is
+                        // rare enough not to matter.
+                        if data.len() < 4 || data[0..4] != random_bytes {
+                            continue;
+                        }
+                        ping = Some(PingInfo {
+                            ping_ms: start.elapsed().as_millis() as u64,
+                        });
+                    }
+                    _ => continue,
+                }
+                pending.remove(index);
+            }
+
+            if pending.is_empty() {
+                break;
+            }
+        }
+
+        Ok(ServerSnapshot {
+            address: self.server_addr,
+            timestamp: unix_timestamp_ms(),
+            info: info.ok_or(Error::Timeout)?,
+            rules,
+            players,
+            ping,
+        })
     }
 
     pub async fn query(&self, query_type: QueryType) -> Result<Box<dyn std::any::Any>> {
@@ -238,9 +399,9 @@ impl Client {
                 Ok(Box::new(ping))
             }
             QueryType::Rcon => {
-                return Err(Error::InvalidQueryType(
+                Err(Error::InvalidQueryType(
                     "RCON queries require a password and command".to_string(),
-                ));
+                ))
             }
         }
     }
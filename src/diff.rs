@@ -0,0 +1,156 @@
+//! Differences between two snapshots of a server.
+//!
+//! [`SnapshotDiff::between`] compares the information, rules and player
+//! lists of an older and a newer [`ServerSnapshot`], e.g. one saved to disk
+//! and one taken now.
+//!
+//! ```
+//! use samp_query::diff::SnapshotDiff;
+//! use samp_query::{DetailedPlayer, DetailedPlayerList, ServerInfo, ServerSnapshot};
+//!
+//! let player = |name: &str| DetailedPlayer {
+//!     id: 0,
+//!     name: name.to_string(),
+//!     score: 0,
+//!     ping: 0,
+//! };
+//! let old = ServerSnapshot {
+//!     address: "127.0.0.1:7777".parse().unwrap(),
+//!     timestamp: 0,
+//!     info: ServerInfo {
+//!         hostname: "Freeroam".to_string(),
+//!         players: 1,
+//!         ..Default::default()
+//!     },
+//!     rules: None,
+//!     players: Some(DetailedPlayerList { players: vec![player("Carl")] }),
+//!     ping: None,
+//! };
+//! let mut new = old.clone();
+//! new.info.hostname = "Freeroam | Event".to_string();
+//! new.players = Some(DetailedPlayerList { players: vec![player("Sweet")] });
+//!
+//! let diff = SnapshotDiff::between(&old, &new);
+//! assert_eq!(diff.joined, ["Sweet"]);
+//! assert_eq!(diff.left, ["Carl"]);
+//! assert_eq!(diff.info[0].field, "hostname");
+//! ```
+
+use crate::types::{ServerInfo, ServerSnapshot};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeSet;
+use std::fmt;
+
+/// A value that differs between the two snapshots. `None` means the value
+/// is absent from that snapshot, e.g. a rule that was added or removed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Change {
+    /// Name of the information field or rule.
+    pub field: String,
+    pub old: Option<String>,
+    pub new: Option<String>,
+}
+
+impl fmt::Display for Change {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (&self.old, &self.new) {
+            (Some(old), Some(new)) => write!(f, "{} changed from \"{}\" to \"{}\"", self.field, old, new),
+            (None, Some(new)) => write!(f, "{} added as \"{}\"", self.field, new),
+            (Some(old), None) => write!(f, "{} removed (was \"{}\")", self.field, old),
+            (None, None) => write!(f, "{} unchanged", self.field),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SnapshotDiff {
+    /// Players in the newer snapshot but not the older one, sorted by name.
+    pub joined: Vec<String>,
+    /// Players in the older snapshot but not the newer one, sorted by name.
+    pub left: Vec<String>,
+    /// Changed information fields.
+    pub info: Vec<Change>,
+    /// Added, removed and changed rules, sorted by name.
+    pub rules: Vec<Change>,
+}
+
+impl SnapshotDiff {
+    /// Compares `old` with `new`. Players and rules are only compared when
+    /// both snapshots include them.
+    pub fn between(old: &ServerSnapshot, new: &ServerSnapshot) -> Self {
+        let mut diff = Self {
+            info: info_changes(&old.info, &new.info),
+            ..Self::default()
+        };
+
+        if let (Some(old), Some(new)) = (&old.players, &new.players) {
+            let old: BTreeSet<&str> = old.players.iter().map(|p| p.name.as_str()).collect();
+            let new: BTreeSet<&str> = new.players.iter().map(|p| p.name.as_str()).collect();
+            diff.joined = new.difference(&old).map(|name| name.to_string()).collect();
+            diff.left = old.difference(&new).map(|name| name.to_string()).collect();
+        }
+
+        if let (Some(old), Some(new)) = (&old.rules, &new.rules) {
+            let names: BTreeSet<&String> = old.rules.keys().chain(new.rules.keys()).collect();
+            for name in names {
+                let (old, new) = (old.rules.get(name), new.rules.get(name));
+                if old != new {
+                    diff.rules.push(Change {
+                        field: name.clone(),
+                        old: old.cloned(),
+                        new: new.cloned(),
+                    });
+                }
+            }
+        }
+        diff
+    }
+
+    /// Returns true if the snapshots do not differ.
+    pub fn is_empty(&self) -> bool {
+        self.joined.is_empty() && self.left.is_empty() && self.info.is_empty() && self.rules.is_empty()
+    }
+}
+
+/// Reads one compared information field.
+type Field = fn(&ServerInfo) -> String;
+
+fn info_changes(old: &ServerInfo, new: &ServerInfo) -> Vec<Change> {
+    let fields: [(&str, Field); 6] = [
+        ("hostname", |info| info.hostname.clone()),
+        ("gamemode", |info| info.gamemode.clone()),
+        ("language", |info| info.language.clone()),
+        ("players", |info| info.players.to_string()),
+        ("max_players", |info| info.max_players.to_string()),
+        ("password", |info| info.password.to_string()),
+    ];
+    fields
+        .iter()
+        .filter_map(|(field, value)| {
+            let (old, new) = (value(old), value(new));
+            (old != new).then(|| Change {
+                field: field.to_string(),
+                old: Some(old),
+                new: Some(new),
+            })
+        })
+        .collect()
+}
+
+impl fmt::Display for SnapshotDiff {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.is_empty() {
+            return writeln!(f, "No differences");
+        }
+        for name in &self.joined {
+            writeln!(f, "+ {} joined", name)?;
+        }
+        for name in &self.left {
+            writeln!(f, "- {} left", name)?;
+        }
+        for change in self.info.iter().chain(&self.rules) {
+            writeln!(f, "~ {}", change)?;
+        }
+        Ok(())
+    }
+}
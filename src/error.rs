@@ -3,6 +3,7 @@
 use std::io;
 use std::net::AddrParseError;
 use std::string::FromUtf8Error;
+use std::time::Duration;
 use thiserror::Error;
 
 pub type Result<T> = std::result::Result<T, Error>;
@@ -18,6 +19,9 @@ pub enum Error {
     #[error("Failed to connect to server: {0}")]
     Connect(#[source] io::Error),
 
+    #[error("Failed to resolve host: {0}")]
+    Resolve(String),
+
     #[error("Connection timed out")]
     Timeout,
 
@@ -42,6 +46,17 @@ pub enum Error {
     #[error("Invalid query type: {0}")]
     InvalidQueryType(String),
 
+    #[error("HTTP error: {0}")]
+    Http(String),
+
+    /// The rate limiter does not allow another query to the server yet; it
+    /// may be retried after the given time.
+    #[error("Query rate limit reached, retry in {0:?}")]
+    RateLimited(Duration),
+
+    #[error("Storage error: {0}")]
+    Storage(String),
+
     #[error("IO error: {0}")]
     Io(#[from] io::Error),
 
@@ -58,6 +73,10 @@ impl Error {
         matches!(self, Error::RconAuthFailed)
     }
 
+    pub fn is_rate_limited(&self) -> bool {
+        matches!(self, Error::RateLimited(_))
+    }
+
     pub fn is_server_error(&self) -> bool {
         matches!(self, Error::ServerError(_))
     }
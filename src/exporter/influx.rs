@@ -0,0 +1,242 @@
+//! InfluxDB line-protocol output.
+//!
+//! [`InfluxSink`] batches snapshots as line protocol and either writes them to
+//! stdout (for the Telegraf `exec`/`execd` input plugins), to any writer, or,
+//! with the `http` feature, pushes them to the InfluxDB write API.
+//!
+//! ```rust,no_run
+//! use samp_query::exporter::influx::{InfluxConfig, InfluxSink};
+//! use samp_query::Client;
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let mut sink = InfluxSink::stdout(InfluxConfig::default());
+//!
+//! let client = Client::connect("127.0.0.1:7777".parse().unwrap()).await?;
+//! sink.push(&client.snapshot().await?).await?;
+//! sink.flush().await?;
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::Result;
+use crate::types::ServerSnapshot;
+use crate::utils::to_influx_line;
+use std::fmt;
+use std::io::Write;
+use std::time::{Duration, Instant};
+use tracing::warn;
+
+#[derive(Debug, Clone)]
+pub struct InfluxConfig {
+    /// Measurement name of every record.
+    pub measurement: String,
+    /// Extra tags added to every record.
+    pub tags: Vec<(String, String)>,
+    /// Whether to tag records with the server hostname as well as its address.
+    pub tag_hostname: bool,
+    /// Number of records buffered before they are written.
+    pub batch_size: usize,
+    /// Most records kept while writes fail; beyond it the oldest are dropped.
+    pub max_buffered: usize,
+    /// How long [`InfluxSink::push`] waits before retrying a failed write,
+    /// doubled after every further failure.
+    pub retry_delay: Duration,
+    /// Upper bound of the delay between retries.
+    pub max_retry_delay: Duration,
+}
+
+impl Default for InfluxConfig {
+    fn default() -> Self {
+        Self {
+            measurement: "samp".to_string(),
+            tags: Vec::new(),
+            tag_hostname: true,
+            batch_size: 100,
+            max_buffered: 10_000,
+            retry_delay: Duration::from_secs(1),
+            max_retry_delay: Duration::from_secs(60),
+        }
+    }
+}
+
+/// Where batches are written.
+pub enum InfluxOutput {
+    Stdout,
+    Writer(Box<dyn Write + Send>),
+    /// InfluxDB write endpoint, e.g.
+    /// `http://localhost:8086/api/v2/write?org=ops&bucket=samp&precision=ns`.
+    #[cfg(feature = "http")]
+    Http { url: String, token: Option<String> },
+}
+
+impl fmt::Debug for InfluxOutput {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            InfluxOutput::Stdout => write!(f, "Stdout"),
+            InfluxOutput::Writer(_) => write!(f, "Writer"),
+            #[cfg(feature = "http")]
+            InfluxOutput::Http { url, .. } => f.debug_struct("Http").field("url", url).finish(),
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct InfluxSink {
+    config: InfluxConfig,
+    output: InfluxOutput,
+    buffer: Vec<String>,
+    /// Writes failed in a row.
+    failures: u32,
+    /// When `push` may try to write again after a failure.
+    retry_at: Option<Instant>,
+    /// Records dropped since the last successful write.
+    dropped: usize,
+    #[cfg(feature = "http")]
+    http: reqwest::Client,
+}
+
+impl InfluxSink {
+    pub fn new(config: InfluxConfig, output: InfluxOutput) -> Self {
+        Self {
+            config,
+            output,
+            buffer: Vec::new(),
+            failures: 0,
+            retry_at: None,
+            dropped: 0,
+            #[cfg(feature = "http")]
+            http: crate::resolver::http_client(),
+        }
+    }
+
+    pub fn stdout(config: InfluxConfig) -> Self {
+        Self::new(config, InfluxOutput::Stdout)
+    }
+
+    pub fn writer<W: Write + Send + 'static>(config: InfluxConfig, writer: W) -> Self {
+        Self::new(config, InfluxOutput::Writer(Box::new(writer)))
+    }
+
+    #[cfg(feature = "http")]
+    pub fn http(config: InfluxConfig, url: impl Into<String>, token: Option<String>) -> Self {
+        Self::new(
+            config,
+            InfluxOutput::Http {
+                url: url.into(),
+                token,
+            },
+        )
+    }
+
+    pub fn config(&self) -> &InfluxConfig {
+        &self.config
+    }
+
+    /// Formats a snapshot with the configured measurement and tags.
+    pub fn line(&self, snapshot: &ServerSnapshot) -> String {
+        let mut tags: Vec<(&str, &str)> = self
+            .config
+            .tags
+            .iter()
+            .map(|(name, value)| (name.as_str(), value.as_str()))
+            .collect();
+        if self.config.tag_hostname && !snapshot.info.hostname.is_empty() {
+            tags.push(("hostname", &snapshot.info.hostname));
+        }
+
+        to_influx_line(snapshot, &self.config.measurement, &tags)
+    }
+
+    /// Number of records waiting for the next flush.
+    pub fn buffered(&self) -> usize {
+        self.buffer.len()
+    }
+
+    /// Buffers a snapshot, flushing once the batch is full.
+    ///
+    /// After a failed write, the next one is only attempted once the retry
+    /// delay has passed; until then records are buffered, up to
+    /// `max_buffered`.
+    pub async fn push(&mut self, snapshot: &ServerSnapshot) -> Result<()> {
+        self.buffer.push(self.line(snapshot));
+
+        let max = self.config.max_buffered.max(self.config.batch_size).max(1);
+        if self.buffer.len() > max {
+            let excess = self.buffer.len() - max;
+            if self.dropped == 0 {
+                warn!("InfluxDB write buffer is full, dropping the oldest records");
+            }
+            self.buffer.drain(..excess);
+            self.dropped += excess;
+        }
+
+        let waiting = self.retry_at.is_some_and(|at| Instant::now() < at);
+        if self.buffer.len() >= self.config.batch_size.max(1) && !waiting {
+            self.flush().await?;
+        }
+
+        Ok(())
+    }
+
+    /// Writes every buffered record.
+    ///
+    /// Records are kept in the buffer if the write fails, so it can be retried.
+    pub async fn flush(&mut self) -> Result<()> {
+        if self.buffer.is_empty() {
+            return Ok(());
+        }
+
+        if let Err(e) = self.write().await {
+            let factor = 1u32.checked_shl(self.failures.min(31)).unwrap_or(u32::MAX);
+            let delay = self.config.retry_delay.saturating_mul(factor).min(self.config.max_retry_delay);
+            self.failures = self.failures.saturating_add(1);
+            self.retry_at = Some(Instant::now() + delay);
+            return Err(e);
+        }
+
+        if self.dropped > 0 {
+            warn!("Dropped {} InfluxDB records while writes were failing", self.dropped);
+        }
+        self.buffer.clear();
+        self.failures = 0;
+        self.retry_at = None;
+        self.dropped = 0;
+        Ok(())
+    }
+
+    async fn write(&mut self) -> Result<()> {
+        let mut body = self.buffer.join("\n");
+        body.push('\n');
+
+        match &mut self.output {
+            InfluxOutput::Stdout => {
+                let mut stdout = std::io::stdout().lock();
+                stdout.write_all(body.as_bytes())?;
+                stdout.flush()?;
+            }
+            InfluxOutput::Writer(writer) => {
+                writer.write_all(body.as_bytes())?;
+                writer.flush()?;
+            }
+            #[cfg(feature = "http")]
+            InfluxOutput::Http { url, token } => {
+                let mut request = self
+                    .http
+                    .post(url.as_str())
+                    .header("Content-Type", "text/plain; charset=utf-8")
+                    .body(body);
+                if let Some(token) = token {
+                    request = request.header("Authorization", format!("Token {}", token));
+                }
+
+                request
+                    .send()
+                    .await
+                    .and_then(|response| response.error_for_status())
+                    .map_err(|e| crate::Error::Http(e.to_string()))?;
+            }
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,4 @@
+//! Metric exporters for external monitoring systems.
+
+pub mod influx;
+pub mod prometheus;
@@ -0,0 +1,347 @@
+//! Prometheus exporter.
+//!
+//! [`PrometheusExporter`] polls a fixed set of servers on an interval and
+//! serves the results on an HTTP `/metrics` endpoint in the Prometheus text
+//! format, so servers can be scraped like any other target.
+//!
+//! ```rust,no_run
+//! use samp_query::exporter::prometheus::{ExporterConfig, PrometheusExporter};
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let config = ExporterConfig {
+//!     servers: vec!["127.0.0.1:7777".parse().unwrap()],
+//!     ..ExporterConfig::default()
+//! };
+//!
+//! PrometheusExporter::new(config).run().await
+//! # }
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::error::{Error, Result};
+use crate::protocol::constants;
+use crate::stats::LatencyStats;
+use crate::types::ServerInfo;
+use crate::utils::escape_prometheus_label;
+use std::collections::BTreeMap;
+use std::fmt::Write;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::io::{AsyncReadExt, AsyncWriteExt};
+use tokio::net::{TcpListener, TcpStream};
+use tokio::sync::Semaphore;
+use tracing::{debug, warn};
+
+/// Most scrapes served at once.
+pub const MAX_CONNECTIONS: usize = 64;
+
+/// How long to wait after failing to accept a connection, e.g. when out of
+/// file descriptors, before accepting again.
+const ACCEPT_ERROR_DELAY: Duration = Duration::from_millis(100);
+
+/// How long a scrape may take, from accepting the connection to sending the
+/// last byte of the response.
+pub const CONNECTION_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Upper bounds in milliseconds of the default ping histogram buckets.
+pub const DEFAULT_PING_BUCKETS: &[u64] = &[10, 25, 50, 100, 150, 250, 500, 1000];
+
+#[derive(Debug, Clone)]
+pub struct ExporterConfig {
+    /// Servers to poll.
+    pub servers: Vec<SocketAddr>,
+    /// Interval between polls.
+    pub interval: Duration,
+    /// Client configuration used for each poll.
+    pub client: ClientConfig,
+    /// Address the `/metrics` endpoint listens on.
+    pub bind: SocketAddr,
+    /// Upper bounds in milliseconds of the ping histogram buckets.
+    pub ping_buckets: Vec<u64>,
+}
+
+impl Default for ExporterConfig {
+    fn default() -> Self {
+        Self {
+            servers: Vec::new(),
+            interval: Duration::from_secs(15),
+            client: ClientConfig::default(),
+            bind: SocketAddr::from(([0, 0, 0, 0], 9777)),
+            ping_buckets: DEFAULT_PING_BUCKETS.to_vec(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+struct ServerMetrics {
+    up: bool,
+    players: u16,
+    max_players: u16,
+    password: bool,
+    ping_ms: Option<u64>,
+    bucket_counts: Vec<u64>,
+    ping_sum: u64,
+    ping_count: u64,
+    latency: LatencyStats,
+    failures: u64,
+}
+
+/// Gauges, counters and histograms for a set of servers.
+///
+/// The registry can also be fed directly by long-running consumers that do
+/// their own polling, and rendered on demand.
+#[derive(Debug)]
+pub struct MetricsRegistry {
+    buckets: Vec<u64>,
+    servers: Mutex<BTreeMap<SocketAddr, ServerMetrics>>,
+}
+
+impl MetricsRegistry {
+    pub fn new(mut buckets: Vec<u64>) -> Self {
+        buckets.sort_unstable();
+        buckets.dedup();
+
+        Self {
+            buckets,
+            servers: Mutex::new(BTreeMap::new()),
+        }
+    }
+
+    fn with_server<F: FnOnce(&mut ServerMetrics)>(&self, addr: SocketAddr, f: F) {
+        let mut servers = self.servers.lock().unwrap();
+        let metrics = servers.entry(addr).or_insert_with(|| ServerMetrics {
+            bucket_counts: vec![0; self.buckets.len()],
+            ..ServerMetrics::default()
+        });
+        f(metrics);
+    }
+
+    /// Records a successful poll.
+    pub fn record_success(&self, addr: SocketAddr, info: &ServerInfo, ping_ms: Option<u64>) {
+        self.with_server(addr, |metrics| {
+            metrics.up = true;
+            metrics.players = info.players;
+            metrics.max_players = info.max_players;
+            metrics.password = info.password;
+            metrics.ping_ms = ping_ms;
+
+            if let Some(ping) = ping_ms {
+                for (count, bound) in metrics.bucket_counts.iter_mut().zip(&self.buckets) {
+                    if ping <= *bound {
+                        *count += 1;
+                    }
+                }
+                metrics.ping_sum += ping;
+                metrics.ping_count += 1;
+                metrics.latency.record(Duration::from_millis(ping));
+            }
+        });
+    }
+
+    /// Records a failed poll.
+    pub fn record_failure(&self, addr: SocketAddr) {
+        self.with_server(addr, |metrics| {
+            metrics.up = false;
+            metrics.players = 0;
+            metrics.ping_ms = None;
+            metrics.failures += 1;
+        });
+    }
+
+    /// Renders every metric in the Prometheus text exposition format.
+    pub fn render(&self) -> String {
+        let servers = self.servers.lock().unwrap();
+        let mut output = String::new();
+
+        let gauges: [(&str, &str, GaugeValue); 10] = [
+            ("samp_up", "Whether the server answered the last poll.", |m| Some(m.up as u64)),
+            ("samp_players", "Current number of players online.", |m| Some(m.players as u64)),
+            ("samp_max_players", "Maximum number of players.", |m| Some(m.max_players as u64)),
+            ("samp_password", "Whether the server is password protected.", |m| Some(m.password as u64)),
+            ("samp_ping_ms", "Ping of the last poll in milliseconds.", |m| m.ping_ms),
+            ("samp_ping_p50_ms", "Median ping over the recent polls in milliseconds.", |m| latency_ms(m.latency.p50())),
+            ("samp_ping_p95_ms", "95th percentile ping over the recent polls in milliseconds.", |m| latency_ms(m.latency.p95())),
+            ("samp_ping_p99_ms", "99th percentile ping over the recent polls in milliseconds.", |m| latency_ms(m.latency.p99())),
+            ("samp_ping_ewma_ms", "Moving average of the ping in milliseconds.", |m| latency_ms(m.latency.ewma())),
+            ("samp_ping_jitter_ms", "Jitter between consecutive pings in milliseconds.", |m| latency_ms(m.latency.jitter())),
+        ];
+        for (name, help, value) in gauges {
+            let _ = writeln!(output, "# HELP {} {}", name, help);
+            let _ = writeln!(output, "# TYPE {} gauge", name);
+            for (addr, metrics) in servers.iter() {
+                if let Some(value) = value(metrics) {
+                    let _ = writeln!(output, "{}{{{}}} {}", name, address_label(addr), value);
+                }
+            }
+        }
+
+        let _ = writeln!(output, "# HELP samp_query_failures_total Number of failed polls.");
+        let _ = writeln!(output, "# TYPE samp_query_failures_total counter");
+        for (addr, metrics) in servers.iter() {
+            let _ = writeln!(
+                output,
+                "samp_query_failures_total{{{}}} {}",
+                address_label(addr),
+                metrics.failures
+            );
+        }
+
+        let _ = writeln!(output, "# HELP samp_ping_duration_ms Distribution of ping in milliseconds.");
+        let _ = writeln!(output, "# TYPE samp_ping_duration_ms histogram");
+        for (addr, metrics) in servers.iter() {
+            let label = address_label(addr);
+            for (bound, count) in self.buckets.iter().zip(&metrics.bucket_counts) {
+                let _ = writeln!(output, "samp_ping_duration_ms_bucket{{{},le=\"{}\"}} {}", label, bound, count);
+            }
+            let _ = writeln!(output, "samp_ping_duration_ms_bucket{{{},le=\"+Inf\"}} {}", label, metrics.ping_count);
+            let _ = writeln!(output, "samp_ping_duration_ms_sum{{{}}} {}", label, metrics.ping_sum);
+            let _ = writeln!(output, "samp_ping_duration_ms_count{{{}}} {}", label, metrics.ping_count);
+        }
+
+        output
+    }
+}
+
+type GaugeValue = fn(&ServerMetrics) -> Option<u64>;
+
+fn latency_ms(value: Option<Duration>) -> Option<u64> {
+    value.map(|value| value.as_millis() as u64)
+}
+
+fn address_label(addr: &SocketAddr) -> String {
+    format!("address=\"{}\"", escape_prometheus_label(&addr.to_string()))
+}
+
+#[derive(Debug, Clone)]
+pub struct PrometheusExporter {
+    config: ExporterConfig,
+    registry: Arc<MetricsRegistry>,
+}
+
+impl PrometheusExporter {
+    pub fn new(mut config: ExporterConfig) -> Self {
+        // A zero period makes the tokio ticker of `run` panic.
+        config.interval = config.interval.max(Duration::from_millis(constants::MIN_POLL_INTERVAL_MS));
+        let registry = Arc::new(MetricsRegistry::new(config.ping_buckets.clone()));
+        Self { config, registry }
+    }
+
+    pub fn config(&self) -> &ExporterConfig {
+        &self.config
+    }
+
+    pub fn registry(&self) -> Arc<MetricsRegistry> {
+        self.registry.clone()
+    }
+
+    /// Polls every configured server once.
+    pub async fn poll_once(&self) {
+        let polls = self.config.servers.iter().map(|&addr| async move {
+            match poll(addr, self.config.client.clone()).await {
+                Ok((info, ping_ms)) => self.registry.record_success(addr, &info, ping_ms),
+                Err(e) => {
+                    debug!("Failed to poll {}: {}", addr, e);
+                    self.registry.record_failure(addr);
+                }
+            }
+        });
+
+        futures::future::join_all(polls).await;
+    }
+
+    /// Serves `/metrics` on an already bound listener forever.
+    ///
+    /// At most [`MAX_CONNECTIONS`] scrapes are served at once; further
+    /// connections wait to be accepted. A scrape that takes longer than
+    /// [`CONNECTION_TIMEOUT`] is dropped. Failures to accept a connection are
+    /// logged and do not stop the exporter.
+    pub async fn serve(&self, listener: TcpListener) -> Result<()> {
+        let connections = Arc::new(Semaphore::new(MAX_CONNECTIONS));
+        loop {
+            let permit = connections.clone().acquire_owned().await.expect("the semaphore is never closed");
+            let (stream, peer) = match listener.accept().await {
+                Ok(accepted) => accepted,
+                Err(e) => {
+                    warn!("Failed to accept a metrics connection: {}", e);
+                    tokio::time::sleep(ACCEPT_ERROR_DELAY).await;
+                    continue;
+                }
+            };
+            let registry = self.registry.clone();
+            tokio::spawn(async move {
+                let _permit = permit;
+                match tokio::time::timeout(CONNECTION_TIMEOUT, handle_connection(stream, &registry)).await {
+                    Ok(Ok(())) => {}
+                    Ok(Err(e)) => debug!("Failed to serve metrics to {}: {}", peer, e),
+                    Err(_) => debug!("Timed out serving metrics to {}", peer),
+                }
+            });
+        }
+    }
+
+    /// Binds the configured address, then polls and serves forever.
+    pub async fn run(self) -> Result<()> {
+        let listener = TcpListener::bind(self.config.bind).await.map_err(Error::Bind)?;
+        let poller = self.clone();
+
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(poller.config.interval);
+            loop {
+                ticker.tick().await;
+                poller.poll_once().await;
+            }
+        });
+
+        self.serve(listener).await
+    }
+}
+
+async fn poll(addr: SocketAddr, config: ClientConfig) -> Result<(ServerInfo, Option<u64>)> {
+    let client = Client::connect_with_config(addr, config).await?;
+    let info = client.query_info().await?;
+    let ping_ms = client.query_ping().await.ok().map(|ping| ping.ping_ms);
+
+    Ok((info, ping_ms))
+}
+
+async fn handle_connection(mut stream: TcpStream, registry: &MetricsRegistry) -> Result<()> {
+    const MAX_REQUEST_SIZE: usize = 8192;
+
+    let mut request = Vec::new();
+    let mut buf = [0u8; 1024];
+    while !request.windows(4).any(|w| w == b"\r\n\r\n") {
+        let n = stream.read(&mut buf).await?;
+        if n == 0 || request.len() + n > MAX_REQUEST_SIZE {
+            break;
+        }
+        request.extend_from_slice(&buf[..n]);
+    }
+
+    let request = String::from_utf8_lossy(&request);
+    let mut parts = request.split_whitespace();
+    let (status, content_type, body) = match (parts.next(), parts.next()) {
+        (Some("GET"), Some("/metrics")) => (
+            "200 OK",
+            "text/plain; version=0.0.4",
+            registry.render(),
+        ),
+        (Some(method), Some(path)) => {
+            debug!("Unexpected metrics request: {} {}", method, path);
+            ("404 Not Found", "text/plain", "Not Found\n".to_string())
+        }
+        _ => ("400 Bad Request", "text/plain", "Bad Request\n".to_string()),
+    };
+
+    let response = format!(
+        "HTTP/1.1 {}\r\nContent-Type: {}\r\nContent-Length: {}\r\nConnection: close\r\n\r\n{}",
+        status,
+        content_type,
+        body.len(),
+        body
+    );
+    stream.write_all(response.as_bytes()).await?;
+    stream.shutdown().await?;
+
+    Ok(())
+}
@@ -0,0 +1,206 @@
+//! SA-MP client favorites (`USERDATA.DAT`).
+//!
+//! The SA-MP client stores its favorites list in `USERDATA.DAT`: a `SAMP`
+//! magic, a version, the server count and then, per server, the address,
+//! port, cached hostname, server password and RCON password. Integers are
+//! little-endian `u32`s and strings are length-prefixed with a `u32`.
+//!
+//! Strings are stored in the Windows ANSI code page; they are read and
+//! written as Latin-1 so that unknown bytes survive a round trip.
+//!
+//! ```
+//! use samp_query::favorites::{Favorite, Favorites};
+//!
+//! let mut favorites = Favorites::default();
+//! favorites.servers.push(Favorite::new("127.0.0.1", 7777));
+//!
+//! let bytes = favorites.to_bytes();
+//! assert_eq!(Favorites::parse(&bytes).unwrap(), favorites);
+//! ```
+
+use crate::error::{Error, Result};
+use crate::types::ServerInfo;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+
+const MAGIC: &[u8; 4] = b"SAMP";
+
+/// File version written by the 0.3.x clients.
+pub const USERDATA_VERSION: u32 = 1;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Favorite {
+    /// Host name or IP address as entered by the user.
+    pub host: String,
+    pub port: u16,
+    /// Hostname cached by the client from the last query.
+    pub hostname: String,
+    pub server_password: String,
+    pub rcon_password: String,
+}
+
+impl Favorite {
+    pub fn new(host: impl Into<String>, port: u16) -> Self {
+        Self {
+            host: host.into(),
+            port,
+            hostname: String::new(),
+            server_password: String::new(),
+            rcon_password: String::new(),
+        }
+    }
+
+    /// The favorite as a `host:port` string.
+    pub fn address(&self) -> String {
+        format!("{}:{}", self.host, self.port)
+    }
+
+    /// Updates the cached data from a fresh information query.
+    pub fn update(&mut self, info: &ServerInfo) {
+        self.hostname = info.hostname.clone();
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Favorites {
+    pub version: u32,
+    pub servers: Vec<Favorite>,
+}
+
+impl Default for Favorites {
+    fn default() -> Self {
+        Self {
+            version: USERDATA_VERSION,
+            servers: Vec::new(),
+        }
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+}
+
+impl Reader<'_> {
+    fn take(&mut self, len: usize) -> Result<&[u8]> {
+        if self.data.len() < len {
+            return Err(Error::Other("USERDATA.DAT is truncated".to_string()));
+        }
+
+        let (head, tail) = self.data.split_at(len);
+        self.data = tail;
+        Ok(head)
+    }
+
+    fn u32(&mut self) -> Result<u32> {
+        let bytes = self.take(4)?;
+        Ok(u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]))
+    }
+
+    fn string(&mut self) -> Result<String> {
+        let len = self.u32()? as usize;
+        Ok(self.take(len)?.iter().map(|&b| b as char).collect())
+    }
+}
+
+fn write_string(out: &mut Vec<u8>, value: &str) {
+    let bytes: Vec<u8> = value
+        .chars()
+        .map(|c| u8::try_from(c as u32).unwrap_or(b'?'))
+        .collect();
+    out.extend_from_slice(&(bytes.len() as u32).to_le_bytes());
+    out.extend_from_slice(&bytes);
+}
+
+impl Favorites {
+    pub fn parse(data: &[u8]) -> Result<Self> {
+        let mut reader = Reader { data };
+        if reader.take(4)? != MAGIC {
+            return Err(Error::Other("USERDATA.DAT has an invalid signature".to_string()));
+        }
+
+        let version = reader.u32()?;
+        let count = reader.u32()?;
+        let mut servers = Vec::with_capacity(count.min(1024) as usize);
+        for _ in 0..count {
+            let host = reader.string()?;
+            let port = u16::try_from(reader.u32()?)
+                .map_err(|_| Error::Other("USERDATA.DAT has an invalid port".to_string()))?;
+
+            servers.push(Favorite {
+                host,
+                port,
+                hostname: reader.string()?,
+                server_password: reader.string()?,
+                rcon_password: reader.string()?,
+            });
+        }
+
+        Ok(Self { version, servers })
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut out = Vec::new();
+        out.extend_from_slice(MAGIC);
+        out.extend_from_slice(&self.version.to_le_bytes());
+        out.extend_from_slice(&(self.servers.len() as u32).to_le_bytes());
+
+        for server in &self.servers {
+            write_string(&mut out, &server.host);
+            out.extend_from_slice(&(server.port as u32).to_le_bytes());
+            write_string(&mut out, &server.hostname);
+            write_string(&mut out, &server.server_password);
+            write_string(&mut out, &server.rcon_password);
+        }
+
+        out
+    }
+
+    pub fn read<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&std::fs::read(path)?)
+    }
+
+    pub fn write<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_bytes())?;
+        Ok(())
+    }
+
+    /// Location of the file used by the Windows client, if `USERPROFILE` is set.
+    pub fn default_path() -> Option<PathBuf> {
+        let profile = std::env::var_os("USERPROFILE")?;
+        Some(
+            PathBuf::from(profile)
+                .join("Documents")
+                .join("GTA San Andreas User Files")
+                .join("SAMP")
+                .join("USERDATA.DAT"),
+        )
+    }
+
+    /// Queries every favorite and updates the cached hostnames.
+    ///
+    /// Returns the query result for each favorite, in order. Favorites that
+    /// cannot be reached keep their previous hostname.
+    #[cfg(feature = "net")]
+    pub async fn refresh(&mut self, config: crate::client::ClientConfig) -> Vec<Result<ServerInfo>> {
+        let queries = self.servers.iter().map(|server| {
+            let config = config.clone();
+            let address = server.address();
+            async move {
+                let (_, addr) = crate::resolver::Resolver::global()
+                    .resolve_address(&address, server.port)
+                    .await?;
+                let client = crate::client::Client::connect_with_config(addr, config).await?;
+                client.query_info().await
+            }
+        });
+        let results = futures::future::join_all(queries).await;
+
+        for (server, result) in self.servers.iter_mut().zip(&results) {
+            if let Ok(info) = result {
+                server.update(info);
+            }
+        }
+
+        results
+    }
+}
@@ -0,0 +1,262 @@
+//! Allow and block lists for server listings.
+//!
+//! A [`ServerFilter`] decides whether a server belongs in a listing, from its
+//! address, its hostname and its rules. The [`Scanner`](crate::scanner::Scanner),
+//! the [`Aggregator`](crate::aggregate::Aggregator) and the masterlist clients
+//! all accept one, so a hosting provider can keep known fake-server farms out
+//! of every list it builds with a single set of rules.
+//!
+//! Filters can be built in code or parsed from a plain text file, one rule
+//! per line:
+//!
+//! ```text
+//! # Only list our own network, minus the abuse range.
+//! allow-ip 203.0.113.0/24
+//! block-ip 203.0.113.128/25
+//! block-hostname free\s*vip
+//! require-rule weburl
+//! block-rule weburl~fakefarm\.example$
+//! ```
+//!
+//! ```rust
+//! use samp_query::filter::ServerFilter;
+//!
+//! let filter = ServerFilter::parse("allow-ip 203.0.113.0/24\nblock-hostname ^bot farm").unwrap();
+//!
+//! assert!(filter.allows_addr(&"203.0.113.7:7777".parse().unwrap()));
+//! assert!(!filter.allows_addr(&"198.51.100.1:7777".parse().unwrap()));
+//! assert!(!filter.allows_hostname("{FF0000}Bot Farm #12"));
+//! ```
+
+use crate::error::{Error, Result};
+use crate::scanner::Ipv4Cidr;
+use crate::types::{ServerInfo, ServerRules};
+use crate::utils::normalize_hostname;
+use regex::{Regex, RegexBuilder};
+use std::fmt;
+use std::net::SocketAddr;
+use std::str::FromStr;
+
+/// A condition on a server's rules.
+#[derive(Debug, Clone)]
+pub enum RulePredicate {
+    /// The rule is set, whatever its value.
+    Present(String),
+    /// The rule has this value, ignoring case.
+    Equals(String, String),
+    /// The rule's value matches this regular expression.
+    Matches(String, Regex),
+}
+
+impl RulePredicate {
+    /// A predicate matching the rule `key` against a case-insensitive regular expression.
+    pub fn regex(key: &str, pattern: &str) -> Result<Self> {
+        Ok(RulePredicate::Matches(key.to_string(), compile(pattern)?))
+    }
+
+    pub fn key(&self) -> &str {
+        match self {
+            RulePredicate::Present(key) | RulePredicate::Equals(key, _) | RulePredicate::Matches(key, _) => key,
+        }
+    }
+
+    pub fn matches(&self, rules: &ServerRules) -> bool {
+        let value = rules
+            .rules
+            .iter()
+            .find(|(key, _)| key.eq_ignore_ascii_case(self.key()))
+            .map(|(_, value)| value.as_str());
+
+        match (self, value) {
+            (_, None) => false,
+            (RulePredicate::Present(_), Some(_)) => true,
+            (RulePredicate::Equals(_, expected), Some(value)) => value.eq_ignore_ascii_case(expected),
+            (RulePredicate::Matches(_, regex), Some(value)) => regex.is_match(value),
+        }
+    }
+}
+
+/// Parses `key`, `key=value` or `key~regex`.
+impl FromStr for RulePredicate {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let s = s.trim();
+        let split = s.find(['=', '~']);
+
+        match split {
+            Some(i) if s[..i].trim().is_empty() => Err(Error::Other(format!("Invalid rule predicate: {}", s))),
+            Some(i) if s.as_bytes()[i] == b'=' => Ok(RulePredicate::Equals(
+                s[..i].trim().to_string(),
+                s[i + 1..].trim().to_string(),
+            )),
+            Some(i) => RulePredicate::regex(s[..i].trim(), s[i + 1..].trim()),
+            None if s.is_empty() => Err(Error::Other("Empty rule predicate".to_string())),
+            None => Ok(RulePredicate::Present(s.to_string())),
+        }
+    }
+}
+
+impl fmt::Display for RulePredicate {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RulePredicate::Present(key) => write!(f, "{}", key),
+            RulePredicate::Equals(key, value) => write!(f, "{}={}", key, value),
+            RulePredicate::Matches(key, regex) => write!(f, "{}~{}", key, regex),
+        }
+    }
+}
+
+/// Allow and block rules over server addresses, hostnames and rules.
+///
+/// Block rules always win. When any allow rule of a kind is set, a server
+/// must also match one of them (for IP ranges and hostnames) or all of them
+/// (for rule predicates). The default filter lets everything through.
+///
+/// Hostnames are matched with their color codes stripped and lowercased, so
+/// farms cannot dodge a pattern by recoloring their names.
+#[derive(Debug, Clone, Default)]
+pub struct ServerFilter {
+    allow_ranges: Vec<Ipv4Cidr>,
+    block_ranges: Vec<Ipv4Cidr>,
+    allow_hostnames: Vec<Regex>,
+    block_hostnames: Vec<Regex>,
+    require_rules: Vec<RulePredicate>,
+    block_rules: Vec<RulePredicate>,
+}
+
+impl ServerFilter {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parses a filter file; see the [module documentation](self) for the format.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut filter = Self::new();
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (directive, value) = line.split_once(char::is_whitespace).unwrap_or((line, ""));
+            let value = value.trim();
+            let invalid = |e: Error| Error::Other(format!("Filter line {}: {}", number + 1, e));
+
+            filter = match directive {
+                "allow-ip" => filter.allow_range(value.parse().map_err(invalid)?),
+                "block-ip" => filter.block_range(value.parse().map_err(invalid)?),
+                "allow-hostname" => filter.allow_hostname(value).map_err(invalid)?,
+                "block-hostname" => filter.block_hostname(value).map_err(invalid)?,
+                "require-rule" => filter.require_rule(value.parse().map_err(invalid)?),
+                "block-rule" => filter.block_rule(value.parse().map_err(invalid)?),
+                _ => {
+                    return Err(Error::Other(format!(
+                        "Filter line {}: unknown directive {}",
+                        number + 1,
+                        directive
+                    )))
+                }
+            };
+        }
+
+        Ok(filter)
+    }
+
+    /// Reads and parses a filter file.
+    pub fn load<P: AsRef<std::path::Path>>(path: P) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn allow_range(mut self, range: Ipv4Cidr) -> Self {
+        self.allow_ranges.push(range);
+        self
+    }
+
+    pub fn block_range(mut self, range: Ipv4Cidr) -> Self {
+        self.block_ranges.push(range);
+        self
+    }
+
+    /// Allows hostnames matching a case-insensitive regular expression.
+    pub fn allow_hostname(mut self, pattern: &str) -> Result<Self> {
+        self.allow_hostnames.push(compile(pattern)?);
+        Ok(self)
+    }
+
+    /// Blocks hostnames matching a case-insensitive regular expression.
+    pub fn block_hostname(mut self, pattern: &str) -> Result<Self> {
+        self.block_hostnames.push(compile(pattern)?);
+        Ok(self)
+    }
+
+    pub fn require_rule(mut self, predicate: RulePredicate) -> Self {
+        self.require_rules.push(predicate);
+        self
+    }
+
+    pub fn block_rule(mut self, predicate: RulePredicate) -> Self {
+        self.block_rules.push(predicate);
+        self
+    }
+
+    /// Whether the filter has no rules at all.
+    pub fn is_empty(&self) -> bool {
+        !self.checks_addresses() && !self.checks_hostnames() && !self.checks_rules()
+    }
+
+    pub fn checks_addresses(&self) -> bool {
+        !self.allow_ranges.is_empty() || !self.block_ranges.is_empty()
+    }
+
+    pub fn checks_hostnames(&self) -> bool {
+        !self.allow_hostnames.is_empty() || !self.block_hostnames.is_empty()
+    }
+
+    /// Whether the filter needs a server's rules to decide; callers that
+    /// would not query them otherwise can use this to skip the query.
+    pub fn checks_rules(&self) -> bool {
+        !self.require_rules.is_empty() || !self.block_rules.is_empty()
+    }
+
+    /// Checks the address alone. IPv6 addresses never fall in a range.
+    pub fn allows_addr(&self, addr: &SocketAddr) -> bool {
+        let in_range = |ranges: &[Ipv4Cidr]| match addr {
+            SocketAddr::V4(v4) => ranges.iter().any(|range| range.contains(*v4.ip())),
+            SocketAddr::V6(_) => false,
+        };
+
+        !in_range(&self.block_ranges) && (self.allow_ranges.is_empty() || in_range(&self.allow_ranges))
+    }
+
+    pub fn allows_hostname(&self, hostname: &str) -> bool {
+        if !self.checks_hostnames() {
+            return true;
+        }
+
+        let hostname = normalize_hostname(hostname);
+        !self.block_hostnames.iter().any(|regex| regex.is_match(&hostname))
+            && (self.allow_hostnames.is_empty() || self.allow_hostnames.iter().any(|regex| regex.is_match(&hostname)))
+    }
+
+    pub fn allows_rules(&self, rules: &ServerRules) -> bool {
+        !self.block_rules.iter().any(|predicate| predicate.matches(rules))
+            && self.require_rules.iter().all(|predicate| predicate.matches(rules))
+    }
+
+    /// Checks everything known about a server. Rule predicates are skipped
+    /// when `rules` is `None`.
+    pub fn allows(&self, addr: &SocketAddr, info: &ServerInfo, rules: Option<&ServerRules>) -> bool {
+        self.allows_addr(addr)
+            && self.allows_hostname(&info.hostname)
+            && rules.is_none_or(|rules| self.allows_rules(rules))
+    }
+}
+
+fn compile(pattern: &str) -> Result<Regex> {
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .build()
+        .map_err(|e| Error::Other(format!("Invalid pattern: {}", e)))
+}
@@ -0,0 +1,136 @@
+//! Cross-server player search.
+//!
+//! [`find_player`] queries a set of servers concurrently and reports every
+//! server on which a player matching a [`NamePattern`] is online. SA-MP
+//! nicknames are case-insensitive, so all patterns are too.
+//!
+//! ```rust,no_run
+//! use samp_query::finder::{find_player, NamePattern};
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let servers = vec!["127.0.0.1:7777".parse().unwrap()];
+//! let pattern = NamePattern::regex(r"^Carl_?Johnson\d*$")?;
+//!
+//! for (addr, player) in find_player(&pattern, &servers).await {
+//!     println!("{} is on {} (id {})", player.name, addr, player.id);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::error::{Error, Result};
+use crate::pipeline::{self, PipelineConfig};
+use crate::ratelimit::RateLimiter;
+use crate::types::{DetailedPlayer, DetailedPlayerList};
+use futures::stream::{self, StreamExt};
+use regex::{Regex, RegexBuilder};
+use std::net::SocketAddr;
+use tracing::debug;
+
+#[derive(Debug, Clone)]
+pub enum NamePattern {
+    /// The whole name, ignoring case.
+    Exact(String),
+    /// A substring of the name, ignoring case.
+    Contains(String),
+    Regex(Regex),
+}
+
+impl NamePattern {
+    /// Compiles a case-insensitive regular expression.
+    pub fn regex(pattern: &str) -> Result<Self> {
+        RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map(NamePattern::Regex)
+            .map_err(|e| Error::Other(format!("Invalid name pattern: {}", e)))
+    }
+
+    pub fn matches(&self, name: &str) -> bool {
+        match self {
+            NamePattern::Exact(exact) => name.eq_ignore_ascii_case(exact),
+            NamePattern::Contains(part) => name.to_lowercase().contains(&part.to_lowercase()),
+            NamePattern::Regex(regex) => regex.is_match(name),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct FindConfig {
+    pub client: ClientConfig,
+    /// Maximum number of servers queried at the same time.
+    pub concurrency: usize,
+    /// Paces the queries, e.g. a limiter shared with other components.
+    pub limiter: Option<RateLimiter>,
+}
+
+impl Default for FindConfig {
+    fn default() -> Self {
+        Self {
+            client: ClientConfig::default(),
+            concurrency: 32,
+            limiter: None,
+        }
+    }
+}
+
+/// Searches `servers` with the default [`FindConfig`].
+pub async fn find_player(pattern: &NamePattern, servers: &[SocketAddr]) -> Vec<(SocketAddr, DetailedPlayer)> {
+    find_player_with_config(pattern, servers, FindConfig::default()).await
+}
+
+/// Searches `servers` for matching players, in the order the servers were given.
+///
+/// Servers that do not answer are skipped. When a server disables the
+/// detailed player query, its client list is searched instead and the
+/// matches are reported with a ping and id of zero.
+pub async fn find_player_with_config(
+    pattern: &NamePattern,
+    servers: &[SocketAddr],
+    config: FindConfig,
+) -> Vec<(SocketAddr, DetailedPlayer)> {
+    let config = PipelineConfig {
+        client: config.client,
+        max_in_flight: config.concurrency,
+        ordered: true,
+        limiter: config.limiter,
+    };
+
+    pipeline::query(stream::iter(servers.iter().copied()), config, players)
+        .flat_map(|(addr, result)| {
+            let matches = match result {
+                Ok(mut list) => {
+                    list.players.retain(|player| pattern.matches(&player.name));
+                    list.players
+                }
+                Err(e) => {
+                    debug!("Skipping {}: {}", addr, e);
+                    Vec::new()
+                }
+            };
+            stream::iter(matches.into_iter().map(move |player| (addr, player)))
+        })
+        .collect()
+        .await
+}
+
+async fn players(client: Client) -> Result<DetailedPlayerList> {
+    match client.query_detailed_player_info().await {
+        Ok(players) => Ok(players),
+        Err(_) => Ok(DetailedPlayerList {
+            players: client
+                .query_client_list()
+                .await?
+                .players
+                .into_iter()
+                .map(|player| DetailedPlayer {
+                    id: 0,
+                    name: player.name,
+                    score: player.score,
+                    ping: 0,
+                })
+                .collect(),
+        }),
+    }
+}
@@ -0,0 +1,235 @@
+//! Server classification from hostnames, gamemodes and rules.
+//!
+//! [`fingerprint`] applies keyword heuristics to a server's hostname and
+//! gamemode string and inspects its rules to tag it with a play style, a
+//! known gamemode, the server platform and the hosting provider. The tags
+//! serialize to stable strings (`category:roleplay`, `platform:openmp`, ...)
+//! so aggregators can facet on them.
+//!
+//! ```
+//! use samp_query::fingerprint::{fingerprint, Category, Tag};
+//! use samp_query::ServerInfo;
+//!
+//! let info = ServerInfo {
+//!     hostname: "Los Santos Roleplay | lsrp.example".to_string(),
+//!     gamemode: "LS-RP v3.1".to_string(),
+//!     ..Default::default()
+//! };
+//!
+//! let fingerprint = fingerprint(&info, None);
+//! assert!(fingerprint.has(&Tag::Category(Category::Roleplay)));
+//! assert!(fingerprint.has(&Tag::Gamemode("lsrp".to_string())));
+//! ```
+
+use crate::types::{ServerInfo, ServerRules};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Category {
+    Roleplay,
+    Freeroam,
+    Deathmatch,
+    CopsAndRobbers,
+    Race,
+    Stunt,
+    Drift,
+    Derby,
+    Zombie,
+    Trucking,
+}
+
+impl Category {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Category::Roleplay => "roleplay",
+            Category::Freeroam => "freeroam",
+            Category::Deathmatch => "deathmatch",
+            Category::CopsAndRobbers => "cops_and_robbers",
+            Category::Race => "race",
+            Category::Stunt => "stunt",
+            Category::Drift => "drift",
+            Category::Derby => "derby",
+            Category::Zombie => "zombie",
+            Category::Trucking => "trucking",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Platform {
+    Samp,
+    #[serde(rename = "openmp")]
+    OpenMp,
+}
+
+impl Platform {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Platform::Samp => "samp",
+            Platform::OpenMp => "openmp",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Hash, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(tag = "kind", content = "value", rename_all = "snake_case")]
+pub enum Tag {
+    Category(Category),
+    /// A widely used gamemode, by short identifier.
+    Gamemode(String),
+    Platform(Platform),
+    /// The hosting provider the server runs on.
+    Host(String),
+}
+
+impl fmt::Display for Tag {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Tag::Category(category) => write!(f, "category:{}", category.as_str()),
+            Tag::Gamemode(gamemode) => write!(f, "gamemode:{}", gamemode),
+            Tag::Platform(platform) => write!(f, "platform:{}", platform.as_str()),
+            Tag::Host(host) => write!(f, "host:{}", host),
+        }
+    }
+}
+
+/// Keywords matched against whole words of the hostname and gamemode.
+const CATEGORY_KEYWORDS: &[(Category, &[&str])] = &[
+    (Category::Roleplay, &["rp", "roleplay", "rpg", "lsrp", "ngrp"]),
+    (Category::Freeroam, &["freeroam"]),
+    (Category::Deathmatch, &["dm", "tdm", "deathmatch", "gangwar", "war", "lvdm"]),
+    (Category::CopsAndRobbers, &["cnr"]),
+    (Category::Race, &["race", "races", "racing"]),
+    (Category::Stunt, &["stunt", "stunts", "stunting"]),
+    (Category::Drift, &["drift", "drifting"]),
+    (Category::Derby, &["derby"]),
+    (Category::Zombie, &["zombie", "zombies"]),
+    (Category::Trucking, &["trucking", "trucker", "truckers"]),
+];
+
+/// Phrases matched as substrings, for names usually written with spaces.
+const CATEGORY_PHRASES: &[(Category, &str)] = &[
+    (Category::Roleplay, "role play"),
+    (Category::Roleplay, "role-play"),
+    (Category::Freeroam, "free roam"),
+    (Category::CopsAndRobbers, "cops and robbers"),
+    (Category::CopsAndRobbers, "cops & robbers"),
+    (Category::CopsAndRobbers, "c&r"),
+];
+
+/// Known gamemodes: identifier, implied category and gamemode-string patterns.
+const GAMEMODES: &[(&str, Category, &[&str])] = &[
+    ("lsrp", Category::Roleplay, &["ls-rp", "lsrp", "los santos roleplay"]),
+    ("ngrp", Category::Roleplay, &["ng-rp", "ngrp", "next generation roleplay"]),
+    ("godfather", Category::Roleplay, &["godfather", "gf edit"]),
+    ("grandlarc", Category::Freeroam, &["grand larceny", "grandlarc"]),
+    ("lvdm", Category::Deathmatch, &["lvdm"]),
+    ("rivershell", Category::Deathmatch, &["rivershell"]),
+    ("ppc_trucking", Category::Trucking, &["ppc trucking", "ppc_trucking"]),
+];
+
+/// Hosting providers, by identifier and the domain found in hostnames or `weburl`.
+const HOSTS: &[(&str, &str)] = &[
+    ("zap-hosting", "zap-hosting"),
+    ("gtxgaming", "gtxgaming"),
+    ("ultra-h", "ultra-h"),
+];
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Fingerprint {
+    /// Tags in a stable order, without duplicates.
+    pub tags: Vec<Tag>,
+}
+
+impl Fingerprint {
+    pub fn has(&self, tag: &Tag) -> bool {
+        self.tags.contains(tag)
+    }
+
+    pub fn categories(&self) -> impl Iterator<Item = Category> + '_ {
+        self.tags.iter().filter_map(|tag| match tag {
+            Tag::Category(category) => Some(*category),
+            _ => None,
+        })
+    }
+
+    pub fn platform(&self) -> Option<Platform> {
+        self.tags.iter().find_map(|tag| match tag {
+            Tag::Platform(platform) => Some(*platform),
+            _ => None,
+        })
+    }
+
+    /// The tags as facet strings, e.g. `category:roleplay`.
+    pub fn facets(&self) -> Vec<String> {
+        self.tags.iter().map(ToString::to_string).collect()
+    }
+}
+
+/// Detects the server platform from its rules.
+///
+/// open.mp servers add an `allowed_clients` rule and report a `version`
+/// starting with `omp`; SA-MP servers report versions such as `0.3.7-R2`.
+pub fn detect_platform(rules: &ServerRules) -> Option<Platform> {
+    let version = rules.rules.get("version").map(|v| v.to_lowercase());
+    if rules.rules.contains_key("allowed_clients")
+        || version.as_deref().is_some_and(|v| v.starts_with("omp") || v.starts_with("open.mp"))
+    {
+        return Some(Platform::OpenMp);
+    }
+
+    version
+        .filter(|v| v.starts_with("0.3") || v.starts_with("0.2"))
+        .map(|_| Platform::Samp)
+}
+
+/// Classifies a server. Rules are optional; without them no platform is
+/// detected and hosts are only matched in the hostname.
+pub fn fingerprint(info: &ServerInfo, rules: Option<&ServerRules>) -> Fingerprint {
+    let text = format!("{} {}", info.hostname, info.gamemode).to_lowercase();
+    let words: Vec<&str> = text
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| !word.is_empty())
+        .collect();
+    let gamemode = info.gamemode.to_lowercase();
+    let mut tags = Vec::new();
+
+    for (category, keywords) in CATEGORY_KEYWORDS {
+        if keywords.iter().any(|keyword| words.contains(keyword)) {
+            tags.push(Tag::Category(*category));
+        }
+    }
+    for (category, phrase) in CATEGORY_PHRASES {
+        if text.contains(phrase) {
+            tags.push(Tag::Category(*category));
+        }
+    }
+
+    for (id, category, patterns) in GAMEMODES {
+        if patterns.iter().any(|pattern| gamemode.contains(pattern)) {
+            tags.push(Tag::Gamemode(id.to_string()));
+            tags.push(Tag::Category(*category));
+        }
+    }
+
+    if let Some(platform) = rules.and_then(detect_platform) {
+        tags.push(Tag::Platform(platform));
+    }
+
+    let weburl = rules
+        .and_then(|rules| rules.rules.get("weburl"))
+        .map(|url| url.to_lowercase())
+        .unwrap_or_default();
+    for (id, domain) in HOSTS {
+        if text.contains(domain) || weburl.contains(domain) {
+            tags.push(Tag::Host(id.to_string()));
+        }
+    }
+
+    tags.sort();
+    tags.dedup();
+    Fingerprint { tags }
+}
@@ -0,0 +1,135 @@
+//! Game-agnostic server queries.
+//!
+//! [`GameServerClient`] is the common surface of query clients for different
+//! games, returning the normalized [`GameInfo`] and [`GamePlayer`] types, so
+//! multi-game trackers can be written once against the trait. The SA-MP
+//! [`Client`] implements it; clients for other games can implement it
+//! outside this crate.
+//!
+//! ```rust,no_run
+//! use samp_query::game::GameServerClient;
+//! use samp_query::Client;
+//!
+//! async fn describe(server: &dyn GameServerClient) -> samp_query::Result<String> {
+//!     let info = server.info().await?;
+//!     Ok(format!("[{}] {} {}/{}", server.game(), info.name, info.players, info.max_players))
+//! }
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let client = Client::connect("127.0.0.1:7777".parse().unwrap()).await?;
+//! println!("{}", describe(&client).await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::Client;
+use crate::error::Result;
+use crate::types::{DetailedPlayer, Player, ServerInfo};
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::time::Duration;
+
+/// Server information common to every game.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GameInfo {
+    pub name: String,
+    /// Gamemode, map or resource name, whichever the game reports.
+    pub mode: String,
+    pub players: u32,
+    pub max_players: u32,
+    pub password: bool,
+    pub language: Option<String>,
+}
+
+impl From<ServerInfo> for GameInfo {
+    fn from(info: ServerInfo) -> Self {
+        Self {
+            name: info.hostname,
+            mode: info.gamemode,
+            players: info.players as u32,
+            max_players: info.max_players as u32,
+            password: info.password,
+            language: Some(info.language).filter(|language| !language.is_empty()),
+        }
+    }
+}
+
+/// A connected player. Games that do not report a field leave it `None`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GamePlayer {
+    pub name: String,
+    pub score: Option<i64>,
+    pub ping: Option<Duration>,
+}
+
+impl From<DetailedPlayer> for GamePlayer {
+    fn from(player: DetailedPlayer) -> Self {
+        Self {
+            name: player.name,
+            score: Some(player.score as i64),
+            ping: Some(Duration::from_millis(player.ping as u64)),
+        }
+    }
+}
+
+impl From<Player> for GamePlayer {
+    fn from(player: Player) -> Self {
+        Self {
+            name: player.name,
+            score: Some(player.score as i64),
+            ping: None,
+        }
+    }
+}
+
+#[async_trait]
+pub trait GameServerClient: Send + Sync {
+    /// Short identifier of the game, e.g. `samp`.
+    fn game(&self) -> &'static str;
+
+    fn address(&self) -> SocketAddr;
+
+    async fn info(&self) -> Result<GameInfo>;
+
+    async fn players(&self) -> Result<Vec<GamePlayer>>;
+
+    /// Round-trip time of a query.
+    async fn ping(&self) -> Result<Duration>;
+}
+
+#[async_trait]
+impl GameServerClient for Client {
+    fn game(&self) -> &'static str {
+        "samp"
+    }
+
+    fn address(&self) -> SocketAddr {
+        self.server_addr()
+    }
+
+    async fn info(&self) -> Result<GameInfo> {
+        self.query_info().await.map(GameInfo::from)
+    }
+
+    /// The detailed player list, or the client list without pings when the
+    /// server disables the detailed query.
+    async fn players(&self) -> Result<Vec<GamePlayer>> {
+        match self.query_detailed_player_info().await {
+            Ok(list) => Ok(list.players.into_iter().map(GamePlayer::from).collect()),
+            Err(_) => Ok(self
+                .query_client_list()
+                .await?
+                .players
+                .into_iter()
+                .map(GamePlayer::from)
+                .collect()),
+        }
+    }
+
+    async fn ping(&self) -> Result<Duration> {
+        self.query_ping()
+            .await
+            .map(|ping| Duration::from_millis(ping.ping_ms))
+    }
+}
@@ -0,0 +1,109 @@
+//! Country and network lookups for server addresses.
+//!
+//! [`GeoIp`] reads MaxMind databases in the `.mmdb` format, such as the free
+//! GeoLite2 Country and ASN databases. Each lookup asks every database given
+//! to [`GeoIp::open`], so a country and an ASN database can be combined:
+//!
+//! ```rust,no_run
+//! use samp_query::geoip::GeoIp;
+//!
+//! # fn run() -> samp_query::Result<()> {
+//! let geoip = GeoIp::open(["GeoLite2-Country.mmdb", "GeoLite2-ASN.mmdb"])?;
+//! let location = geoip.lookup("203.0.113.7".parse().unwrap());
+//! println!("{}", location);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use maxminddb::{geoip2, Reader};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::net::IpAddr;
+use std::path::Path;
+
+/// What the databases know about an address. Fields the databases do not
+/// cover are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct GeoInfo {
+    /// ISO 3166-1 alpha-2 country code, e.g. `DE`.
+    pub country: Option<String>,
+    /// Autonomous system number of the network.
+    pub asn: Option<u32>,
+    /// Organization owning the network, usually the hosting provider.
+    pub organization: Option<String>,
+}
+
+impl GeoInfo {
+    pub fn is_empty(&self) -> bool {
+        self.country.is_none() && self.asn.is_none() && self.organization.is_none()
+    }
+}
+
+impl fmt::Display for GeoInfo {
+    /// Formats as `DE AS24940 Hetzner Online GmbH`, or `-` if nothing is known.
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let mut parts = Vec::new();
+        parts.extend(self.country.clone());
+        parts.extend(self.asn.map(|asn| format!("AS{}", asn)));
+        parts.extend(self.organization.clone());
+        if parts.is_empty() {
+            return f.write_str("-");
+        }
+        f.write_str(&parts.join(" "))
+    }
+}
+
+/// MaxMind databases opened for lookups.
+pub struct GeoIp {
+    readers: Vec<Reader<Vec<u8>>>,
+}
+
+impl fmt::Debug for GeoIp {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let types: Vec<_> = self.readers.iter().map(|reader| &reader.metadata.database_type).collect();
+        f.debug_struct("GeoIp").field("databases", &types).finish()
+    }
+}
+
+impl GeoIp {
+    /// Reads every database in `paths` into memory.
+    pub fn open<P: AsRef<Path>>(paths: impl IntoIterator<Item = P>) -> Result<Self> {
+        let readers = paths
+            .into_iter()
+            .map(|path| {
+                let path = path.as_ref();
+                Reader::open_readfile(path)
+                    .map_err(|e| Error::Other(format!("Failed to open GeoIP database {}: {}", path.display(), e)))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        if readers.is_empty() {
+            return Err(Error::Other("No GeoIP database given".to_string()));
+        }
+        Ok(Self { readers })
+    }
+
+    /// Looks `ip` up in every database and merges the answers; the first
+    /// database that knows a field wins.
+    pub fn lookup(&self, ip: IpAddr) -> GeoInfo {
+        let mut info = GeoInfo::default();
+        for reader in &self.readers {
+            if info.country.is_none() {
+                if let Ok(record) = reader.lookup::<geoip2::Country>(ip) {
+                    info.country = record
+                        .country
+                        .or(record.registered_country)
+                        .and_then(|country| country.iso_code)
+                        .map(str::to_string);
+                }
+            }
+            if info.asn.is_none() {
+                if let Ok(record) = reader.lookup::<geoip2::Asn>(ip) {
+                    info.asn = record.autonomous_system_number;
+                    info.organization = record.autonomous_system_organization.map(str::to_string);
+                }
+            }
+        }
+        info
+    }
+}
@@ -0,0 +1,195 @@
+//! In-memory history of server observations.
+//!
+//! [`History`] keeps a bounded, timestamped buffer of observations per server
+//! and answers simple time-series questions over it.
+//!
+//! ```
+//! use samp_query::history::{History, HistoryEntry};
+//! use std::net::SocketAddr;
+//!
+//! let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+//! let mut history = History::new(1000);
+//!
+//! for (timestamp, players, ping) in [(1_000, 10, 40), (2_000, 25, 60), (3_000, 15, 50)] {
+//!     history.record(addr, HistoryEntry {
+//!         timestamp,
+//!         online: true,
+//!         players,
+//!         max_players: 50,
+//!         ping_ms: Some(ping),
+//!     });
+//! }
+//!
+//! assert_eq!(history.peak_players(&addr, 0), Some((2_000, 25)));
+//! assert_eq!(history.ping_percentile(&addr, 0, 50.0), Some(50));
+//! assert_eq!(history.player_series(&addr, 2_000), vec![(2_000, 25), (3_000, 15)]);
+//! ```
+
+use crate::monitor::{MonitorEvent, MonitorEventKind};
+use crate::types::ServerSnapshot;
+use crate::utils::unix_timestamp_ms;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+
+/// A single observation of a server.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct HistoryEntry {
+    /// Unix timestamp in milliseconds of the observation.
+    pub timestamp: u64,
+    /// Whether the server answered.
+    pub online: bool,
+    /// Players online (0 when offline).
+    pub players: u16,
+    /// Maximum number of players (0 when offline).
+    pub max_players: u16,
+    /// Ping in milliseconds, if measured.
+    pub ping_ms: Option<u64>,
+}
+
+impl From<&ServerSnapshot> for HistoryEntry {
+    fn from(snapshot: &ServerSnapshot) -> Self {
+        Self {
+            timestamp: snapshot.timestamp,
+            online: true,
+            players: snapshot.info.players,
+            max_players: snapshot.info.max_players,
+            ping_ms: snapshot.ping.as_ref().map(|ping| ping.ping_ms),
+        }
+    }
+}
+
+impl HistoryEntry {
+    pub fn offline(timestamp: u64) -> Self {
+        Self {
+            timestamp,
+            online: false,
+            players: 0,
+            max_players: 0,
+            ping_ms: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct History {
+    capacity: usize,
+    servers: HashMap<SocketAddr, VecDeque<HistoryEntry>>,
+}
+
+impl History {
+    /// Creates a history keeping at most `capacity` entries per server.
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity: capacity.max(1),
+            servers: HashMap::new(),
+        }
+    }
+
+    pub fn record(&mut self, addr: SocketAddr, entry: HistoryEntry) {
+        let entries = self.servers.entry(addr).or_default();
+        entries.push_back(entry);
+        while entries.len() > self.capacity {
+            entries.pop_front();
+        }
+    }
+
+    pub fn record_snapshot(&mut self, snapshot: &ServerSnapshot) {
+        self.record(snapshot.address, HistoryEntry::from(snapshot));
+    }
+
+    /// Records `Polled` and `Offline` monitor events; other events are ignored.
+    pub fn observe(&mut self, event: &MonitorEvent) {
+        match &event.kind {
+            MonitorEventKind::Polled { info, ping_ms, .. } => self.record(
+                event.address,
+                HistoryEntry {
+                    timestamp: event.timestamp,
+                    online: true,
+                    players: info.players,
+                    max_players: info.max_players,
+                    ping_ms: *ping_ms,
+                },
+            ),
+            MonitorEventKind::Offline { .. } => {
+                self.record(event.address, HistoryEntry::offline(event.timestamp))
+            }
+            _ => {}
+        }
+    }
+
+    /// Servers with at least one entry.
+    pub fn servers(&self) -> impl Iterator<Item = &SocketAddr> {
+        self.servers.keys()
+    }
+
+    /// Entries for a server recorded at or after `since`, oldest first.
+    pub fn entries(&self, addr: &SocketAddr, since: u64) -> impl Iterator<Item = &HistoryEntry> {
+        self.servers
+            .get(addr)
+            .into_iter()
+            .flatten()
+            .filter(move |entry| entry.timestamp >= since)
+    }
+
+    /// `(timestamp, players)` pairs since `since`, oldest first.
+    pub fn player_series(&self, addr: &SocketAddr, since: u64) -> Vec<(u64, u16)> {
+        self.entries(addr, since)
+            .map(|entry| (entry.timestamp, entry.players))
+            .collect()
+    }
+
+    /// `(timestamp, ping)` pairs since `since` for entries with a measured ping.
+    pub fn ping_series(&self, addr: &SocketAddr, since: u64) -> Vec<(u64, u64)> {
+        self.entries(addr, since)
+            .filter_map(|entry| entry.ping_ms.map(|ping| (entry.timestamp, ping)))
+            .collect()
+    }
+
+    /// The given ping percentile (0-100, nearest rank) since `since`.
+    pub fn ping_percentile(&self, addr: &SocketAddr, since: u64, percentile: f64) -> Option<u64> {
+        let mut pings: Vec<u64> = self
+            .ping_series(addr, since)
+            .into_iter()
+            .map(|(_, ping)| ping)
+            .collect();
+        if pings.is_empty() {
+            return None;
+        }
+
+        pings.sort_unstable();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * pings.len() as f64).ceil() as usize;
+        Some(pings[rank.saturating_sub(1).min(pings.len() - 1)])
+    }
+
+    /// The highest player count since `since` as `(timestamp, players)`.
+    ///
+    /// The earliest observation wins when the peak was reached several times.
+    pub fn peak_players(&self, addr: &SocketAddr, since: u64) -> Option<(u64, u16)> {
+        self.entries(addr, since)
+            .map(|entry| (entry.timestamp, entry.players))
+            .fold(None, |peak: Option<(u64, u16)>, current| match peak {
+                Some(peak) if peak.1 >= current.1 => Some(peak),
+                _ => Some(current),
+            })
+    }
+
+    /// The highest player count since midnight UTC.
+    pub fn peak_players_today(&self, addr: &SocketAddr) -> Option<(u64, u16)> {
+        const DAY_MS: u64 = 24 * 60 * 60 * 1000;
+        let now = unix_timestamp_ms();
+
+        self.peak_players(addr, now - now % DAY_MS)
+    }
+
+    /// Fraction of observations since `since` in which the server was online, as a percentage.
+    pub fn online_percentage(&self, addr: &SocketAddr, since: u64) -> Option<f64> {
+        let (online, total) = self
+            .entries(addr, since)
+            .fold((0usize, 0usize), |(online, total), entry| {
+                (online + entry.online as usize, total + 1)
+            });
+
+        (total > 0).then(|| online as f64 / total as f64 * 100.0)
+    }
+}
@@ -0,0 +1,174 @@
+//! Fake-player detection.
+//!
+//! Hosting lists rank servers by player count, so some servers report more
+//! players than are actually connected, or fill slots with bots. This module
+//! looks for the usual signs and combines them into a confidence score:
+//!
+//! - the reported count does not match the length of the player list,
+//! - the same name appears more than once (the server refuses duplicate
+//!   names, so this only happens with a spoofed list),
+//! - many names share a stem and differ only by a number (`Player_1`,
+//!   `Player_2`, ...),
+//! - many players report a ping of zero.
+//!
+//! The SA-MP server does not answer player list queries above 100 players,
+//! so the count check is skipped for such servers.
+//!
+//! ```
+//! use samp_query::inflation::InflationReport;
+//! use samp_query::{DetailedPlayer, DetailedPlayerList, ServerInfo};
+//!
+//! let info = ServerInfo { players: 40, max_players: 50, ..Default::default() };
+//! let players = DetailedPlayerList {
+//!     players: (0..10)
+//!         .map(|id| DetailedPlayer { id, name: format!("Bot_{}", id), score: 0, ping: 0 })
+//!         .collect(),
+//! };
+//!
+//! let report = InflationReport::from_detailed(&info, &players);
+//! assert!(report.is_likely_inflated());
+//! ```
+
+use crate::types::{DetailedPlayerList, PlayerList, ServerInfo, ServerSnapshot};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+/// Largest player count for which the server still answers list queries.
+pub const MAX_LISTED_PLAYERS: u16 = 100;
+
+/// Names sharing a numbered stem at least this often count as patterned.
+const PATTERN_THRESHOLD: usize = 3;
+
+/// Score at or above which a server is considered inflated.
+pub const INFLATED_THRESHOLD: f64 = 0.5;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InflationReport {
+    /// Player count from the information query.
+    pub reported_players: u16,
+    /// Number of entries in the player list.
+    pub listed_players: usize,
+    /// Entries whose name already appeared earlier in the list.
+    pub duplicate_names: usize,
+    /// Entries whose name shares a numbered stem with other entries.
+    pub patterned_names: usize,
+    /// Entries with a ping of zero, if pings were available.
+    pub zero_ping: Option<usize>,
+    /// Confidence from 0.0 to 1.0 that the player count is inflated.
+    pub score: f64,
+    /// Human-readable description of each signal that contributed.
+    pub reasons: Vec<String>,
+}
+
+impl InflationReport {
+    /// Analyses the `'c'` client list.
+    pub fn from_client_list(info: &ServerInfo, list: &PlayerList) -> Self {
+        let names: Vec<&str> = list.players.iter().map(|p| p.name.as_str()).collect();
+        analyze(info, &names, None)
+    }
+
+    /// Analyses the `'d'` detailed player list, which also carries pings.
+    pub fn from_detailed(info: &ServerInfo, list: &DetailedPlayerList) -> Self {
+        let names: Vec<&str> = list.players.iter().map(|p| p.name.as_str()).collect();
+        let pings: Vec<u32> = list.players.iter().map(|p| p.ping).collect();
+        analyze(info, &names, Some(&pings))
+    }
+
+    /// Analyses a snapshot, if it contains a player list.
+    pub fn from_snapshot(snapshot: &ServerSnapshot) -> Option<Self> {
+        snapshot
+            .players
+            .as_ref()
+            .map(|players| Self::from_detailed(&snapshot.info, players))
+    }
+
+    pub fn is_likely_inflated(&self) -> bool {
+        self.score >= INFLATED_THRESHOLD
+    }
+}
+
+/// Strips a trailing number and separators, so `Player_12` becomes `player`.
+fn stem(name: &str) -> Option<String> {
+    let trimmed = name.trim_end_matches(|c: char| c.is_ascii_digit());
+    if trimmed.len() == name.len() {
+        return None;
+    }
+
+    let stem = trimmed.trim_end_matches(['_', '-', '.', ' ', '[', ']', '(', ')']);
+    Some(stem.to_lowercase())
+}
+
+fn analyze(info: &ServerInfo, names: &[&str], pings: Option<&[u32]>) -> InflationReport {
+    let listed = names.len();
+    let mut reasons = Vec::new();
+    let mut score: f64 = 0.0;
+
+    if info.players <= MAX_LISTED_PLAYERS && (listed as u16) < info.players {
+        let missing = (info.players as usize - listed) as f64 / info.players as f64;
+        score += 0.6 * missing;
+        reasons.push(format!(
+            "{} players reported but only {} listed",
+            info.players, listed
+        ));
+    }
+
+    let mut seen = HashMap::new();
+    let mut stems: HashMap<String, usize> = HashMap::new();
+    for name in names {
+        *seen.entry(name.to_lowercase()).or_insert(0usize) += 1;
+        if let Some(stem) = stem(name) {
+            *stems.entry(stem).or_insert(0) += 1;
+        }
+    }
+
+    let duplicate_names: usize = seen.values().map(|count| count - 1).sum();
+    if duplicate_names > 0 {
+        score += 0.3 + 0.5 * duplicate_names as f64 / listed as f64;
+        reasons.push(format!("{} duplicate names", duplicate_names));
+    }
+
+    let patterned_names: usize = stems
+        .values()
+        .filter(|&&count| count >= PATTERN_THRESHOLD)
+        .sum();
+    if listed > 0 && patterned_names > 0 {
+        score += 0.5 * patterned_names as f64 / listed as f64;
+        reasons.push(format!("{} names follow a numbered pattern", patterned_names));
+    }
+
+    let zero_ping = pings.map(|pings| pings.iter().filter(|&&ping| ping == 0).count());
+    if let Some(zero) = zero_ping.filter(|&zero| zero > 0) {
+        score += 0.3 * zero as f64 / listed as f64;
+        reasons.push(format!("{} players with zero ping", zero));
+    }
+
+    InflationReport {
+        reported_players: info.players,
+        listed_players: listed,
+        duplicate_names,
+        patterned_names,
+        zero_ping,
+        score: score.min(1.0),
+        reasons,
+    }
+}
+
+/// Queries a server and analyses its player list.
+///
+/// The detailed list is preferred because it carries pings; the client list
+/// is used when a server disables detailed queries.
+#[cfg(feature = "net")]
+pub async fn check(client: &crate::client::Client) -> crate::error::Result<InflationReport> {
+    let info = client.query_info().await?;
+    if info.players > MAX_LISTED_PLAYERS {
+        return Ok(analyze(&info, &[], None));
+    }
+
+    match client.query_detailed_player_info().await {
+        Ok(players) => Ok(InflationReport::from_detailed(&info, &players)),
+        Err(_) => {
+            let list = client.query_client_list().await?;
+            Ok(InflationReport::from_client_list(&info, &list))
+        }
+    }
+}
@@ -12,6 +12,8 @@
 //! - Robust packet handling with proper timeout and error management
 //! - Memory-safe and efficient data structures
 //! - Complete protocol compliance with the SA-MP Query Mechanism
+//! - Pluggable transports; without the default `net` feature the crate builds
+//!   for targets without sockets, such as `wasm32-wasip1`
 //!
 //! ## Example
 //!
@@ -40,13 +42,66 @@ pub use error::{Error, Result};
 pub use protocol::QueryType;
 pub use types::*;
 
+#[cfg(feature = "net")]
+pub mod aggregate;
+#[cfg(feature = "http")]
+pub mod announce;
+#[cfg(feature = "net")]
+pub mod availability;
+#[cfg(feature = "net")]
+pub mod cache;
 pub mod client;
+pub mod diff;
 pub mod error;
-#[cfg(any(test, feature = "benchmarks"))]
+#[cfg(feature = "net")]
+pub mod exporter;
+pub mod favorites;
+#[cfg(feature = "net")]
+pub mod filter;
+#[cfg(feature = "net")]
+pub mod finder;
+pub mod fingerprint;
+pub mod game;
+#[cfg(feature = "geoip")]
+pub mod geoip;
+#[cfg(feature = "net")]
+pub mod history;
+pub mod inflation;
+#[cfg(feature = "http")]
+pub mod masterlist;
+#[cfg(feature = "test-util")]
+pub mod mock;
+#[cfg(feature = "net")]
+pub mod monitor;
+#[cfg(feature = "net")]
+pub mod notify;
 pub mod packet;
-#[cfg(not(any(test, feature = "benchmarks")))]
-mod packet;
+#[cfg(feature = "net")]
+pub mod pipeline;
+pub mod players;
 pub mod protocol;
+#[cfg(feature = "net")]
+pub mod proxy;
+#[cfg(feature = "net")]
+pub mod ratelimit;
+pub mod rcon;
+#[cfg(feature = "net")]
+pub mod resolver;
+#[cfg(feature = "net")]
+pub mod record;
+#[cfg(feature = "net")]
+pub mod scanner;
+#[cfg(feature = "net")]
+pub mod scheduler;
+pub mod server;
+pub mod stats;
+#[cfg(feature = "sqlite")]
+pub mod storage;
+#[cfg(feature = "net")]
+pub mod stress;
+pub mod transport;
 pub mod types;
 
 pub mod utils;
+#[cfg(feature = "net")]
+pub mod watchdog;
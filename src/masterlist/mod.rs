@@ -0,0 +1,9 @@
+//! Clients for public server lists.
+//!
+//! Available with the `http` feature.
+//!
+//! - [`omp`]: the open.mp HTTP server list.
+//! - [`samp`]: the classic SA-MP internet and hosted lists.
+
+pub mod omp;
+pub mod samp;
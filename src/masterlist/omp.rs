@@ -0,0 +1,201 @@
+//! Client for the open.mp server list API.
+//!
+//! ```rust,no_run
+//! use samp_query::masterlist::omp::{OmpFilter, OmpMasterlist};
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let masterlist = OmpMasterlist::new();
+//! let filter = OmpFilter {
+//!     gamemode: Some("roleplay".to_string()),
+//!     ..OmpFilter::default()
+//! };
+//!
+//! for entry in filter.apply(masterlist.fetch().await?) {
+//!     println!("{} - {} ({}/{})", entry.address, entry.hostname, entry.players, entry.max_players);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::filter::ServerFilter;
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+
+/// Base URL of the public open.mp API.
+pub const DEFAULT_BASE_URL: &str = "https://api.open.mp";
+
+/// A server as listed by the open.mp API.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct OmpServerEntry {
+    /// Server address as `ip:port`.
+    #[serde(rename = "ip")]
+    pub address: String,
+    #[serde(rename = "hn")]
+    pub hostname: String,
+    #[serde(rename = "pc")]
+    pub players: u16,
+    #[serde(rename = "pm")]
+    pub max_players: u16,
+    #[serde(rename = "gm")]
+    pub gamemode: String,
+    #[serde(rename = "la")]
+    pub language: String,
+    #[serde(rename = "pa")]
+    pub password: bool,
+    /// Server version string.
+    #[serde(rename = "vn", default)]
+    pub version: String,
+    /// Whether the server runs open.mp.
+    #[serde(default)]
+    pub omp: bool,
+    /// Whether the server is an open.mp partner.
+    #[serde(rename = "pr", default)]
+    pub partner: bool,
+}
+
+impl OmpServerEntry {
+    /// The entry's socket address, ready to be queried with a [`Client`](crate::Client).
+    pub fn addr(&self) -> Option<SocketAddr> {
+        self.address.parse().ok()
+    }
+}
+
+/// Client-side filter over masterlist entries.
+#[derive(Debug, Clone, Default)]
+pub struct OmpFilter {
+    /// Keep entries whose gamemode contains this text (case-insensitive).
+    pub gamemode: Option<String>,
+    /// Keep entries whose language contains this text (case-insensitive).
+    pub language: Option<String>,
+    /// Drop password protected servers.
+    pub hide_passworded: bool,
+    /// Drop empty servers.
+    pub hide_empty: bool,
+    /// Keep only open.mp servers.
+    pub omp_only: bool,
+    /// Address and hostname allow/block lists. The API does not list rules,
+    /// so its rule predicates are not checked.
+    pub servers: ServerFilter,
+}
+
+impl OmpFilter {
+    pub fn matches(&self, entry: &OmpServerEntry) -> bool {
+        let contains = |haystack: &str, needle: &Option<String>| {
+            needle
+                .as_ref()
+                .is_none_or(|n| haystack.to_lowercase().contains(&n.to_lowercase()))
+        };
+
+        contains(&entry.gamemode, &self.gamemode)
+            && contains(&entry.language, &self.language)
+            && !(self.hide_passworded && entry.password)
+            && !(self.hide_empty && entry.players == 0)
+            && (!self.omp_only || entry.omp)
+            && entry
+                .addr()
+                .map_or(!self.servers.checks_addresses(), |addr| self.servers.allows_addr(&addr))
+            && self.servers.allows_hostname(&entry.hostname)
+    }
+
+    pub fn apply(&self, entries: Vec<OmpServerEntry>) -> Vec<OmpServerEntry> {
+        entries.into_iter().filter(|e| self.matches(e)).collect()
+    }
+}
+
+/// One page of masterlist entries.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Zero-based page number.
+    pub page: usize,
+    pub per_page: usize,
+    /// Total number of entries across all pages.
+    pub total: usize,
+}
+
+impl<T> Page<T> {
+    /// Number of pages needed for all entries.
+    pub fn page_count(&self) -> usize {
+        self.total.div_ceil(self.per_page.max(1))
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct OmpMasterlist {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Default for OmpMasterlist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl OmpMasterlist {
+    pub fn new() -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL)
+    }
+
+    /// Creates a client for a mirror or a self-hosted instance of the API.
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            http: crate::resolver::http_client(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Fetches every listed server.
+    pub async fn fetch(&self) -> Result<Vec<OmpServerEntry>> {
+        let url = format!("{}/servers", self.base_url);
+
+        self.http
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::Http(e.to_string()))?
+            .json()
+            .await
+            .map_err(|e| Error::Http(e.to_string()))
+    }
+
+    /// Fetches the servers matching `filter`.
+    pub async fn fetch_filtered(&self, filter: &OmpFilter) -> Result<Vec<OmpServerEntry>> {
+        Ok(filter.apply(self.fetch().await?))
+    }
+
+    /// Fetches one page of the servers matching `filter`, most players first.
+    ///
+    /// The API returns the whole list at once, so pagination happens locally.
+    pub async fn fetch_page(
+        &self,
+        filter: &OmpFilter,
+        page: usize,
+        per_page: usize,
+    ) -> Result<Page<OmpServerEntry>> {
+        let mut entries = self.fetch_filtered(filter).await?;
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.players));
+
+        Ok(paginate(entries, page, per_page))
+    }
+}
+
+/// Splits `entries` into pages and returns the requested one.
+pub fn paginate<T>(entries: Vec<T>, page: usize, per_page: usize) -> Page<T> {
+    let per_page = per_page.max(1);
+    let total = entries.len();
+    let items = entries
+        .into_iter()
+        .skip(page.saturating_mul(per_page))
+        .take(per_page)
+        .collect();
+
+    Page {
+        items,
+        page,
+        per_page,
+        total,
+    }
+}
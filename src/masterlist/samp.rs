@@ -0,0 +1,111 @@
+//! Client for the classic SA-MP hosted/internet lists.
+//!
+//! The lists are plain text with one `ip:port` per line.
+//!
+//! ```
+//! use samp_query::masterlist::samp::parse_list;
+//!
+//! let servers = parse_list("127.0.0.1:7777\r\n\r\n127.0.0.1:7778\nnot an address\n");
+//! assert_eq!(servers.len(), 2);
+//! assert_eq!(servers[1].port(), 7778);
+//! ```
+
+use crate::error::{Error, Result};
+use crate::filter::ServerFilter;
+use std::collections::HashSet;
+use std::fmt;
+use std::net::SocketAddr;
+
+/// Base URL of the classic SA-MP list service.
+pub const DEFAULT_BASE_URL: &str = "http://lists.sa-mp.com/0.3.7";
+
+/// Which tab of the server browser to fetch.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListKind {
+    /// Every announced server.
+    Internet,
+    /// Servers hosted by official partners.
+    Hosted,
+}
+
+impl ListKind {
+    fn path(&self) -> &'static str {
+        match self {
+            ListKind::Internet => "internet",
+            ListKind::Hosted => "hosted",
+        }
+    }
+}
+
+impl fmt::Display for ListKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ListKind::Internet => write!(f, "Internet"),
+            ListKind::Hosted => write!(f, "Hosted"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SampMasterlist {
+    http: reqwest::Client,
+    base_url: String,
+}
+
+impl Default for SampMasterlist {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SampMasterlist {
+    pub fn new() -> Self {
+        Self::with_base_url(DEFAULT_BASE_URL)
+    }
+
+    /// Creates a client for a mirror of the list service.
+    pub fn with_base_url(base_url: &str) -> Self {
+        Self {
+            http: crate::resolver::http_client(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        }
+    }
+
+    /// Fetches the addresses listed on the given tab.
+    pub async fn fetch(&self, kind: ListKind) -> Result<Vec<SocketAddr>> {
+        let url = format!("{}/{}", self.base_url, kind.path());
+
+        let body = self
+            .http
+            .get(&url)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::Http(e.to_string()))?
+            .text()
+            .await
+            .map_err(|e| Error::Http(e.to_string()))?;
+
+        Ok(parse_list(&body))
+    }
+
+    /// Fetches the addresses listed on the given tab that `filter` allows.
+    ///
+    /// The list only carries addresses, so hostname and rule checks need a
+    /// query of each server and are left to the caller.
+    pub async fn fetch_filtered(&self, kind: ListKind, filter: &ServerFilter) -> Result<Vec<SocketAddr>> {
+        let mut servers = self.fetch(kind).await?;
+        servers.retain(|addr| filter.allows_addr(addr));
+        Ok(servers)
+    }
+}
+
+/// Parses a list response, skipping blank or invalid lines and duplicates.
+pub fn parse_list(body: &str) -> Vec<SocketAddr> {
+    let mut seen = HashSet::new();
+
+    body.lines()
+        .filter_map(|line| line.trim().parse::<SocketAddr>().ok())
+        .filter(|addr| seen.insert(*addr))
+        .collect()
+}
@@ -0,0 +1,167 @@
+//! Mock SA-MP server for tests.
+//!
+//! Available with the `test-util` feature. [`MockServer`] listens on an
+//! ephemeral UDP port, replies with canned responses per query type and
+//! records every query it receives.
+
+use crate::error::{Error, Result};
+use crate::packet::{encode, Packet};
+use crate::protocol::{constants, QueryType};
+use crate::types::*;
+use std::collections::{HashMap, VecDeque};
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::net::UdpSocket;
+use tokio::task::JoinHandle;
+
+/// A canned reply to a single query.
+#[derive(Debug, Clone)]
+pub enum MockResponse {
+    Info(ServerInfo),
+    Rules(ServerRules),
+    Players(PlayerList),
+    DetailedPlayers(DetailedPlayerList),
+    /// Echoes the ping payload back.
+    Pong,
+    /// Sends the request header followed by the given body.
+    Body(Vec<u8>),
+    /// Sends the given bytes verbatim, without a header.
+    Raw(Vec<u8>),
+    /// Does not reply at all.
+    Drop,
+    /// Sends the inner response after a delay.
+    Delayed(Duration, Box<MockResponse>),
+}
+
+/// Responses served by a [`MockServer`], queued per query type.
+///
+/// Each query consumes the next queued response for its type; the last one is
+/// repeated once the queue is exhausted. Query types without responses are
+/// dropped.
+#[derive(Debug, Clone, Default)]
+pub struct MockResponses {
+    queues: HashMap<QueryType, VecDeque<MockResponse>>,
+}
+
+impl MockResponses {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues a response for the given query type.
+    pub fn on(mut self, query_type: QueryType, response: MockResponse) -> Self {
+        self.queues.entry(query_type).or_default().push_back(response);
+        self
+    }
+
+    fn next(&mut self, query_type: QueryType) -> MockResponse {
+        match self.queues.get_mut(&query_type) {
+            Some(queue) if queue.len() > 1 => queue.pop_front().unwrap_or(MockResponse::Drop),
+            Some(queue) => queue.front().cloned().unwrap_or(MockResponse::Drop),
+            None => MockResponse::Drop,
+        }
+    }
+}
+
+/// A query received by a [`MockServer`].
+#[derive(Debug, Clone)]
+pub struct ReceivedQuery {
+    /// The query type, if the opcode was recognised.
+    pub query_type: Option<QueryType>,
+    /// The raw request bytes.
+    pub data: Vec<u8>,
+}
+
+#[derive(Debug)]
+pub struct MockServer {
+    addr: SocketAddr,
+    received: Arc<Mutex<Vec<ReceivedQuery>>>,
+    task: JoinHandle<()>,
+}
+
+impl MockServer {
+    /// Starts a mock server on an ephemeral localhost port.
+    pub async fn start(responses: MockResponses) -> Result<Self> {
+        let socket = UdpSocket::bind("127.0.0.1:0").await.map_err(Error::Bind)?;
+        let addr = socket.local_addr()?;
+        let socket = Arc::new(socket);
+        let received = Arc::new(Mutex::new(Vec::new()));
+
+        let task = tokio::spawn(serve(socket, responses, received.clone()));
+
+        Ok(Self {
+            addr,
+            received,
+            task,
+        })
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Returns every query received so far.
+    pub fn received(&self) -> Vec<ReceivedQuery> {
+        self.received.lock().unwrap().clone()
+    }
+}
+
+impl Drop for MockServer {
+    fn drop(&mut self) {
+        self.task.abort();
+    }
+}
+
+async fn serve(
+    socket: Arc<UdpSocket>,
+    mut responses: MockResponses,
+    received: Arc<Mutex<Vec<ReceivedQuery>>>,
+) {
+    let mut buf = vec![0u8; constants::MAX_PACKET_SIZE];
+
+    while let Ok((size, peer)) = socket.recv_from(&mut buf).await {
+        let request = Packet::from_bytes(&buf[..size]);
+        let query_type = request.query_type();
+
+        received.lock().unwrap().push(ReceivedQuery {
+            query_type,
+            data: request.as_bytes().to_vec(),
+        });
+
+        let Some(query_type) = query_type else {
+            continue;
+        };
+
+        let response = responses.next(query_type);
+        let socket = socket.clone();
+        tokio::spawn(async move {
+            let mut response = response;
+            while let MockResponse::Delayed(delay, inner) = response {
+                tokio::time::sleep(delay).await;
+                response = *inner;
+            }
+
+            if let Some(bytes) = render(&request, response) {
+                let _ = socket.send_to(&bytes, peer).await;
+            }
+        });
+    }
+}
+
+fn render(request: &Packet, response: MockResponse) -> Option<Vec<u8>> {
+    let body = match response {
+        MockResponse::Info(info) => encode::info(&info),
+        MockResponse::Rules(rules) => encode::rules(&rules).ok()?,
+        MockResponse::Players(players) => encode::client_list(&players).ok()?,
+        MockResponse::DetailedPlayers(players) => encode::detailed_players(&players).ok()?,
+        MockResponse::Pong => request.as_bytes().get(constants::HEADER_SIZE..)?.to_vec(),
+        MockResponse::Body(body) => body,
+        MockResponse::Raw(bytes) => return Some(bytes),
+        MockResponse::Drop | MockResponse::Delayed(..) => return None,
+    };
+
+    Packet::create_response(request, &body)
+        .ok()
+        .map(|packet| packet.as_bytes().to_vec())
+}
@@ -0,0 +1,373 @@
+//! Server monitoring with an event stream.
+//!
+//! A [`Monitor`] polls a single server at a fixed interval and turns the
+//! differences between consecutive polls into [`MonitorEvent`]s.
+//!
+//! ```rust,no_run
+//! use futures::StreamExt;
+//! use samp_query::monitor::Monitor;
+//! use std::time::Duration;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut events = Monitor::new("127.0.0.1:7777".parse()?, Duration::from_secs(30)).stream();
+//!
+//! while let Some(event) = events.next().await {
+//!     println!("{}", event);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::protocol::constants;
+use crate::stats::{self, LatencyStats, LatencySummary};
+use crate::types::ServerInfo;
+use crate::utils::unix_timestamp_ms;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashSet, VecDeque};
+use std::fmt;
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::time::{interval, MissedTickBehavior};
+
+#[derive(Debug, Clone)]
+pub struct MonitorConfig {
+    /// Client configuration used for every poll.
+    pub client: ClientConfig,
+    /// Interval between polls; zero is raised to one millisecond.
+    pub interval: Duration,
+    /// Whether to query the client list to detect joins and leaves.
+    pub track_players: bool,
+    /// A ping this many times above the running average is reported as a spike.
+    pub ping_spike_factor: f64,
+    /// Pings below this value are never reported as spikes, in milliseconds.
+    pub ping_spike_min_ms: u64,
+    /// Number of recent pings the latency statistics are computed over.
+    pub latency_window: usize,
+    /// Fit the query timeout to the observed latency, using the client
+    /// timeout as the upper bound.
+    pub adaptive_timeout: bool,
+}
+
+impl Default for MonitorConfig {
+    fn default() -> Self {
+        Self {
+            client: ClientConfig::default(),
+            interval: Duration::from_secs(30),
+            track_players: true,
+            ping_spike_factor: 3.0,
+            ping_spike_min_ms: 150,
+            latency_window: stats::DEFAULT_WINDOW,
+            adaptive_timeout: false,
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MonitorEvent {
+    /// Address of the monitored server.
+    pub address: SocketAddr,
+    /// Unix timestamp in milliseconds at which the event was observed.
+    pub timestamp: u64,
+    /// What happened.
+    pub kind: MonitorEventKind,
+}
+
+impl MonitorEvent {
+    /// Returns true for events describing a change, i.e. everything but [`MonitorEventKind::Polled`].
+    pub fn is_change(&self) -> bool {
+        !matches!(self.kind, MonitorEventKind::Polled { .. })
+    }
+}
+
+impl fmt::Display for MonitorEvent {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "[{}] {}", self.address, self.kind)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum MonitorEventKind {
+    /// The server answered after being offline (or on the first poll).
+    Online,
+    /// The server stopped answering.
+    Offline { reason: String },
+    /// A player appeared in the client list.
+    PlayerJoined { name: String },
+    /// A player disappeared from the client list.
+    PlayerLeft { name: String },
+    /// The reported player count changed.
+    PlayerCountChanged { old: u16, new: u16 },
+    /// The hostname changed.
+    HostnameChanged { old: String, new: String },
+    /// The gamemode changed.
+    GamemodeChanged { old: String, new: String },
+    /// The ping was well above its running average.
+    PingSpike { ping_ms: u64, average_ms: u64 },
+    /// Emitted after every successful poll with the latest data.
+    Polled {
+        info: ServerInfo,
+        ping_ms: Option<u64>,
+        /// Latency statistics including this poll, once a ping succeeded.
+        #[serde(default, skip_serializing_if = "Option::is_none")]
+        latency: Option<LatencySummary>,
+    },
+}
+
+impl fmt::Display for MonitorEventKind {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MonitorEventKind::Online => write!(f, "Server is online"),
+            MonitorEventKind::Offline { reason } => write!(f, "Server is offline: {}", reason),
+            MonitorEventKind::PlayerJoined { name } => write!(f, "{} joined", name),
+            MonitorEventKind::PlayerLeft { name } => write!(f, "{} left", name),
+            MonitorEventKind::PlayerCountChanged { old, new } => {
+                write!(f, "Players changed from {} to {}", old, new)
+            }
+            MonitorEventKind::HostnameChanged { old, new } => {
+                write!(f, "Hostname changed from \"{}\" to \"{}\"", old, new)
+            }
+            MonitorEventKind::GamemodeChanged { old, new } => {
+                write!(f, "Gamemode changed from \"{}\" to \"{}\"", old, new)
+            }
+            MonitorEventKind::PingSpike {
+                ping_ms,
+                average_ms,
+            } => write!(f, "Ping spike: {} ms (average {} ms)", ping_ms, average_ms),
+            MonitorEventKind::Polled { info, ping_ms, .. } => match ping_ms {
+                Some(ping) => write!(
+                    f,
+                    "{} ({}/{}, {} ms)",
+                    info.hostname, info.players, info.max_players, ping
+                ),
+                None => write!(f, "{} ({}/{})", info.hostname, info.players, info.max_players),
+            },
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Monitor {
+    addr: SocketAddr,
+    config: MonitorConfig,
+}
+
+impl Monitor {
+    pub fn new(addr: SocketAddr, interval: Duration) -> Self {
+        Self::with_config(
+            addr,
+            MonitorConfig {
+                interval,
+                ..MonitorConfig::default()
+            },
+        )
+    }
+
+    pub fn with_config(addr: SocketAddr, mut config: MonitorConfig) -> Self {
+        // A zero period makes the tokio ticker panic.
+        config.interval = config.interval.max(Duration::from_millis(constants::MIN_POLL_INTERVAL_MS));
+        Self { addr, config }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Starts polling and returns the stream of events.
+    ///
+    /// The first poll happens immediately and only establishes a baseline, so
+    /// it yields `Online`/`Offline` and `Polled` but no join or change events.
+    pub fn stream(self) -> BoxStream<'static, MonitorEvent> {
+        let mut ticker = interval(self.config.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+        let poller = Poller::new(self.addr, self.config);
+
+        stream::unfold((poller, ticker), |(mut poller, mut ticker)| async move {
+            loop {
+                if let Some(event) = poller.pending.pop_front() {
+                    return Some((event, (poller, ticker)));
+                }
+
+                ticker.tick().await;
+                poller.poll().await;
+            }
+        })
+        .boxed()
+    }
+}
+
+/// Polls one server and turns the differences between polls into events.
+pub(crate) struct Poller {
+    addr: SocketAddr,
+    config: MonitorConfig,
+    client: Option<Client>,
+    online: Option<bool>,
+    last_info: Option<ServerInfo>,
+    players: Option<HashSet<String>>,
+    latency: LatencyStats,
+    /// Set when a poll fails, so the next one waits the full client timeout
+    /// instead of one fitted to a latency the server no longer has. Cleared
+    /// once a ping is recorded again.
+    timeout_restored: bool,
+    pending: VecDeque<MonitorEvent>,
+}
+
+impl Poller {
+    /// Lower bound of adaptive timeouts, in milliseconds.
+    const MIN_ADAPTIVE_TIMEOUT_MS: u64 = 100;
+
+    pub(crate) fn new(addr: SocketAddr, config: MonitorConfig) -> Self {
+        Self {
+            latency: LatencyStats::new(config.latency_window),
+            addr,
+            config,
+            client: None,
+            online: None,
+            last_info: None,
+            players: None,
+            timeout_restored: false,
+            pending: VecDeque::new(),
+        }
+    }
+
+    pub(crate) fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Whether the last poll succeeded.
+    pub(crate) fn is_online(&self) -> bool {
+        self.online == Some(true)
+    }
+
+    /// Removes and returns the events produced since the last call.
+    pub(crate) fn take_events(&mut self) -> impl Iterator<Item = MonitorEvent> + '_ {
+        self.pending.drain(..)
+    }
+
+    pub(crate) async fn poll(&mut self) {
+        if self.client.is_none() {
+            match Client::connect_with_config(self.addr, self.config.client.clone()).await {
+                Ok(client) => self.client = Some(client),
+                Err(e) => return self.set_offline(e.to_string()),
+            }
+        }
+        if self.config.adaptive_timeout && !self.timeout_restored {
+            let max = Duration::from_millis(self.config.client.timeout_ms);
+            let min = Duration::from_millis(Self::MIN_ADAPTIVE_TIMEOUT_MS).min(max);
+            if let (Some(client), Some(timeout)) = (&mut self.client, self.latency.suggested_timeout(min, max)) {
+                client.set_timeout_ms(timeout.as_millis() as u64);
+            }
+        }
+        let Some(client) = &self.client else {
+            return;
+        };
+
+        let info = match client.query_info().await {
+            Ok(info) => info,
+            Err(e) => return self.set_offline(e.to_string()),
+        };
+        let ping = client.query_ping().await.ok().map(|p| p.ping_ms);
+        let players = if self.config.track_players {
+            client
+                .query_client_list()
+                .await
+                .ok()
+                .map(|list| list.players.into_iter().map(|p| p.name).collect::<HashSet<_>>())
+        } else {
+            None
+        };
+
+        if self.online != Some(true) {
+            self.online = Some(true);
+            self.push(MonitorEventKind::Online);
+        }
+
+        if let Some(old) = self.last_info.take() {
+            if old.players != info.players {
+                self.push(MonitorEventKind::PlayerCountChanged {
+                    old: old.players,
+                    new: info.players,
+                });
+            }
+            if old.hostname != info.hostname {
+                self.push(MonitorEventKind::HostnameChanged {
+                    old: old.hostname,
+                    new: info.hostname.clone(),
+                });
+            }
+            if old.gamemode != info.gamemode {
+                self.push(MonitorEventKind::GamemodeChanged {
+                    old: old.gamemode,
+                    new: info.gamemode.clone(),
+                });
+            }
+        }
+
+        if let Some(current) = players {
+            if let Some(previous) = &self.players {
+                let mut joined: Vec<_> = current.difference(previous).cloned().collect();
+                let mut left: Vec<_> = previous.difference(&current).cloned().collect();
+                joined.sort();
+                left.sort();
+
+                for name in joined {
+                    self.push(MonitorEventKind::PlayerJoined { name });
+                }
+                for name in left {
+                    self.push(MonitorEventKind::PlayerLeft { name });
+                }
+            }
+            self.players = Some(current);
+        }
+
+        if let Some(ping_ms) = ping {
+            if let Some(average) = self.latency.ewma() {
+                let average = average.as_secs_f64() * 1000.0;
+                if ping_ms >= self.config.ping_spike_min_ms
+                    && ping_ms as f64 > average * self.config.ping_spike_factor
+                {
+                    self.push(MonitorEventKind::PingSpike {
+                        ping_ms,
+                        average_ms: average.round() as u64,
+                    });
+                }
+            }
+            self.latency.record(Duration::from_millis(ping_ms));
+            self.timeout_restored = false;
+        }
+
+        self.push(MonitorEventKind::Polled {
+            info: info.clone(),
+            ping_ms: ping,
+            latency: self.latency.summary(),
+        });
+        self.last_info = Some(info);
+    }
+
+    fn set_offline(&mut self, reason: String) {
+        if self.online != Some(false) {
+            self.online = Some(false);
+            self.push(MonitorEventKind::Offline { reason });
+        }
+        // Start from a clean baseline once the server comes back.
+        self.players = None;
+        // Samples only come from answered pings, so a server that became
+        // slower than the fitted timeout would otherwise never be heard again.
+        if self.config.adaptive_timeout {
+            if let Some(client) = &mut self.client {
+                client.set_timeout_ms(self.config.client.timeout_ms);
+                self.timeout_restored = true;
+            }
+        }
+    }
+
+    fn push(&mut self, kind: MonitorEventKind) {
+        self.pending.push_back(MonitorEvent {
+            address: self.addr,
+            timestamp: unix_timestamp_ms(),
+            kind,
+        });
+    }
+}
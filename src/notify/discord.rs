@@ -0,0 +1,191 @@
+//! Discord webhook notifications for monitor events.
+//!
+//! Available with the `http` feature. [`DiscordNotifier`] turns monitor
+//! events into embeds and posts them to a webhook: the server going down or
+//! coming back up, the player count crossing a milestone, and hostname
+//! changes. Message text comes from [`DiscordTemplates`], rendered with
+//! [`render_template`].
+//!
+//! ```rust,no_run
+//! use futures::StreamExt;
+//! use samp_query::monitor::Monitor;
+//! use samp_query::notify::discord::DiscordNotifier;
+//! use std::time::Duration;
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let notifier = DiscordNotifier::new("https://discord.com/api/webhooks/...");
+//! let mut events = Monitor::new("127.0.0.1:7777".parse().unwrap(), Duration::from_secs(30)).stream();
+//!
+//! while let Some(event) = events.next().await {
+//!     notifier.notify(&event).await?;
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use super::EventSink;
+use crate::error::{Error, Result};
+use crate::monitor::{MonitorEvent, MonitorEventKind};
+use crate::utils::render_template;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+
+const COLOR_RED: u32 = 0xE74C3C;
+const COLOR_GREEN: u32 = 0x2ECC71;
+const COLOR_BLUE: u32 = 0x3498DB;
+const COLOR_GOLD: u32 = 0xF1C40F;
+
+/// Message templates.
+///
+/// Every template can use `{address}`; the offline template also has
+/// `{reason}`, the milestone template `{milestone}`, `{old}` and `{new}`, and
+/// the hostname template `{old}` and `{new}`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DiscordTemplates {
+    pub offline: String,
+    pub online: String,
+    pub milestone: String,
+    pub hostname_changed: String,
+}
+
+impl Default for DiscordTemplates {
+    fn default() -> Self {
+        Self {
+            offline: "**{address}** is down: {reason}".to_string(),
+            online: "**{address}** is back up".to_string(),
+            milestone: "**{address}** reached {milestone} players ({new} online)".to_string(),
+            hostname_changed: "**{address}** was renamed from \"{old}\" to \"{new}\"".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscordConfig {
+    pub webhook_url: String,
+    /// Overrides the webhook's default username.
+    pub username: Option<String>,
+    /// Player counts that trigger a notification when reached from below.
+    pub milestones: Vec<u16>,
+    pub templates: DiscordTemplates,
+}
+
+impl DiscordConfig {
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        Self {
+            webhook_url: webhook_url.into(),
+            username: None,
+            milestones: vec![50, 100, 200, 500],
+            templates: DiscordTemplates::default(),
+        }
+    }
+}
+
+/// A Discord embed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Embed {
+    pub title: String,
+    pub description: String,
+    pub color: u32,
+}
+
+/// Body of a webhook call.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct WebhookMessage {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub username: Option<String>,
+    pub embeds: Vec<Embed>,
+}
+
+#[derive(Debug, Clone)]
+pub struct DiscordNotifier {
+    http: reqwest::Client,
+    config: DiscordConfig,
+}
+
+impl DiscordNotifier {
+    pub fn new(webhook_url: impl Into<String>) -> Self {
+        Self::with_config(DiscordConfig::new(webhook_url))
+    }
+
+    pub fn with_config(config: DiscordConfig) -> Self {
+        Self {
+            http: crate::resolver::http_client(),
+            config,
+        }
+    }
+
+    pub fn config(&self) -> &DiscordConfig {
+        &self.config
+    }
+
+    /// Builds the message for an event, or `None` if the event is not notified.
+    pub fn message(&self, event: &MonitorEvent) -> Option<WebhookMessage> {
+        let templates = &self.config.templates;
+        let mut vars = HashMap::from([("address".to_string(), event.address.to_string())]);
+
+        let (title, template, color) = match &event.kind {
+            MonitorEventKind::Offline { reason } => {
+                vars.insert("reason".to_string(), reason.clone());
+                ("Server down", &templates.offline, COLOR_RED)
+            }
+            MonitorEventKind::Online => ("Server up", &templates.online, COLOR_GREEN),
+            MonitorEventKind::PlayerCountChanged { old, new } => {
+                let milestone = self
+                    .config
+                    .milestones
+                    .iter()
+                    .filter(|&&milestone| *old < milestone && *new >= milestone)
+                    .max()?;
+                vars.insert("milestone".to_string(), milestone.to_string());
+                vars.insert("old".to_string(), old.to_string());
+                vars.insert("new".to_string(), new.to_string());
+                ("Player milestone", &templates.milestone, COLOR_GOLD)
+            }
+            MonitorEventKind::HostnameChanged { old, new } => {
+                vars.insert("old".to_string(), old.clone());
+                vars.insert("new".to_string(), new.clone());
+                ("Hostname changed", &templates.hostname_changed, COLOR_BLUE)
+            }
+            _ => return None,
+        };
+
+        Some(WebhookMessage {
+            username: self.config.username.clone(),
+            embeds: vec![Embed {
+                title: title.to_string(),
+                description: render_template(template, &vars),
+                color,
+            }],
+        })
+    }
+
+    /// Posts the message for an event. Returns whether anything was sent.
+    pub async fn notify(&self, event: &MonitorEvent) -> Result<bool> {
+        let Some(message) = self.message(event) else {
+            return Ok(false);
+        };
+
+        self.send(&message).await?;
+        Ok(true)
+    }
+
+    pub async fn send(&self, message: &WebhookMessage) -> Result<()> {
+        self.http
+            .post(&self.config.webhook_url)
+            .json(message)
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::Http(e.to_string()))?;
+
+        Ok(())
+    }
+}
+
+#[async_trait]
+impl EventSink for DiscordNotifier {
+    async fn publish(&self, event: &MonitorEvent) -> Result<()> {
+        self.notify(event).await.map(|_| ())
+    }
+}
@@ -0,0 +1,116 @@
+//! Notifications for monitor events.
+//!
+//! [`EventSink`] is the extension point for anything that reacts to monitor
+//! events. The built-in sinks log events ([`LogSink`]), forward them to a
+//! channel ([`ChannelSink`]) or, with the `http` feature, POST them as JSON
+//! ([`webhook::WebhookSink`]) or to Discord ([`discord::DiscordNotifier`]).
+//!
+//! ```rust,no_run
+//! use samp_query::monitor::Monitor;
+//! use samp_query::notify::{forward, EventSink, LogSink};
+//! use std::time::Duration;
+//!
+//! # async fn run() {
+//! let events = Monitor::new("127.0.0.1:7777".parse().unwrap(), Duration::from_secs(30)).stream();
+//! let sinks: Vec<Box<dyn EventSink>> = vec![Box::new(LogSink)];
+//!
+//! forward(events, &sinks).await;
+//! # }
+//! ```
+
+#[cfg(feature = "http")]
+pub mod discord;
+#[cfg(feature = "http")]
+pub mod webhook;
+
+use crate::error::{Error, Result};
+use crate::monitor::MonitorEvent;
+use async_trait::async_trait;
+use futures::stream::{Stream, StreamExt};
+use tokio::sync::mpsc;
+use tracing::{debug, info, warn};
+
+/// A destination for monitor events.
+#[async_trait]
+pub trait EventSink: Send + Sync {
+    async fn publish(&self, event: &MonitorEvent) -> Result<()>;
+}
+
+#[async_trait]
+impl<S: EventSink + ?Sized> EventSink for Box<S> {
+    async fn publish(&self, event: &MonitorEvent) -> Result<()> {
+        (**self).publish(event).await
+    }
+}
+
+#[async_trait]
+impl<S: EventSink + ?Sized> EventSink for std::sync::Arc<S> {
+    async fn publish(&self, event: &MonitorEvent) -> Result<()> {
+        (**self).publish(event).await
+    }
+}
+
+/// Logs change events at `info` level and polls at `debug` level.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct LogSink;
+
+#[async_trait]
+impl EventSink for LogSink {
+    async fn publish(&self, event: &MonitorEvent) -> Result<()> {
+        if event.is_change() {
+            info!("{}", event);
+        } else {
+            debug!("{}", event);
+        }
+
+        Ok(())
+    }
+}
+
+/// Forwards every event to a channel.
+#[derive(Debug, Clone)]
+pub struct ChannelSink {
+    tx: mpsc::Sender<MonitorEvent>,
+}
+
+impl ChannelSink {
+    pub fn new(tx: mpsc::Sender<MonitorEvent>) -> Self {
+        Self { tx }
+    }
+
+    /// Creates a sink together with the receiving end of its channel.
+    pub fn channel(capacity: usize) -> (Self, mpsc::Receiver<MonitorEvent>) {
+        let (tx, rx) = mpsc::channel(capacity.max(1));
+        (Self::new(tx), rx)
+    }
+}
+
+#[async_trait]
+impl EventSink for ChannelSink {
+    async fn publish(&self, event: &MonitorEvent) -> Result<()> {
+        self.tx
+            .send(event.clone())
+            .await
+            .map_err(|_| Error::Other("Event channel is closed".to_string()))
+    }
+}
+
+/// Publishes every event of a stream to all sinks until the stream ends.
+///
+/// Each event is published to the sinks concurrently, and once every sink
+/// is done with it, the next one is; so a slow sink delays the others but
+/// every sink sees the events in order. Sink failures are logged and do not
+/// stop the other sinks.
+pub async fn forward<St, S>(events: St, sinks: &[S])
+where
+    St: Stream<Item = MonitorEvent>,
+    S: EventSink,
+{
+    futures::pin_mut!(events);
+    while let Some(event) = events.next().await {
+        let results = futures::future::join_all(sinks.iter().map(|sink| sink.publish(&event))).await;
+        for e in results.into_iter().filter_map(Result::err) {
+            warn!("Failed to publish event for {}: {}", event.address, e);
+        }
+    }
+}
@@ -0,0 +1,66 @@
+//! Generic JSON webhook sink.
+//!
+//! Available with the `http` feature. [`WebhookSink`] POSTs every change event
+//! as JSON, in the same shape as [`MonitorEvent`]'s serde representation.
+
+use super::EventSink;
+use crate::error::{Error, Result};
+use crate::monitor::MonitorEvent;
+use async_trait::async_trait;
+
+#[derive(Debug, Clone)]
+pub struct WebhookSink {
+    http: reqwest::Client,
+    url: String,
+    headers: Vec<(String, String)>,
+    changes_only: bool,
+}
+
+impl WebhookSink {
+    pub fn new(url: impl Into<String>) -> Self {
+        Self {
+            http: crate::resolver::http_client(),
+            url: url.into(),
+            headers: Vec::new(),
+            changes_only: true,
+        }
+    }
+
+    /// Adds a header to every request, e.g. for authentication.
+    pub fn header(mut self, name: impl Into<String>, value: impl Into<String>) -> Self {
+        self.headers.push((name.into(), value.into()));
+        self
+    }
+
+    /// Whether `Polled` events are skipped. Defaults to true.
+    pub fn changes_only(mut self, changes_only: bool) -> Self {
+        self.changes_only = changes_only;
+        self
+    }
+
+    pub fn url(&self) -> &str {
+        &self.url
+    }
+}
+
+#[async_trait]
+impl EventSink for WebhookSink {
+    async fn publish(&self, event: &MonitorEvent) -> Result<()> {
+        if self.changes_only && !event.is_change() {
+            return Ok(());
+        }
+
+        let mut request = self.http.post(&self.url).json(event);
+        for (name, value) in &self.headers {
+            request = request.header(name.as_str(), value.as_str());
+        }
+
+        request
+            .send()
+            .await
+            .and_then(|response| response.error_for_status())
+            .map_err(|e| Error::Http(e.to_string()))?;
+
+        Ok(())
+    }
+}
@@ -12,6 +12,12 @@ pub struct Packet {
     data: BytesMut,
 }
 
+impl Default for Packet {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl Packet {
     pub fn new() -> Self {
         Self {
@@ -96,10 +102,10 @@ impl Packet {
         
         let mut packet = Self::create_query(server_addr, QueryType::Rcon)?;
 
-        packet.data.put_u16_le(password.len() as u16);
+        packet.data.put_u16_le(length_16("RCON password", password)?);
         packet.data.extend_from_slice(password.as_bytes());
 
-        packet.data.put_u16_le(command.len() as u16);
+        packet.data.put_u16_le(length_16("RCON command", command)?);
         packet.data.extend_from_slice(command.as_bytes());
 
         Ok(packet)
@@ -137,6 +143,460 @@ impl Packet {
 
         Ok(self.data[constants::HEADER_SIZE..].to_vec())
     }
+
+    /// Returns the query type encoded in the packet header, if any.
+    pub fn query_type(&self) -> Option<QueryType> {
+        if self.data.len() < constants::HEADER_SIZE {
+            return None;
+        }
+
+        QueryType::from_opcode(self.data[constants::HEADER_SIZE - 1])
+    }
+
+    /// Creates a response to `request` by echoing its header followed by `body`.
+    pub fn create_response(request: &Packet, body: &[u8]) -> Result<Self> {
+        request.validate_response()?;
+
+        let mut packet = Self::with_capacity(constants::HEADER_SIZE + body.len());
+        packet
+            .data
+            .extend_from_slice(&request.data[..constants::HEADER_SIZE]);
+        packet.data.extend_from_slice(body);
+
+        Ok(packet)
+    }
+}
+
+/// Decoders for response bodies, as returned by [`Packet::parse_response`].
+pub mod decode {
+    use super::utils as packet_utils;
+    use crate::error::Result;
+    use crate::types::*;
+    use std::collections::HashMap;
+    use std::io::Cursor;
+
+    /// Bounds the preallocation for `count` entries of at least `min_size`
+    /// bytes each by what the remaining data can hold, so a forged count
+    /// cannot force a large allocation.
+    fn capacity(cursor: &Cursor<&[u8]>, count: usize, min_size: usize) -> usize {
+        let remaining = cursor.get_ref().len().saturating_sub(cursor.position() as usize);
+        count.min(remaining / min_size)
+    }
+
+    pub fn info(data: &[u8]) -> Result<ServerInfo> {
+        let mut cursor = Cursor::new(data);
+
+        let password = packet_utils::read_u8(&mut cursor)? != 0;
+        let players = packet_utils::read_u16_le(&mut cursor)?;
+        let max_players = packet_utils::read_u16_le(&mut cursor)?;
+
+        let hostname = packet_utils::read_length_prefixed_string_32(&mut cursor)?;
+        let gamemode = packet_utils::read_length_prefixed_string_32(&mut cursor)?;
+        let language = packet_utils::read_length_prefixed_string_32(&mut cursor)?;
+
+        Ok(ServerInfo {
+            password,
+            players,
+            max_players,
+            hostname,
+            gamemode,
+            language,
+        })
+    }
+
+    pub fn rules(data: &[u8]) -> Result<ServerRules> {
+        let mut cursor = Cursor::new(data);
+
+        let rule_count = packet_utils::read_u16_le(&mut cursor)? as usize;
+        let mut rules = HashMap::with_capacity(capacity(&cursor, rule_count, 2));
+
+        for _ in 0..rule_count {
+            let name = packet_utils::read_length_prefixed_string(&mut cursor)?;
+            let value = packet_utils::read_length_prefixed_string(&mut cursor)?;
+            rules.insert(name, value);
+        }
+
+        Ok(ServerRules { rules })
+    }
+
+    pub fn client_list(data: &[u8]) -> Result<PlayerList> {
+        let mut cursor = Cursor::new(data);
+
+        let player_count = packet_utils::read_u16_le(&mut cursor)? as usize;
+        let mut players = Vec::with_capacity(capacity(&cursor, player_count, 5));
+
+        for _ in 0..player_count {
+            let name = packet_utils::read_length_prefixed_string(&mut cursor)?;
+            let score = packet_utils::read_i32_le(&mut cursor)?;
+
+            players.push(Player { name, score });
+        }
+
+        Ok(PlayerList { players })
+    }
+
+    pub fn detailed_players(data: &[u8]) -> Result<DetailedPlayerList> {
+        let mut cursor = Cursor::new(data);
+
+        let player_count = packet_utils::read_u16_le(&mut cursor)? as usize;
+        let mut players = Vec::with_capacity(capacity(&cursor, player_count, 10));
+
+        for _ in 0..player_count {
+            let id = packet_utils::read_u8(&mut cursor)?;
+            let name = packet_utils::read_length_prefixed_string(&mut cursor)?;
+            let score = packet_utils::read_i32_le(&mut cursor)?;
+            let ping = packet_utils::read_u32_le(&mut cursor)?;
+
+            players.push(DetailedPlayer {
+                id,
+                name,
+                score,
+                ping,
+            });
+        }
+
+        Ok(DetailedPlayerList { players })
+    }
+
+    /// Decodes one line of RCON output: a `u16` length followed by the text.
+    pub fn rcon_line(data: &[u8]) -> Result<String> {
+        let mut cursor = Cursor::new(data);
+
+        packet_utils::read_length_prefixed_string_16(&mut cursor)
+    }
+}
+
+/// The length of `value` as the `u8` prefix of a string field.
+fn length_8(field: &str, value: &str) -> Result<u8> {
+    u8::try_from(value.len()).map_err(|_| too_long(field, value.len(), u8::MAX.into()))
+}
+
+/// The length of `value` as the `u16` prefix of a string field.
+fn length_16(field: &str, value: &str) -> Result<u16> {
+    u16::try_from(value.len()).map_err(|_| too_long(field, value.len(), u16::MAX.into()))
+}
+
+fn too_long(field: &str, len: usize, max: usize) -> Error {
+    Error::InvalidQueryType(format!("{} too long ({} bytes, max {})", field, len, max))
+}
+
+/// The number of entries of a list as its `u16` count.
+fn count_16(list: &str, len: usize) -> Result<u16> {
+    u16::try_from(len)
+        .map_err(|_| Error::InvalidQueryType(format!("Too many {} ({}, max {})", list, len, u16::MAX)))
+}
+
+/// Encoders for response bodies, the inverse of the client-side parsers.
+pub mod encode {
+    use crate::error::Result;
+    use crate::types::{DetailedPlayerList, PlayerList, ServerInfo, ServerRules};
+    use bytes::BufMut;
+
+    /// Fails rather than truncating, which could split a UTF-8 character.
+    fn put_string_8(buf: &mut Vec<u8>, field: &str, value: &str) -> Result<()> {
+        buf.put_u8(super::length_8(field, value)?);
+        buf.extend_from_slice(value.as_bytes());
+        Ok(())
+    }
+
+    fn put_string_32(buf: &mut Vec<u8>, value: &str) {
+        buf.put_u32_le(value.len() as u32);
+        buf.extend_from_slice(value.as_bytes());
+    }
+
+    pub fn info(info: &ServerInfo) -> Vec<u8> {
+        let mut buf = Vec::new();
+        buf.put_u8(info.password as u8);
+        buf.put_u16_le(info.players);
+        buf.put_u16_le(info.max_players);
+        put_string_32(&mut buf, &info.hostname);
+        put_string_32(&mut buf, &info.gamemode);
+        put_string_32(&mut buf, &info.language);
+        buf
+    }
+
+    /// Fails if there are more than 65535 rules or a name or value is
+    /// longer than 255 bytes.
+    pub fn rules(rules: &ServerRules) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.put_u16_le(super::count_16("rules", rules.rules.len())?);
+        for (name, value) in &rules.rules {
+            put_string_8(&mut buf, "Rule name", name)?;
+            put_string_8(&mut buf, "Rule value", value)?;
+        }
+        Ok(buf)
+    }
+
+    /// Fails if there are more than 65535 players or a name is longer than
+    /// 255 bytes.
+    pub fn client_list(players: &PlayerList) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.put_u16_le(super::count_16("players", players.players.len())?);
+        for player in &players.players {
+            put_string_8(&mut buf, "Player name", &player.name)?;
+            buf.put_i32_le(player.score);
+        }
+        Ok(buf)
+    }
+
+    /// Fails if there are more than 65535 players or a name is longer than
+    /// 255 bytes.
+    pub fn detailed_players(players: &DetailedPlayerList) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.put_u16_le(super::count_16("players", players.players.len())?);
+        for player in &players.players {
+            buf.put_u8(player.id);
+            put_string_8(&mut buf, "Player name", &player.name)?;
+            buf.put_i32_le(player.score);
+            buf.put_u32_le(player.ping);
+        }
+        Ok(buf)
+    }
+
+    /// Encodes one line of RCON output; fails if it is too long for its
+    /// `u16` length.
+    pub fn rcon_line(line: &str) -> Result<Vec<u8>> {
+        let mut buf = Vec::new();
+        buf.put_u16_le(super::length_16("RCON output line", line)?);
+        buf.extend_from_slice(line.as_bytes());
+        Ok(buf)
+    }
+}
+
+/// Field-by-field annotations of raw packets, for protocol traces.
+///
+/// Unlike [`decode`], these never fail: bytes that do not fit the expected
+/// layout are reported as truncated or trailing, which is what is wanted
+/// when looking at packets from a server that speaks the protocol slightly
+/// differently.
+///
+/// ```rust
+/// use samp_query::packet::explain::{hexdump, Direction};
+///
+/// let request = b"SAMP\x7f\x00\x00\x01\x61\x1ei";
+/// assert_eq!(
+///     hexdump(request, Direction::Request),
+///     "0000  53 41 4d 50  signature \"SAMP\"\n\
+///      0004  7f 00 00 01  server IP 127.0.0.1\n\
+///      0008  61 1e        server port 7777\n\
+///      000a  69           opcode 'i' (Information)\n"
+/// );
+/// ```
+pub mod explain {
+    use crate::protocol::{constants, QueryType};
+    use std::fmt::Write;
+    use std::ops::Range;
+
+    /// Target of the `trace` events with the packets a
+    /// [`Client`](crate::Client) sends and receives.
+    pub const TRACE_TARGET: &str = "samp_query::packets";
+
+    /// Bytes shown per hexdump line.
+    const BYTES_PER_LINE: usize = 16;
+
+    /// Which way a packet travels; the body layouts differ.
+    #[derive(Debug, Clone, Copy, PartialEq, Eq)]
+    pub enum Direction {
+        Request,
+        Response,
+    }
+
+    /// A span of bytes and what they mean.
+    #[derive(Debug, Clone, PartialEq, Eq)]
+    pub struct Field {
+        pub range: Range<usize>,
+        pub description: String,
+        /// Whether the bytes are secret, such as an RCON password, and left
+        /// out of dumps.
+        pub hidden: bool,
+    }
+
+    struct Fields<'a> {
+        data: &'a [u8],
+        position: usize,
+        fields: Vec<Field>,
+    }
+
+    impl Fields<'_> {
+        /// Claims the next `len` bytes, or records the rest as truncated.
+        fn take(&mut self, len: usize, describe: impl FnOnce(&[u8]) -> String) -> Option<&[u8]> {
+            let start = self.position;
+            if self.data.len() - start < len {
+                // Marked even when the packet ends exactly before the field.
+                self.fields.push(Field {
+                    range: start..self.data.len(),
+                    description: "truncated".to_string(),
+                    hidden: false,
+                });
+                self.position = self.data.len();
+                return None;
+            }
+            self.position += len;
+            let bytes = &self.data[start..self.position];
+            self.fields.push(Field {
+                range: start..self.position,
+                description: describe(bytes),
+                hidden: false,
+            });
+            Some(bytes)
+        }
+
+        fn u8(&mut self, label: &str) -> Option<u8> {
+            self.take(1, |bytes| format!("{} {}", label, bytes[0])).map(|bytes| bytes[0])
+        }
+
+        fn u16(&mut self, label: &str) -> Option<u16> {
+            let value = |bytes: &[u8]| u16::from_le_bytes([bytes[0], bytes[1]]);
+            self.take(2, |bytes| format!("{} {}", label, value(bytes))).map(value)
+        }
+
+        fn u32(&mut self, label: &str) -> Option<u32> {
+            let value = |bytes: &[u8]| u32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            self.take(4, |bytes| format!("{} {}", label, value(bytes))).map(value)
+        }
+
+        fn i32(&mut self, label: &str) -> Option<i32> {
+            let value = |bytes: &[u8]| i32::from_le_bytes([bytes[0], bytes[1], bytes[2], bytes[3]]);
+            self.take(4, |bytes| format!("{} {}", label, value(bytes))).map(value)
+        }
+
+        /// A string after a length prefix of `prefix` bytes.
+        fn string(&mut self, label: &str, prefix: usize) -> Option<()> {
+            let length = self.take(prefix, |bytes| format!("{} length {}", label, le_value(bytes)))?;
+            let length = le_value(length) as usize;
+            self.take(length, |bytes| format!("{} {:?}", label, String::from_utf8_lossy(bytes)))
+                .map(|_| ())
+        }
+
+        fn hidden(&mut self, label: &str, prefix: usize) -> Option<()> {
+            let length = self.take(prefix, |bytes| format!("{} length {}", label, le_value(bytes)))?;
+            let length = le_value(length) as usize;
+            self.take(length, |_| format!("{} (hidden)", label))?;
+            if let Some(field) = self.fields.last_mut() {
+                field.hidden = true;
+            }
+            Some(())
+        }
+
+        fn rest(&mut self, description: &str) {
+            if self.position < self.data.len() {
+                self.fields.push(Field {
+                    range: self.position..self.data.len(),
+                    description: description.to_string(),
+                    hidden: false,
+                });
+                self.position = self.data.len();
+            }
+        }
+
+        fn body(&mut self, query_type: QueryType, direction: Direction) -> Option<()> {
+            match (query_type, direction) {
+                (QueryType::Ping, _) => {
+                    self.take(4, |_| "ping payload".to_string())?;
+                }
+                (QueryType::Rcon, Direction::Request) => {
+                    self.hidden("password", 2)?;
+                    self.string("command", 2)?;
+                }
+                (QueryType::Rcon, Direction::Response) => self.string("line", 2)?,
+                (_, Direction::Request) => {}
+                (QueryType::Information, Direction::Response) => {
+                    self.take(1, |bytes| format!("password {}", if bytes[0] != 0 { "yes" } else { "no" }))?;
+                    self.u16("players")?;
+                    self.u16("max players")?;
+                    self.string("hostname", 4)?;
+                    self.string("gamemode", 4)?;
+                    self.string("language", 4)?;
+                }
+                (QueryType::Rules, Direction::Response) => {
+                    for _ in 0..self.u16("rule count")? {
+                        self.string("rule name", 1)?;
+                        self.string("rule value", 1)?;
+                    }
+                }
+                (QueryType::ClientList, Direction::Response) => {
+                    for _ in 0..self.u16("player count")? {
+                        self.string("name", 1)?;
+                        self.i32("score")?;
+                    }
+                }
+                (QueryType::DetailedPlayerInfo, Direction::Response) => {
+                    for _ in 0..self.u16("player count")? {
+                        self.u8("player id")?;
+                        self.string("name", 1)?;
+                        self.i32("score")?;
+                        self.u32("ping")?;
+                    }
+                }
+            }
+            Some(())
+        }
+    }
+
+    fn le_value(bytes: &[u8]) -> u64 {
+        bytes.iter().rev().fold(0, |value, byte| value << 8 | *byte as u64)
+    }
+
+    /// Splits `data` into the header fields and the body fields of its
+    /// opcode. Packets without the `SAMP` signature are one unknown field.
+    pub fn fields(data: &[u8], direction: Direction) -> Vec<Field> {
+        let mut fields = Fields {
+            data,
+            position: 0,
+            fields: Vec::new(),
+        };
+        if !data.starts_with(constants::SAMP_SIGNATURE) {
+            fields.rest("not a SA-MP query packet");
+            return fields.fields;
+        }
+
+        fields.take(4, |_| "signature \"SAMP\"".to_string());
+        fields.take(4, |bytes| format!("server IP {}.{}.{}.{}", bytes[0], bytes[1], bytes[2], bytes[3]));
+        fields.u16("server port");
+        let opcode = fields.take(1, |bytes| match QueryType::from_opcode(bytes[0]) {
+            Some(query_type) => format!("opcode '{}' ({})", bytes[0] as char, query_type),
+            None => format!("unknown opcode {:#04x}", bytes[0]),
+        });
+        if let Some(query_type) = opcode.and_then(|opcode| QueryType::from_opcode(opcode[0])) {
+            if fields.body(query_type, direction).is_some() {
+                fields.rest("trailing bytes");
+            }
+        } else {
+            fields.rest("body");
+        }
+        fields.fields
+    }
+
+    /// Formats `data` as a hexdump with one line per field, or a few for
+    /// fields longer than a line. Hidden fields are shown as `**`.
+    pub fn hexdump(data: &[u8], direction: Direction) -> String {
+        let fields = fields(data, direction);
+        let widest = fields
+            .iter()
+            .map(|field| field.range.len().min(BYTES_PER_LINE))
+            .max()
+            .unwrap_or(0);
+
+        let mut output = String::new();
+        for field in &fields {
+            // Empty fields, such as a blank hostname, still get a line.
+            let mut starts: Vec<usize> = field.range.clone().step_by(BYTES_PER_LINE).collect();
+            if starts.is_empty() {
+                starts.push(field.range.start);
+            }
+            for (index, start) in starts.into_iter().enumerate() {
+                let end = (start + BYTES_PER_LINE).min(field.range.end);
+                let hex: Vec<String> = data[start..end]
+                    .iter()
+                    .map(|byte| if field.hidden { "**".to_string() } else { format!("{:02x}", byte) })
+                    .collect();
+                let description = if index == 0 { field.description.as_str() } else { "" };
+                let line = format!("{:04x}  {:<width$}  {}", start, hex.join(" "), description, width = (widest * 3).saturating_sub(1));
+                let _ = writeln!(output, "{}", line.trim_end());
+            }
+        }
+        output
+    }
 }
 
 pub mod utils {
@@ -144,6 +604,51 @@ pub mod utils {
     use bytes::Buf;
     use std::io::{Cursor, Read};
 
+    fn ensure_remaining<B: AsRef<[u8]>>(cursor: &Cursor<B>, needed: usize) -> Result<()>
+    where
+        Cursor<B>: Buf,
+    {
+        if cursor.remaining() < needed {
+            return Err(Error::InvalidResponse(
+                "Response packet is truncated".to_string(),
+            ));
+        }
+
+        Ok(())
+    }
+
+    pub fn read_u8<B: AsRef<[u8]>>(cursor: &mut Cursor<B>) -> Result<u8>
+    where
+        Cursor<B>: Buf,
+    {
+        ensure_remaining(cursor, 1)?;
+        Ok(cursor.get_u8())
+    }
+
+    pub fn read_u16_le<B: AsRef<[u8]>>(cursor: &mut Cursor<B>) -> Result<u16>
+    where
+        Cursor<B>: Buf,
+    {
+        ensure_remaining(cursor, 2)?;
+        Ok(cursor.get_u16_le())
+    }
+
+    pub fn read_u32_le<B: AsRef<[u8]>>(cursor: &mut Cursor<B>) -> Result<u32>
+    where
+        Cursor<B>: Buf,
+    {
+        ensure_remaining(cursor, 4)?;
+        Ok(cursor.get_u32_le())
+    }
+
+    pub fn read_i32_le<B: AsRef<[u8]>>(cursor: &mut Cursor<B>) -> Result<i32>
+    where
+        Cursor<B>: Buf,
+    {
+        ensure_remaining(cursor, 4)?;
+        Ok(cursor.get_i32_le())
+    }
+
     pub fn read_string<B: AsRef<[u8]>>(cursor: &mut Cursor<B>) -> Result<String> {
         let mut bytes = Vec::new();
         let mut byte = [0u8; 1];
@@ -162,7 +667,7 @@ pub mod utils {
     where
         Cursor<B>: Buf,
     {
-        let length = cursor.get_u8() as usize;
+        let length = read_u8(cursor)? as usize;
         
         if length > constants::MAX_PACKET_SIZE {
             return Err(Error::InvalidResponse(
@@ -180,7 +685,7 @@ pub mod utils {
     where
         Cursor<B>: Buf,
     {
-        let length = cursor.get_u16_le() as usize;
+        let length = read_u16_le(cursor)? as usize;
         
         if length > constants::MAX_PACKET_SIZE {
             return Err(Error::InvalidResponse(
@@ -198,7 +703,7 @@ pub mod utils {
     where
         Cursor<B>: Buf,
     {
-        let length = cursor.get_u32_le() as usize;
+        let length = read_u32_le(cursor)? as usize;
         
         if length > constants::MAX_PACKET_SIZE {
             return Err(Error::InvalidResponse(
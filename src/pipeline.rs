@@ -0,0 +1,120 @@
+//! Streaming bulk queries.
+//!
+//! [`query`] turns a stream of addresses into a stream of results. At most
+//! `max_in_flight` queries (and therefore sockets) are open at once, and new
+//! addresses are only pulled from the input when a slot frees up and the
+//! consumer asks for more, so a slow consumer slows the queries down instead
+//! of buffering results. That keeps memory and socket use flat even when
+//! the input is a full masterlist.
+//!
+//! ```rust,no_run
+//! use futures::{stream, StreamExt};
+//! use samp_query::pipeline::{self, PipelineConfig};
+//! use std::net::SocketAddr;
+//!
+//! # async fn run(addresses: Vec<SocketAddr>) {
+//! let config = PipelineConfig {
+//!     max_in_flight: 512,
+//!     ..PipelineConfig::default()
+//! };
+//!
+//! let mut results = pipeline::info(stream::iter(addresses), config);
+//! while let Some((addr, result)) = results.next().await {
+//!     match result {
+//!         Ok(info) => println!("{} {}", addr, info.hostname),
+//!         Err(e) => eprintln!("{} {}", addr, e),
+//!     }
+//! }
+//! # }
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::error::Result;
+use crate::ratelimit::RateLimiter;
+use crate::types::{ServerInfo, ServerSnapshot};
+use futures::stream::{BoxStream, Stream, StreamExt};
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+
+#[derive(Debug, Clone)]
+pub struct PipelineConfig {
+    pub client: ClientConfig,
+    /// Maximum number of queries running at the same time.
+    pub max_in_flight: usize,
+    /// Whether results keep the order of the input. Unordered results are
+    /// yielded as soon as they arrive; ordered results may wait behind a
+    /// slow server, which also holds back new queries.
+    pub ordered: bool,
+    /// Paces the start of every query, e.g. a limiter shared with other components.
+    pub limiter: Option<RateLimiter>,
+}
+
+impl Default for PipelineConfig {
+    fn default() -> Self {
+        Self {
+            client: ClientConfig::default(),
+            max_in_flight: 128,
+            ordered: false,
+            limiter: None,
+        }
+    }
+}
+
+/// Runs `query` against every address of the input stream.
+///
+/// A client is connected for each address and dropped once its query is
+/// done. Connection failures are reported as that address's result.
+pub fn query<'a, S, F, Fut, T>(
+    addresses: S,
+    config: PipelineConfig,
+    query: F,
+) -> BoxStream<'a, (SocketAddr, Result<T>)>
+where
+    S: Stream<Item = SocketAddr> + Send + 'a,
+    F: Fn(Client) -> Fut + Send + Sync + 'a,
+    Fut: Future<Output = Result<T>> + Send + 'a,
+    T: Send + 'a,
+{
+    let client_config = config.client;
+    let limiter = config.limiter;
+    let query = Arc::new(query);
+    let futures = addresses.map(move |addr| {
+        let client_config = client_config.clone();
+        let limiter = limiter.clone();
+        let query = query.clone();
+        async move {
+            if let Some(limiter) = limiter {
+                limiter.acquire(addr).await;
+            }
+            let result = match Client::connect_with_config(addr, client_config).await {
+                Ok(client) => query(client).await,
+                Err(e) => Err(e),
+            };
+            (addr, result)
+        }
+    });
+
+    let limit = config.max_in_flight.max(1);
+    if config.ordered {
+        futures.buffered(limit).boxed()
+    } else {
+        futures.buffer_unordered(limit).boxed()
+    }
+}
+
+/// Queries the server information of every address.
+pub fn info<'a, S>(addresses: S, config: PipelineConfig) -> BoxStream<'a, (SocketAddr, Result<ServerInfo>)>
+where
+    S: Stream<Item = SocketAddr> + Send + 'a,
+{
+    query(addresses, config, |client| async move { client.query_info().await })
+}
+
+/// Takes a full [`ServerSnapshot`] of every address.
+pub fn snapshots<'a, S>(addresses: S, config: PipelineConfig) -> BoxStream<'a, (SocketAddr, Result<ServerSnapshot>)>
+where
+    S: Stream<Item = SocketAddr> + Send + 'a,
+{
+    query(addresses, config, |client| async move { client.snapshot().await })
+}
@@ -0,0 +1,160 @@
+//! Sorting and filtering player lists.
+//!
+//! [`PlayerFilter`] keeps the players matching a minimum score and a name
+//! pattern, orders them and cuts the list down to the first few. It works on
+//! [`Player`], [`DetailedPlayer`] and [`GamePlayer`] alike.
+//!
+//! ```
+//! use samp_query::players::{PlayerFilter, PlayerSort};
+//! use samp_query::Player;
+//!
+//! let mut players = vec![
+//!     Player { name: "Carl_Johnson".to_string(), score: 120 },
+//!     Player { name: "Big_Smoke".to_string(), score: 80 },
+//!     Player { name: "Sweet".to_string(), score: 5 },
+//! ];
+//!
+//! let filter = PlayerFilter {
+//!     sort: Some(PlayerSort::Score),
+//!     descending: true,
+//!     min_score: Some(10),
+//!     top: Some(1),
+//!     ..Default::default()
+//! };
+//! filter.apply(&mut players);
+//! assert_eq!(players[0].name, "Carl_Johnson");
+//! assert_eq!(players.len(), 1);
+//! ```
+
+use crate::error::{Error, Result};
+use crate::game::GamePlayer;
+use crate::types::{DetailedPlayer, Player};
+use regex::{Regex, RegexBuilder};
+use serde::{Deserialize, Serialize};
+use std::cmp::Ordering;
+
+/// The fields players are sorted and filtered on.
+pub trait PlayerFields {
+    fn name(&self) -> &str;
+    fn score(&self) -> Option<i64>;
+    /// Ping in milliseconds, if the list reports one.
+    fn ping_ms(&self) -> Option<u64>;
+}
+
+impl PlayerFields for Player {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn score(&self) -> Option<i64> {
+        Some(self.score as i64)
+    }
+
+    fn ping_ms(&self) -> Option<u64> {
+        None
+    }
+}
+
+impl PlayerFields for DetailedPlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn score(&self) -> Option<i64> {
+        Some(self.score as i64)
+    }
+
+    fn ping_ms(&self) -> Option<u64> {
+        Some(self.ping as u64)
+    }
+}
+
+impl PlayerFields for GamePlayer {
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn score(&self) -> Option<i64> {
+        self.score
+    }
+
+    fn ping_ms(&self) -> Option<u64> {
+        self.ping.map(|ping| ping.as_millis() as u64)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlayerSort {
+    /// Lowest score first.
+    Score,
+    /// Alphabetically, ignoring case.
+    Name,
+    /// Lowest ping first; players without a ping go last.
+    Ping,
+}
+
+impl PlayerSort {
+    /// Compares two players in ascending order.
+    pub fn compare<P: PlayerFields>(self, a: &P, b: &P) -> Ordering {
+        match self {
+            PlayerSort::Score => a.score().cmp(&b.score()),
+            PlayerSort::Name => a.name().to_lowercase().cmp(&b.name().to_lowercase()),
+            PlayerSort::Ping => a.ping_ms().unwrap_or(u64::MAX).cmp(&b.ping_ms().unwrap_or(u64::MAX)),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default)]
+pub struct PlayerFilter {
+    /// Order of the remaining players; `None` keeps the server's order.
+    pub sort: Option<PlayerSort>,
+    /// Reverse the order given by `sort`.
+    pub descending: bool,
+    /// Drop players with a lower score. Players without a score are kept.
+    pub min_score: Option<i64>,
+    /// Keep only the players whose name matches.
+    pub name: Option<Regex>,
+    /// Keep at most this many players, after sorting.
+    pub top: Option<usize>,
+}
+
+impl PlayerFilter {
+    /// Compiles `pattern` as the case-insensitive name filter.
+    pub fn name_pattern(mut self, pattern: &str) -> Result<Self> {
+        let regex = RegexBuilder::new(pattern)
+            .case_insensitive(true)
+            .build()
+            .map_err(|e| Error::Other(format!("Invalid name pattern: {}", e)))?;
+        self.name = Some(regex);
+        Ok(self)
+    }
+
+    /// Returns true if `player` passes the score and name filters.
+    pub fn matches<P: PlayerFields>(&self, player: &P) -> bool {
+        let score_ok = match (self.min_score, player.score()) {
+            (Some(min), Some(score)) => score >= min,
+            _ => true,
+        };
+        score_ok && self.name.as_ref().is_none_or(|name| name.is_match(player.name()))
+    }
+
+    /// Filters, sorts and truncates `players` in place. Sorting is stable,
+    /// so equal players keep the server's order.
+    pub fn apply<P: PlayerFields>(&self, players: &mut Vec<P>) {
+        players.retain(|player| self.matches(player));
+        if let Some(sort) = self.sort {
+            players.sort_by(|a, b| {
+                let ordering = sort.compare(a, b);
+                if self.descending {
+                    ordering.reverse()
+                } else {
+                    ordering
+                }
+            });
+        }
+        if let Some(top) = self.top {
+            players.truncate(top);
+        }
+    }
+}
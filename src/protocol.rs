@@ -94,4 +94,25 @@ pub mod constants {
     
     /// The maximum number of retries for a query.
     pub const MAX_RETRIES: usize = 3;
+
+    /// The default SA-MP server port.
+    pub const DEFAULT_PORT: u16 = 7777;
+
+    /// Minimum interval between queries to the same server that stays clear of
+    /// the default SA-MP query flood protection, in milliseconds.
+    pub const DEFAULT_QUERY_COOLDOWN_MS: u64 = 250;
+
+    /// Window over which the server counts queries from a single IP, in milliseconds.
+    pub const QUERY_FLOOD_WINDOW_MS: u64 = 1000;
+
+    /// Upper bound for an advised query interval, in milliseconds.
+    pub const MAX_QUERY_INTERVAL_MS: u64 = 10_000;
+
+    /// How long to wait for a further RCON output line before a command is
+    /// considered complete, in milliseconds.
+    pub const RCON_LINE_TIMEOUT_MS: u64 = 200;
+
+    /// Shortest interval of the polling loops, in milliseconds; a zero
+    /// interval in their configuration is raised to it.
+    pub const MIN_POLL_INTERVAL_MS: u64 = 1;
 }
@@ -0,0 +1,221 @@
+//! Caching query relay.
+//!
+//! [`QueryProxy`] listens on UDP and answers queries from a cached copy of an
+//! upstream server's data, refreshing it at most once per refresh interval.
+//! Point server browsers (or a public list) at the proxy and the game server
+//! only ever sees a handful of queries, no matter how hard the proxy is
+//! flooded.
+//!
+//! Once the upstream has not answered for longer than `max_stale`, the proxy
+//! stops answering too, so the server correctly shows up as offline. Ping
+//! queries are answered by the proxy itself and measure the latency to it.
+//!
+//! ```rust,no_run
+//! use samp_query::proxy::{ProxyConfig, QueryProxy};
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let config = ProxyConfig::new(
+//!     "10.0.0.5:7777".parse().unwrap(),
+//!     "0.0.0.0:7777".parse().unwrap(),
+//! );
+//!
+//! QueryProxy::bind(config).await?.run().await
+//! # }
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::error::Result;
+use crate::protocol::constants;
+use crate::server::{DataSource, Server};
+use crate::types::*;
+use std::net::SocketAddr;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, Instant};
+use tracing::{debug, warn};
+
+#[derive(Debug, Clone)]
+pub struct ProxyConfig {
+    /// Server whose data is relayed.
+    pub upstream: SocketAddr,
+    /// Address the proxy answers queries on.
+    pub listen: SocketAddr,
+    /// Minimum time between two refreshes from the upstream.
+    pub refresh_interval: Duration,
+    /// How long cached data is served after the upstream stopped answering.
+    pub max_stale: Duration,
+    /// Client configuration used for upstream queries.
+    pub client: ClientConfig,
+}
+
+impl ProxyConfig {
+    pub fn new(upstream: SocketAddr, listen: SocketAddr) -> Self {
+        Self {
+            upstream,
+            listen,
+            refresh_interval: Duration::from_secs(5),
+            max_stale: Duration::from_secs(60),
+            client: ClientConfig::default(),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Cached {
+    info: ServerInfo,
+    rules: ServerRules,
+    players: DetailedPlayerList,
+    fetched_at: Instant,
+}
+
+/// The data source behind a [`QueryProxy`], holding the last upstream data.
+#[derive(Debug)]
+pub struct ProxySource {
+    max_stale: Duration,
+    cached: RwLock<Option<Cached>>,
+}
+
+impl ProxySource {
+    pub fn new(max_stale: Duration) -> Self {
+        Self {
+            max_stale,
+            cached: RwLock::new(None),
+        }
+    }
+
+    /// Replaces the cached data.
+    pub fn update(&self, info: ServerInfo, rules: ServerRules, players: DetailedPlayerList) {
+        *self.cached.write().unwrap() = Some(Cached {
+            info,
+            rules,
+            players,
+            fetched_at: Instant::now(),
+        });
+    }
+
+    /// When the cached data was last refreshed.
+    pub fn last_refresh(&self) -> Option<Instant> {
+        self.cached.read().unwrap().as_ref().map(|cached| cached.fetched_at)
+    }
+
+    fn read<T>(&self, f: impl FnOnce(&Cached) -> T) -> Option<T> {
+        self.cached.read().unwrap().as_ref().map(f)
+    }
+}
+
+impl DataSource for ProxySource {
+    fn info(&self) -> ServerInfo {
+        self.read(|cached| cached.info.clone()).unwrap_or_default()
+    }
+
+    fn rules(&self) -> ServerRules {
+        self.read(|cached| cached.rules.clone()).unwrap_or_default()
+    }
+
+    fn players(&self) -> DetailedPlayerList {
+        self.read(|cached| cached.players.clone()).unwrap_or_default()
+    }
+
+    fn available(&self) -> bool {
+        self.read(|cached| cached.fetched_at.elapsed() <= self.max_stale)
+            .unwrap_or(false)
+    }
+}
+
+#[derive(Debug)]
+pub struct QueryProxy {
+    config: ProxyConfig,
+    source: Arc<ProxySource>,
+    server: Server<ProxySource>,
+}
+
+impl QueryProxy {
+    /// Binds the listening socket. No upstream query is made until [`run`](Self::run)
+    /// or [`refresh`](Self::refresh) is called.
+    pub async fn bind(mut config: ProxyConfig) -> Result<Self> {
+        // A zero period makes the tokio ticker of `run` panic.
+        config.refresh_interval = config.refresh_interval.max(Duration::from_millis(constants::MIN_POLL_INTERVAL_MS));
+        let source = Arc::new(ProxySource::new(config.max_stale));
+        let server = Server::bind_shared(config.listen, source.clone()).await?;
+
+        Ok(Self {
+            config,
+            source,
+            server,
+        })
+    }
+
+    pub fn config(&self) -> &ProxyConfig {
+        &self.config
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.server.local_addr()
+    }
+
+    pub fn source(&self) -> Arc<ProxySource> {
+        self.source.clone()
+    }
+
+    /// Fetches fresh data from the upstream server.
+    ///
+    /// Only the information query must succeed; rules and players fall back
+    /// to the previously cached values.
+    pub async fn refresh(&self) -> Result<()> {
+        let client = Client::connect_with_config(self.config.upstream, self.config.client.clone()).await?;
+        let info = client.query_info().await?;
+
+        let rules = match client.query_rules().await {
+            Ok(rules) => rules,
+            Err(_) => self.source.rules(),
+        };
+        // Some servers disable the detailed player query but still answer the
+        // client list, so it is used as a fallback with zero pings.
+        let players = match client.query_detailed_player_info().await {
+            Ok(players) => players,
+            Err(_) => match client.query_client_list().await {
+                Ok(list) => DetailedPlayerList {
+                    players: list
+                        .players
+                        .into_iter()
+                        .enumerate()
+                        .map(|(id, player)| DetailedPlayer {
+                            id: id as u8,
+                            name: player.name,
+                            score: player.score,
+                            ping: 0,
+                        })
+                        .collect(),
+                },
+                Err(_) => self.source.players(),
+            },
+        };
+
+        self.source.update(info, rules, players);
+        Ok(())
+    }
+
+    /// Answers queries from the cache without refreshing it, for callers
+    /// that drive [`refresh`](Self::refresh) themselves.
+    pub async fn serve(&self) -> Result<()> {
+        self.server.run().await
+    }
+
+    /// Refreshes on the configured interval and answers queries until the socket fails.
+    pub async fn run(&self) -> Result<()> {
+        let refresh = async {
+            let mut ticker = tokio::time::interval(self.config.refresh_interval);
+            loop {
+                ticker.tick().await;
+                match self.refresh().await {
+                    Ok(()) => debug!("Refreshed {}", self.config.upstream),
+                    Err(e) => warn!("Failed to refresh {}: {}", self.config.upstream, e),
+                }
+            }
+        };
+
+        tokio::select! {
+            result = self.serve() => result,
+            _ = refresh => Ok(()),
+        }
+    }
+}
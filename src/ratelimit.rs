@@ -0,0 +1,199 @@
+//! Query rate limiting.
+//!
+//! A [`RateLimiter`] spaces out queries to stay under a global rate and,
+//! optionally, under a per-destination rate, so bulk tools do not trip the
+//! flood protection of the servers they query. Clones share their limits,
+//! so scans, aggregations, pipelines and the API server can all draw from
+//! one limiter and the etiquette holds across all of them.
+//!
+//! ```rust,no_run
+//! use samp_query::ratelimit::RateLimiter;
+//!
+//! # async fn run() {
+//! // 200 queries per second overall, 4 per second to any one server.
+//! let limiter = RateLimiter::new(200.0).per_destination(4.0);
+//!
+//! let addr = "127.0.0.1:7777".parse().unwrap();
+//! for _ in 0..10 {
+//!     limiter.acquire(addr).await;
+//!     // query addr
+//! }
+//! # }
+//! ```
+
+use crate::protocol::constants;
+use std::collections::HashMap;
+use std::net::SocketAddr;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// Per-destination rate matching the default SA-MP query flood protection.
+pub const DEFAULT_PER_DESTINATION_QPS: f64 = 1000.0 / constants::DEFAULT_QUERY_COOLDOWN_MS as f64;
+
+/// Destinations idle for this long are forgotten.
+const IDLE_EXPIRY: Duration = Duration::from_secs(60);
+
+/// A token bucket, tracked as the time its next token becomes available.
+#[derive(Debug, Clone, Copy)]
+struct Bucket {
+    interval: Duration,
+    /// How far ahead of the schedule a burst may run.
+    tolerance: Duration,
+    next: Instant,
+}
+
+impl Bucket {
+    fn new(qps: f64, burst: u32, now: Instant) -> Self {
+        let interval = Duration::from_secs_f64(1.0 / qps.max(1e-6)).max(Duration::from_nanos(1));
+        Self {
+            interval,
+            tolerance: interval * burst.max(1).saturating_sub(1),
+            next: now,
+        }
+    }
+
+    /// Earliest time the next query may go out.
+    fn earliest(&self, now: Instant) -> Instant {
+        self.next
+            .checked_sub(self.tolerance)
+            .map_or(now, |earliest| earliest.max(now))
+    }
+
+    fn take(&mut self, at: Instant) {
+        self.next = self.next.max(at) + self.interval;
+    }
+}
+
+#[derive(Debug)]
+struct Limits {
+    global: Option<Bucket>,
+    destinations: HashMap<SocketAddr, Bucket>,
+    last_prune: Instant,
+}
+
+#[derive(Debug, Clone)]
+pub struct RateLimiter {
+    /// Per-destination rate and burst.
+    destination: Option<(f64, u32)>,
+    limits: Arc<Mutex<Limits>>,
+}
+
+impl RateLimiter {
+    /// A limiter allowing `qps` queries per second overall, without bursts.
+    pub fn new(qps: f64) -> Self {
+        Self::with_burst(qps, 1)
+    }
+
+    /// A limiter allowing `qps` queries per second overall, with up to
+    /// `burst` queries at once after an idle period.
+    pub fn with_burst(qps: f64, burst: u32) -> Self {
+        Self::build(Some(Bucket::new(qps, burst, Instant::now())))
+    }
+
+    /// A limiter without a global rate, for use with [`per_destination`](Self::per_destination).
+    pub fn unlimited() -> Self {
+        Self::build(None)
+    }
+
+    fn build(global: Option<Bucket>) -> Self {
+        Self {
+            destination: None,
+            limits: Arc::new(Mutex::new(Limits {
+                global,
+                destinations: HashMap::new(),
+                last_prune: Instant::now(),
+            })),
+        }
+    }
+
+    /// Also limits every destination to `qps` queries per second.
+    pub fn per_destination(self, qps: f64) -> Self {
+        self.per_destination_with_burst(qps, 1)
+    }
+
+    pub fn per_destination_with_burst(mut self, qps: f64, burst: u32) -> Self {
+        self.destination = Some((qps, burst));
+        self
+    }
+
+    /// Waits until a query to `addr` is allowed and accounts for it.
+    pub async fn acquire(&self, addr: SocketAddr) {
+        if let Err(at) = self.reserve(Some(addr), true) {
+            tokio::time::sleep_until(at).await;
+        }
+    }
+
+    /// Waits until a query is allowed under the global rate alone, for
+    /// callers that do not query a single destination per call.
+    pub async fn acquire_global(&self) {
+        if let Err(at) = self.reserve(None, true) {
+            tokio::time::sleep_until(at).await;
+        }
+    }
+
+    /// Accounts for a query to `addr` if it is allowed right now; otherwise
+    /// returns how long until it would be, without accounting for it.
+    pub fn try_acquire(&self, addr: SocketAddr) -> Result<(), Duration> {
+        match self.reserve(Some(addr), false) {
+            Ok(_) => Ok(()),
+            Err(at) => Err(at.saturating_duration_since(Instant::now())),
+        }
+    }
+
+    /// Reserves the earliest slot. Returns `Ok` when it is now, and `Err`
+    /// with the slot otherwise; the slot is only taken when `wait` is set.
+    fn reserve(&self, addr: Option<SocketAddr>, wait: bool) -> Result<Instant, Instant> {
+        let now = Instant::now();
+        let mut limits = self.limits.lock().unwrap();
+        limits.prune(now);
+
+        let destination = match (addr, self.destination) {
+            (Some(addr), Some((qps, burst))) => Some(
+                *limits
+                    .destinations
+                    .entry(addr)
+                    .or_insert_with(|| Bucket::new(qps, burst, now)),
+            ),
+            _ => None,
+        };
+
+        let at = limits
+            .global
+            .iter()
+            .chain(destination.iter())
+            .map(|bucket| bucket.earliest(now))
+            .max()
+            .unwrap_or(now);
+        if at > now && !wait {
+            return Err(at);
+        }
+
+        if let Some(global) = &mut limits.global {
+            global.take(at);
+        }
+        if let (Some(addr), Some(_)) = (addr, destination) {
+            if let Some(bucket) = limits.destinations.get_mut(&addr) {
+                bucket.take(at);
+            }
+        }
+
+        if at > now {
+            Err(at)
+        } else {
+            Ok(at)
+        }
+    }
+}
+
+impl Limits {
+    fn prune(&mut self, now: Instant) {
+        if now.saturating_duration_since(self.last_prune) < IDLE_EXPIRY {
+            return;
+        }
+
+        self.last_prune = now;
+        self.destinations
+            .retain(|_, bucket| bucket.next + IDLE_EXPIRY > now);
+    }
+}
@@ -0,0 +1,312 @@
+//! RCON sessions and scripts.
+//!
+//! An [`RconSession`] runs commands against one server with a fixed
+//! password and returns each command's output lines. A [`Script`] is a list
+//! of commands read from a file, with `${name}` variables, pauses and
+//! per-command error handling, run through a session:
+//!
+//! ```text
+//! # Weekly maintenance
+//! @delay 500ms
+//! say Server restarting in 1 minute, ${player}!
+//! @wait 60s
+//! -kick ${player}
+//! gmx
+//! ```
+//!
+//! - `@delay <duration>` sets the pause after every following command.
+//! - `@wait <duration>` pauses once.
+//! - A leading `-` lets the script continue when that command fails.
+//!
+//! Durations are written as `500ms`, `2s` or `1m`.
+//!
+//! ```rust,no_run
+//! use samp_query::rcon::{RconSession, Script};
+//! use std::collections::HashMap;
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let session = RconSession::connect("127.0.0.1:7777".parse().unwrap(), "changeme").await?;
+//! let script = Script::load("maintenance.rcon")?;
+//! let vars = HashMap::from([("player".to_string(), "Carl_Johnson".to_string())]);
+//!
+//! let report = script.run(&session, &vars).await?;
+//! for output in &report.outputs {
+//!     println!("> {}\n{}", output.command, output.lines.join("\n"));
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::Client;
+#[cfg(feature = "net")]
+use crate::client::ClientConfig;
+use crate::error::{Error, Result};
+use crate::protocol::constants;
+use crate::utils;
+use std::collections::HashMap;
+#[cfg(feature = "net")]
+use std::net::SocketAddr;
+use std::path::Path;
+use std::time::Duration;
+
+/// Runs RCON commands against one server.
+#[derive(Debug)]
+pub struct RconSession {
+    client: Client,
+    password: String,
+    line_timeout: Duration,
+}
+
+impl RconSession {
+    pub fn new(client: Client, password: &str) -> Self {
+        Self {
+            client,
+            password: password.to_string(),
+            line_timeout: Duration::from_millis(constants::RCON_LINE_TIMEOUT_MS),
+        }
+    }
+
+    #[cfg(feature = "net")]
+    pub async fn connect(addr: SocketAddr, password: &str) -> Result<Self> {
+        Ok(Self::new(Client::connect_with_config(addr, ClientConfig::default()).await?, password))
+    }
+
+    /// Sets how long to wait for further output lines before a command is
+    /// considered complete. Slow links may need more than the default.
+    pub fn with_line_timeout(mut self, line_timeout: Duration) -> Self {
+        self.line_timeout = line_timeout;
+        self
+    }
+
+    pub fn client(&self) -> &Client {
+        &self.client
+    }
+
+    /// Runs a command and returns its output lines, which may be empty.
+    ///
+    /// Fails with [`Error::RconAuthFailed`] on a wrong password and with
+    /// [`Error::ServerError`] when the server does not know the command.
+    pub async fn command(&self, command: &str) -> Result<Vec<String>> {
+        let lines = self
+            .client
+            .rcon_lines(&self.password, command, self.line_timeout)
+            .await?;
+
+        match lines.first() {
+            Some(line) if line.starts_with("Unknown command or variable") => Err(Error::ServerError(lines.join(" "))),
+            _ => Ok(lines),
+        }
+    }
+}
+
+/// One step of a [`Script`].
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Step {
+    Command {
+        command: String,
+        /// Keep running the script when this command fails.
+        ignore_errors: bool,
+        /// Pause after the command.
+        delay: Duration,
+    },
+    Wait(Duration),
+}
+
+/// The captured result of one command.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CommandOutput {
+    /// The command as sent, with variables substituted.
+    pub command: String,
+    pub lines: Vec<String>,
+    pub error: Option<String>,
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ScriptReport {
+    /// Outputs of the commands that ran, in order.
+    pub outputs: Vec<CommandOutput>,
+    /// Whether a failing command stopped the script early.
+    pub aborted: bool,
+}
+
+impl ScriptReport {
+    /// Whether every command ran and none failed.
+    pub fn is_success(&self) -> bool {
+        !self.aborted && self.outputs.iter().all(|output| output.error.is_none())
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Script {
+    steps: Vec<Step>,
+}
+
+impl Script {
+    pub fn new(steps: Vec<Step>) -> Self {
+        Self { steps }
+    }
+
+    /// Parses a script; see the [module documentation](self) for the format.
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut steps = Vec::new();
+        let mut delay = Duration::ZERO;
+
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let invalid = |e: Error| Error::Other(format!("Script line {}: {}", number + 1, e));
+            if let Some(directive) = line.strip_prefix('@') {
+                let (name, value) = directive.split_once(char::is_whitespace).unwrap_or((directive, ""));
+                match name {
+                    "delay" => delay = parse_duration(value).map_err(invalid)?,
+                    "wait" => steps.push(Step::Wait(parse_duration(value).map_err(invalid)?)),
+                    _ => return Err(invalid(Error::Other(format!("unknown directive @{}", name)))),
+                }
+                continue;
+            }
+
+            let (command, ignore_errors) = match line.strip_prefix('-') {
+                Some(command) => (command.trim_start(), true),
+                None => (line, false),
+            };
+            if command.is_empty() {
+                return Err(invalid(Error::Other("empty command".to_string())));
+            }
+            steps.push(Step::Command {
+                command: command.to_string(),
+                ignore_errors,
+                delay,
+            });
+        }
+
+        Ok(Self { steps })
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::parse(&std::fs::read_to_string(path)?)
+    }
+
+    pub fn steps(&self) -> &[Step] {
+        &self.steps
+    }
+
+    /// Names of the variables the script uses.
+    pub fn variables(&self) -> Vec<String> {
+        let mut names = Vec::new();
+        for step in &self.steps {
+            if let Step::Command { command, .. } = step {
+                let mut rest = command.as_str();
+                while let Some((name, after)) = next_variable(rest) {
+                    if !names.iter().any(|n| n == name) {
+                        names.push(name.to_string());
+                    }
+                    rest = after;
+                }
+            }
+        }
+        names
+    }
+
+    /// The script with every `${name}` replaced from `vars`.
+    ///
+    /// Fails on the first variable without a value, so a script never runs
+    /// half-substituted.
+    pub fn render(&self, vars: &HashMap<String, String>) -> Result<Self> {
+        let steps = self
+            .steps
+            .iter()
+            .map(|step| match step {
+                Step::Command {
+                    command,
+                    ignore_errors,
+                    delay,
+                } => Ok(Step::Command {
+                    command: substitute(command, vars)?,
+                    ignore_errors: *ignore_errors,
+                    delay: *delay,
+                }),
+                Step::Wait(duration) => Ok(Step::Wait(*duration)),
+            })
+            .collect::<Result<_>>()?;
+
+        Ok(Self { steps })
+    }
+
+    /// Renders the script and runs it through `session`.
+    ///
+    /// A failing command stops the script unless it is marked with `-`;
+    /// its error is captured in the report either way. Only rendering
+    /// errors are returned as `Err`, before anything is sent.
+    pub async fn run(&self, session: &RconSession, vars: &HashMap<String, String>) -> Result<ScriptReport> {
+        let script = self.render(vars)?;
+        let mut report = ScriptReport::default();
+
+        for step in &script.steps {
+            match step {
+                Step::Wait(duration) => tokio::time::sleep(*duration).await,
+                Step::Command {
+                    command,
+                    ignore_errors,
+                    delay,
+                } => {
+                    let (lines, error) = match session.command(command).await {
+                        Ok(lines) => (lines, None),
+                        Err(e) => (Vec::new(), Some(e.to_string())),
+                    };
+                    let failed = error.is_some();
+                    report.outputs.push(CommandOutput {
+                        command: command.clone(),
+                        lines,
+                        error,
+                    });
+
+                    if failed && !ignore_errors {
+                        report.aborted = true;
+                        break;
+                    }
+                    if !delay.is_zero() {
+                        tokio::time::sleep(*delay).await;
+                    }
+                }
+            }
+        }
+
+        Ok(report)
+    }
+}
+
+/// Finds the next `${name}` in `text`, returning the name and the text after it.
+fn next_variable(text: &str) -> Option<(&str, &str)> {
+    let start = text.find("${")?;
+    let after = &text[start + 2..];
+    let end = after.find('}')?;
+
+    Some((&after[..end], &after[end + 1..]))
+}
+
+fn substitute(command: &str, vars: &HashMap<String, String>) -> Result<String> {
+    let mut output = String::with_capacity(command.len());
+    let mut rest = command;
+
+    while let Some(start) = rest.find("${") {
+        let Some((name, after)) = next_variable(rest) else {
+            break;
+        };
+        let value = vars
+            .get(name)
+            .ok_or_else(|| Error::Other(format!("Undefined script variable: {}", name)))?;
+        output.push_str(&rest[..start]);
+        output.push_str(value);
+        rest = after;
+    }
+    output.push_str(rest);
+
+    Ok(output)
+}
+
+fn parse_duration(value: &str) -> Result<Duration> {
+    utils::parse_duration(value).map_err(|e| Error::Other(format!("Invalid duration: {}", e)))
+}
@@ -0,0 +1,313 @@
+//! Session recording and deterministic replay.
+//!
+//! A [`Recorder`] wraps any [`Transport`] and logs every datagram sent and
+//! received, with its time relative to the start of the session. The
+//! resulting [`Session`] serializes to JSON, so a user can attach it to a bug
+//! report, and [`ReplayTransport`] plays it back through a regular
+//! [`Client`]: each request must match the recorded one, and responses are
+//! delivered at their recorded offsets.
+//!
+//! Replay waits on tokio's clock, so under a paused runtime
+//! (`#[tokio::test(start_paused = true)]`) the session runs in virtual time:
+//! instantly, yet with the same timeouts, retries and pings as the original
+//! (to the millisecond resolution of tokio's timer).
+//!
+//! ```rust,no_run
+//! use samp_query::record::{Recorder, Session};
+//! use samp_query::{Client, ClientConfig};
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let addr = "127.0.0.1:7777".parse().unwrap();
+//! let recorder = Recorder::connect(addr).await?;
+//! let log = recorder.log();
+//!
+//! let client = Client::with_transport(addr, recorder, ClientConfig::default());
+//! let _ = client.query_info().await;
+//! log.session().save("session.json")?;
+//!
+//! let replayed = Session::load("session.json")?.client(ClientConfig::default());
+//! println!("{}", replayed.query_info().await?);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::error::{Error, Result};
+use crate::protocol::constants::HEADER_SIZE;
+use crate::transport::Transport;
+use async_trait::async_trait;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tokio::time::Instant;
+
+/// One datagram of a recorded session.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(tag = "direction", rename_all = "snake_case")]
+pub enum SessionEvent {
+    Sent {
+        /// Microseconds since the start of the session.
+        at_us: u64,
+        #[serde(with = "hex_bytes")]
+        data: Vec<u8>,
+    },
+    Received {
+        at_us: u64,
+        #[serde(with = "hex_bytes")]
+        data: Vec<u8>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Session {
+    /// The server the session talked to; it is embedded in every request.
+    pub server: SocketAddr,
+    pub events: Vec<SessionEvent>,
+}
+
+impl Session {
+    pub fn to_json(&self) -> Result<String> {
+        serde_json::to_string_pretty(self).map_err(|e| Error::Other(e.to_string()))
+    }
+
+    pub fn from_json(json: &str) -> Result<Self> {
+        serde_json::from_str(json).map_err(|e| Error::Other(format!("Invalid session: {}", e)))
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        std::fs::write(path, self.to_json()?)?;
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_json(&std::fs::read_to_string(path)?)
+    }
+
+    /// A client that replays this session.
+    pub fn client(&self, config: ClientConfig) -> Client {
+        Client::with_transport(self.server, ReplayTransport::new(self.clone()), config)
+    }
+}
+
+mod hex_bytes {
+    use serde::{de::Error, Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(data: &[u8], serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&data.iter().map(|b| format!("{:02x}", b)).collect::<String>())
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Vec<u8>, D::Error> {
+        let hex = String::deserialize(deserializer)?;
+        if hex.len() % 2 != 0 {
+            return Err(D::Error::custom("odd number of hex digits"));
+        }
+
+        hex.as_bytes()
+            .chunks(2)
+            .map(|pair| {
+                std::str::from_utf8(pair)
+                    .ok()
+                    .and_then(|pair| u8::from_str_radix(pair, 16).ok())
+                    .ok_or_else(|| D::Error::custom(format!("invalid hex digits {:?}", String::from_utf8_lossy(pair))))
+            })
+            .collect()
+    }
+}
+
+/// Shared view of the events logged by a [`Recorder`].
+#[derive(Debug, Clone)]
+pub struct SessionLog {
+    server: SocketAddr,
+    started: Instant,
+    events: Arc<Mutex<Vec<SessionEvent>>>,
+}
+
+impl SessionLog {
+    /// The session recorded so far.
+    pub fn session(&self) -> Session {
+        Session {
+            server: self.server,
+            events: self.events.lock().unwrap().clone(),
+        }
+    }
+
+    fn push(&self, event: impl FnOnce(u64) -> SessionEvent) {
+        let at_us = self.started.elapsed().as_micros() as u64;
+        self.events.lock().unwrap().push(event(at_us));
+    }
+}
+
+/// A transport that records the traffic of the transport it wraps.
+#[derive(Debug)]
+pub struct Recorder<T> {
+    inner: T,
+    log: SessionLog,
+}
+
+impl<T: Transport> Recorder<T> {
+    pub fn new(server: SocketAddr, inner: T) -> Self {
+        Self {
+            inner,
+            log: SessionLog {
+                server,
+                started: Instant::now(),
+                events: Arc::new(Mutex::new(Vec::new())),
+            },
+        }
+    }
+
+    /// A handle to the recording that stays usable after the recorder has
+    /// been moved into a client.
+    pub fn log(&self) -> SessionLog {
+        self.log.clone()
+    }
+}
+
+#[cfg(feature = "net")]
+impl Recorder<crate::transport::UdpTransport> {
+    /// Records a UDP session with `server`.
+    pub async fn connect(server: SocketAddr) -> Result<Self> {
+        Ok(Self::new(server, crate::transport::UdpTransport::connect(server).await?))
+    }
+}
+
+#[async_trait]
+impl<T: Transport> Transport for Recorder<T> {
+    async fn send(&self, data: &[u8]) -> Result<()> {
+        self.log.push(|at_us| SessionEvent::Sent {
+            at_us,
+            data: data.to_vec(),
+        });
+        self.inner.send(data).await
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        let size = self.inner.recv(buf).await?;
+        self.log.push(|at_us| SessionEvent::Received {
+            at_us,
+            data: buf[..size].to_vec(),
+        });
+        Ok(size)
+    }
+}
+
+#[derive(Debug)]
+struct ReplayState {
+    events: VecDeque<SessionEvent>,
+    /// Responses released by the requests sent so far, with their offsets.
+    responses: VecDeque<(u64, Vec<u8>)>,
+    /// Maps session offsets to the replay clock; moved at every request.
+    anchor: Instant,
+    /// Payload of the last ping request, copied into ping responses.
+    ping_payload: Option<[u8; 4]>,
+}
+
+/// A transport that plays back a recorded [`Session`].
+///
+/// Ping requests carry random bytes that the server echoes, so those four
+/// bytes are exempt from request matching and patched into the responses.
+#[derive(Debug)]
+pub struct ReplayTransport {
+    state: Mutex<ReplayState>,
+}
+
+fn is_ping(data: &[u8]) -> bool {
+    data.len() >= HEADER_SIZE + 4 && data[HEADER_SIZE - 1] == b'p'
+}
+
+impl ReplayTransport {
+    pub fn new(session: Session) -> Self {
+        Self {
+            state: Mutex::new(ReplayState {
+                events: session.events.into(),
+                responses: VecDeque::new(),
+                anchor: Instant::now(),
+                ping_payload: None,
+            }),
+        }
+    }
+
+    /// Whether every recorded request has been replayed.
+    pub fn is_finished(&self) -> bool {
+        let state = self.state.lock().unwrap();
+        !state
+            .events
+            .iter()
+            .any(|event| matches!(event, SessionEvent::Sent { .. }))
+    }
+}
+
+#[async_trait]
+impl Transport for ReplayTransport {
+    async fn send(&self, data: &[u8]) -> Result<()> {
+        let mut state = self.state.lock().unwrap();
+        while let Some(SessionEvent::Received { at_us, data }) = state.events.front().cloned() {
+            // Responses recorded before this request; release them as well.
+            state.events.pop_front();
+            state.responses.push_back((at_us, data));
+        }
+
+        let Some(SessionEvent::Sent { at_us, data: expected }) = state.events.pop_front() else {
+            return Err(Error::Other("Replay session has no more requests".to_string()));
+        };
+
+        let matches = if is_ping(data) && is_ping(&expected) {
+            data[..HEADER_SIZE] == expected[..HEADER_SIZE] && data.len() == expected.len()
+        } else {
+            data == expected.as_slice()
+        };
+        if !matches {
+            return Err(Error::Other(format!(
+                "Replay diverged at {} us: request does not match the recording",
+                at_us
+            )));
+        }
+
+        let now = Instant::now();
+        state.anchor = now.checked_sub(Duration::from_micros(at_us)).unwrap_or(now);
+        state.ping_payload = is_ping(data)
+            .then(|| [data[HEADER_SIZE], data[HEADER_SIZE + 1], data[HEADER_SIZE + 2], data[HEADER_SIZE + 3]]);
+        while let Some(SessionEvent::Received { at_us, data }) = state.events.front().cloned() {
+            state.events.pop_front();
+            state.responses.push_back((at_us, data));
+        }
+
+        Ok(())
+    }
+
+    async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+        let due = {
+            let state = self.state.lock().unwrap();
+            state
+                .responses
+                .front()
+                .map(|(at_us, _)| state.anchor + Duration::from_micros(*at_us))
+        };
+        // Without a recorded response the request timed out, so never answer.
+        let Some(due) = due else {
+            return std::future::pending().await;
+        };
+        tokio::time::sleep_until(due).await;
+
+        let response = {
+            let mut state = self.state.lock().unwrap();
+            let ping_payload = state.ping_payload;
+            state.responses.pop_front().map(|(_, mut data)| {
+                if let Some(payload) = ping_payload.filter(|_| is_ping(&data)) {
+                    data[HEADER_SIZE..HEADER_SIZE + 4].copy_from_slice(&payload);
+                }
+                data
+            })
+        };
+        let Some(data) = response else {
+            return std::future::pending().await;
+        };
+
+        let size = data.len().min(buf.len());
+        buf[..size].copy_from_slice(&data[..size]);
+        Ok(size)
+    }
+}
@@ -0,0 +1,312 @@
+//! Caching hostname resolution.
+//!
+//! A [`Resolver`] caches lookups for as long as their TTL allows, and caches
+//! failures for a shorter negative TTL, so workloads that query the same
+//! hostnames over and over (monitors, the API server, favorites lists) do
+//! not send a DNS request per query. Clones share one cache, and
+//! [`Resolver::global`] is the instance used by [`Client::connect_host`],
+//! the masterlist clients and the API server.
+//!
+//! The system resolver does not report TTLs, so its answers are kept for
+//! [`ResolverConfig::default_ttl`]. With the `hickory` feature,
+//! [`Resolver::hickory`] talks to the system's name servers directly and
+//! honours the record TTLs.
+//!
+//! ```rust,no_run
+//! use samp_query::resolver::Resolver;
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let resolver = Resolver::global();
+//! let (canonical, addr) = resolver.resolve_address("play.example.com:7777", 7777).await?;
+//! println!("{} is {}", canonical, addr);
+//! # Ok(())
+//! # }
+//! ```
+//!
+//! [`Client::connect_host`]: crate::Client::connect_host
+
+use crate::error::{Error, Result};
+use crate::utils::{join_host_port, split_host_port};
+use async_trait::async_trait;
+use std::collections::HashMap;
+use std::net::{IpAddr, SocketAddr};
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::Duration;
+use tokio::time::Instant;
+use tracing::debug;
+
+#[derive(Debug, Clone)]
+pub struct ResolverConfig {
+    /// How long answers without a TTL are cached.
+    pub default_ttl: Duration,
+    /// How long failed lookups are cached.
+    pub negative_ttl: Duration,
+    /// Bounds applied to record TTLs.
+    pub min_ttl: Duration,
+    pub max_ttl: Duration,
+    /// Maximum number of cached hostnames; expired entries are evicted first.
+    pub max_entries: usize,
+}
+
+impl Default for ResolverConfig {
+    fn default() -> Self {
+        Self {
+            default_ttl: Duration::from_secs(60),
+            negative_ttl: Duration::from_secs(10),
+            min_ttl: Duration::from_secs(5),
+            max_ttl: Duration::from_secs(60 * 60),
+            max_entries: 10_000,
+        }
+    }
+}
+
+/// A successful lookup.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Lookup {
+    pub addrs: Vec<IpAddr>,
+    /// How long the answer stays valid, if the backend knows.
+    pub ttl: Option<Duration>,
+}
+
+/// Performs uncached lookups for a [`Resolver`].
+#[async_trait]
+pub trait ResolverBackend: Send + Sync {
+    async fn lookup(&self, host: &str) -> Result<Lookup>;
+}
+
+#[async_trait]
+impl<B: ResolverBackend + ?Sized> ResolverBackend for Arc<B> {
+    async fn lookup(&self, host: &str) -> Result<Lookup> {
+        (**self).lookup(host).await
+    }
+}
+
+/// The operating system's resolver, through `getaddrinfo`.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SystemBackend;
+
+#[async_trait]
+impl ResolverBackend for SystemBackend {
+    async fn lookup(&self, host: &str) -> Result<Lookup> {
+        let addrs = tokio::net::lookup_host((host, 0))
+            .await
+            .map_err(|e| Error::Resolve(format!("{}: {}", host, e)))?
+            .map(|addr| addr.ip())
+            .collect();
+
+        Ok(Lookup { addrs, ttl: None })
+    }
+}
+
+#[cfg(feature = "hickory")]
+#[async_trait]
+impl ResolverBackend for hickory_resolver::TokioAsyncResolver {
+    async fn lookup(&self, host: &str) -> Result<Lookup> {
+        let lookup = self
+            .lookup_ip(host)
+            .await
+            .map_err(|e| Error::Resolve(format!("{}: {}", host, e)))?;
+        let ttl = lookup
+            .valid_until()
+            .saturating_duration_since(std::time::Instant::now());
+
+        Ok(Lookup {
+            addrs: lookup.iter().collect(),
+            ttl: Some(ttl),
+        })
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Entry {
+    /// The addresses, or the error message of a failed lookup.
+    result: std::result::Result<Vec<IpAddr>, String>,
+    expires: Instant,
+}
+
+struct Inner {
+    config: ResolverConfig,
+    backend: Box<dyn ResolverBackend>,
+    cache: Mutex<HashMap<String, Entry>>,
+}
+
+#[derive(Clone)]
+pub struct Resolver {
+    inner: Arc<Inner>,
+}
+
+impl std::fmt::Debug for Resolver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Resolver")
+            .field("config", &self.inner.config)
+            .field("entries", &self.len())
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Resolver {
+    fn default() -> Self {
+        Self::new(ResolverConfig::default())
+    }
+}
+
+impl Resolver {
+    /// A resolver using the system resolver.
+    pub fn new(config: ResolverConfig) -> Self {
+        Self::with_backend(config, SystemBackend)
+    }
+
+    pub fn with_backend<B: ResolverBackend + 'static>(config: ResolverConfig, backend: B) -> Self {
+        Self {
+            inner: Arc::new(Inner {
+                config,
+                backend: Box::new(backend),
+                cache: Mutex::new(HashMap::new()),
+            }),
+        }
+    }
+
+    /// A resolver using hickory with the system's name server configuration.
+    #[cfg(feature = "hickory")]
+    pub fn hickory(config: ResolverConfig) -> Result<Self> {
+        let backend = hickory_resolver::TokioAsyncResolver::tokio_from_system_conf()
+            .map_err(|e| Error::Resolve(e.to_string()))?;
+        Ok(Self::with_backend(config, backend))
+    }
+
+    /// The process-wide resolver, created with the default config on first use.
+    pub fn global() -> &'static Resolver {
+        static GLOBAL: OnceLock<Resolver> = OnceLock::new();
+        GLOBAL.get_or_init(Resolver::default)
+    }
+
+    pub fn config(&self) -> &ResolverConfig {
+        &self.inner.config
+    }
+
+    /// Number of cached hostnames, including expired ones not yet evicted.
+    pub fn len(&self) -> usize {
+        self.inner.cache.lock().unwrap().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    pub fn clear(&self) {
+        self.inner.cache.lock().unwrap().clear();
+    }
+
+    /// All addresses of `host`. IP literals are returned as they are.
+    pub async fn lookup(&self, host: &str) -> Result<Vec<IpAddr>> {
+        if let Ok(ip) = host.parse::<IpAddr>() {
+            return Ok(vec![ip]);
+        }
+
+        let key = host.to_ascii_lowercase();
+        let now = Instant::now();
+        let cached = self
+            .inner
+            .cache
+            .lock()
+            .unwrap()
+            .get(&key)
+            .filter(|entry| entry.expires > now)
+            .map(|entry| entry.result.clone());
+        if let Some(result) = cached {
+            return result.map_err(Error::Resolve);
+        }
+
+        let config = &self.inner.config;
+        let entry = match self.inner.backend.lookup(&key).await {
+            Ok(lookup) if !lookup.addrs.is_empty() => {
+                let ttl = lookup
+                    .ttl
+                    .unwrap_or(config.default_ttl)
+                    .clamp(config.min_ttl, config.max_ttl.max(config.min_ttl));
+                debug!("Resolved {} to {:?} for {:?}", key, lookup.addrs, ttl);
+                Entry {
+                    result: Ok(lookup.addrs),
+                    expires: now + ttl,
+                }
+            }
+            Ok(_) => Entry {
+                result: Err(format!("No addresses found for {}", host)),
+                expires: now + config.negative_ttl,
+            },
+            Err(e) => Entry {
+                result: Err(match e {
+                    Error::Resolve(message) => message,
+                    e => e.to_string(),
+                }),
+                expires: now + config.negative_ttl,
+            },
+        };
+
+        let result = entry.result.clone();
+        self.insert(key, entry, now);
+        result.map_err(Error::Resolve)
+    }
+
+    fn insert(&self, key: String, entry: Entry, now: Instant) {
+        let mut cache = self.inner.cache.lock().unwrap();
+        if cache.len() >= self.inner.config.max_entries && !cache.contains_key(&key) {
+            cache.retain(|_, entry| entry.expires > now);
+            if cache.len() >= self.inner.config.max_entries {
+                let oldest = cache
+                    .iter()
+                    .min_by_key(|(_, entry)| entry.expires)
+                    .map(|(key, _)| key.clone());
+                if let Some(oldest) = oldest {
+                    cache.remove(&oldest);
+                }
+            }
+        }
+        cache.insert(key, entry);
+    }
+
+    /// The address to query `host` on, preferring IPv4 since the query
+    /// protocol only supports IPv4.
+    pub async fn resolve(&self, host: &str, port: u16) -> Result<SocketAddr> {
+        let addrs = self.lookup(host).await?;
+        let ip = addrs.iter().find(|ip| ip.is_ipv4()).unwrap_or(&addrs[0]);
+
+        Ok(SocketAddr::new(*ip, port))
+    }
+
+    /// Cached counterpart of [`normalize_address`](crate::utils::normalize_address):
+    /// returns the canonical `host:port` string and the resolved address.
+    pub async fn resolve_address(&self, input: &str, default_port: u16) -> Result<(String, SocketAddr)> {
+        let (host, port) = split_host_port(input, default_port).map_err(Error::Resolve)?;
+        let addr = self.resolve(&host, port).await?;
+
+        Ok((join_host_port(&host, port), addr))
+    }
+}
+
+/// How long the HTTP integrations wait for a request to complete.
+#[cfg(feature = "http")]
+pub(crate) const HTTP_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// An HTTP client resolving through the global resolver, with requests
+/// failing after [`HTTP_TIMEOUT`].
+#[cfg(feature = "http")]
+pub(crate) fn http_client() -> reqwest::Client {
+    reqwest::Client::builder()
+        .dns_resolver(Arc::new(Resolver::global().clone()))
+        .timeout(HTTP_TIMEOUT)
+        .build()
+        .expect("the HTTP client configuration is valid")
+}
+
+#[cfg(feature = "http")]
+impl reqwest::dns::Resolve for Resolver {
+    fn resolve(&self, name: reqwest::dns::Name) -> reqwest::dns::Resolving {
+        let resolver = self.clone();
+        Box::pin(async move {
+            let addrs = resolver.lookup(name.as_str()).await?;
+            let addrs: reqwest::dns::Addrs = Box::new(addrs.into_iter().map(|ip| SocketAddr::new(ip, 0)));
+            Ok(addrs)
+        })
+    }
+}
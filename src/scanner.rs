@@ -0,0 +1,383 @@
+//! Discovery of servers in IP and port ranges.
+//!
+//! [`Scanner`] sends an information query to every address in a set of CIDR
+//! and port ranges from a single shared socket, at a bounded packet rate, and
+//! streams back the servers that answered.
+//!
+//! ```rust,no_run
+//! use futures::StreamExt;
+//! use samp_query::scanner::{ScanConfig, Scanner};
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let config = ScanConfig {
+//!     ranges: vec!["203.0.113.0/24".parse().unwrap()],
+//!     ports: vec!["7777-7787".parse().unwrap()],
+//!     rate_pps: 500,
+//!     ..ScanConfig::default()
+//! };
+//!
+//! let mut servers = Scanner::new(config).scan().await?;
+//! while let Some(server) = servers.next().await {
+//!     println!("{} - {}", server.address, server.info.hostname);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::filter::ServerFilter;
+use crate::packet::{decode, Packet};
+use crate::protocol::{constants, QueryType};
+use crate::ratelimit::RateLimiter;
+use crate::types::ServerInfo;
+use futures::stream::{self, BoxStream, StreamExt};
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::net::{Ipv4Addr, SocketAddr, SocketAddrV4};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+use tokio::sync::mpsc;
+use tokio::task::AbortHandle;
+use tracing::debug;
+
+/// How long the receiver pauses after a socket error.
+const RECV_ERROR_BACKOFF: Duration = Duration::from_millis(10);
+
+/// An IPv4 network in CIDR notation, e.g. `203.0.113.0/24`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct Ipv4Cidr {
+    network: Ipv4Addr,
+    prefix: u8,
+}
+
+impl Ipv4Cidr {
+    pub fn new(addr: Ipv4Addr, prefix: u8) -> Result<Self> {
+        if prefix > 32 {
+            return Err(Error::Other(format!("Invalid CIDR prefix: {}", prefix)));
+        }
+
+        let network = Ipv4Addr::from(u32::from(addr) & Self::mask(prefix));
+        Ok(Self { network, prefix })
+    }
+
+    fn mask(prefix: u8) -> u32 {
+        u32::MAX.checked_shl(32 - prefix as u32).unwrap_or(0)
+    }
+
+    pub fn network(&self) -> Ipv4Addr {
+        self.network
+    }
+
+    pub fn prefix(&self) -> u8 {
+        self.prefix
+    }
+
+    pub fn contains(&self, addr: Ipv4Addr) -> bool {
+        u32::from(addr) & Self::mask(self.prefix) == u32::from(self.network)
+    }
+
+    /// Host addresses in the network.
+    ///
+    /// The network and broadcast addresses are skipped for prefixes shorter than /31.
+    pub fn hosts(&self) -> impl Iterator<Item = Ipv4Addr> {
+        let start = u32::from(self.network) as u64;
+        let end = start + (1u64 << (32 - self.prefix as u32)) - 1;
+        let (start, end) = if self.prefix < 31 {
+            (start + 1, end - 1)
+        } else {
+            (start, end)
+        };
+
+        (start..=end).map(|ip| Ipv4Addr::from(ip as u32))
+    }
+}
+
+impl FromStr for Ipv4Cidr {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let (addr, prefix) = match s.trim().split_once('/') {
+            Some((addr, prefix)) => {
+                let prefix = prefix
+                    .parse()
+                    .map_err(|_| Error::Other(format!("Invalid CIDR prefix: {}", prefix)))?;
+                (addr, prefix)
+            }
+            None => (s.trim(), 32),
+        };
+
+        Self::new(addr.parse()?, prefix)
+    }
+}
+
+impl fmt::Display for Ipv4Cidr {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.network, self.prefix)
+    }
+}
+
+/// An inclusive port range, e.g. `7777-7787` or a single `7777`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct PortRange {
+    pub start: u16,
+    pub end: u16,
+}
+
+impl PortRange {
+    pub fn ports(&self) -> impl Iterator<Item = u16> {
+        self.start..=self.end
+    }
+}
+
+impl FromStr for PortRange {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let parse = |port: &str| {
+            port.trim()
+                .parse::<u16>()
+                .map_err(|_| Error::Other(format!("Invalid port: {}", port)))
+        };
+
+        let (start, end) = match s.split_once('-') {
+            Some((start, end)) => (parse(start)?, parse(end)?),
+            None => (parse(s)?, parse(s)?),
+        };
+        if start > end {
+            return Err(Error::Other(format!("Invalid port range: {}", s)));
+        }
+
+        Ok(Self { start, end })
+    }
+}
+
+impl fmt::Display for PortRange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.start == self.end {
+            write!(f, "{}", self.start)
+        } else {
+            write!(f, "{}-{}", self.start, self.end)
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct ScanConfig {
+    /// Networks to scan.
+    pub ranges: Vec<Ipv4Cidr>,
+    /// Ports to probe on every address.
+    pub ports: Vec<PortRange>,
+    /// Maximum number of probes sent per second.
+    pub rate_pps: u32,
+    /// A limiter shared with other components, used instead of `rate_pps`.
+    pub limiter: Option<RateLimiter>,
+    /// How long to keep listening after the last probe was sent.
+    pub timeout: Duration,
+    /// Addresses it blocks are never probed, and servers whose hostname it
+    /// blocks are not reported. Rule predicates are not checked.
+    pub filter: ServerFilter,
+}
+
+impl Default for ScanConfig {
+    fn default() -> Self {
+        Self {
+            ranges: Vec::new(),
+            ports: vec![PortRange {
+                start: constants::DEFAULT_PORT,
+                end: constants::DEFAULT_PORT,
+            }],
+            rate_pps: 100,
+            limiter: None,
+            timeout: Duration::from_millis(constants::DEFAULT_TIMEOUT_MS),
+            filter: ServerFilter::default(),
+        }
+    }
+}
+
+impl ScanConfig {
+    /// Every address the scan will probe.
+    pub fn targets(&self) -> impl Iterator<Item = SocketAddr> + '_ {
+        self.ranges
+            .iter()
+            .flat_map(move |range| {
+                range.hosts().flat_map(move |ip| {
+                    self.ports
+                        .iter()
+                        .flat_map(|ports| ports.ports())
+                        .map(move |port| SocketAddr::V4(SocketAddrV4::new(ip, port)))
+                })
+            })
+            .filter(|target| self.filter.allows_addr(target))
+    }
+
+    /// Number of probes the scan will send.
+    pub fn target_count(&self) -> u64 {
+        if self.filter.checks_addresses() {
+            return self.targets().count() as u64;
+        }
+
+        let hosts: u64 = self
+            .ranges
+            .iter()
+            .map(|range| range.hosts().count() as u64)
+            .sum();
+        let ports: u64 = self
+            .ports
+            .iter()
+            .map(|ports| (ports.end - ports.start) as u64 + 1)
+            .sum();
+
+        hosts * ports
+    }
+}
+
+/// A server that answered a probe.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Discovered {
+    pub address: SocketAddr,
+    pub info: ServerInfo,
+    /// Time between the probe and the answer, in milliseconds.
+    pub ping_ms: u64,
+}
+
+#[derive(Debug)]
+pub struct Scanner {
+    config: ScanConfig,
+}
+
+impl Scanner {
+    pub fn new(config: ScanConfig) -> Self {
+        Self { config }
+    }
+
+    /// Starts the scan and returns the stream of discovered servers.
+    ///
+    /// The stream ends once every probe has been sent and the configured
+    /// timeout has passed since the last one. Dropping it cancels the scan.
+    pub async fn scan(self) -> Result<BoxStream<'static, Discovered>> {
+        let socket = Arc::new(UdpSocket::bind("0.0.0.0:0").await.map_err(Error::Bind)?);
+        let sent = Arc::new(Mutex::new(HashMap::<SocketAddr, Instant>::new()));
+        let (tx, rx) = mpsc::channel(256);
+
+        let sender = tokio::spawn(send_probes(self.config.clone(), socket.clone(), sent.clone()));
+        let sender_handle = sender.abort_handle();
+        let filter = self.config.filter.clone();
+        let timeout = self.config.timeout;
+
+        let receiver = tokio::spawn(async move {
+            let mut buf = vec![0u8; constants::MAX_PACKET_SIZE];
+            let receive = async {
+                loop {
+                    let (size, peer) = match socket.recv_from(&mut buf).await {
+                        Ok(received) => received,
+                        Err(e) => {
+                            // Often an ICMP error for one probe, but a
+                            // persistent one must not spin the loop.
+                            debug!("Failed to receive a probe response: {}", e);
+                            tokio::time::sleep(RECV_ERROR_BACKOFF).await;
+                            continue;
+                        }
+                    };
+                    let Some(sent_at) = sent.lock().unwrap().remove(&peer) else {
+                        continue;
+                    };
+                    if sent_at.elapsed() > timeout {
+                        continue;
+                    }
+
+                    match parse_info(&buf[..size]) {
+                        Ok(info) if !filter.allows_hostname(&info.hostname) => {
+                            debug!("Filtered out {} ({})", peer, info.hostname);
+                        }
+                        Ok(info) => {
+                            let discovered = Discovered {
+                                address: peer,
+                                info,
+                                ping_ms: sent_at.elapsed().as_millis() as u64,
+                            };
+                            if tx.send(discovered).await.is_err() {
+                                return;
+                            }
+                        }
+                        Err(e) => debug!("Ignoring invalid response from {}: {}", peer, e),
+                    }
+                }
+            };
+
+            tokio::select! {
+                _ = receive => {}
+                _ = async {
+                    let _ = sender.await;
+                    tokio::time::sleep(self.config.timeout).await;
+                } => {}
+            }
+        });
+
+        let tasks = AbortOnDrop(vec![sender_handle, receiver.abort_handle()]);
+        Ok(
+            stream::unfold((rx, tasks), |(mut rx, tasks)| async move {
+                rx.recv().await.map(|d| (d, (rx, tasks)))
+            })
+            .boxed(),
+        )
+    }
+}
+
+/// Aborts the tasks of a scan when dropped.
+struct AbortOnDrop(Vec<AbortHandle>);
+
+impl Drop for AbortOnDrop {
+    fn drop(&mut self) {
+        for task in &self.0 {
+            task.abort();
+        }
+    }
+}
+
+/// Sends probes in bursts of up to 10 ms worth, so the average rate stays
+/// at `rate_pps` without a timer wakeup per probe.
+async fn send_probes(
+    config: ScanConfig,
+    socket: Arc<UdpSocket>,
+    sent: Arc<Mutex<HashMap<SocketAddr, Instant>>>,
+) {
+    let limiter = config
+        .limiter
+        .clone()
+        .unwrap_or_else(|| RateLimiter::with_burst(config.rate_pps.max(1) as f64, config.rate_pps / 100));
+
+    let mut last_expiry = Instant::now();
+    for target in config.targets() {
+        limiter.acquire(target).await;
+
+        let Ok(packet) = Packet::create_query(target, QueryType::Information) else {
+            continue;
+        };
+        {
+            let now = Instant::now();
+            let mut sent = sent.lock().unwrap();
+            // Probes that were not answered in time are forgotten, so a large
+            // scan of mostly empty ranges does not keep one entry per address.
+            if now.duration_since(last_expiry) >= config.timeout {
+                sent.retain(|_, sent_at| now.duration_since(*sent_at) <= config.timeout);
+                last_expiry = now;
+            }
+            sent.insert(target, now);
+        }
+        if let Err(e) = socket.send_to(packet.as_bytes(), target).await {
+            debug!("Failed to probe {}: {}", target, e);
+        }
+    }
+}
+
+fn parse_info(response: &[u8]) -> Result<ServerInfo> {
+    let packet = Packet::from_bytes(response);
+    if packet.query_type() != Some(QueryType::Information) {
+        return Err(Error::InvalidResponse("Not an information response".to_string()));
+    }
+
+    decode::info(&packet.parse_response(QueryType::Information)?)
+}
@@ -0,0 +1,274 @@
+//! Polling many servers on independent schedules.
+//!
+//! A [`Scheduler`] polls every registered server on its own interval and
+//! merges the resulting [`MonitorEvent`]s into a single stream. Polls are
+//! jittered so servers added together do not stay in lockstep, servers that
+//! keep failing are polled exponentially less often, and the number of polls
+//! in flight is bounded.
+//!
+//! Events are the same as a [`Monitor`](crate::monitor::Monitor) would emit
+//! for each server. Servers can be added and removed while the scheduler runs
+//! through a [`SchedulerHandle`].
+//!
+//! ```rust,no_run
+//! use futures::StreamExt;
+//! use samp_query::scheduler::{ScheduleConfig, Scheduler};
+//! use std::time::Duration;
+//!
+//! # async fn run() -> Result<(), Box<dyn std::error::Error>> {
+//! let mut scheduler = Scheduler::new(ScheduleConfig::default());
+//! scheduler.add("127.0.0.1:7777".parse()?);
+//! scheduler.add_with_interval("127.0.0.1:7778".parse()?, Duration::from_secs(5));
+//!
+//! let (handle, mut events) = scheduler.start();
+//! handle.add("127.0.0.1:7779".parse()?, None);
+//!
+//! while let Some(event) = events.next().await {
+//!     println!("{}", event);
+//! }
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::monitor::{MonitorConfig, MonitorEvent, Poller};
+use crate::protocol::constants;
+use futures::future::BoxFuture;
+use futures::stream::{self, BoxStream, FuturesUnordered, StreamExt};
+use futures::FutureExt;
+use rand::Rng;
+use std::cmp::Reverse;
+use std::collections::{BinaryHeap, HashMap};
+use std::net::SocketAddr;
+use std::time::Duration;
+use tokio::sync::mpsc;
+use tokio::time::Instant;
+
+#[derive(Debug, Clone)]
+pub struct ScheduleConfig {
+    /// Settings shared by every poll; `monitor.interval` is the default interval.
+    pub monitor: MonitorConfig,
+    /// Random variation applied to every delay, as a fraction of it.
+    pub jitter: f64,
+    /// Multiplier applied to the interval after each consecutive failure.
+    pub backoff_factor: f64,
+    /// Longest delay between two polls of a failing server.
+    pub max_backoff: Duration,
+    /// Maximum number of polls in flight.
+    pub concurrency: usize,
+}
+
+impl Default for ScheduleConfig {
+    fn default() -> Self {
+        Self {
+            monitor: MonitorConfig::default(),
+            jitter: 0.1,
+            backoff_factor: 2.0,
+            max_backoff: Duration::from_secs(600),
+            concurrency: 64,
+        }
+    }
+}
+
+impl ScheduleConfig {
+    /// Delay before the next poll of a server after `failures` consecutive failures.
+    pub fn next_delay(&self, interval: Duration, failures: u32) -> Duration {
+        let base = if failures == 0 {
+            interval
+        } else {
+            // Multiplied as seconds, since a large factor overflows a `Duration`.
+            let max = self.max_backoff.max(interval);
+            let factor = self.backoff_factor.max(1.0).powi(failures.min(32) as i32);
+            Duration::try_from_secs_f64(interval.as_secs_f64() * factor).map_or(max, |delay| delay.min(max))
+        };
+
+        self.jittered(base)
+    }
+
+    fn jittered(&self, delay: Duration) -> Duration {
+        let jitter = self.jitter.clamp(0.0, 1.0);
+        if jitter == 0.0 {
+            return delay;
+        }
+
+        let factor = rand::thread_rng().gen_range(1.0 - jitter..=1.0 + jitter);
+        Duration::try_from_secs_f64(delay.as_secs_f64() * factor).unwrap_or(delay)
+    }
+}
+
+#[derive(Debug)]
+enum Command {
+    Add(SocketAddr, Option<Duration>),
+    Remove(SocketAddr),
+}
+
+/// Adds and removes servers of a running [`Scheduler`].
+#[derive(Debug, Clone)]
+pub struct SchedulerHandle {
+    commands: mpsc::UnboundedSender<Command>,
+}
+
+impl SchedulerHandle {
+    /// Starts polling a server, with its own interval or the default one.
+    /// Adding a server that is already scheduled replaces its interval.
+    pub fn add(&self, addr: SocketAddr, interval: Option<Duration>) {
+        let _ = self.commands.send(Command::Add(addr, interval));
+    }
+
+    /// Stops polling a server. A poll already in flight is discarded.
+    pub fn remove(&self, addr: SocketAddr) {
+        let _ = self.commands.send(Command::Remove(addr));
+    }
+}
+
+#[derive(Debug)]
+pub struct Scheduler {
+    config: ScheduleConfig,
+    servers: Vec<(SocketAddr, Option<Duration>)>,
+}
+
+struct Entry {
+    interval: Duration,
+    failures: u32,
+    /// `None` while a poll is in flight.
+    poller: Option<Poller>,
+    /// Incremented on every re-add, so stale queue entries are skipped.
+    generation: u64,
+}
+
+impl Scheduler {
+    pub fn new(config: ScheduleConfig) -> Self {
+        Self {
+            config,
+            servers: Vec::new(),
+        }
+    }
+
+    pub fn config(&self) -> &ScheduleConfig {
+        &self.config
+    }
+
+    /// Schedules a server at the default interval.
+    pub fn add(&mut self, addr: SocketAddr) {
+        self.servers.push((addr, None));
+    }
+
+    pub fn add_with_interval(&mut self, addr: SocketAddr, interval: Duration) {
+        self.servers.push((addr, Some(interval)));
+    }
+
+    pub fn len(&self) -> usize {
+        self.servers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.servers.is_empty()
+    }
+
+    /// Starts polling and returns the merged event stream.
+    pub fn stream(self) -> BoxStream<'static, MonitorEvent> {
+        self.start().1
+    }
+
+    /// Starts polling and returns a handle for changing the server set along
+    /// with the merged event stream. Polling stops when the stream is dropped.
+    pub fn start(self) -> (SchedulerHandle, BoxStream<'static, MonitorEvent>) {
+        let (commands_tx, commands_rx) = mpsc::unbounded_channel();
+        let (tx, rx) = mpsc::channel(256);
+
+        for (addr, interval) in self.servers {
+            let _ = commands_tx.send(Command::Add(addr, interval));
+        }
+        tokio::spawn(run(self.config, commands_rx, tx));
+
+        let events = stream::unfold(rx, |mut rx| async move { rx.recv().await.map(|e| (e, rx)) }).boxed();
+        (SchedulerHandle { commands: commands_tx }, events)
+    }
+}
+
+async fn run(
+    config: ScheduleConfig,
+    mut commands: mpsc::UnboundedReceiver<Command>,
+    tx: mpsc::Sender<MonitorEvent>,
+) {
+    let mut entries: HashMap<SocketAddr, Entry> = HashMap::new();
+    let mut queue: BinaryHeap<Reverse<(Instant, u64, SocketAddr)>> = BinaryHeap::new();
+    let mut in_flight: FuturesUnordered<BoxFuture<'static, (u64, Poller)>> = FuturesUnordered::new();
+    let mut generation = 0u64;
+    let mut commands_open = true;
+    let concurrency = config.concurrency.max(1);
+
+    loop {
+        let now = Instant::now();
+        while in_flight.len() < concurrency {
+            let Some(&Reverse((due, entry_generation, addr))) = queue.peek() else {
+                break;
+            };
+            if due > now {
+                break;
+            }
+            queue.pop();
+
+            let Some(entry) = entries.get_mut(&addr).filter(|e| e.generation == entry_generation) else {
+                continue;
+            };
+            if let Some(mut poller) = entry.poller.take() {
+                in_flight.push(
+                    async move {
+                        poller.poll().await;
+                        (entry_generation, poller)
+                    }
+                    .boxed(),
+                );
+            }
+        }
+
+        let next_due = queue.peek().map(|Reverse((due, _, _))| *due);
+        let can_start = in_flight.len() < concurrency;
+
+        tokio::select! {
+            Some((entry_generation, mut poller)) = in_flight.next(), if !in_flight.is_empty() => {
+                let addr = poller.addr();
+                let Some(entry) = entries.get_mut(&addr).filter(|e| e.generation == entry_generation) else {
+                    continue;
+                };
+
+                for event in poller.take_events() {
+                    if tx.send(event).await.is_err() {
+                        return;
+                    }
+                }
+
+                entry.failures = if poller.is_online() { 0 } else { entry.failures.saturating_add(1) };
+                let delay = config.next_delay(entry.interval, entry.failures);
+                entry.poller = Some(poller);
+                queue.push(Reverse((Instant::now() + delay, entry_generation, addr)));
+            }
+            _ = tokio::time::sleep_until(next_due.unwrap_or(now)), if next_due.is_some() && can_start => {}
+            command = commands.recv(), if commands_open => match command {
+                Some(Command::Add(addr, interval)) => {
+                    generation += 1;
+                    // A zero interval would poll the server back to back, and
+                    // back off to zero as well while it is offline.
+                    let interval = interval
+                        .unwrap_or(config.monitor.interval)
+                        .max(Duration::from_millis(constants::MIN_POLL_INTERVAL_MS));
+                    let monitor = MonitorConfig { interval, ..config.monitor.clone() };
+                    entries.insert(addr, Entry {
+                        interval,
+                        failures: 0,
+                        poller: Some(Poller::new(addr, monitor)),
+                        generation,
+                    });
+                    // Spread the first polls over the jitter window.
+                    let offset = interval.mul_f64(config.jitter.clamp(0.0, 1.0) * rand::thread_rng().gen::<f64>());
+                    queue.push(Reverse((Instant::now() + offset, generation, addr)));
+                }
+                Some(Command::Remove(addr)) => {
+                    entries.remove(&addr);
+                }
+                None => commands_open = false,
+            },
+            _ = tx.closed() => return,
+        }
+    }
+}
@@ -0,0 +1,193 @@
+//! Server-side implementation of the SAMP Query protocol.
+//!
+//! [`Server`] binds a UDP socket and answers queries using data provided by a
+//! [`DataSource`], which makes it possible to write launchers, bridges and
+//! placeholder servers that look like a regular SA-MP server to clients.
+
+use crate::error::{Error, Result};
+use crate::packet::{encode, Packet};
+use crate::protocol::{constants, QueryType};
+use crate::types::*;
+use bytes::Buf;
+use std::io::{Cursor, Read};
+#[cfg(feature = "net")]
+use std::net::SocketAddr;
+#[cfg(feature = "net")]
+use std::sync::Arc;
+#[cfg(feature = "net")]
+use tokio::net::UdpSocket;
+use tracing::{debug, warn};
+
+/// Provides the data a [`Server`] answers queries with.
+pub trait DataSource: Send + Sync {
+    /// Data for the information ('i') query.
+    fn info(&self) -> ServerInfo;
+
+    /// Data for the rules ('r') query.
+    fn rules(&self) -> ServerRules;
+
+    /// Data for the client list ('c') and detailed player ('d') queries.
+    fn players(&self) -> DetailedPlayerList;
+
+    /// Handles an RCON ('x') command.
+    ///
+    /// Returns the response lines, or `None` to reject the command (e.g. on a
+    /// wrong password). RCON is disabled by default.
+    fn rcon(&self, _password: &str, _command: &str) -> Option<Vec<String>> {
+        None
+    }
+
+    /// Whether the source has data to answer with.
+    ///
+    /// Every query is dropped while this returns false, so clients see the
+    /// server as offline.
+    fn available(&self) -> bool {
+        true
+    }
+}
+
+/// A [`DataSource`] serving fixed data.
+#[derive(Debug, Clone)]
+pub struct StaticSource {
+    pub info: ServerInfo,
+    pub rules: ServerRules,
+    pub players: DetailedPlayerList,
+}
+
+impl DataSource for StaticSource {
+    fn info(&self) -> ServerInfo {
+        self.info.clone()
+    }
+
+    fn rules(&self) -> ServerRules {
+        self.rules.clone()
+    }
+
+    fn players(&self) -> DetailedPlayerList {
+        self.players.clone()
+    }
+}
+
+#[cfg(feature = "net")]
+#[derive(Debug)]
+pub struct Server<S> {
+    socket: UdpSocket,
+    source: Arc<S>,
+}
+
+#[cfg(feature = "net")]
+impl<S: DataSource> Server<S> {
+    pub async fn bind(addr: SocketAddr, source: S) -> Result<Self> {
+        Self::bind_shared(addr, Arc::new(source)).await
+    }
+
+    /// Binds a server with a data source that is shared with the caller.
+    pub async fn bind_shared(addr: SocketAddr, source: Arc<S>) -> Result<Self> {
+        let socket = UdpSocket::bind(addr).await.map_err(Error::Bind)?;
+
+        Ok(Self { socket, source })
+    }
+
+    pub fn local_addr(&self) -> Result<SocketAddr> {
+        self.socket.local_addr().map_err(Error::from)
+    }
+
+    /// Answers queries until the socket fails.
+    pub async fn run(&self) -> Result<()> {
+        let mut buf = vec![0u8; constants::MAX_PACKET_SIZE];
+
+        loop {
+            let (size, peer) = self.socket.recv_from(&mut buf).await.map_err(Error::Receive)?;
+
+            for response in respond(self.source.as_ref(), &buf[..size]) {
+                if let Err(e) = self.socket.send_to(&response, peer).await {
+                    warn!("Failed to send response to {}: {}", peer, e);
+                }
+            }
+        }
+    }
+}
+
+/// Builds the response packets for a single request.
+///
+/// Returns no packets for malformed or unsupported requests, mirroring the
+/// behaviour of a real server which silently drops them.
+pub fn respond<S: DataSource + ?Sized>(source: &S, request: &[u8]) -> Vec<Vec<u8>> {
+    let request = Packet::from_bytes(request);
+    if request.validate_response().is_err() || !source.available() {
+        return Vec::new();
+    }
+
+    let payload = &request.as_bytes()[constants::HEADER_SIZE..];
+    let bodies = match request.query_type() {
+        Some(QueryType::Information) => vec![encode::info(&source.info())],
+        Some(QueryType::Rules) => single(encode::rules(&source.rules())),
+        Some(QueryType::ClientList) => {
+            let players = source
+                .players()
+                .players
+                .into_iter()
+                .map(|p| Player {
+                    name: p.name,
+                    score: p.score,
+                })
+                .collect();
+            single(encode::client_list(&PlayerList { players }))
+        }
+        Some(QueryType::DetailedPlayerInfo) => single(encode::detailed_players(&source.players())),
+        Some(QueryType::Ping) => {
+            if payload.len() < 4 {
+                return Vec::new();
+            }
+            vec![payload[..4].to_vec()]
+        }
+        Some(QueryType::Rcon) => match parse_rcon_request(payload) {
+            Ok((password, command)) => match source.rcon(&password, &command) {
+                // Lines too long for the protocol are left out.
+                Some(lines) => lines.iter().filter_map(|line| encode::rcon_line(line).ok()).collect(),
+                None => vec![Vec::new()],
+            },
+            Err(e) => {
+                debug!("Dropping malformed RCON request: {}", e);
+                return Vec::new();
+            }
+        },
+        None => return Vec::new(),
+    };
+
+    bodies
+        .iter()
+        .filter_map(|body| Packet::create_response(&request, body).ok())
+        .map(|packet| packet.as_bytes().to_vec())
+        .collect()
+}
+
+/// The body of a response, or none when the data does not fit the protocol.
+fn single(body: Result<Vec<u8>>) -> Vec<Vec<u8>> {
+    match body {
+        Ok(body) => vec![body],
+        Err(e) => {
+            warn!("Not answering query: {}", e);
+            Vec::new()
+        }
+    }
+}
+
+fn parse_rcon_request(payload: &[u8]) -> Result<(String, String)> {
+    let mut cursor = Cursor::new(payload);
+    let password = read_string_16(&mut cursor)?;
+    let command = read_string_16(&mut cursor)?;
+
+    Ok((password, command))
+}
+
+fn read_string_16(cursor: &mut Cursor<&[u8]>) -> Result<String> {
+    if cursor.remaining() < 2 {
+        return Err(Error::InvalidResponse("Truncated string length".to_string()));
+    }
+
+    let mut bytes = vec![0u8; cursor.get_u16_le() as usize];
+    cursor.read_exact(&mut bytes)?;
+
+    String::from_utf8(bytes).map_err(Error::from)
+}
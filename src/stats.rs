@@ -0,0 +1,179 @@
+//! Latency statistics.
+//!
+//! [`LatencyStats`] keeps the most recent ping samples of one server and
+//! derives percentiles, an exponentially weighted moving average and jitter
+//! from them. The monitor uses it for spike detection and adaptive
+//! timeouts, and the Prometheus exporter publishes its summary.
+//!
+//! ```rust
+//! use samp_query::stats::LatencyStats;
+//! use std::time::Duration;
+//!
+//! let mut stats = LatencyStats::new(100);
+//! for ms in [40, 42, 45, 41, 120] {
+//!     stats.record(Duration::from_millis(ms));
+//! }
+//!
+//! assert_eq!(stats.p50(), Some(Duration::from_millis(42)));
+//! assert_eq!(stats.p99(), Some(Duration::from_millis(120)));
+//! assert!(stats.jitter().unwrap() > Duration::ZERO);
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::time::Duration;
+
+/// Number of samples kept by [`LatencyStats::default`].
+pub const DEFAULT_WINDOW: usize = 100;
+
+/// Weight of the newest sample in the moving average by default.
+pub const DEFAULT_SMOOTHING: f64 = 0.2;
+
+/// Samples needed before [`LatencyStats::suggested_timeout`] gives an answer.
+const MIN_TIMEOUT_SAMPLES: usize = 5;
+
+/// Rolling latency statistics over a window of samples.
+#[derive(Debug, Clone)]
+pub struct LatencyStats {
+    window: usize,
+    smoothing: f64,
+    samples: VecDeque<Duration>,
+    /// Moving average in milliseconds.
+    ewma: Option<f64>,
+    /// Interarrival jitter in milliseconds, estimated as in RFC 3550.
+    jitter: Option<f64>,
+}
+
+impl Default for LatencyStats {
+    fn default() -> Self {
+        Self::new(DEFAULT_WINDOW)
+    }
+}
+
+impl LatencyStats {
+    /// Statistics over the last `window` samples.
+    pub fn new(window: usize) -> Self {
+        Self {
+            window: window.max(1),
+            smoothing: DEFAULT_SMOOTHING,
+            samples: VecDeque::new(),
+            ewma: None,
+            jitter: None,
+        }
+    }
+
+    /// Sets the weight of the newest sample in the moving average, between 0 and 1.
+    pub fn with_smoothing(mut self, smoothing: f64) -> Self {
+        self.smoothing = smoothing.clamp(f64::EPSILON, 1.0);
+        self
+    }
+
+    pub fn record(&mut self, sample: Duration) {
+        let ms = sample.as_secs_f64() * 1000.0;
+
+        if let Some(&last) = self.samples.back() {
+            let delta = (ms - last.as_secs_f64() * 1000.0).abs();
+            let jitter = self.jitter.unwrap_or(0.0);
+            self.jitter = Some(jitter + (delta - jitter) / 16.0);
+        }
+        self.ewma = Some(match self.ewma {
+            Some(average) => average + (ms - average) * self.smoothing,
+            None => ms,
+        });
+
+        if self.samples.len() == self.window {
+            self.samples.pop_front();
+        }
+        self.samples.push_back(sample);
+    }
+
+    /// Number of samples in the window.
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    pub fn last(&self) -> Option<Duration> {
+        self.samples.back().copied()
+    }
+
+    /// The `percentile` (0 to 100) of the window, by nearest rank.
+    pub fn percentile(&self, percentile: f64) -> Option<Duration> {
+        if self.samples.is_empty() {
+            return None;
+        }
+
+        let mut sorted: Vec<_> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+        let rank = (percentile.clamp(0.0, 100.0) / 100.0 * sorted.len() as f64).ceil() as usize;
+
+        Some(sorted[rank.clamp(1, sorted.len()) - 1])
+    }
+
+    pub fn p50(&self) -> Option<Duration> {
+        self.percentile(50.0)
+    }
+
+    pub fn p95(&self) -> Option<Duration> {
+        self.percentile(95.0)
+    }
+
+    pub fn p99(&self) -> Option<Duration> {
+        self.percentile(99.0)
+    }
+
+    /// The moving average over every sample recorded, not only the window.
+    pub fn ewma(&self) -> Option<Duration> {
+        self.ewma.map(from_ms)
+    }
+
+    /// The smoothed difference between consecutive samples, once there are two.
+    pub fn jitter(&self) -> Option<Duration> {
+        self.jitter.map(from_ms)
+    }
+
+    /// A query timeout fitted to the observed latency: the larger of twice
+    /// the p99 and the average plus four times the jitter, clamped to
+    /// `min..=max`. `None` until enough samples were recorded.
+    pub fn suggested_timeout(&self, min: Duration, max: Duration) -> Option<Duration> {
+        if self.samples.len() < MIN_TIMEOUT_SAMPLES {
+            return None;
+        }
+
+        let p99 = self.p99()?.as_secs_f64() * 1000.0;
+        let spread = self.ewma? + 4.0 * self.jitter.unwrap_or(0.0);
+
+        Some(from_ms((2.0 * p99).max(spread)).clamp(min, max.max(min)))
+    }
+
+    pub fn summary(&self) -> Option<LatencySummary> {
+        Some(LatencySummary {
+            samples: self.samples.len(),
+            last_ms: self.last()?.as_millis() as u64,
+            p50_ms: self.p50()?.as_millis() as u64,
+            p95_ms: self.p95()?.as_millis() as u64,
+            p99_ms: self.p99()?.as_millis() as u64,
+            ewma_ms: self.ewma?.round() as u64,
+            jitter_ms: self.jitter.unwrap_or(0.0).round() as u64,
+        })
+    }
+}
+
+/// A serializable snapshot of [`LatencyStats`], in whole milliseconds.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub samples: usize,
+    pub last_ms: u64,
+    pub p50_ms: u64,
+    pub p95_ms: u64,
+    pub p99_ms: u64,
+    pub ewma_ms: u64,
+    pub jitter_ms: u64,
+}
+
+fn from_ms(ms: f64) -> Duration {
+    Duration::from_micros((ms.max(0.0) * 1000.0).round() as u64)
+}
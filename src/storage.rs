@@ -0,0 +1,301 @@
+//! SQLite persistence for snapshots and monitor events.
+//!
+//! Available with the `sqlite` feature. [`SqliteStore`] writes the same data
+//! the in-memory [`History`](crate::history::History) keeps, plus monitor
+//! events, to a local database file.
+
+use crate::error::{Error, Result};
+use crate::history::HistoryEntry;
+use crate::monitor::{MonitorEvent, MonitorEventKind};
+use crate::types::ServerSnapshot;
+use rusqlite::{params, Connection, OptionalExtension};
+use serde::{Deserialize, Serialize};
+use std::net::SocketAddr;
+use std::path::Path;
+use std::sync::Mutex;
+
+const SCHEMA: &str = "
+CREATE TABLE IF NOT EXISTS snapshots (
+    id          INTEGER PRIMARY KEY,
+    address     TEXT    NOT NULL,
+    timestamp   INTEGER NOT NULL,
+    online      INTEGER NOT NULL,
+    players     INTEGER NOT NULL,
+    max_players INTEGER NOT NULL,
+    ping_ms     INTEGER,
+    hostname    TEXT,
+    gamemode    TEXT
+);
+CREATE INDEX IF NOT EXISTS snapshots_address_timestamp ON snapshots (address, timestamp);
+
+CREATE TABLE IF NOT EXISTS events (
+    id        INTEGER PRIMARY KEY,
+    address   TEXT    NOT NULL,
+    timestamp INTEGER NOT NULL,
+    kind      TEXT    NOT NULL,
+    payload   TEXT    NOT NULL
+);
+CREATE INDEX IF NOT EXISTS events_address_timestamp ON events (address, timestamp);
+";
+
+impl From<rusqlite::Error> for Error {
+    fn from(e: rusqlite::Error) -> Self {
+        Error::Storage(e.to_string())
+    }
+}
+
+/// A metric that can be read back as a time series.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Metric {
+    Players,
+    Ping,
+}
+
+/// One bucket of a time series.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct SeriesPoint {
+    /// Unix timestamp in milliseconds of the start of the bucket.
+    pub timestamp: u64,
+    /// Average value within the bucket.
+    pub value: f64,
+}
+
+/// Aggregate statistics over a time range.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HistoryStats {
+    /// Number of recorded observations.
+    pub samples: u64,
+    /// Highest player count observed.
+    pub peak_players: Option<u16>,
+    /// Unix timestamp in milliseconds at which the peak was first observed.
+    pub peak_at: Option<u64>,
+    /// Average ping in milliseconds over online observations.
+    pub average_ping_ms: Option<f64>,
+    /// Percentage of observations in which the server was online.
+    pub uptime_percentage: Option<f64>,
+}
+
+#[derive(Debug)]
+pub struct SqliteStore {
+    conn: Mutex<Connection>,
+}
+
+impl SqliteStore {
+    /// Opens (or creates) a database file and applies the schema.
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::init(Connection::open(path)?)
+    }
+
+    /// Opens a private in-memory database, mostly useful for tests.
+    pub fn open_in_memory() -> Result<Self> {
+        Self::init(Connection::open_in_memory()?)
+    }
+
+    fn init(conn: Connection) -> Result<Self> {
+        conn.execute_batch(SCHEMA)?;
+
+        Ok(Self {
+            conn: Mutex::new(conn),
+        })
+    }
+
+    fn conn(&self) -> std::sync::MutexGuard<'_, Connection> {
+        self.conn.lock().unwrap_or_else(|e| e.into_inner())
+    }
+
+    pub fn record_entry(
+        &self,
+        addr: SocketAddr,
+        entry: &HistoryEntry,
+        hostname: Option<&str>,
+        gamemode: Option<&str>,
+    ) -> Result<()> {
+        self.conn().execute(
+            "INSERT INTO snapshots (address, timestamp, online, players, max_players, ping_ms, hostname, gamemode)
+             VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8)",
+            params![
+                addr.to_string(),
+                entry.timestamp as i64,
+                entry.online,
+                entry.players,
+                entry.max_players,
+                entry.ping_ms.map(|ping| ping as i64),
+                hostname,
+                gamemode,
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    pub fn record_snapshot(&self, snapshot: &ServerSnapshot) -> Result<()> {
+        self.record_entry(
+            snapshot.address,
+            &HistoryEntry::from(snapshot),
+            Some(&snapshot.info.hostname),
+            Some(&snapshot.info.gamemode),
+        )
+    }
+
+    /// Records a monitor event.
+    ///
+    /// `Polled` and `Offline` events are stored as observations; every change
+    /// event is stored in the event log.
+    pub fn record_event(&self, event: &MonitorEvent) -> Result<()> {
+        match &event.kind {
+            MonitorEventKind::Polled { info, ping_ms, .. } => {
+                let entry = HistoryEntry {
+                    timestamp: event.timestamp,
+                    online: true,
+                    players: info.players,
+                    max_players: info.max_players,
+                    ping_ms: *ping_ms,
+                };
+                return self.record_entry(
+                    event.address,
+                    &entry,
+                    Some(&info.hostname),
+                    Some(&info.gamemode),
+                );
+            }
+            MonitorEventKind::Offline { .. } => {
+                self.record_entry(
+                    event.address,
+                    &HistoryEntry::offline(event.timestamp),
+                    None,
+                    None,
+                )?;
+            }
+            _ => {}
+        }
+
+        let payload = serde_json::to_value(&event.kind).map_err(|e| Error::Storage(e.to_string()))?;
+        let kind = payload["type"].as_str().unwrap_or_default().to_string();
+        self.conn().execute(
+            "INSERT INTO events (address, timestamp, kind, payload) VALUES (?1, ?2, ?3, ?4)",
+            params![
+                event.address.to_string(),
+                event.timestamp as i64,
+                kind,
+                payload.to_string()
+            ],
+        )?;
+
+        Ok(())
+    }
+
+    /// Observations for a server at or after `since`, oldest first.
+    pub fn entries(&self, addr: SocketAddr, since: u64) -> Result<Vec<HistoryEntry>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, online, players, max_players, ping_ms FROM snapshots
+             WHERE address = ?1 AND timestamp >= ?2 ORDER BY timestamp",
+        )?;
+
+        let entries = stmt
+            .query_map(params![addr.to_string(), since as i64], |row| {
+                Ok(HistoryEntry {
+                    timestamp: row.get::<_, i64>(0)? as u64,
+                    online: row.get(1)?,
+                    players: row.get(2)?,
+                    max_players: row.get(3)?,
+                    ping_ms: row.get::<_, Option<i64>>(4)?.map(|ping| ping as u64),
+                })
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(entries)
+    }
+
+    /// A metric averaged over buckets of `step_ms`, for online observations since `since`.
+    pub fn series(
+        &self,
+        addr: SocketAddr,
+        metric: Metric,
+        since: u64,
+        step_ms: u64,
+    ) -> Result<Vec<SeriesPoint>> {
+        let column = match metric {
+            Metric::Players => "players",
+            Metric::Ping => "ping_ms",
+        };
+        let sql = format!(
+            "SELECT (timestamp / ?3) * ?3 AS bucket, AVG({column}) FROM snapshots
+             WHERE address = ?1 AND timestamp >= ?2 AND online = 1 AND {column} IS NOT NULL
+             GROUP BY bucket ORDER BY bucket"
+        );
+
+        let conn = self.conn();
+        let mut stmt = conn.prepare(&sql)?;
+        let points = stmt
+            .query_map(
+                params![addr.to_string(), since as i64, step_ms.max(1) as i64],
+                |row| {
+                    Ok(SeriesPoint {
+                        timestamp: row.get::<_, i64>(0)? as u64,
+                        value: row.get(1)?,
+                    })
+                },
+            )?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        Ok(points)
+    }
+
+    pub fn stats(&self, addr: SocketAddr, since: u64) -> Result<HistoryStats> {
+        let conn = self.conn();
+        let address = addr.to_string();
+
+        let (samples, online, average_ping_ms): (i64, i64, Option<f64>) = conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(online), 0), AVG(CASE WHEN online = 1 THEN ping_ms END)
+             FROM snapshots WHERE address = ?1 AND timestamp >= ?2",
+            params![address, since as i64],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?)),
+        )?;
+
+        let peak: Option<(u16, i64)> = conn
+            .query_row(
+                "SELECT players, timestamp FROM snapshots
+                 WHERE address = ?1 AND timestamp >= ?2 AND online = 1
+                 ORDER BY players DESC, timestamp ASC LIMIT 1",
+                params![address, since as i64],
+                |row| Ok((row.get(0)?, row.get(1)?)),
+            )
+            .optional()?;
+
+        Ok(HistoryStats {
+            samples: samples as u64,
+            peak_players: peak.map(|(players, _)| players),
+            peak_at: peak.map(|(_, timestamp)| timestamp as u64),
+            average_ping_ms,
+            uptime_percentage: (samples > 0).then(|| online as f64 / samples as f64 * 100.0),
+        })
+    }
+
+    /// Change events for a server at or after `since`, oldest first.
+    pub fn events(&self, addr: SocketAddr, since: u64) -> Result<Vec<MonitorEvent>> {
+        let conn = self.conn();
+        let mut stmt = conn.prepare(
+            "SELECT timestamp, payload FROM events
+             WHERE address = ?1 AND timestamp >= ?2 ORDER BY timestamp, id",
+        )?;
+
+        let rows = stmt
+            .query_map(params![addr.to_string(), since as i64], |row| {
+                Ok((row.get::<_, i64>(0)?, row.get::<_, String>(1)?))
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+
+        rows.into_iter()
+            .map(|(timestamp, payload)| {
+                let kind = serde_json::from_str(&payload).map_err(|e| Error::Storage(e.to_string()))?;
+                Ok(MonitorEvent {
+                    address: addr,
+                    timestamp: timestamp as u64,
+                    kind,
+                })
+            })
+            .collect()
+    }
+}
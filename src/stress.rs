@@ -0,0 +1,289 @@
+//! Load and stress testing.
+//!
+//! [`StressTest`] sends queries to a target at a fixed rate for a fixed
+//! duration from a single socket and reports loss and latency. It is meant for
+//! benchmarking query handling (of this crate's [`Server`](crate::server::Server),
+//! or a real server behind a DDoS filter), so only point it at servers you run.
+//!
+//! Ping queries carry a sequence number in their payload, so every answer is
+//! matched to its request exactly. Other query types have identical requests
+//! and are matched first-in, first-out.
+//!
+//! ```rust,no_run
+//! use samp_query::stress::{StressConfig, StressTest};
+//! use std::time::Duration;
+//!
+//! # async fn run() -> samp_query::Result<()> {
+//! let config = StressConfig {
+//!     qps: 500,
+//!     duration: Duration::from_secs(10),
+//!     ..StressConfig::new("127.0.0.1:7777".parse().unwrap())
+//! };
+//!
+//! let report = StressTest::new(config).run().await?;
+//! println!("{}", report);
+//! # Ok(())
+//! # }
+//! ```
+
+use crate::error::{Error, Result};
+use crate::packet::Packet;
+use crate::protocol::{constants, QueryType};
+use crate::ratelimit::RateLimiter;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::net::SocketAddr;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+use tokio::net::UdpSocket;
+
+/// Upper bounds in milliseconds of the latency histogram buckets.
+pub const LATENCY_BUCKETS_MS: &[f64] = &[1.0, 2.0, 5.0, 10.0, 20.0, 50.0, 100.0, 200.0, 500.0, 1000.0];
+
+#[derive(Debug, Clone)]
+pub struct StressConfig {
+    pub target: SocketAddr,
+    /// Queries sent per second.
+    pub qps: u32,
+    /// How long queries are sent for.
+    pub duration: Duration,
+    pub query_type: QueryType,
+    /// How long an answer is waited for before the query counts as lost.
+    pub timeout: Duration,
+}
+
+impl StressConfig {
+    pub fn new(target: SocketAddr) -> Self {
+        Self {
+            target,
+            qps: 100,
+            duration: Duration::from_secs(10),
+            query_type: QueryType::Ping,
+            timeout: Duration::from_millis(constants::DEFAULT_TIMEOUT_MS),
+        }
+    }
+}
+
+/// Number of answers at or below a latency bound.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+pub struct HistogramBucket {
+    pub le_ms: f64,
+    pub count: u64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LatencySummary {
+    pub min_ms: f64,
+    pub mean_ms: f64,
+    pub p50_ms: f64,
+    pub p90_ms: f64,
+    pub p99_ms: f64,
+    pub max_ms: f64,
+    /// Cumulative histogram over [`LATENCY_BUCKETS_MS`].
+    pub histogram: Vec<HistogramBucket>,
+}
+
+impl LatencySummary {
+    fn from_samples(mut samples: Vec<f64>) -> Option<Self> {
+        if samples.is_empty() {
+            return None;
+        }
+
+        samples.sort_by(f64::total_cmp);
+        let percentile = |pct: f64| {
+            let rank = (pct / 100.0 * samples.len() as f64).ceil() as usize;
+            samples[rank.saturating_sub(1).min(samples.len() - 1)]
+        };
+        let histogram = LATENCY_BUCKETS_MS
+            .iter()
+            .map(|&le_ms| HistogramBucket {
+                le_ms,
+                count: samples.partition_point(|&sample| sample <= le_ms) as u64,
+            })
+            .collect();
+
+        Some(Self {
+            min_ms: samples[0],
+            mean_ms: samples.iter().sum::<f64>() / samples.len() as f64,
+            p50_ms: percentile(50.0),
+            p90_ms: percentile(90.0),
+            p99_ms: percentile(99.0),
+            max_ms: samples[samples.len() - 1],
+            histogram,
+        })
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct StressReport {
+    pub target: SocketAddr,
+    pub sent: u64,
+    pub received: u64,
+    pub lost: u64,
+    /// Lost queries as a percentage of sent queries.
+    pub loss_percentage: f64,
+    /// Time spent sending, in milliseconds.
+    pub elapsed_ms: u64,
+    /// Queries actually sent per second.
+    pub achieved_qps: f64,
+    /// Latency of answered queries, if any were answered.
+    pub latency: Option<LatencySummary>,
+}
+
+impl fmt::Display for StressReport {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Target:   {}", self.target)?;
+        writeln!(
+            f,
+            "Sent:     {} ({:.1} qps over {} ms)",
+            self.sent, self.achieved_qps, self.elapsed_ms
+        )?;
+        writeln!(f, "Received: {}", self.received)?;
+        write!(f, "Lost:     {} ({:.2}%)", self.lost, self.loss_percentage)?;
+
+        if let Some(latency) = &self.latency {
+            write!(
+                f,
+                "\nLatency:  min {:.2} / mean {:.2} / p50 {:.2} / p90 {:.2} / p99 {:.2} / max {:.2} ms",
+                latency.min_ms, latency.mean_ms, latency.p50_ms, latency.p90_ms, latency.p99_ms, latency.max_ms
+            )?;
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Default)]
+struct Outstanding {
+    by_sequence: HashMap<u32, Instant>,
+    fifo: VecDeque<Instant>,
+    sent: u64,
+    lost: u64,
+    samples: Vec<f64>,
+}
+
+impl Outstanding {
+    fn expire(&mut self, timeout: Duration) {
+        let before = self.by_sequence.len() + self.fifo.len();
+        self.by_sequence.retain(|_, sent_at| sent_at.elapsed() < timeout);
+        while self.fifo.front().is_some_and(|sent_at| sent_at.elapsed() >= timeout) {
+            self.fifo.pop_front();
+        }
+        self.lost += (before - self.by_sequence.len() - self.fifo.len()) as u64;
+    }
+}
+
+#[derive(Debug)]
+pub struct StressTest {
+    config: StressConfig,
+}
+
+impl StressTest {
+    pub fn new(config: StressConfig) -> Self {
+        Self { config }
+    }
+
+    /// Runs the test and waits for outstanding answers before reporting.
+    pub async fn run(self) -> Result<StressReport> {
+        let config = self.config;
+        if config.query_type == QueryType::Rcon {
+            return Err(Error::InvalidQueryType(
+                "RCON cannot be used for stress testing".to_string(),
+            ));
+        }
+
+        let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(Error::Bind)?;
+        socket.connect(config.target).await.map_err(Error::Connect)?;
+        let request = Packet::create_query(config.target, config.query_type)?;
+        let state = Mutex::new(Outstanding::default());
+        let started = Instant::now();
+
+        let send = async {
+            // Bursts of up to 10 ms worth of queries keep timer wakeups low.
+            let limiter = RateLimiter::with_burst(config.qps.max(1) as f64, config.qps / 100);
+            let mut sequence = 0u32;
+
+            while started.elapsed() < config.duration {
+                limiter.acquire_global().await;
+                if started.elapsed() >= config.duration {
+                    break;
+                }
+                let mut data = request.as_bytes().to_vec();
+                if config.query_type == QueryType::Ping {
+                    data.extend_from_slice(&sequence.to_le_bytes());
+                }
+
+                {
+                    let mut state = state.lock().unwrap();
+                    state.sent += 1;
+                    if config.query_type == QueryType::Ping {
+                        state.by_sequence.insert(sequence, Instant::now());
+                    } else {
+                        state.fifo.push_back(Instant::now());
+                    }
+                }
+                sequence = sequence.wrapping_add(1);
+
+                socket.send(&data).await.map_err(Error::Send)?;
+            }
+
+            Ok::<Duration, Error>(started.elapsed())
+        };
+
+        let receive = async {
+            let mut buf = vec![0u8; constants::MAX_PACKET_SIZE];
+            loop {
+                let Ok(size) = socket.recv(&mut buf).await else {
+                    continue;
+                };
+                let response = Packet::from_bytes(&buf[..size]);
+                if response.query_type() != Some(config.query_type) {
+                    continue;
+                }
+
+                let mut state = state.lock().unwrap();
+                state.expire(config.timeout);
+                let sent_at = if config.query_type == QueryType::Ping {
+                    buf.get(constants::HEADER_SIZE..constants::HEADER_SIZE + 4)
+                        .map(|seq| u32::from_le_bytes([seq[0], seq[1], seq[2], seq[3]]))
+                        .and_then(|seq| state.by_sequence.remove(&seq))
+                } else {
+                    state.fifo.pop_front()
+                };
+                if let Some(sent_at) = sent_at {
+                    state.samples.push(sent_at.elapsed().as_secs_f64() * 1000.0);
+                }
+            }
+        };
+
+        let elapsed = tokio::select! {
+            result = async {
+                let elapsed = send.await?;
+                tokio::time::sleep(config.timeout).await;
+                Ok::<Duration, Error>(elapsed)
+            } => result?,
+            _ = receive => unreachable!("the receive loop never ends"),
+        };
+
+        let mut state = state.into_inner().unwrap();
+        state.lost += (state.by_sequence.len() + state.fifo.len()) as u64;
+        let received = state.samples.len() as u64;
+        let elapsed_ms = elapsed.as_millis() as u64;
+
+        Ok(StressReport {
+            target: config.target,
+            sent: state.sent,
+            received,
+            lost: state.lost,
+            loss_percentage: if state.sent > 0 {
+                state.lost as f64 / state.sent as f64 * 100.0
+            } else {
+                0.0
+            },
+            elapsed_ms,
+            achieved_qps: state.sent as f64 / elapsed.as_secs_f64().max(f64::EPSILON),
+            latency: LatencySummary::from_samples(state.samples),
+        })
+    }
+}
@@ -0,0 +1,86 @@
+//! Pluggable datagram transport.
+//!
+//! [`Client`](crate::Client) talks to a server through a [`Transport`], a
+//! connected datagram channel. With the default `net` feature the crate
+//! provides [`UdpTransport`] on top of tokio; without it the socket layer is
+//! compiled out, so the protocol, packet and type modules build for targets
+//! such as `wasm32-wasip1`, where the host supplies its own transport.
+//!
+//! ```rust
+//! use async_trait::async_trait;
+//! use samp_query::transport::Transport;
+//! use std::sync::Mutex;
+//!
+//! /// Answers every query with a canned response.
+//! struct Canned(Mutex<Vec<u8>>);
+//!
+//! #[async_trait]
+//! impl Transport for Canned {
+//!     async fn send(&self, _data: &[u8]) -> samp_query::Result<()> {
+//!         Ok(())
+//!     }
+//!
+//!     async fn recv(&self, buf: &mut [u8]) -> samp_query::Result<usize> {
+//!         let response = self.0.lock().unwrap();
+//!         buf[..response.len()].copy_from_slice(&response);
+//!         Ok(response.len())
+//!     }
+//! }
+//! ```
+
+use crate::error::Result;
+use async_trait::async_trait;
+
+/// A datagram channel connected to a single server.
+#[async_trait]
+pub trait Transport: Send + Sync {
+    /// Sends one datagram.
+    async fn send(&self, data: &[u8]) -> Result<()>;
+
+    /// Waits for the next datagram and returns its size.
+    async fn recv(&self, buf: &mut [u8]) -> Result<usize>;
+}
+
+#[cfg(feature = "net")]
+pub use udp::UdpTransport;
+
+#[cfg(feature = "net")]
+mod udp {
+    use super::Transport;
+    use crate::error::{Error, Result};
+    use async_trait::async_trait;
+    use std::net::SocketAddr;
+    use tokio::net::UdpSocket;
+
+    /// A tokio UDP socket connected to the server.
+    #[derive(Debug)]
+    pub struct UdpTransport {
+        socket: UdpSocket,
+    }
+
+    impl UdpTransport {
+        /// Binds an ephemeral local port and connects it to `server_addr`.
+        pub async fn connect(server_addr: SocketAddr) -> Result<Self> {
+            let socket = UdpSocket::bind("0.0.0.0:0").await.map_err(Error::Bind)?;
+            socket.connect(server_addr).await.map_err(Error::Connect)?;
+
+            Ok(Self { socket })
+        }
+
+        pub fn local_addr(&self) -> Result<SocketAddr> {
+            Ok(self.socket.local_addr()?)
+        }
+    }
+
+    #[async_trait]
+    impl Transport for UdpTransport {
+        async fn send(&self, data: &[u8]) -> Result<()> {
+            self.socket.send(data).await.map_err(Error::Send)?;
+            Ok(())
+        }
+
+        async fn recv(&self, buf: &mut [u8]) -> Result<usize> {
+            self.socket.recv(buf).await.map_err(Error::Receive)
+        }
+    }
+}
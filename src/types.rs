@@ -3,8 +3,9 @@
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 use std::fmt;
+use std::net::SocketAddr;
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ServerInfo {
     /// Whether the server has a password.
     pub password: bool,
@@ -31,7 +32,7 @@ impl fmt::Display for ServerInfo {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct ServerRules {
     pub rules: HashMap<String, String>,
 }
@@ -46,7 +47,7 @@ impl fmt::Display for ServerRules {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct Player {
     /// Players nickname.
     pub name: String,
@@ -60,7 +61,7 @@ impl fmt::Display for Player {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DetailedPlayer {
     /// Players ID.
     pub id: u8,
@@ -82,7 +83,7 @@ impl fmt::Display for DetailedPlayer {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PlayerList {
     /// List of players.
     pub players: Vec<Player>,
@@ -98,7 +99,7 @@ impl fmt::Display for PlayerList {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct DetailedPlayerList {
     /// List of detailed players.
     pub players: Vec<DetailedPlayer>,
@@ -114,7 +115,7 @@ impl fmt::Display for DetailedPlayerList {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct RconResponse {
     /// Response message.
     pub message: String,
@@ -126,7 +127,7 @@ impl fmt::Display for RconResponse {
     }
 }
 
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PingInfo {
     /// Ping time in milliseconds.
     pub ping_ms: u64,
@@ -137,3 +138,36 @@ impl fmt::Display for PingInfo {
         write!(f, "Ping: {} ms", self.ping_ms)
     }
 }
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ServerSnapshot {
+    /// Address of the queried server.
+    pub address: SocketAddr,
+    /// Unix timestamp in milliseconds at which the snapshot was taken.
+    pub timestamp: u64,
+    /// Server information.
+    pub info: ServerInfo,
+    /// Server rules, if the rules query succeeded.
+    pub rules: Option<ServerRules>,
+    /// Detailed player list, if the server answered the detailed player query.
+    pub players: Option<DetailedPlayerList>,
+    /// Server ping, if the ping query succeeded.
+    pub ping: Option<PingInfo>,
+}
+
+impl fmt::Display for ServerSnapshot {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "Address: {}", self.address)?;
+        write!(f, "{}", self.info)?;
+        if let Some(ping) = &self.ping {
+            writeln!(f, "{}", ping)?;
+        }
+        if let Some(rules) = &self.rules {
+            write!(f, "{}", rules)?;
+        }
+        if let Some(players) = &self.players {
+            write!(f, "{}", players)?;
+        }
+        Ok(())
+    }
+}
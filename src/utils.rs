@@ -1,7 +1,10 @@
 //! Utility functions for the SAMP Query library.
 
-use std::net::{IpAddr, Ipv4Addr, SocketAddr};
-use std::time::{Duration, Instant};
+use crate::types::ServerSnapshot;
+use std::collections::HashMap;
+use std::fmt::Write;
+use std::net::{IpAddr, Ipv4Addr, Ipv6Addr, SocketAddr};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 
 /// Converts a string to a socket address.
 ///
@@ -40,6 +43,116 @@ pub fn parse_address(address: &str) -> Result<SocketAddr, String> {
     ))
 }
 
+/// Splits user input into a host and a port.
+///
+/// Accepts `host:port`, `host,port` (as copied from many server lists) and a
+/// bare host, in which case `default_port` is used. Surrounding whitespace is
+/// ignored and the host is lowercased.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::split_host_port;
+///
+/// assert_eq!(split_host_port(" Play.Example.com ", 7777).unwrap(), ("play.example.com".to_string(), 7777));
+/// assert_eq!(split_host_port("127.0.0.1, 7778", 7777).unwrap(), ("127.0.0.1".to_string(), 7778));
+/// ```
+pub fn split_host_port(input: &str, default_port: u16) -> Result<(String, u16), String> {
+    let input = input.trim();
+    if input.is_empty() {
+        return Err("Address is empty".to_string());
+    }
+
+    if let Ok(addr) = input.parse::<SocketAddr>() {
+        return Ok((addr.ip().to_string(), addr.port()));
+    }
+    if let Ok(ip) = input.parse::<IpAddr>() {
+        return Ok((ip.to_string(), default_port));
+    }
+    if let Some(ip) = input.strip_prefix('[').and_then(|rest| rest.strip_suffix(']')) {
+        let ip = ip.parse::<Ipv6Addr>().map_err(|_| format!("Invalid host: {}", input))?;
+        return Ok((ip.to_string(), default_port));
+    }
+
+    let (host, port) = match input.rfind([':', ',']) {
+        Some(idx) => {
+            let port_str = input[idx + 1..].trim();
+            let port = port_str
+                .parse::<u16>()
+                .map_err(|_| format!("Invalid port: {}", port_str))?;
+            (input[..idx].trim(), port)
+        }
+        None => (input, default_port),
+    };
+
+    if host.is_empty() || host.chars().any(|c| c.is_whitespace() || c == '/') {
+        return Err(format!("Invalid host: {}", host));
+    }
+
+    Ok((host.to_lowercase(), port))
+}
+
+/// Joins a host and a port as `host:port`, bracketing IPv6 addresses so the
+/// result parses back.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::join_host_port;
+///
+/// assert_eq!(join_host_port("127.0.0.1", 7777), "127.0.0.1:7777");
+/// assert_eq!(join_host_port("::1", 7777), "[::1]:7777");
+/// ```
+pub fn join_host_port(host: &str, port: u16) -> String {
+    if host.contains(':') {
+        format!("[{}]:{}", host, port)
+    } else {
+        format!("{}:{}", host, port)
+    }
+}
+
+/// Normalizes a user-supplied server address.
+///
+/// Returns the canonical `host:port` string together with the resolved socket
+/// address. Hostnames are resolved with the system resolver and IPv4 results
+/// are preferred, since the query protocol only supports IPv4.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::normalize_address;
+///
+/// let (canonical, addr) = normalize_address("127.0.0.1,7778 ", 7777).unwrap();
+/// assert_eq!(canonical, "127.0.0.1:7778");
+/// assert_eq!(addr.port(), 7778);
+///
+/// let (canonical, _) = normalize_address("127.0.0.1", 7777).unwrap();
+/// assert_eq!(canonical, "127.0.0.1:7777");
+///
+/// let (canonical, addr) = normalize_address("[::1]", 7777).unwrap();
+/// assert_eq!(canonical, "[::1]:7777");
+/// assert_eq!(canonical.parse(), Ok(addr));
+/// ```
+pub fn normalize_address(input: &str, default_port: u16) -> Result<(String, SocketAddr), String> {
+    let (host, port) = split_host_port(input, default_port)?;
+    let canonical = join_host_port(&host, port);
+
+    if let Ok(ip) = host.parse::<IpAddr>() {
+        return Ok((canonical, SocketAddr::new(ip, port)));
+    }
+
+    let addrs: Vec<SocketAddr> = std::net::ToSocketAddrs::to_socket_addrs(&(host.as_str(), port))
+        .map_err(|e| format!("Failed to resolve {}: {}", host, e))?
+        .collect();
+
+    addrs
+        .iter()
+        .find(|addr| addr.is_ipv4())
+        .or_else(|| addrs.first())
+        .map(|addr| (canonical, *addr))
+        .ok_or_else(|| format!("No addresses found for {}", host))
+}
+
 /// Formats a duration as a human-readable string.
 ///
 /// # Examples
@@ -65,6 +178,38 @@ pub fn format_duration(duration: &Duration) -> String {
     }
 }
 
+/// Parses a duration such as `500ms`, `90s`, `30m`, `24h`, `7d` or `2w`.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::parse_duration;
+/// use std::time::Duration;
+///
+/// assert_eq!(parse_duration("30m").unwrap(), Duration::from_secs(1800));
+/// assert_eq!(parse_duration("500ms").unwrap(), Duration::from_millis(500));
+/// assert!(parse_duration("10").is_err());
+/// assert!(parse_duration(&format!("{}w", u64::MAX)).is_err());
+/// ```
+pub fn parse_duration(input: &str) -> Result<Duration, String> {
+    let input = input.trim();
+    let invalid = || format!("expected a duration like 90s, 30m, 24h or 7d, got {:?}", input);
+    let split = input.find(|c: char| !c.is_ascii_digit()).unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+    let number: u64 = number.parse().map_err(|_| invalid())?;
+
+    let seconds = match unit {
+        "ms" => return Ok(Duration::from_millis(number)),
+        "s" => 1,
+        "m" => 60,
+        "h" => 3600,
+        "d" => 86_400,
+        "w" => 604_800,
+        _ => return Err(invalid()),
+    };
+    number.checked_mul(seconds).map(Duration::from_secs).ok_or_else(invalid)
+}
+
 /// Measures the execution time of a function.
 ///
 /// # Examples
@@ -91,6 +236,35 @@ where
     (result, duration)
 }
 
+/// Measures the execution time of a future.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::measure_time_async;
+///
+/// # tokio_test::block_on(async {
+/// let (result, duration) = measure_time_async(async {
+///     // Some async work
+///     42
+/// })
+/// .await;
+///
+/// assert_eq!(result, 42);
+/// println!("Execution time: {:?}", duration);
+/// # });
+/// ```
+pub async fn measure_time_async<Fut, T>(fut: Fut) -> (T, Duration)
+where
+    Fut: std::future::Future<Output = T>,
+{
+    let start = Instant::now();
+    let result = fut.await;
+    let duration = start.elapsed();
+
+    (result, duration)
+}
+
 /// Formats a byte size as a human-readable string.
 ///
 /// # Examples
@@ -116,3 +290,598 @@ pub fn format_bytes(bytes: u64) -> String {
         format!("{:.1} GiB", bytes as f64 / GB as f64)
     }
 }
+
+/// Backoff strategy used between retry attempts.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Backoff {
+    /// Retry immediately without waiting.
+    None,
+    /// Wait `initial_delay` between every attempt.
+    Fixed,
+    /// Double the delay after every attempt, starting at `initial_delay`.
+    Exponential,
+}
+
+/// Policy describing how often and how quickly an operation is retried.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Maximum number of attempts, including the first one.
+    pub max_attempts: usize,
+    /// Backoff strategy between attempts.
+    pub backoff: Backoff,
+    /// Delay before the second attempt.
+    pub initial_delay: Duration,
+    /// Upper bound for a single delay.
+    pub max_delay: Duration,
+    /// Stop retrying once this much time has passed since the first attempt.
+    pub max_elapsed: Option<Duration>,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        Self {
+            max_attempts: crate::protocol::constants::MAX_RETRIES,
+            backoff: Backoff::Exponential,
+            initial_delay: Duration::from_millis(100),
+            max_delay: Duration::from_secs(5),
+            max_elapsed: None,
+        }
+    }
+}
+
+impl RetryPolicy {
+    /// A policy that retries up to `max_attempts` times without waiting.
+    pub fn immediate(max_attempts: usize) -> Self {
+        Self {
+            max_attempts,
+            backoff: Backoff::None,
+            ..Self::default()
+        }
+    }
+
+    /// Returns the delay to wait after the given (zero-based) failed attempt.
+    pub fn delay_for(&self, attempt: usize) -> Duration {
+        let delay = match self.backoff {
+            Backoff::None => Duration::ZERO,
+            Backoff::Fixed => self.initial_delay,
+            Backoff::Exponential => {
+                let factor = 1u32.checked_shl(attempt.min(31) as u32).unwrap_or(u32::MAX);
+                self.initial_delay.saturating_mul(factor)
+            }
+        };
+
+        delay.min(self.max_delay)
+    }
+}
+
+/// Runs an async operation, retrying every failure according to `policy`.
+///
+/// The closure receives the zero-based attempt number. The last error is
+/// returned once the policy is exhausted.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::{retry, RetryPolicy};
+///
+/// # tokio_test::block_on(async {
+/// let result: Result<usize, &str> = retry(&RetryPolicy::immediate(3), |attempt| async move {
+///     if attempt < 2 { Err("not yet") } else { Ok(attempt) }
+/// })
+/// .await;
+///
+/// assert_eq!(result, Ok(2));
+/// # });
+/// ```
+pub async fn retry<T, E, F, Fut>(policy: &RetryPolicy, op: F) -> Result<T, E>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+{
+    retry_if(policy, op, |_| true).await
+}
+
+/// Like [`retry`], but only retries errors for which `should_retry` returns true.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::{retry_if, RetryPolicy};
+/// use samp_query::Error;
+///
+/// # tokio_test::block_on(async {
+/// let result: Result<(), Error> = retry_if(
+///     &RetryPolicy::immediate(3),
+///     |_| async { Err(Error::RconAuthFailed) },
+///     |e| e.is_timeout(),
+/// )
+/// .await;
+///
+/// assert!(result.unwrap_err().is_auth_error());
+/// # });
+/// ```
+pub async fn retry_if<T, E, F, Fut, P>(policy: &RetryPolicy, mut op: F, should_retry: P) -> Result<T, E>
+where
+    F: FnMut(usize) -> Fut,
+    Fut: std::future::Future<Output = Result<T, E>>,
+    P: Fn(&E) -> bool,
+{
+    let start = Instant::now();
+    let max_attempts = policy.max_attempts.max(1);
+    let mut attempt = 0;
+
+    loop {
+        match op(attempt).await {
+            Ok(value) => return Ok(value),
+            Err(e) => {
+                attempt += 1;
+
+                if attempt >= max_attempts || !should_retry(&e) {
+                    return Err(e);
+                }
+
+                let delay = policy.delay_for(attempt - 1);
+                if let Some(max_elapsed) = policy.max_elapsed {
+                    if start.elapsed() + delay >= max_elapsed {
+                        return Err(e);
+                    }
+                }
+
+                if !delay.is_zero() {
+                    tokio::time::sleep(delay).await;
+                }
+            }
+        }
+    }
+}
+
+/// Normalizes a server hostname for comparison.
+///
+/// Strips SA-MP `{RRGGBB}` color codes, lowercases the text and collapses
+/// runs of whitespace into a single space.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::normalize_hostname;
+///
+/// assert_eq!(normalize_hostname("{FF0000}My   Server {FFFFFF}RP "), "my server rp");
+/// ```
+pub fn normalize_hostname(hostname: &str) -> String {
+    let chars: Vec<char> = hostname.chars().collect();
+    let mut stripped = String::with_capacity(hostname.len());
+    let mut i = 0;
+
+    while i < chars.len() {
+        if chars[i] == '{'
+            && i + 7 < chars.len()
+            && chars[i + 7] == '}'
+            && chars[i + 1..i + 7].iter().all(|c| c.is_ascii_hexdigit())
+        {
+            i += 8;
+            continue;
+        }
+        stripped.push(chars[i]);
+        i += 1;
+    }
+
+    stripped
+        .to_lowercase()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Computes how similar two hostnames are, from `0.0` (unrelated) to `1.0` (identical).
+///
+/// Both hostnames are normalized with [`normalize_hostname`] and compared using
+/// the Levenshtein edit distance relative to the longer name.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::hostname_similarity;
+///
+/// assert_eq!(hostname_similarity("{00FF00}Best RP", "best rp"), 1.0);
+/// assert!(hostname_similarity("Best RP | Server 1", "Best RP | Server 2") > 0.9);
+/// assert!(hostname_similarity("Best RP", "Freeroam Party") < 0.5);
+/// ```
+pub fn hostname_similarity(a: &str, b: &str) -> f64 {
+    let a: Vec<char> = normalize_hostname(a).chars().collect();
+    let b: Vec<char> = normalize_hostname(b).chars().collect();
+
+    let longest = a.len().max(b.len());
+    if longest == 0 {
+        return 1.0;
+    }
+
+    let mut previous: Vec<usize> = (0..=b.len()).collect();
+    let mut current = vec![0; b.len() + 1];
+
+    for (i, ca) in a.iter().enumerate() {
+        current[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let cost = if ca == cb { 0 } else { 1 };
+            current[j + 1] = (previous[j + 1] + 1)
+                .min(current[j] + 1)
+                .min(previous[j] + cost);
+        }
+        std::mem::swap(&mut previous, &mut current);
+    }
+
+    1.0 - previous[b.len()] as f64 / longest as f64
+}
+
+/// A `{name}` or `{name|fallback}` placeholder of a template.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Placeholder<'a> {
+    pub name: &'a str,
+    /// The text after `|`, if there is one.
+    pub fallback: Option<&'a str>,
+}
+
+/// Replaces `{name}` placeholders with values from `vars`.
+///
+/// Placeholders without a matching variable are left untouched, so SA-MP
+/// `{RRGGBB}` color codes survive rendering, unless they give a fallback
+/// after `|`, as in `{ping|?}`. `{{` and `}}` are literal braces.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::render_template;
+/// use std::collections::HashMap;
+///
+/// let vars = HashMap::from([("players".to_string(), "42".to_string())]);
+/// assert_eq!(render_template("{FF0000}{players} online", &vars), "{FF0000}42 online");
+/// assert_eq!(render_template("{ping|?} ms {{players}}", &vars), "? ms {players}");
+/// ```
+pub fn render_template(template: &str, vars: &HashMap<String, String>) -> String {
+    render_template_with(template, |placeholder| {
+        vars.get(placeholder.name)
+            .cloned()
+            .or_else(|| placeholder.fallback.map(str::to_string))
+    })
+}
+
+/// Like [`render_template`], with `value` giving the text of each
+/// placeholder, or `None` to leave it untouched.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::render_template_with;
+///
+/// let rendered = render_template_with("{a} {b|none} {c}", |placeholder| match placeholder.name {
+///     "c" => None,
+///     name => Some(placeholder.fallback.unwrap_or(name).to_uppercase()),
+/// });
+/// assert_eq!(rendered, "A NONE {c}");
+/// ```
+pub fn render_template_with<'a>(template: &'a str, mut value: impl FnMut(Placeholder<'a>) -> Option<String>) -> String {
+    let mut output = String::with_capacity(template.len());
+    let mut rest = template;
+
+    while let Some(start) = rest.find(['{', '}']) {
+        output.push_str(&rest[..start]);
+        let brace = &rest[start..start + 1];
+        let after = &rest[start + 1..];
+        if after.starts_with(brace) {
+            output.push_str(brace);
+            rest = &after[1..];
+            continue;
+        }
+        if brace == "}" {
+            output.push('}');
+            rest = after;
+            continue;
+        }
+
+        let replaced = after.find('}').and_then(|end| {
+            let placeholder = match after[..end].split_once('|') {
+                Some((name, fallback)) => Placeholder {
+                    name,
+                    fallback: Some(fallback),
+                },
+                None => Placeholder {
+                    name: &after[..end],
+                    fallback: None,
+                },
+            };
+            Some((end, value(placeholder)?))
+        });
+        match replaced {
+            Some((end, text)) => {
+                output.push_str(&text);
+                rest = &after[end + 1..];
+            }
+            None => {
+                output.push('{');
+                rest = after;
+            }
+        }
+    }
+    output.push_str(rest);
+
+    output
+}
+
+/// The placeholders of a template, in order, as [`render_template`] sees
+/// them.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::{template_placeholders, Placeholder};
+///
+/// let placeholders = template_placeholders("{{literal}} {hostname} ({ping|?})");
+/// assert_eq!(placeholders, [
+///     Placeholder { name: "hostname", fallback: None },
+///     Placeholder { name: "ping", fallback: Some("?") },
+/// ]);
+/// ```
+pub fn template_placeholders(template: &str) -> Vec<Placeholder<'_>> {
+    let mut placeholders = Vec::new();
+    render_template_with(template, |placeholder| {
+        placeholders.push(placeholder);
+        None
+    });
+    placeholders
+}
+
+/// Returns the current Unix time in milliseconds.
+pub fn unix_timestamp_ms() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_millis() as u64)
+        .unwrap_or(0)
+}
+
+/// Formats snapshots in the Prometheus text exposition format.
+///
+/// Every sample carries an `address` label plus the given extra labels.
+/// Each metric family is written once, with the samples of every snapshot.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::to_prometheus;
+/// use samp_query::{ServerInfo, ServerSnapshot};
+///
+/// let snapshot = ServerSnapshot {
+///     address: "127.0.0.1:7777".parse().unwrap(),
+///     timestamp: 0,
+///     info: ServerInfo {
+///         password: false,
+///         players: 5,
+///         max_players: 50,
+///         hostname: "Test Server".to_string(),
+///         gamemode: "Freeroam".to_string(),
+///         language: "English".to_string(),
+///     },
+///     rules: None,
+///     players: None,
+///     ping: None,
+/// };
+/// let other = ServerSnapshot {
+///     address: "127.0.0.1:7778".parse().unwrap(),
+///     ..snapshot.clone()
+/// };
+///
+/// let text = to_prometheus(&[snapshot, other], &[("env", "prod")]);
+/// assert!(text.contains("samp_players{address=\"127.0.0.1:7777\",env=\"prod\"} 5"));
+/// assert!(text.contains("samp_players{address=\"127.0.0.1:7778\",env=\"prod\"} 5"));
+/// assert_eq!(text.matches("# TYPE samp_players gauge").count(), 1);
+/// ```
+pub fn to_prometheus(snapshots: &[ServerSnapshot], labels: &[(&str, &str)]) -> String {
+    type Value = fn(&ServerSnapshot) -> Option<u64>;
+    let families: [(&str, &str, Value); 5] = [
+        ("samp_up", "Whether the server answered the information query.", |_| Some(1)),
+        ("samp_players", "Current number of players online.", |s| Some(s.info.players as u64)),
+        ("samp_max_players", "Maximum number of players.", |s| Some(s.info.max_players as u64)),
+        ("samp_password", "Whether the server is password protected.", |s| Some(s.info.password as u64)),
+        ("samp_ping_ms", "Server ping in milliseconds.", |s| s.ping.as_ref().map(|ping| ping.ping_ms)),
+    ];
+
+    let label_texts: Vec<String> = snapshots
+        .iter()
+        .map(|snapshot| {
+            let mut text = format!("address=\"{}\"", escape_prometheus_label(&snapshot.address.to_string()));
+            for (name, value) in labels {
+                let _ = write!(text, ",{}=\"{}\"", name, escape_prometheus_label(value));
+            }
+            text
+        })
+        .collect();
+
+    let mut output = String::new();
+    for (name, help, value) in families {
+        let samples: Vec<_> = snapshots
+            .iter()
+            .zip(&label_texts)
+            .filter_map(|(snapshot, labels)| value(snapshot).map(|value| (labels, value)))
+            .collect();
+        if samples.is_empty() {
+            continue;
+        }
+        let _ = writeln!(output, "# HELP {} {}", name, help);
+        let _ = writeln!(output, "# TYPE {} gauge", name);
+        for (labels, value) in samples {
+            let _ = writeln!(output, "{}{{{}}} {}", name, labels, value);
+        }
+    }
+
+    output
+}
+
+/// Formats a snapshot as a single InfluxDB line-protocol record.
+///
+/// The server address is always added as a tag; the snapshot timestamp is
+/// written with nanosecond precision.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::to_influx_line;
+/// use samp_query::{ServerInfo, ServerSnapshot};
+///
+/// let snapshot = ServerSnapshot {
+///     address: "127.0.0.1:7777".parse().unwrap(),
+///     timestamp: 1000,
+///     info: ServerInfo {
+///         password: false,
+///         players: 5,
+///         max_players: 50,
+///         hostname: "Test Server".to_string(),
+///         gamemode: "Freeroam".to_string(),
+///         language: "English".to_string(),
+///     },
+///     rules: None,
+///     players: None,
+///     ping: None,
+/// };
+///
+/// assert_eq!(
+///     to_influx_line(&snapshot, "samp", &[("region", "eu west")]),
+///     "samp,address=127.0.0.1:7777,region=eu\\ west players=5i,max_players=50i,password=false,hostname=\"Test Server\",gamemode=\"Freeroam\" 1000000000"
+/// );
+/// ```
+pub fn to_influx_line(snapshot: &ServerSnapshot, measurement: &str, tags: &[(&str, &str)]) -> String {
+    let mut line = escape_influx_key(measurement);
+    let _ = write!(line, ",address={}", escape_influx_key(&snapshot.address.to_string()));
+    for (name, value) in tags {
+        let _ = write!(line, ",{}={}", escape_influx_key(name), escape_influx_key(value));
+    }
+
+    let _ = write!(
+        line,
+        " players={}i,max_players={}i,password={}",
+        snapshot.info.players, snapshot.info.max_players, snapshot.info.password
+    );
+    if let Some(ping) = &snapshot.ping {
+        let _ = write!(line, ",ping_ms={}i", ping.ping_ms);
+    }
+    let _ = write!(
+        line,
+        ",hostname=\"{}\",gamemode=\"{}\" {}",
+        escape_influx_string(&snapshot.info.hostname),
+        escape_influx_string(&snapshot.info.gamemode),
+        snapshot.timestamp as u128 * 1_000_000
+    );
+
+    line
+}
+
+pub(crate) fn escape_prometheus_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+pub(crate) fn escape_influx_key(value: &str) -> String {
+    value
+        .replace(',', "\\,")
+        .replace('=', "\\=")
+        .replace(' ', "\\ ")
+}
+
+pub(crate) fn escape_influx_string(value: &str) -> String {
+    value.replace('\\', "\\\\").replace('"', "\\\"")
+}
+
+/// Recommends a minimum interval between queries to a single server.
+///
+/// Servers with query flood protection silently drop queries that arrive too
+/// quickly, which shows up as timeouts right after a burst. The advisor
+/// doubles its recommendation whenever a timeout follows the previous query
+/// within the
+/// [`QUERY_FLOOD_WINDOW_MS`](crate::protocol::constants::QUERY_FLOOD_WINDOW_MS)
+/// the server counts queries over, and slowly relaxes back towards
+/// [`DEFAULT_QUERY_COOLDOWN_MS`](crate::protocol::constants::DEFAULT_QUERY_COOLDOWN_MS)
+/// after a run of successful queries.
+///
+/// # Examples
+///
+/// ```
+/// use samp_query::utils::QueryIntervalAdvisor;
+/// use std::time::{Duration, Instant};
+///
+/// let mut advisor = QueryIntervalAdvisor::new();
+/// let start = Instant::now();
+///
+/// advisor.record(start, false);
+/// advisor.record(start + Duration::from_millis(10), true);
+///
+/// assert!(advisor.recommended_interval() > Duration::from_millis(250));
+///
+/// // A timeout long after the previous query does not raise it further.
+/// let before = advisor.recommended_interval();
+/// advisor.record(start + Duration::from_secs(5), true);
+/// assert_eq!(advisor.recommended_interval(), before);
+/// ```
+#[derive(Debug, Clone)]
+pub struct QueryIntervalAdvisor {
+    interval: Duration,
+    floor: Duration,
+    last_query: Option<Instant>,
+    successes: usize,
+}
+
+impl Default for QueryIntervalAdvisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl QueryIntervalAdvisor {
+    /// Number of consecutive successes before the interval is relaxed.
+    const RELAX_AFTER: usize = 10;
+
+    /// Creates an advisor starting at the default query cooldown.
+    pub fn new() -> Self {
+        Self::with_floor(Duration::from_millis(
+            crate::protocol::constants::DEFAULT_QUERY_COOLDOWN_MS,
+        ))
+    }
+
+    /// Creates an advisor that never recommends less than `floor`.
+    pub fn with_floor(floor: Duration) -> Self {
+        Self {
+            interval: floor,
+            floor,
+            last_query: None,
+            successes: 0,
+        }
+    }
+
+    /// Records the outcome of a query sent at `sent_at`.
+    pub fn record(&mut self, sent_at: Instant, timed_out: bool) {
+        let gap = self
+            .last_query
+            .map(|last| sent_at.saturating_duration_since(last));
+        self.last_query = Some(sent_at);
+
+        let max = Duration::from_millis(crate::protocol::constants::MAX_QUERY_INTERVAL_MS);
+        let flood_window = Duration::from_millis(crate::protocol::constants::QUERY_FLOOD_WINDOW_MS);
+
+        if timed_out {
+            self.successes = 0;
+            // Timeouts after a longer gap are not the flood protection's doing.
+            if gap.is_some_and(|gap| gap < flood_window) {
+                self.interval = (self.interval * 2).min(max);
+            }
+        } else {
+            self.successes += 1;
+            if self.successes >= Self::RELAX_AFTER {
+                self.successes = 0;
+                self.interval = self.interval.mul_f64(0.9).max(self.floor);
+            }
+        }
+    }
+
+    /// Returns the currently recommended minimum interval between queries.
+    pub fn recommended_interval(&self) -> Duration {
+        self.interval
+    }
+}
@@ -0,0 +1,293 @@
+//! Restarting servers that stop answering.
+//!
+//! A [`Watchdog`] queries one server on an interval and, after a number of
+//! consecutive failed checks, runs a [`RestartHook`]: a shell command
+//! ([`CommandHook`]), an async closure ([`hook_fn`]) or any custom hook.
+//!
+//! Two guards keep a broken server from being restarted in a loop. After
+//! the hook runs, it is held back for a cooldown while the server comes
+//! back up. And when the hook has run too often within the flap window, it
+//! is held back until the oldest of those runs leaves the window, so a
+//! server that dies right after every restart needs a human instead.
+//!
+//! ```rust,no_run
+//! use futures::StreamExt;
+//! use samp_query::watchdog::{CommandHook, Watchdog, WatchdogConfig};
+//!
+//! # async fn run() {
+//! let hook = CommandHook::new("systemctl", &["restart", "samp-server"]);
+//! let watchdog = Watchdog::new("127.0.0.1:7777".parse().unwrap(), WatchdogConfig::default(), hook);
+//!
+//! let mut events = watchdog.stream();
+//! while let Some(event) = events.next().await {
+//!     println!("{:?}", event);
+//! }
+//! # }
+//! ```
+
+use crate::client::{Client, ClientConfig};
+use crate::error::{Error, Result};
+use crate::protocol::constants;
+use async_trait::async_trait;
+use futures::stream::{self, BoxStream, StreamExt};
+use std::collections::VecDeque;
+use std::future::Future;
+use std::net::SocketAddr;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::time::{Instant, MissedTickBehavior};
+use tracing::{info, warn};
+
+#[derive(Debug, Clone)]
+pub struct WatchdogConfig {
+    pub client: ClientConfig,
+    /// Time between two checks.
+    pub interval: Duration,
+    /// Consecutive failed checks that trigger the hook.
+    pub failure_threshold: u32,
+    /// Minimum time between two runs of the hook.
+    pub cooldown: Duration,
+    /// Window over which hook runs are counted for flap damping.
+    pub flap_window: Duration,
+    /// Maximum hook runs within the flap window.
+    pub max_restarts: usize,
+}
+
+impl Default for WatchdogConfig {
+    fn default() -> Self {
+        Self {
+            client: ClientConfig::default(),
+            interval: Duration::from_secs(10),
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(120),
+            flap_window: Duration::from_secs(30 * 60),
+            max_restarts: 3,
+        }
+    }
+}
+
+/// Action run when a watched server is considered down.
+#[async_trait]
+pub trait RestartHook: Send + Sync {
+    async fn trigger(&self, addr: SocketAddr) -> Result<()>;
+}
+
+#[async_trait]
+impl<H: RestartHook + ?Sized> RestartHook for Box<H> {
+    async fn trigger(&self, addr: SocketAddr) -> Result<()> {
+        (**self).trigger(addr).await
+    }
+}
+
+#[async_trait]
+impl<H: RestartHook + ?Sized> RestartHook for Arc<H> {
+    async fn trigger(&self, addr: SocketAddr) -> Result<()> {
+        (**self).trigger(addr).await
+    }
+}
+
+/// Runs a program, failing when it exits unsuccessfully.
+#[derive(Debug, Clone)]
+pub struct CommandHook {
+    program: String,
+    args: Vec<String>,
+}
+
+impl CommandHook {
+    pub fn new(program: &str, args: &[&str]) -> Self {
+        Self {
+            program: program.to_string(),
+            args: args.iter().map(|arg| arg.to_string()).collect(),
+        }
+    }
+}
+
+#[async_trait]
+impl RestartHook for CommandHook {
+    async fn trigger(&self, _addr: SocketAddr) -> Result<()> {
+        let status = tokio::process::Command::new(&self.program)
+            .args(&self.args)
+            .status()
+            .await?;
+        if !status.success() {
+            return Err(Error::Other(format!("{} exited with {}", self.program, status)));
+        }
+
+        Ok(())
+    }
+}
+
+/// A hook running an async closure; see [`hook_fn`].
+#[derive(Debug, Clone)]
+pub struct FnHook<F>(F);
+
+/// Wraps an async closure as a [`RestartHook`].
+pub fn hook_fn<F, Fut>(f: F) -> FnHook<F>
+where
+    F: Fn(SocketAddr) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    FnHook(f)
+}
+
+#[async_trait]
+impl<F, Fut> RestartHook for FnHook<F>
+where
+    F: Fn(SocketAddr) -> Fut + Send + Sync,
+    Fut: Future<Output = Result<()>> + Send,
+{
+    async fn trigger(&self, addr: SocketAddr) -> Result<()> {
+        (self.0)(addr).await
+    }
+}
+
+/// Why a due hook run was held back.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Suppression {
+    Cooldown { remaining: Duration },
+    /// The hook already ran `restarts` times within the flap window.
+    Flapping { restarts: usize },
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WatchdogEvent {
+    /// A check failed; `consecutive` counts failures since the last success
+    /// or hook run.
+    CheckFailed { consecutive: u32, error: String },
+    /// The server answered again after failing.
+    Recovered { after_failures: u32 },
+    /// The hook ran, with its error if it failed.
+    Triggered { error: Option<String> },
+    Suppressed(Suppression),
+}
+
+pub struct Watchdog {
+    addr: SocketAddr,
+    config: WatchdogConfig,
+    hook: Box<dyn RestartHook>,
+    failures: u32,
+    last_trigger: Option<Instant>,
+    /// Times of the hook runs within the flap window.
+    triggers: VecDeque<Instant>,
+}
+
+impl std::fmt::Debug for Watchdog {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("Watchdog")
+            .field("addr", &self.addr)
+            .field("config", &self.config)
+            .field("failures", &self.failures)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Watchdog {
+    pub fn new<H: RestartHook + 'static>(addr: SocketAddr, mut config: WatchdogConfig, hook: H) -> Self {
+        // A zero period makes the tokio ticker of `stream` panic.
+        config.interval = config.interval.max(Duration::from_millis(constants::MIN_POLL_INTERVAL_MS));
+        Self {
+            addr,
+            config,
+            hook: Box::new(hook),
+            failures: 0,
+            last_trigger: None,
+            triggers: VecDeque::new(),
+        }
+    }
+
+    pub fn addr(&self) -> SocketAddr {
+        self.addr
+    }
+
+    /// Consecutive failed checks since the last success or hook run.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Checks the server once and runs the hook if it is due.
+    pub async fn check(&mut self) -> Vec<WatchdogEvent> {
+        let result = match Client::connect_with_config(self.addr, self.config.client.clone()).await {
+            Ok(client) => client.query_info().await.map(|_| ()),
+            Err(e) => Err(e),
+        };
+
+        match result {
+            Ok(()) => {
+                let failures = std::mem::take(&mut self.failures);
+                if failures > 0 {
+                    info!("{} recovered after {} failed checks", self.addr, failures);
+                    vec![WatchdogEvent::Recovered { after_failures: failures }]
+                } else {
+                    Vec::new()
+                }
+            }
+            Err(e) => {
+                self.failures += 1;
+                let mut events = vec![WatchdogEvent::CheckFailed {
+                    consecutive: self.failures,
+                    error: e.to_string(),
+                }];
+                if self.failures >= self.config.failure_threshold.max(1) {
+                    events.push(self.trigger().await);
+                }
+                events
+            }
+        }
+    }
+
+    async fn trigger(&mut self) -> WatchdogEvent {
+        let now = Instant::now();
+        if let Some(remaining) = self
+            .last_trigger
+            .map(|last| self.config.cooldown.saturating_sub(now - last))
+            .filter(|remaining| !remaining.is_zero())
+        {
+            return WatchdogEvent::Suppressed(Suppression::Cooldown { remaining });
+        }
+
+        while self
+            .triggers
+            .front()
+            .is_some_and(|at| now - *at >= self.config.flap_window)
+        {
+            self.triggers.pop_front();
+        }
+        if self.triggers.len() >= self.config.max_restarts {
+            warn!("{} is flapping; not running the restart hook", self.addr);
+            return WatchdogEvent::Suppressed(Suppression::Flapping {
+                restarts: self.triggers.len(),
+            });
+        }
+
+        info!("{} failed {} checks; running the restart hook", self.addr, self.failures);
+        self.failures = 0;
+        self.last_trigger = Some(now);
+        self.triggers.push_back(now);
+
+        let error = self.hook.trigger(self.addr).await.err().map(|e| e.to_string());
+        if let Some(error) = &error {
+            warn!("Restart hook for {} failed: {}", self.addr, error);
+        }
+        WatchdogEvent::Triggered { error }
+    }
+
+    /// Checks the server on the configured interval and streams the events.
+    pub fn stream(self) -> BoxStream<'static, WatchdogEvent> {
+        let mut ticker = tokio::time::interval(self.config.interval);
+        ticker.set_missed_tick_behavior(MissedTickBehavior::Delay);
+
+        stream::unfold((self, ticker), |(mut watchdog, mut ticker)| async move {
+            ticker.tick().await;
+            let events = watchdog.check().await;
+            Some((stream::iter(events), (watchdog, ticker)))
+        })
+        .flatten()
+        .boxed()
+    }
+
+    /// Runs the watchdog until the task is cancelled.
+    pub async fn run(self) {
+        let mut events = self.stream();
+        while events.next().await.is_some() {}
+    }
+}
@@ -1,6 +1,48 @@
 //! Integration tests for the SAMP Query library.
 
-use samp_query::{Client, QueryType};
+use futures::StreamExt;
+use samp_query::aggregate::{AggregateConfig, Aggregator, RankBy};
+use samp_query::announce::{AnnounceConfig, Announcer, OMP_ENDPOINT, SAMP_ENDPOINT};
+use samp_query::cache::{CacheConfig, CachedValue, QueryCache};
+use samp_query::diff::{Change, SnapshotDiff};
+use samp_query::exporter::influx::{InfluxConfig, InfluxSink};
+use samp_query::exporter::prometheus::{ExporterConfig, PrometheusExporter};
+use samp_query::favorites::{Favorite, Favorites};
+use samp_query::filter::{RulePredicate, ServerFilter};
+use samp_query::finder::{find_player_with_config, FindConfig, NamePattern};
+use samp_query::fingerprint::{fingerprint, Category, Platform, Tag};
+use samp_query::game::{GameInfo, GameServerClient};
+use samp_query::geoip::{GeoInfo, GeoIp};
+use samp_query::history::HistoryEntry;
+use samp_query::inflation::{self, InflationReport};
+use samp_query::masterlist::omp::{OmpFilter, OmpServerEntry};
+use samp_query::mock::{MockResponse, MockResponses, MockServer};
+use samp_query::monitor::{Monitor, MonitorConfig, MonitorEvent, MonitorEventKind};
+use samp_query::notify::discord::{DiscordConfig, DiscordNotifier};
+use samp_query::notify::{forward, ChannelSink, EventSink, LogSink};
+use samp_query::packet::explain::{self, Direction};
+use samp_query::packet::{decode, encode, Packet};
+use samp_query::pipeline::{self, PipelineConfig};
+use samp_query::players::{PlayerFilter, PlayerSort};
+use samp_query::proxy::{ProxyConfig, QueryProxy};
+use samp_query::ratelimit::RateLimiter;
+use samp_query::rcon::{RconSession, Script, Step};
+use samp_query::resolver::{Lookup, Resolver, ResolverBackend, ResolverConfig};
+use samp_query::record::{Recorder, ReplayTransport, Session};
+use samp_query::scanner::{PortRange, ScanConfig, Scanner};
+use samp_query::scheduler::{ScheduleConfig, Scheduler};
+use samp_query::server::{respond, DataSource, Server, StaticSource};
+use samp_query::stats::LatencyStats;
+use samp_query::storage::{Metric, SqliteStore};
+use samp_query::stress::{StressConfig, StressTest};
+use samp_query::transport::{Transport, UdpTransport};
+use samp_query::utils::{Backoff, RetryPolicy};
+use samp_query::watchdog::{hook_fn, Suppression, Watchdog, WatchdogConfig, WatchdogEvent};
+use samp_query::{
+    Client, ClientConfig, DetailedPlayer, DetailedPlayerList, Player, PlayerList, QueryType,
+    ServerInfo, ServerRules, ServerSnapshot,
+};
+use std::collections::HashMap;
 use std::net::SocketAddr;
 
 #[tokio::test]
@@ -18,6 +60,7 @@ async fn test_client_creation_with_config() {
     let config = samp_query::client::ClientConfig {
         timeout_ms: 500,
         max_retries: 1,
+        ..Default::default()
     };
 
     let _result = Client::connect_with_config(addr, config).await;
@@ -40,3 +83,2186 @@ fn test_query_types() {
     assert_eq!(QueryType::from_opcode(b'x'), Some(QueryType::Rcon));
     assert_eq!(QueryType::from_opcode(b'z'), None);
 }
+
+#[tokio::test]
+async fn test_server_round_trip() {
+    let source = StaticSource {
+        info: ServerInfo {
+            password: true,
+            players: 1,
+            max_players: 50,
+            hostname: "Test Server".to_string(),
+            gamemode: "Freeroam".to_string(),
+            language: "English".to_string(),
+        },
+        rules: ServerRules {
+            rules: HashMap::from([("weburl".to_string(), "example.com".to_string())]),
+        },
+        players: DetailedPlayerList {
+            players: vec![DetailedPlayer {
+                id: 3,
+                name: "Player".to_string(),
+                score: 10,
+                ping: 42,
+            }],
+        },
+    };
+
+    let server = Server::bind("127.0.0.1:0".parse().unwrap(), source)
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(async move { server.run().await });
+
+    let client = Client::connect(addr).await.unwrap();
+
+    let info = client.query_info().await.unwrap();
+    assert!(info.password);
+    assert_eq!(info.hostname, "Test Server");
+    assert_eq!(info.max_players, 50);
+
+    let rules = client.query_rules().await.unwrap();
+    assert_eq!(rules.rules["weburl"], "example.com");
+
+    let players = client.query_client_list().await.unwrap();
+    assert_eq!(players.players[0].name, "Player");
+    assert_eq!(players.players[0].score, 10);
+
+    let detailed = client.query_detailed_player_info().await.unwrap();
+    assert_eq!(detailed.players[0].id, 3);
+    assert_eq!(detailed.players[0].ping, 42);
+
+    client.query_ping().await.unwrap();
+}
+
+fn test_info() -> ServerInfo {
+    ServerInfo {
+        password: false,
+        players: 5,
+        max_players: 50,
+        hostname: "Mock Server".to_string(),
+        gamemode: "Freeroam".to_string(),
+        language: "English".to_string(),
+    }
+}
+
+#[tokio::test]
+async fn test_mock_server_retries_dropped_queries() {
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Drop)
+            .on(QueryType::Information, MockResponse::Info(test_info())),
+    )
+    .await
+    .unwrap();
+
+    let config = ClientConfig {
+        timeout_ms: 100,
+        max_retries: 3,
+        ..Default::default()
+    };
+    let client = Client::connect_with_config(server.addr(), config).await.unwrap();
+
+    let info = client.query_info().await.unwrap();
+    assert_eq!(info.hostname, "Mock Server");
+    assert_eq!(info.players, 5);
+
+    let received = server.received();
+    assert_eq!(received.len(), 2);
+    assert!(received
+        .iter()
+        .all(|q| q.query_type == Some(QueryType::Information)));
+}
+
+#[tokio::test]
+async fn test_mock_server_timeout() {
+    let server = MockServer::start(MockResponses::new().on(
+        QueryType::Information,
+        MockResponse::Delayed(
+            std::time::Duration::from_millis(500),
+            Box::new(MockResponse::Info(test_info())),
+        ),
+    ))
+    .await
+    .unwrap();
+
+    let config = ClientConfig {
+        timeout_ms: 50,
+        max_retries: 2,
+        ..Default::default()
+    };
+    let client = Client::connect_with_config(server.addr(), config).await.unwrap();
+
+    assert!(client.query_info().await.unwrap_err().is_timeout());
+    assert_eq!(server.received().len(), 2);
+}
+
+#[tokio::test]
+async fn test_client_retry_policy() {
+    let server = MockServer::start(MockResponses::new().on(
+        QueryType::Information,
+        MockResponse::Delayed(
+            std::time::Duration::from_millis(500),
+            Box::new(MockResponse::Info(test_info())),
+        ),
+    ))
+    .await
+    .unwrap();
+
+    // The second pause would end past max_elapsed, so only two attempts are sent.
+    let config = ClientConfig {
+        timeout_ms: 20,
+        retry: Some(RetryPolicy {
+            max_attempts: 10,
+            backoff: Backoff::Fixed,
+            initial_delay: std::time::Duration::from_millis(100),
+            max_elapsed: Some(std::time::Duration::from_millis(200)),
+            ..RetryPolicy::default()
+        }),
+        ..Default::default()
+    };
+    let client = Client::connect_with_config(server.addr(), config).await.unwrap();
+
+    let started = std::time::Instant::now();
+    assert!(client.query_info().await.unwrap_err().is_timeout());
+    assert!(started.elapsed() >= std::time::Duration::from_millis(100));
+    assert_eq!(server.received().len(), 2);
+}
+
+#[tokio::test]
+async fn test_mock_server_malformed_response() {
+    let server = MockServer::start(
+        MockResponses::new().on(QueryType::Rules, MockResponse::Raw(b"NOPE".to_vec())),
+    )
+    .await
+    .unwrap();
+
+    let client = Client::connect(server.addr()).await.unwrap();
+
+    assert!(matches!(
+        client.query_rules().await,
+        Err(samp_query::Error::InvalidResponse(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_concurrent_snapshot() {
+    let rules = HashMap::from([("version".to_string(), "0.3.7".to_string())]);
+    let server = MockServer::start(
+        MockResponses::new()
+            // Answered after the other queries, so responses arrive out of order.
+            .on(
+                QueryType::Information,
+                MockResponse::Delayed(std::time::Duration::from_millis(50), Box::new(MockResponse::Info(test_info()))),
+            )
+            .on(QueryType::Rules, MockResponse::Rules(ServerRules { rules }))
+            .on(QueryType::DetailedPlayerInfo, MockResponse::Drop)
+            .on(QueryType::Ping, MockResponse::Pong),
+    )
+    .await
+    .unwrap();
+
+    let config = ClientConfig {
+        timeout_ms: 200,
+        max_retries: 2,
+        ..Default::default()
+    };
+    let client = Client::connect_with_config(server.addr(), config).await.unwrap();
+    let snapshot = client.snapshot_concurrent().await.unwrap();
+
+    assert_eq!(snapshot.info, test_info());
+    assert_eq!(snapshot.rules.unwrap().rules["version"], "0.3.7");
+    assert!(snapshot.players.is_none());
+    assert!(snapshot.ping.is_some());
+
+    // Only the unanswered detailed player query is sent again.
+    let received = server.received();
+    assert_eq!(received.len(), 5);
+    assert_eq!(
+        received
+            .iter()
+            .filter(|q| q.query_type == Some(QueryType::DetailedPlayerInfo))
+            .count(),
+        2
+    );
+}
+
+#[test]
+fn test_player_filter() {
+    let player = |id: u8, name: &str, score: i32, ping: u32| DetailedPlayer {
+        id,
+        name: name.to_string(),
+        score,
+        ping,
+    };
+    let mut players = vec![
+        player(0, "Carl_Johnson", 120, 45),
+        player(1, "Big_Smoke", 80, 60),
+        player(2, "Ryder", -5, 30),
+        player(3, "carl_jr", 10, 200),
+    ];
+
+    let filter = PlayerFilter {
+        sort: Some(PlayerSort::Ping),
+        ..Default::default()
+    }
+    .name_pattern("^carl")
+    .unwrap();
+    filter.apply(&mut players);
+    assert_eq!(players.iter().map(|p| p.id).collect::<Vec<_>>(), [0, 3]);
+
+    let filter = PlayerFilter {
+        sort: Some(PlayerSort::Name),
+        descending: true,
+        min_score: Some(50),
+        ..Default::default()
+    };
+    filter.apply(&mut players);
+    assert_eq!(players.iter().map(|p| p.id).collect::<Vec<_>>(), [0]);
+    assert!(PlayerFilter::default().name_pattern("(").is_err());
+}
+
+#[test]
+fn test_snapshot_diff() {
+    let old = ServerSnapshot {
+        address: "127.0.0.1:7777".parse().unwrap(),
+        timestamp: 0,
+        info: test_info(),
+        rules: Some(ServerRules {
+            rules: HashMap::from([
+                ("version".to_string(), "0.3.7".to_string()),
+                ("weather".to_string(), "10".to_string()),
+            ]),
+        }),
+        players: None,
+        ping: None,
+    };
+    let mut new = old.clone();
+    new.info.players = 6;
+    new.rules = Some(ServerRules {
+        rules: HashMap::from([
+            ("version".to_string(), "0.3.7".to_string()),
+            ("mapname".to_string(), "San Andreas".to_string()),
+        ]),
+    });
+    new.players = Some(DetailedPlayerList { players: Vec::new() });
+
+    let diff = SnapshotDiff::between(&old, &new);
+    assert_eq!(
+        diff.info,
+        [Change {
+            field: "players".to_string(),
+            old: Some("5".to_string()),
+            new: Some("6".to_string()),
+        }]
+    );
+    assert_eq!(
+        diff.rules,
+        [
+            Change {
+                field: "mapname".to_string(),
+                old: None,
+                new: Some("San Andreas".to_string()),
+            },
+            Change {
+                field: "weather".to_string(),
+                old: Some("10".to_string()),
+                new: None,
+            },
+        ]
+    );
+    // Players are only compared when both snapshots have a list.
+    assert!(diff.joined.is_empty() && diff.left.is_empty());
+    assert!(SnapshotDiff::between(&old, &old).is_empty());
+}
+
+#[tokio::test]
+async fn test_monitor_events() {
+    let player = |name: &str| Player {
+        name: name.to_string(),
+        score: 0,
+    };
+    let renamed = ServerInfo {
+        hostname: "Renamed Server".to_string(),
+        ..test_info()
+    };
+
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Information, MockResponse::Info(renamed))
+            .on(QueryType::Ping, MockResponse::Pong)
+            .on(
+                QueryType::ClientList,
+                MockResponse::Players(PlayerList {
+                    players: vec![player("Alice"), player("Bob")],
+                }),
+            )
+            .on(
+                QueryType::ClientList,
+                MockResponse::Players(PlayerList {
+                    players: vec![player("Bob"), player("Carol")],
+                }),
+            ),
+    )
+    .await
+    .unwrap();
+
+    let config = MonitorConfig {
+        interval: std::time::Duration::from_millis(50),
+        ..MonitorConfig::default()
+    };
+    let kinds: Vec<_> = Monitor::with_config(server.addr(), config)
+        .stream()
+        .filter(|event| futures::future::ready(event.is_change()))
+        .take(4)
+        .map(|event| event.kind)
+        .collect()
+        .await;
+
+    assert_eq!(
+        kinds,
+        vec![
+            MonitorEventKind::Online,
+            MonitorEventKind::HostnameChanged {
+                old: "Mock Server".to_string(),
+                new: "Renamed Server".to_string(),
+            },
+            MonitorEventKind::PlayerJoined {
+                name: "Carol".to_string(),
+            },
+            MonitorEventKind::PlayerLeft {
+                name: "Alice".to_string(),
+            },
+        ]
+    );
+}
+
+#[tokio::test]
+async fn test_monitor_adaptive_timeout_recovers_from_latency_jump() {
+    let slow = |response| MockResponse::Delayed(std::time::Duration::from_millis(300), Box::new(response));
+    let mut responses = MockResponses::new();
+    for _ in 0..5 {
+        responses = responses
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Ping, MockResponse::Pong);
+    }
+    // The reply that times out is lost, so it is not read by a later query.
+    let responses = responses
+        .on(QueryType::Information, MockResponse::Drop)
+        .on(QueryType::Information, slow(MockResponse::Info(test_info())))
+        .on(QueryType::Ping, slow(MockResponse::Pong));
+    let server = MockServer::start(responses).await.unwrap();
+
+    let config = MonitorConfig {
+        client: ClientConfig {
+            timeout_ms: 1000,
+            max_retries: 1,
+            ..Default::default()
+        },
+        interval: std::time::Duration::from_millis(20),
+        track_players: false,
+        adaptive_timeout: true,
+        ..MonitorConfig::default()
+    };
+    // Five fast polls fit the timeout to its 100 ms floor, so the first poll
+    // after the jump times out; after that the monitor must keep hearing the
+    // server instead of reporting it offline on every other poll.
+    let kinds: Vec<_> = Monitor::with_config(server.addr(), config)
+        .stream()
+        .map(|event| event.kind)
+        .filter(|kind| {
+            futures::future::ready(matches!(kind, MonitorEventKind::Online | MonitorEventKind::Offline { .. }))
+        })
+        .take_until(tokio::time::sleep(std::time::Duration::from_secs(3)))
+        .collect()
+        .await;
+
+    assert_eq!(kinds.len(), 3, "{:?}", kinds);
+    assert_eq!(kinds[0], MonitorEventKind::Online);
+    assert!(matches!(kinds[1], MonitorEventKind::Offline { .. }));
+    assert_eq!(kinds[2], MonitorEventKind::Online);
+}
+
+#[tokio::test]
+async fn test_monitor_zero_interval() {
+    let server = MockServer::start(MockResponses::new().on(QueryType::Information, MockResponse::Info(test_info())))
+        .await
+        .unwrap();
+
+    let config = MonitorConfig {
+        interval: std::time::Duration::ZERO,
+        track_players: false,
+        ..MonitorConfig::default()
+    };
+    let mut events = Monitor::with_config(server.addr(), config).stream();
+    assert_eq!(events.next().await.unwrap().kind, MonitorEventKind::Online);
+}
+
+#[test]
+fn test_sqlite_store() {
+    let store = SqliteStore::open_in_memory().unwrap();
+    let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+
+    for (timestamp, players) in [(0, 10), (1_000, 30), (2_000, 20)] {
+        let entry = HistoryEntry {
+            timestamp,
+            online: true,
+            players,
+            max_players: 50,
+            ping_ms: Some(40),
+        };
+        store
+            .record_entry(addr, &entry, Some("Test Server"), None)
+            .unwrap();
+    }
+    store
+        .record_event(&MonitorEvent {
+            address: addr,
+            timestamp: 3_000,
+            kind: MonitorEventKind::Offline {
+                reason: "Connection timed out".to_string(),
+            },
+        })
+        .unwrap();
+
+    let stats = store.stats(addr, 0).unwrap();
+    assert_eq!(stats.samples, 4);
+    assert_eq!(stats.peak_players, Some(30));
+    assert_eq!(stats.peak_at, Some(1_000));
+    assert_eq!(stats.average_ping_ms, Some(40.0));
+    assert_eq!(stats.uptime_percentage, Some(75.0));
+
+    let series = store.series(addr, Metric::Players, 0, 2_000).unwrap();
+    assert_eq!(series.len(), 2);
+    assert_eq!(series[0].value, 20.0);
+
+    let events = store.events(addr, 0).unwrap();
+    assert_eq!(events.len(), 1);
+    assert!(matches!(events[0].kind, MonitorEventKind::Offline { .. }));
+}
+
+#[test]
+fn test_omp_masterlist_entries() {
+    let json = r#"[
+        {"ip":"127.0.0.1:7777","hn":"Roleplay Server","pc":40,"pm":100,"gm":"RP v2","la":"English","pa":false,"vn":"omp 1.1.0","omp":true,"pr":false},
+        {"ip":"127.0.0.1:7778","hn":"DM Server","pc":0,"pm":50,"gm":"Deathmatch","la":"Russian","pa":true}
+    ]"#;
+    let entries: Vec<OmpServerEntry> = serde_json::from_str(json).unwrap();
+
+    assert_eq!(entries[0].addr(), Some("127.0.0.1:7777".parse().unwrap()));
+    assert!(!entries[1].omp);
+
+    let filter = OmpFilter {
+        gamemode: Some("rp".to_string()),
+        hide_passworded: true,
+        ..OmpFilter::default()
+    };
+    let filtered = filter.apply(entries);
+    assert_eq!(filtered.len(), 1);
+    assert_eq!(filtered[0].hostname, "Roleplay Server");
+}
+
+#[tokio::test]
+async fn test_announce_flavor_detection() {
+    let config = AnnounceConfig {
+        version: "0.3.DL".to_string(),
+        ..AnnounceConfig::new(7777)
+    };
+    let samp = config.request(SAMP_ENDPOINT, Platform::Samp, None);
+    assert_eq!(samp.url, "http://server.sa-mp.com/0.3.DL/announce/7777");
+    assert_eq!(samp.user_agent, "SAMP/0.3.DL");
+    let omp = config.request(OMP_ENDPOINT, Platform::OpenMp, Some("omp 1.2.0"));
+    assert_eq!(omp.url, "https://api.open.mp/0.3.7/announce/7777");
+    assert_eq!(omp.user_agent, "open.mp/1.2.0");
+
+    let server = MockServer::start(MockResponses::new().on(
+        QueryType::Rules,
+        MockResponse::Rules(ServerRules {
+            rules: HashMap::from([
+                ("version".to_string(), "omp 1.2.0".to_string()),
+                ("allowed_clients".to_string(), "0.3.7, 0.3.DL".to_string()),
+            ]),
+        }),
+    ))
+    .await
+    .unwrap();
+    let announcer = Announcer::with_config(AnnounceConfig::new(server.addr().port()));
+    assert_eq!(announcer.flavor().await, Platform::OpenMp);
+    assert_eq!(announcer.flavor().await, Platform::OpenMp);
+    // Detection is kept after the first answer.
+    assert_eq!(server.received().len(), 1);
+
+    // Nothing answers on this port, so the SA-MP format is used.
+    let announcer = Announcer::with_config(AnnounceConfig::new(1));
+    assert_eq!(announcer.flavor().await, Platform::Samp);
+}
+
+#[tokio::test]
+async fn test_announce_zero_interval() {
+    let announcer = Announcer::with_config(AnnounceConfig {
+        endpoints: Vec::new(),
+        interval: std::time::Duration::ZERO,
+        ..AnnounceConfig::new(7777)
+    });
+    assert!(!announcer.config().interval.is_zero());
+    // Runs until the timeout instead of panicking on the first tick.
+    assert!(tokio::time::timeout(std::time::Duration::from_millis(50), announcer.run()).await.is_err());
+}
+
+#[tokio::test]
+async fn test_game_server_client() {
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(
+                QueryType::ClientList,
+                MockResponse::Players(PlayerList {
+                    players: vec![Player {
+                        name: "Sweet".to_string(),
+                        score: 7,
+                    }],
+                }),
+            )
+            .on(QueryType::Ping, MockResponse::Pong),
+    )
+    .await
+    .unwrap();
+    let client = Client::connect_with_config(
+        server.addr(),
+        ClientConfig {
+            timeout_ms: 200,
+            max_retries: 1,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let servers: Vec<Box<dyn GameServerClient>> = vec![Box::new(client)];
+
+    let server = &servers[0];
+    assert_eq!(server.game(), "samp");
+    assert_eq!(
+        server.info().await.unwrap(),
+        GameInfo {
+            name: "Mock Server".to_string(),
+            mode: "Freeroam".to_string(),
+            players: 5,
+            max_players: 50,
+            password: false,
+            language: Some("English".to_string()),
+        }
+    );
+
+    // The detailed list is not answered, so the client list is used.
+    let players = server.players().await.unwrap();
+    assert_eq!(players.len(), 1);
+    assert_eq!((players[0].name.as_str(), players[0].score, players[0].ping), ("Sweet", Some(7), None));
+    assert!(server.ping().await.is_ok());
+}
+
+struct RconSource(std::sync::Mutex<Vec<String>>);
+
+impl DataSource for RconSource {
+    fn info(&self) -> ServerInfo {
+        test_info()
+    }
+
+    fn rules(&self) -> ServerRules {
+        ServerRules { rules: HashMap::new() }
+    }
+
+    fn players(&self) -> DetailedPlayerList {
+        DetailedPlayerList { players: Vec::new() }
+    }
+
+    fn rcon(&self, password: &str, command: &str) -> Option<Vec<String>> {
+        if password != "secret" {
+            return None;
+        }
+        self.0.lock().unwrap().push(command.to_string());
+
+        match command.split_once(' ').map_or(command, |(name, _)| name) {
+            "players" => Some(vec!["ID\tName".to_string(), "0\tCarl_Johnson".to_string()]),
+            "kick" | "say" => Some(Vec::new()),
+            _ => Some(vec![
+                "Unknown command or variable:".to_string(),
+                command.to_string(),
+            ]),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_rcon_script() {
+    let source = std::sync::Arc::new(RconSource(std::sync::Mutex::new(Vec::new())));
+    let server = Server::bind_shared("127.0.0.1:0".parse().unwrap(), source.clone())
+        .await
+        .unwrap();
+    let addr = server.local_addr().unwrap();
+    tokio::spawn(async move { server.run().await });
+    let config = ClientConfig {
+        timeout_ms: 200,
+        max_retries: 1,
+        ..Default::default()
+    };
+
+    let client = Client::connect_with_config(addr, config.clone()).await.unwrap();
+    let response = client.rcon_command("secret", "players").await.unwrap();
+    assert_eq!(response.message, "ID\tName\n0\tCarl_Johnson");
+    assert!(client.rcon_command("wrong", "players").await.unwrap_err().is_auth_error());
+
+    let script = Script::parse(
+        "# maintenance\n\
+         @delay 10ms\n\
+         say Bye ${player}\n\
+         @wait 20ms\n\
+         -frobnicate\n\
+         players\n\
+         kick ${player}\n",
+    )
+    .unwrap();
+    assert_eq!(script.steps().len(), 5);
+    assert_eq!(script.steps()[1], Step::Wait(std::time::Duration::from_millis(20)));
+    assert_eq!(script.variables(), vec!["player".to_string()]);
+    assert_eq!(Script::new(script.steps().to_vec()), script);
+    assert!(Script::parse("@sleep 1s").is_err());
+    assert!(Script::parse(&format!("@wait {}m", u64::MAX)).is_err());
+
+    let session = RconSession::new(Client::connect_with_config(addr, config.clone()).await.unwrap(), "secret")
+        .with_line_timeout(std::time::Duration::from_millis(50));
+    // Missing variables fail before anything is sent.
+    assert!(script.run(&session, &HashMap::new()).await.is_err());
+    source.0.lock().unwrap().clear();
+
+    let vars = HashMap::from([("player".to_string(), "Carl_Johnson".to_string())]);
+    let report = script.run(&session, &vars).await.unwrap();
+    assert!(!report.aborted);
+    assert!(!report.is_success());
+    assert_eq!(report.outputs.len(), 4);
+    assert!(report.outputs[1].error.is_some());
+    assert_eq!(report.outputs[2].lines[1], "0\tCarl_Johnson");
+    assert_eq!(source.0.lock().unwrap().last().unwrap(), "kick Carl_Johnson");
+
+    // Without the `-`, the unknown command aborts the script.
+    let strict = Script::parse("frobnicate\nkick ${player}").unwrap();
+    let report = strict.run(&session, &vars).await.unwrap();
+    assert!(report.aborted);
+    assert_eq!(report.outputs.len(), 1);
+
+    let wrong = RconSession::new(Client::connect_with_config(addr, config).await.unwrap(), "wrong");
+    let report = script.run(&wrong, &vars).await.unwrap();
+    assert!(report.aborted);
+    assert_eq!(report.outputs[0].error.as_deref(), Some("RCON authentication failed"));
+}
+
+#[tokio::test]
+async fn test_watchdog_cooldown_and_flap_damping() {
+    let calls = std::sync::Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let hook_calls = calls.clone();
+    let hook = hook_fn(move |_addr| {
+        let calls = hook_calls.clone();
+        async move {
+            calls.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            Ok(())
+        }
+    });
+    let config = WatchdogConfig {
+        client: ClientConfig {
+            timeout_ms: 20,
+            max_retries: 1,
+            ..Default::default()
+        },
+        failure_threshold: 2,
+        cooldown: std::time::Duration::from_millis(300),
+        max_restarts: 2,
+        ..WatchdogConfig::default()
+    };
+    let mut watchdog = Watchdog::new("127.0.0.1:1".parse().unwrap(), config.clone(), hook);
+    let failed = |consecutive| move |event: &WatchdogEvent| {
+        matches!(event, WatchdogEvent::CheckFailed { consecutive: c, .. } if *c == consecutive)
+    };
+
+    let events = watchdog.check().await;
+    assert!(events.len() == 1 && failed(1)(&events[0]));
+    let events = watchdog.check().await;
+    assert_eq!(events[1], WatchdogEvent::Triggered { error: None });
+    assert_eq!(watchdog.failures(), 0);
+
+    watchdog.check().await;
+    let events = watchdog.check().await;
+    assert!(matches!(events[1], WatchdogEvent::Suppressed(Suppression::Cooldown { .. })));
+
+    tokio::time::sleep(std::time::Duration::from_millis(320)).await;
+    let events = watchdog.check().await;
+    assert!(failed(3)(&events[0]));
+    assert_eq!(events[1], WatchdogEvent::Triggered { error: None });
+
+    tokio::time::sleep(std::time::Duration::from_millis(320)).await;
+    watchdog.check().await;
+    let events = watchdog.check().await;
+    assert_eq!(events[1], WatchdogEvent::Suppressed(Suppression::Flapping { restarts: 2 }));
+    assert_eq!(calls.load(std::sync::atomic::Ordering::SeqCst), 2);
+
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Drop)
+            .on(QueryType::Information, MockResponse::Info(test_info())),
+    )
+    .await
+    .unwrap();
+    let config = WatchdogConfig {
+        client: ClientConfig {
+            timeout_ms: 100,
+            max_retries: 1,
+            ..Default::default()
+        },
+        ..config
+    };
+    let mut watchdog = Watchdog::new(server.addr(), config, hook_fn(|_addr| async { Ok(()) }));
+    assert!(failed(1)(&watchdog.check().await[0]));
+    assert_eq!(watchdog.check().await, vec![WatchdogEvent::Recovered { after_failures: 1 }]);
+}
+
+#[tokio::test]
+async fn test_watchdog_zero_interval() {
+    let config = WatchdogConfig {
+        client: ClientConfig {
+            timeout_ms: 20,
+            max_retries: 1,
+            ..Default::default()
+        },
+        interval: std::time::Duration::ZERO,
+        ..WatchdogConfig::default()
+    };
+    let watchdog = Watchdog::new("127.0.0.1:1".parse().unwrap(), config, hook_fn(|_addr| async { Ok(()) }));
+    let mut events = watchdog.stream();
+    assert!(matches!(events.next().await, Some(WatchdogEvent::CheckFailed { consecutive: 1, .. })));
+}
+
+struct CountingBackend(std::sync::atomic::AtomicUsize);
+
+#[async_trait::async_trait]
+impl ResolverBackend for CountingBackend {
+    async fn lookup(&self, host: &str) -> samp_query::Result<Lookup> {
+        self.0.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+        match host {
+            "play.example.com" => Ok(Lookup {
+                addrs: vec!["::1".parse().unwrap(), "127.0.0.1".parse().unwrap()],
+                ttl: Some(std::time::Duration::from_millis(50)),
+            }),
+            _ => Err(samp_query::Error::Resolve(format!("{}: not found", host))),
+        }
+    }
+}
+
+#[tokio::test]
+async fn test_resolver_caching() {
+    let backend = std::sync::Arc::new(CountingBackend(std::sync::atomic::AtomicUsize::new(0)));
+    let lookups = || backend.0.load(std::sync::atomic::Ordering::SeqCst);
+    let resolver = Resolver::with_backend(
+        ResolverConfig {
+            min_ttl: std::time::Duration::ZERO,
+            negative_ttl: std::time::Duration::from_secs(60),
+            ..ResolverConfig::default()
+        },
+        backend.clone(),
+    );
+
+    let (canonical, addr) = resolver.resolve_address("Play.Example.com,7778", 7777).await.unwrap();
+    assert_eq!(canonical, "play.example.com:7778");
+    assert_eq!(addr, "127.0.0.1:7778".parse().unwrap());
+    resolver.resolve("play.example.com", 7777).await.unwrap();
+    assert_eq!(lookups(), 1);
+
+    // The answer expires with its TTL.
+    tokio::time::sleep(std::time::Duration::from_millis(60)).await;
+    resolver.resolve("play.example.com", 7777).await.unwrap();
+    assert_eq!(lookups(), 2);
+
+    // Failures are cached too, and IP literals never reach the backend.
+    assert!(matches!(resolver.lookup("gone.example.com").await, Err(samp_query::Error::Resolve(_))));
+    assert!(resolver.lookup("gone.example.com").await.is_err());
+    assert_eq!(resolver.lookup("10.0.0.1").await.unwrap(), vec!["10.0.0.1".parse::<std::net::IpAddr>().unwrap()]);
+    assert_eq!(lookups(), 3);
+    assert_eq!(resolver.len(), 2);
+
+    let server = MockServer::start(
+        MockResponses::new().on(QueryType::Information, MockResponse::Info(test_info())),
+    )
+    .await
+    .unwrap();
+    let client = Client::connect_host(&format!("localhost:{}", server.addr().port()), ClientConfig::default())
+        .await
+        .unwrap();
+    assert_eq!(client.server_addr(), server.addr());
+    assert_eq!(client.query_info().await.unwrap().hostname, "Mock Server");
+}
+
+#[test]
+fn test_latency_stats() {
+    let ms = std::time::Duration::from_millis;
+    let mut stats = LatencyStats::new(5);
+    assert_eq!(stats.p50(), None);
+    assert_eq!(stats.suggested_timeout(ms(100), ms(1000)), None);
+
+    for sample in [80, 50, 60, 50, 60, 50] {
+        stats.record(ms(sample));
+    }
+    // The first sample fell out of the window; the average still covers it.
+    assert_eq!(stats.len(), 5);
+    assert_eq!(stats.p50(), Some(ms(50)));
+    assert_eq!(stats.p95(), Some(ms(60)));
+    let ewma = stats.ewma().unwrap();
+    assert!(ewma > ms(60) && ewma < ms(80));
+    let jitter = stats.jitter().unwrap();
+    assert!(jitter > ms(1) && jitter < ms(10));
+
+    let summary = stats.summary().unwrap();
+    assert_eq!((summary.samples, summary.last_ms, summary.p99_ms), (5, 50, 60));
+
+    // Twice the p99 wins over the spread of a stable server.
+    assert_eq!(stats.suggested_timeout(ms(100), ms(1000)), Some(ms(120)));
+    assert_eq!(stats.suggested_timeout(ms(200), ms(1000)), Some(ms(200)));
+    assert_eq!(stats.suggested_timeout(ms(10), ms(80)), Some(ms(80)));
+}
+
+#[tokio::test(start_paused = true)]
+async fn test_rate_limiter() {
+    let a: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+    let b: SocketAddr = "127.0.0.1:7778".parse().unwrap();
+    let elapsed = |started: tokio::time::Instant| started.elapsed().as_millis();
+
+    let limiter = RateLimiter::new(10.0);
+    let started = tokio::time::Instant::now();
+    for _ in 0..5 {
+        limiter.acquire_global().await;
+    }
+    assert_eq!(elapsed(started), 400);
+
+    // Bursts go out at once, then the rate applies.
+    let limiter = RateLimiter::with_burst(10.0, 3);
+    let started = tokio::time::Instant::now();
+    for _ in 0..4 {
+        limiter.acquire(a).await;
+    }
+    assert_eq!(elapsed(started), 100);
+
+    // Clones share the limits; each destination has its own budget.
+    let limiter = RateLimiter::new(100.0).per_destination(2.0);
+    let shared = limiter.clone();
+    let started = tokio::time::Instant::now();
+    limiter.acquire(a).await;
+    shared.acquire(b).await;
+    assert_eq!(elapsed(started), 10);
+    let wait = limiter.try_acquire(a).unwrap_err();
+    assert!(wait > std::time::Duration::from_millis(480) && wait <= std::time::Duration::from_millis(500));
+    shared.acquire(a).await;
+    assert_eq!(elapsed(started), 500);
+    tokio::time::sleep(std::time::Duration::from_secs(1)).await;
+    assert!(limiter.try_acquire(b).is_ok());
+}
+
+#[tokio::test]
+async fn test_truncated_response() {
+    let server = MockServer::start(
+        MockResponses::new().on(QueryType::Information, MockResponse::Body(vec![0, 5])),
+    )
+    .await
+    .unwrap();
+
+    let client = Client::connect(server.addr()).await.unwrap();
+
+    assert!(matches!(
+        client.query_info().await,
+        Err(samp_query::Error::InvalidResponse(_))
+    ));
+}
+
+#[tokio::test]
+async fn test_scanner_discovers_servers() {
+    let server = MockServer::start(
+        MockResponses::new().on(QueryType::Information, MockResponse::Info(test_info())),
+    )
+    .await
+    .unwrap();
+    let port = server.addr().port();
+
+    let config = ScanConfig {
+        ranges: vec!["127.0.0.1/32".parse().unwrap()],
+        ports: vec![PortRange {
+            start: port,
+            end: port,
+        }],
+        rate_pps: 1000,
+        timeout: std::time::Duration::from_millis(200),
+        ..ScanConfig::default()
+    };
+    assert_eq!(config.target_count(), 1);
+
+    let discovered: Vec<_> = Scanner::new(config).scan().await.unwrap().collect().await;
+
+    assert_eq!(discovered.len(), 1);
+    assert_eq!(discovered[0].address, server.addr());
+    assert_eq!(discovered[0].info.hostname, "Mock Server");
+}
+
+#[tokio::test]
+async fn test_dropping_the_scan_stops_probing() {
+    let server = MockServer::start(
+        MockResponses::new().on(QueryType::Information, MockResponse::Info(test_info())),
+    )
+    .await
+    .unwrap();
+    let port = PortRange {
+        start: server.addr().port(),
+        end: server.addr().port(),
+    };
+
+    // The same server, probed 1000 times at 50 per second.
+    let config = ScanConfig {
+        ranges: vec!["127.0.0.1/32".parse().unwrap()],
+        ports: vec![port; 1000],
+        rate_pps: 50,
+        ..ScanConfig::default()
+    };
+    let mut stream = Scanner::new(config).scan().await.unwrap();
+    assert!(stream.next().await.is_some());
+    drop(stream);
+
+    tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+    let probes = server.received().len();
+    tokio::time::sleep(std::time::Duration::from_millis(300)).await;
+    assert_eq!(server.received().len(), probes);
+}
+
+#[tokio::test]
+async fn test_query_cache() {
+    let renamed = ServerInfo {
+        hostname: "Renamed Server".to_string(),
+        ..test_info()
+    };
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Information, MockResponse::Info(renamed))
+            .on(QueryType::ClientList, MockResponse::Raw(b"NOPE".to_vec())),
+    )
+    .await
+    .unwrap();
+
+    let cache = QueryCache::new(CacheConfig {
+        client: ClientConfig {
+            timeout_ms: 100,
+            max_retries: 1,
+            ..Default::default()
+        },
+        ..CacheConfig::default()
+    });
+
+    assert_eq!(cache.info(server.addr()).await.unwrap().hostname, "Mock Server");
+    assert_eq!(cache.info(server.addr()).await.unwrap().hostname, "Mock Server");
+    assert_eq!(server.received().len(), 1);
+
+    cache.invalidate(server.addr());
+    assert_eq!(cache.info(server.addr()).await.unwrap().hostname, "Renamed Server");
+
+    // Failures are cached too, so the dead rules query is only sent once.
+    assert!(cache.rules(server.addr()).await.unwrap_err().is_timeout());
+    assert!(cache.rules(server.addr()).await.unwrap_err().is_timeout());
+    assert_eq!(server.received().len(), 3);
+
+    // Cached failures keep their kind.
+    for _ in 0..2 {
+        assert!(matches!(
+            cache.client_list(server.addr()).await,
+            Err(samp_query::Error::InvalidResponse(_))
+        ));
+    }
+    assert_eq!(server.received().len(), 4);
+}
+
+#[tokio::test]
+async fn test_query_cache_coalesces_fetches() {
+    let delay = std::time::Duration::from_millis(50);
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Delayed(delay, Box::new(MockResponse::Info(test_info()))))
+            .on(QueryType::Ping, MockResponse::Delayed(delay, Box::new(MockResponse::Pong))),
+    )
+    .await
+    .unwrap();
+
+    let cache = QueryCache::new(CacheConfig {
+        client: ClientConfig {
+            timeout_ms: 100,
+            max_retries: 1,
+            ..Default::default()
+        },
+        limiter: Some(RateLimiter::unlimited().per_destination(1.0)),
+        ..CacheConfig::default()
+    });
+
+    // Concurrent fetches share one query, also with `fresh`.
+    let fetches = (0..8).map(|_| cache.fetch(server.addr(), QueryType::Information));
+    for result in futures::future::join_all(fetches).await {
+        assert_eq!(result.unwrap(), CachedValue::Info(test_info()));
+    }
+    assert_eq!(server.received().len(), 1);
+
+    // The next query to the server is over the limit and is not sent.
+    let err = cache.fetch(server.addr(), QueryType::Ping).await.unwrap_err();
+    assert!(err.is_rate_limited());
+    assert!(cache.fetch_snapshot(server.addr()).await.unwrap_err().is_rate_limited());
+    assert_eq!(cache.info(server.addr()).await.unwrap(), test_info());
+    assert_eq!(server.received().len(), 1);
+}
+
+#[tokio::test]
+async fn test_query_cache_snapshot() {
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Rules, MockResponse::Rules(ServerRules::default()))
+            .on(QueryType::Ping, MockResponse::Pong),
+    )
+    .await
+    .unwrap();
+
+    let cache = QueryCache::new(CacheConfig {
+        client: ClientConfig {
+            timeout_ms: 100,
+            max_retries: 1,
+            ..Default::default()
+        },
+        ..CacheConfig::default()
+    });
+
+    // One pass sends all four queries; the players are not answered.
+    let snapshot = cache.snapshot(server.addr()).await.unwrap();
+    assert_eq!(snapshot.info, test_info());
+    assert!(snapshot.rules.is_some() && snapshot.ping.is_some());
+    assert!(snapshot.players.is_none());
+    assert_eq!(server.received().len(), 4);
+
+    // The parts are cached, also for the single queries.
+    let again = cache.snapshot(server.addr()).await.unwrap();
+    assert_eq!(again.info, snapshot.info);
+    assert_eq!(cache.info(server.addr()).await.unwrap(), test_info());
+    assert!(cache.detailed_players(server.addr()).await.unwrap_err().is_timeout());
+    assert_eq!(server.received().len(), 4);
+
+    cache.fetch_snapshot(server.addr()).await.unwrap();
+    assert_eq!(server.received().len(), 8);
+}
+
+#[tokio::test]
+async fn test_prometheus_exporter() {
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Ping, MockResponse::Pong),
+    )
+    .await
+    .unwrap();
+    let dead: SocketAddr = "127.0.0.1:9".parse().unwrap();
+
+    let exporter = PrometheusExporter::new(ExporterConfig {
+        servers: vec![server.addr(), dead],
+        client: ClientConfig {
+            timeout_ms: 100,
+            max_retries: 1,
+            ..Default::default()
+        },
+        ..ExporterConfig::default()
+    });
+    exporter.poll_once().await;
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let metrics_addr = listener.local_addr().unwrap();
+    let serving = exporter.clone();
+    tokio::spawn(async move { serving.serve(listener).await });
+
+    let mut stream = tokio::net::TcpStream::connect(metrics_addr).await.unwrap();
+    stream
+        .write_all(b"GET /metrics HTTP/1.1\r\nHost: localhost\r\n\r\n")
+        .await
+        .unwrap();
+    let mut response = String::new();
+    stream.read_to_string(&mut response).await.unwrap();
+
+    let up = format!("samp_up{{address=\"{}\"}} 1", server.addr());
+    let players = format!("samp_players{{address=\"{}\"}} 5", server.addr());
+    let histogram = format!("samp_ping_duration_ms_count{{address=\"{}\"}} 1", server.addr());
+    assert!(response.starts_with("HTTP/1.1 200 OK"));
+    assert!(response.contains(&up));
+    assert!(response.contains(&players));
+    assert!(response.contains(&histogram));
+    assert!(response.contains(&format!("samp_ping_p50_ms{{address=\"{}\"}}", server.addr())));
+    assert!(response.contains("samp_up{address=\"127.0.0.1:9\"} 0"));
+    assert!(response.contains("samp_query_failures_total{address=\"127.0.0.1:9\"} 1"));
+}
+
+#[test]
+fn test_prometheus_exporter_zero_interval() {
+    let exporter = PrometheusExporter::new(ExporterConfig {
+        interval: std::time::Duration::ZERO,
+        ..ExporterConfig::default()
+    });
+    assert_eq!(exporter.config().interval, std::time::Duration::from_millis(1));
+}
+
+#[tokio::test]
+async fn test_influx_sink_batching() {
+    #[derive(Clone, Default)]
+    struct SharedBuffer(std::sync::Arc<std::sync::Mutex<Vec<u8>>>);
+
+    impl std::io::Write for SharedBuffer {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.0.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let output = SharedBuffer::default();
+    let mut sink = InfluxSink::writer(
+        InfluxConfig {
+            tags: vec![("region".to_string(), "eu".to_string())],
+            batch_size: 2,
+            ..InfluxConfig::default()
+        },
+        output.clone(),
+    );
+    let snapshot = ServerSnapshot {
+        address: "127.0.0.1:7777".parse().unwrap(),
+        timestamp: 1000,
+        info: test_info(),
+        rules: None,
+        players: None,
+        ping: None,
+    };
+
+    sink.push(&snapshot).await.unwrap();
+    assert_eq!(sink.buffered(), 1);
+    assert!(output.0.lock().unwrap().is_empty());
+
+    sink.push(&snapshot).await.unwrap();
+    assert_eq!(sink.buffered(), 0);
+
+    let written = String::from_utf8(output.0.lock().unwrap().clone()).unwrap();
+    let lines: Vec<&str> = written.lines().collect();
+    assert_eq!(lines.len(), 2);
+    assert!(lines[0].starts_with("samp,address=127.0.0.1:7777,region=eu,hostname=Mock\\ Server players=5i"));
+}
+
+#[tokio::test]
+async fn test_influx_sink_caps_the_buffer_while_writes_fail() {
+    /// Fails every write until `down` is cleared, counting the attempts.
+    #[derive(Clone, Default)]
+    struct FlakyWriter {
+        down: std::sync::Arc<std::sync::atomic::AtomicBool>,
+        attempts: std::sync::Arc<std::sync::atomic::AtomicUsize>,
+        written: std::sync::Arc<std::sync::Mutex<Vec<u8>>>,
+    }
+
+    impl std::io::Write for FlakyWriter {
+        fn write(&mut self, buf: &[u8]) -> std::io::Result<usize> {
+            self.attempts.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+            if self.down.load(std::sync::atomic::Ordering::SeqCst) {
+                return Err(std::io::Error::other("database is down"));
+            }
+            self.written.lock().unwrap().extend_from_slice(buf);
+            Ok(buf.len())
+        }
+
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+
+    let output = FlakyWriter::default();
+    output.down.store(true, std::sync::atomic::Ordering::SeqCst);
+    let mut sink = InfluxSink::writer(
+        InfluxConfig {
+            batch_size: 1,
+            max_buffered: 3,
+            retry_delay: std::time::Duration::from_secs(3600),
+            ..InfluxConfig::default()
+        },
+        output.clone(),
+    );
+    let snapshot = |timestamp| ServerSnapshot {
+        address: "127.0.0.1:7777".parse().unwrap(),
+        timestamp,
+        info: test_info(),
+        rules: None,
+        players: None,
+        ping: None,
+    };
+
+    assert!(sink.push(&snapshot(1)).await.is_err());
+    // Within the retry delay, pushes only buffer, dropping the oldest records.
+    for timestamp in 2..=5 {
+        sink.push(&snapshot(timestamp)).await.unwrap();
+    }
+    assert_eq!(output.attempts.load(std::sync::atomic::Ordering::SeqCst), 1);
+    assert_eq!(sink.buffered(), 3);
+
+    output.down.store(false, std::sync::atomic::Ordering::SeqCst);
+    sink.flush().await.unwrap();
+    assert_eq!(sink.buffered(), 0);
+    let written = String::from_utf8(output.written.lock().unwrap().clone()).unwrap();
+    let timestamps: Vec<_> = written.lines().map(|line| line.rsplit(' ').next().unwrap()).collect();
+    assert_eq!(timestamps, ["3000000", "4000000", "5000000"]);
+}
+
+#[test]
+fn test_discord_messages() {
+    let mut config = DiscordConfig::new("http://127.0.0.1:9/webhook");
+    config.milestones = vec![50, 100];
+    config.templates.offline = "{address} went away ({reason})".to_string();
+    let notifier = DiscordNotifier::with_config(config);
+
+    let event = |kind| MonitorEvent {
+        address: "127.0.0.1:7777".parse().unwrap(),
+        timestamp: 0,
+        kind,
+    };
+
+    let offline = notifier
+        .message(&event(MonitorEventKind::Offline {
+            reason: "Connection timed out".to_string(),
+        }))
+        .unwrap();
+    assert_eq!(
+        offline.embeds[0].description,
+        "127.0.0.1:7777 went away (Connection timed out)"
+    );
+
+    let milestone = notifier
+        .message(&event(MonitorEventKind::PlayerCountChanged { old: 90, new: 101 }))
+        .unwrap();
+    assert!(milestone.embeds[0].description.contains("reached 100 players"));
+
+    assert!(notifier
+        .message(&event(MonitorEventKind::PlayerCountChanged { old: 101, new: 120 }))
+        .is_none());
+    assert!(notifier
+        .message(&event(MonitorEventKind::PlayerJoined {
+            name: "Player".to_string()
+        }))
+        .is_none());
+}
+
+#[tokio::test]
+async fn test_event_sinks() {
+    let (channel, mut rx) = ChannelSink::channel(16);
+    let sinks: Vec<Box<dyn EventSink>> = vec![Box::new(LogSink), Box::new(channel)];
+
+    let events: Vec<MonitorEvent> = [MonitorEventKind::Online, MonitorEventKind::Offline {
+        reason: "Connection timed out".to_string(),
+    }]
+    .into_iter()
+    .map(|kind| MonitorEvent {
+        address: "127.0.0.1:7777".parse().unwrap(),
+        timestamp: 0,
+        kind,
+    })
+    .collect();
+
+    forward(futures::stream::iter(events.clone()), &sinks).await;
+    drop(sinks);
+
+    let mut received = Vec::new();
+    while let Some(event) = rx.recv().await {
+        received.push(event);
+    }
+    assert_eq!(received, events);
+}
+
+#[tokio::test]
+async fn test_custom_transport() {
+    /// Answers queries in-process, without any socket.
+    struct InProcess {
+        source: StaticSource,
+        responses: std::sync::Mutex<std::collections::VecDeque<Vec<u8>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl Transport for InProcess {
+        async fn send(&self, data: &[u8]) -> samp_query::Result<()> {
+            self.responses
+                .lock()
+                .unwrap()
+                .extend(respond(&self.source, data));
+            Ok(())
+        }
+
+        async fn recv(&self, buf: &mut [u8]) -> samp_query::Result<usize> {
+            let response = self.responses.lock().unwrap().pop_front();
+            match response {
+                Some(response) => {
+                    buf[..response.len()].copy_from_slice(&response);
+                    Ok(response.len())
+                }
+                None => std::future::pending().await,
+            }
+        }
+    }
+
+    let transport = InProcess {
+        source: StaticSource {
+            info: test_info(),
+            rules: ServerRules {
+                rules: HashMap::new(),
+            },
+            players: DetailedPlayerList { players: vec![] },
+        },
+        responses: Default::default(),
+    };
+    let client = Client::with_transport(
+        "127.0.0.1:7777".parse().unwrap(),
+        transport,
+        ClientConfig {
+            timeout_ms: 100,
+            max_retries: 1,
+            ..Default::default()
+        },
+    );
+
+    assert_eq!(client.query_info().await.unwrap(), test_info());
+    assert!(client.query_ping().await.is_ok());
+}
+
+#[tokio::test]
+async fn test_query_proxy() {
+    let upstream = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Rules, MockResponse::Rules(ServerRules::default()))
+            .on(
+                QueryType::DetailedPlayerInfo,
+                MockResponse::DetailedPlayers(DetailedPlayerList::default()),
+            ),
+    )
+    .await
+    .unwrap();
+
+    let proxy = QueryProxy::bind(ProxyConfig {
+        client: ClientConfig {
+            timeout_ms: 100,
+            max_retries: 1,
+            ..Default::default()
+        },
+        ..ProxyConfig::new(upstream.addr(), "127.0.0.1:0".parse().unwrap())
+    })
+    .await
+    .unwrap();
+    let proxy = std::sync::Arc::new(proxy);
+    let proxy_addr = proxy.local_addr().unwrap();
+    let running = proxy.clone();
+    tokio::spawn(async move { running.serve().await });
+
+    let config = ClientConfig {
+        timeout_ms: 100,
+        max_retries: 1,
+        ..Default::default()
+    };
+    let client = Client::connect_with_config(proxy_addr, config).await.unwrap();
+
+    // Nothing is answered until the first successful refresh.
+    assert!(client.query_info().await.unwrap_err().is_timeout());
+
+    proxy.refresh().await.unwrap();
+    let upstream_queries = upstream.received().len();
+    for _ in 0..5 {
+        assert_eq!(client.query_info().await.unwrap(), test_info());
+    }
+    assert_eq!(upstream.received().len(), upstream_queries);
+}
+
+#[tokio::test]
+async fn test_query_proxy_zero_refresh_interval() {
+    let upstream = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Rules, MockResponse::Rules(ServerRules::default()))
+            .on(
+                QueryType::DetailedPlayerInfo,
+                MockResponse::DetailedPlayers(DetailedPlayerList::default()),
+            ),
+    )
+    .await
+    .unwrap();
+    let proxy = QueryProxy::bind(ProxyConfig {
+        refresh_interval: std::time::Duration::ZERO,
+        ..ProxyConfig::new(upstream.addr(), "127.0.0.1:0".parse().unwrap())
+    })
+    .await
+    .unwrap();
+    assert!(!proxy.config().refresh_interval.is_zero());
+    let proxy_addr = proxy.local_addr().unwrap();
+    tokio::spawn(async move { proxy.run().await });
+
+    let client = Client::connect_with_config(
+        proxy_addr,
+        ClientConfig {
+            timeout_ms: 200,
+            max_retries: 3,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    assert_eq!(client.query_info().await.unwrap(), test_info());
+}
+
+#[tokio::test]
+async fn test_stress_against_mock_server() {
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Ping, MockResponse::Pong)
+            .on(QueryType::Information, MockResponse::Info(test_info())),
+    )
+    .await
+    .unwrap();
+
+    for query_type in [QueryType::Ping, QueryType::Information] {
+        let report = StressTest::new(StressConfig {
+            qps: 200,
+            duration: std::time::Duration::from_millis(250),
+            query_type,
+            timeout: std::time::Duration::from_millis(200),
+            ..StressConfig::new(server.addr())
+        })
+        .run()
+        .await
+        .unwrap();
+
+        assert!(report.sent >= 20, "only {} queries sent", report.sent);
+        assert_eq!(report.received, report.sent);
+        assert_eq!(report.lost, 0);
+        let latency = report.latency.unwrap();
+        assert_eq!(latency.histogram.last().unwrap().count, report.received);
+    }
+}
+
+#[tokio::test]
+async fn test_favorites_round_trip_and_refresh() {
+    let mut bytes = b"SAMP".to_vec();
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(&1u32.to_le_bytes());
+    bytes.extend_from_slice(&9u32.to_le_bytes());
+    bytes.extend_from_slice(b"127.0.0.1");
+    bytes.extend_from_slice(&7777u32.to_le_bytes());
+    for field in [&b"Old \xe9"[..], b"secret", b""] {
+        bytes.extend_from_slice(&(field.len() as u32).to_le_bytes());
+        bytes.extend_from_slice(field);
+    }
+
+    let favorites = Favorites::parse(&bytes).unwrap();
+    assert_eq!(favorites.version, 1);
+    assert_eq!(favorites.servers[0].host, "127.0.0.1");
+    assert_eq!(favorites.servers[0].port, 7777);
+    assert_eq!(favorites.servers[0].hostname, "Old \u{e9}");
+    assert_eq!(favorites.servers[0].server_password, "secret");
+    assert_eq!(favorites.to_bytes(), bytes);
+    assert!(Favorites::parse(&bytes[..bytes.len() - 1]).is_err());
+    assert!(Favorites::parse(b"XXXX\x01\0\0\0\0\0\0\0").is_err());
+
+    let server = MockServer::start(MockResponses::new().on(QueryType::Information, MockResponse::Info(test_info())))
+        .await
+        .unwrap();
+    let mut favorites = Favorites::default();
+    favorites.servers.push(Favorite::new("127.0.0.1", server.addr().port()));
+    favorites.servers.push(Favorite {
+        hostname: "Unreachable".to_string(),
+        ..Favorite::new("127.0.0.1", 1)
+    });
+
+    let results = favorites
+        .refresh(ClientConfig {
+            timeout_ms: 200,
+            max_retries: 0,
+            ..Default::default()
+        })
+        .await;
+    assert!(results[0].is_ok());
+    assert!(results[1].is_err());
+    assert_eq!(favorites.servers[0].hostname, "Mock Server");
+    assert_eq!(favorites.servers[1].hostname, "Unreachable");
+
+    let path = std::env::temp_dir().join(format!("samp-query-userdata-{}.dat", std::process::id()));
+    favorites.write(&path).unwrap();
+    assert_eq!(Favorites::read(&path).unwrap(), favorites);
+    std::fs::remove_file(path).unwrap();
+}
+
+#[tokio::test]
+async fn test_inflation_detection() {
+    let names = ["Alice", "Bob", "Carl_Johnson", "Big_Smoke", "Ryder"];
+    let honest = DetailedPlayerList {
+        players: names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| DetailedPlayer {
+                id: id as u8,
+                name: name.to_string(),
+                score: 10,
+                ping: 60 + id as u32,
+            })
+            .collect(),
+    };
+    let report = InflationReport::from_detailed(&test_info(), &honest);
+    assert_eq!(report.score, 0.0);
+    assert!(report.reasons.is_empty());
+
+    let inflated = ServerInfo {
+        players: 20,
+        ..test_info()
+    };
+    let bots = PlayerList {
+        players: ["Bot_1", "Bot_2", "Bot_3", "bot_3", "Alice"]
+            .iter()
+            .map(|name| Player {
+                name: name.to_string(),
+                score: 0,
+            })
+            .collect(),
+    };
+    let report = InflationReport::from_client_list(&inflated, &bots);
+    assert_eq!(report.listed_players, 5);
+    assert_eq!(report.duplicate_names, 1);
+    assert_eq!(report.patterned_names, 4);
+    assert_eq!(report.zero_ping, None);
+    assert!(report.is_likely_inflated());
+
+    // Player lists are not served above 100 players, so a short list is expected.
+    let large = ServerInfo {
+        players: 500,
+        max_players: 1000,
+        ..test_info()
+    };
+    assert_eq!(InflationReport::from_client_list(&large, &PlayerList::default()).score, 0.0);
+
+    let server = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(inflated))
+            .on(QueryType::DetailedPlayerInfo, MockResponse::Drop)
+            .on(QueryType::ClientList, MockResponse::Players(bots)),
+    )
+    .await
+    .unwrap();
+    let client = Client::connect_with_config(
+        server.addr(),
+        ClientConfig {
+            timeout_ms: 200,
+            max_retries: 0,
+            ..Default::default()
+        },
+    )
+    .await
+    .unwrap();
+    let report = inflation::check(&client).await.unwrap();
+    assert_eq!(report.reported_players, 20);
+    assert!(report.is_likely_inflated());
+}
+
+#[test]
+fn test_fingerprint_tags() {
+    let mut rules = HashMap::new();
+    rules.insert("version".to_string(), "omp 1.2.0.2670".to_string());
+    rules.insert("allowed_clients".to_string(), "0.3.7, 0.3.DL".to_string());
+    rules.insert("weburl".to_string(), "www.zap-hosting.com".to_string());
+    let rules = ServerRules { rules };
+
+    let info = ServerInfo {
+        hostname: "[EN] Stunt & Drift Paradise".to_string(),
+        gamemode: "Freeroam/DM".to_string(),
+        ..test_info()
+    };
+    let fp = fingerprint(&info, Some(&rules));
+    assert_eq!(fp.platform(), Some(Platform::OpenMp));
+    assert_eq!(
+        fp.categories().collect::<Vec<_>>(),
+        vec![Category::Freeroam, Category::Deathmatch, Category::Stunt, Category::Drift]
+    );
+    assert!(fp.has(&Tag::Host("zap-hosting".to_string())));
+    assert!(fp.facets().contains(&"platform:openmp".to_string()));
+    assert_eq!(
+        serde_json::to_value(Tag::Category(Category::CopsAndRobbers)).unwrap(),
+        serde_json::json!({"kind": "category", "value": "cops_and_robbers"})
+    );
+
+    let mut rules = HashMap::new();
+    rules.insert("version".to_string(), "0.3.7-R2".to_string());
+    let fp = fingerprint(
+        &ServerInfo {
+            hostname: "Wardens Cops and Robbers".to_string(),
+            gamemode: "Grand Larceny".to_string(),
+            ..test_info()
+        },
+        Some(&ServerRules { rules }),
+    );
+    assert_eq!(fp.platform(), Some(Platform::Samp));
+    assert!(fp.has(&Tag::Category(Category::CopsAndRobbers)));
+    assert!(fp.has(&Tag::Gamemode("grandlarc".to_string())));
+    assert!(!fp.has(&Tag::Category(Category::Deathmatch)));
+
+    assert!(fingerprint(&test_info(), None).platform().is_none());
+}
+
+#[tokio::test]
+async fn test_aggregate_ranking() {
+    let busy = MockServer::start(MockResponses::new().on(
+        QueryType::Information,
+        MockResponse::Info(ServerInfo {
+            players: 40,
+            ..test_info()
+        }),
+    ))
+    .await
+    .unwrap();
+    let quiet = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Ping, MockResponse::Pong),
+    )
+    .await
+    .unwrap();
+    let dead: SocketAddr = "127.0.0.1:1".parse().unwrap();
+
+    let mut aggregator = Aggregator::new(AggregateConfig {
+        client: ClientConfig {
+            timeout_ms: 200,
+            max_retries: 0,
+            ..Default::default()
+        },
+        ..AggregateConfig::default()
+    });
+    let mut table = aggregator.run(&[quiet.addr(), dead, busy.addr()]).await;
+
+    assert_eq!(table.failures.len(), 1);
+    assert_eq!(table.failures[0].address, dead);
+    assert_eq!(table.total_players(), 45);
+    let order: Vec<_> = table.servers.iter().map(|s| (s.rank, s.address)).collect();
+    assert_eq!(order, vec![(1, busy.addr()), (2, quiet.addr())]);
+
+    // Only the quiet server answers pings, so it ranks first by ping.
+    table.rank(RankBy::Ping);
+    assert_eq!(table.servers[0].address, quiet.addr());
+    assert!(table.servers[0].ping_ms.is_some());
+    assert_eq!(table.servers[1].ping_ms, None);
+
+    table.rank(RankBy::Uptime);
+    assert!(table.servers.iter().all(|s| s.uptime_percentage == 100.0));
+    assert!(!aggregator.availability().get(&dead).unwrap().is_online());
+}
+
+#[tokio::test]
+async fn test_server_filter() {
+    let filter = ServerFilter::parse(
+        "# fake farms\n\
+         allow-ip 127.0.0.0/8\n\
+         block-ip 127.0.0.2\n\
+         block-hostname ^bot farm\n\
+         block-rule weburl~fakefarm\\.example$\n",
+    )
+    .unwrap();
+    assert!(filter.allows_addr(&"127.0.0.1:7777".parse().unwrap()));
+    assert!(!filter.allows_addr(&"127.0.0.2:7777".parse().unwrap()));
+    assert!(!filter.allows_addr(&"10.0.0.1:7777".parse().unwrap()));
+    assert!(!filter.allows_hostname("{FF0000}BOT  Farm #3"));
+    assert!(filter.allows_hostname("Mock Server"));
+    assert!(ServerFilter::parse("block-ip 300.0.0.0/8").is_err());
+    assert!(ServerFilter::parse("deny everything").is_err());
+
+    let rules = |weburl: &str| ServerRules {
+        rules: HashMap::from([("weburl".to_string(), weburl.to_string())]),
+    };
+    assert!(!filter.allows_rules(&rules("www.fakefarm.example")));
+    assert!(filter.allows_rules(&rules("sa-mp.com")));
+    assert!("version=0.3.7".parse::<RulePredicate>().unwrap().matches(&ServerRules {
+        rules: HashMap::from([("Version".to_string(), "0.3.7".to_string())]),
+    }));
+
+    let farm = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Rules, MockResponse::Rules(rules("fakefarm.example"))),
+    )
+    .await
+    .unwrap();
+    let honest = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::Information, MockResponse::Info(test_info()))
+            .on(QueryType::Rules, MockResponse::Rules(rules("sa-mp.com"))),
+    )
+    .await
+    .unwrap();
+    let blocked: SocketAddr = "127.0.0.2:7777".parse().unwrap();
+
+    let mut aggregator = Aggregator::new(AggregateConfig {
+        client: ClientConfig {
+            timeout_ms: 200,
+            max_retries: 1,
+            ..Default::default()
+        },
+        filter,
+        ..AggregateConfig::default()
+    });
+    let table = aggregator.run(&[farm.addr(), blocked, honest.addr()]).await;
+
+    assert_eq!(table.servers.len(), 1);
+    assert_eq!(table.servers[0].address, honest.addr());
+    assert_eq!(table.filtered, vec![blocked, farm.addr()]);
+    assert!(table.failures.is_empty());
+}
+
+#[tokio::test]
+async fn test_find_player_across_servers() {
+    let detailed = |names: &[&str]| DetailedPlayerList {
+        players: names
+            .iter()
+            .enumerate()
+            .map(|(id, name)| DetailedPlayer {
+                id: id as u8,
+                name: name.to_string(),
+                score: 0,
+                ping: 50,
+            })
+            .collect(),
+    };
+    let first = MockServer::start(MockResponses::new().on(
+        QueryType::DetailedPlayerInfo,
+        MockResponse::DetailedPlayers(detailed(&["Carl_Johnson", "Sweet"])),
+    ))
+    .await
+    .unwrap();
+    let second = MockServer::start(
+        MockResponses::new()
+            .on(QueryType::DetailedPlayerInfo, MockResponse::Drop)
+            .on(
+                QueryType::ClientList,
+                MockResponse::Players(PlayerList {
+                    players: vec![Player {
+                        name: "carl_johnson2".to_string(),
+                        score: 7,
+                    }],
+                }),
+            ),
+    )
+    .await
+    .unwrap();
+    let servers = [first.addr(), "127.0.0.1:1".parse().unwrap(), second.addr()];
+    let config = || FindConfig {
+        client: ClientConfig {
+            timeout_ms: 200,
+            max_retries: 0,
+            ..Default::default()
+        },
+        ..FindConfig::default()
+    };
+
+    let found = find_player_with_config(&NamePattern::Exact("CARL_JOHNSON".to_string()), &servers, config()).await;
+    assert_eq!(found.len(), 1);
+    assert_eq!(found[0].0, first.addr());
+    assert_eq!(found[0].1.ping, 50);
+
+    let pattern = NamePattern::regex(r"^carl_johnson\d*$").unwrap();
+    let found = find_player_with_config(&pattern, &servers, config()).await;
+    let addrs: Vec<_> = found.iter().map(|(addr, _)| *addr).collect();
+    assert_eq!(addrs, vec![first.addr(), second.addr()]);
+    assert_eq!(found[1].1.score, 7);
+
+    assert!(NamePattern::Contains("swe".to_string()).matches("Sweet"));
+    assert!(NamePattern::regex("(").is_err());
+}
+
+#[tokio::test]
+async fn test_scheduler_merges_servers_and_backs_off() {
+    use std::time::Duration;
+
+    let config = ScheduleConfig {
+        jitter: 0.0,
+        max_backoff: Duration::from_secs(1),
+        ..ScheduleConfig::default()
+    };
+    let interval = Duration::from_millis(100);
+    assert_eq!(config.next_delay(interval, 0), interval);
+    assert_eq!(config.next_delay(interval, 3), Duration::from_millis(800));
+    assert_eq!(config.next_delay(interval, 10), Duration::from_secs(1));
+    let steep = ScheduleConfig {
+        backoff_factor: 1e300,
+        ..config.clone()
+    };
+    assert_eq!(steep.next_delay(interval, 32), Duration::from_secs(1));
+
+    let responses = || MockResponses::new().on(QueryType::Information, MockResponse::Info(test_info()));
+    let first = MockServer::start(responses()).await.unwrap();
+    let second = MockServer::start(responses()).await.unwrap();
+    let dead: SocketAddr = "127.0.0.1:1".parse().unwrap();
+
+    let mut scheduler = Scheduler::new(ScheduleConfig {
+        monitor: MonitorConfig {
+            client: ClientConfig {
+                timeout_ms: 100,
+                max_retries: 0,
+                ..Default::default()
+            },
+            interval: Duration::from_millis(50),
+            track_players: false,
+            ..MonitorConfig::default()
+        },
+        ..config
+    });
+    scheduler.add(first.addr());
+    scheduler.add_with_interval(dead, Duration::from_millis(20));
+    assert_eq!(scheduler.len(), 2);
+    let (handle, mut events) = scheduler.start();
+
+    let mut first_polls = 0;
+    let mut dead_offline = false;
+    let mut second_online = false;
+    tokio::time::timeout(Duration::from_secs(5), async {
+        while let Some(event) = events.next().await {
+            match (event.address, &event.kind) {
+                (addr, MonitorEventKind::Polled { .. }) if addr == first.addr() => {
+                    first_polls += 1;
+                    if first_polls == 2 {
+                        handle.add(second.addr(), None);
+                    }
+                }
+                (addr, MonitorEventKind::Offline { .. }) if addr == dead => dead_offline = true,
+                (addr, MonitorEventKind::Online) if addr == second.addr() => second_online = true,
+                _ => {}
+            }
+            if first_polls >= 3 && dead_offline && second_online {
+                break;
+            }
+        }
+    })
+    .await
+    .unwrap();
+
+    handle.remove(first.addr());
+}
+
+#[tokio::test]
+async fn test_scheduler_zero_interval_backs_off() {
+    use std::time::Duration;
+
+    let server = MockServer::start(MockResponses::new().on(QueryType::Information, MockResponse::Drop))
+        .await
+        .unwrap();
+    let mut scheduler = Scheduler::new(ScheduleConfig {
+        monitor: MonitorConfig {
+            client: ClientConfig {
+                timeout_ms: 20,
+                max_retries: 0,
+                ..Default::default()
+            },
+            track_players: false,
+            ..MonitorConfig::default()
+        },
+        jitter: 0.0,
+        ..ScheduleConfig::default()
+    });
+    scheduler.add_with_interval(server.addr(), Duration::ZERO);
+    let mut events = scheduler.stream();
+    assert!(matches!(events.next().await.unwrap().kind, MonitorEventKind::Offline { .. }));
+
+    // Polls of the offline server back off from the raised interval instead
+    // of following each other back to back.
+    tokio::time::sleep(Duration::from_millis(500)).await;
+    let polls = server.received().len();
+    assert!(polls < 15, "{} polls", polls);
+}
+
+#[tokio::test]
+async fn test_pipeline_ordering_and_in_flight_limit() {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    let slow = MockServer::start(MockResponses::new().on(
+        QueryType::Information,
+        MockResponse::Delayed(Duration::from_millis(300), Box::new(MockResponse::Info(test_info()))),
+    ))
+    .await
+    .unwrap();
+    let fast = MockServer::start(MockResponses::new().on(QueryType::Information, MockResponse::Info(test_info())))
+        .await
+        .unwrap();
+    let config = |ordered| PipelineConfig {
+        client: ClientConfig {
+            timeout_ms: 1000,
+            max_retries: 0,
+            ..Default::default()
+        },
+        max_in_flight: 2,
+        ordered,
+        ..PipelineConfig::default()
+    };
+    let addresses = || futures::stream::iter(vec![slow.addr(), fast.addr()]);
+
+    let unordered: Vec<_> = pipeline::info(addresses(), config(false)).collect().await;
+    assert_eq!(unordered[0].0, fast.addr());
+    assert!(unordered.iter().all(|(_, result)| result.is_ok()));
+
+    let ordered: Vec<_> = pipeline::info(addresses(), config(true)).collect().await;
+    assert_eq!(ordered[0].0, slow.addr());
+
+    let running = Arc::new(AtomicUsize::new(0));
+    let peak = Arc::new(AtomicUsize::new(0));
+    let inputs = futures::stream::iter(vec![fast.addr(); 8]);
+    let results: Vec<_> = pipeline::query(inputs, config(false), |client| {
+        let running = running.clone();
+        let peak = peak.clone();
+        async move {
+            let now = running.fetch_add(1, Ordering::SeqCst) + 1;
+            peak.fetch_max(now, Ordering::SeqCst);
+            tokio::time::sleep(Duration::from_millis(20)).await;
+            let result = client.query_info().await;
+            running.fetch_sub(1, Ordering::SeqCst);
+            result
+        }
+    })
+    .collect()
+    .await;
+    assert_eq!(results.len(), 8);
+    assert_eq!(peak.load(Ordering::SeqCst), 2);
+}
+
+#[test]
+fn test_session_record_and_replay() {
+    use std::time::Duration;
+
+    let mut rules = HashMap::new();
+    rules.insert("version".to_string(), "0.3.7-R2".to_string());
+    let rules = ServerRules { rules };
+    let config = ClientConfig {
+        timeout_ms: 200,
+        max_retries: 2,
+        ..Default::default()
+    };
+
+    let runtime = tokio::runtime::Runtime::new().unwrap();
+    let (session, recorded) = runtime.block_on(async {
+        let server = MockServer::start(
+            MockResponses::new()
+                .on(QueryType::Information, MockResponse::Info(test_info()))
+                .on(QueryType::Ping, MockResponse::Pong)
+                .on(QueryType::Rules, MockResponse::Drop)
+                .on(QueryType::Rules, MockResponse::Rules(rules.clone())),
+        )
+        .await
+        .unwrap();
+        let recorder = Recorder::new(server.addr(), UdpTransport::connect(server.addr()).await.unwrap());
+        let log = recorder.log();
+        let client = Client::with_transport(server.addr(), recorder, config.clone());
+
+        let info = client.query_info().await.unwrap();
+        let ping = client.query_ping().await.unwrap();
+        let rules = client.query_rules().await.unwrap();
+        (log.session(), (info, ping, rules))
+    });
+
+    // One info, one ping and two rules requests (the first one dropped).
+    let sent = session
+        .events
+        .iter()
+        .filter(|event| matches!(event, samp_query::record::SessionEvent::Sent { .. }))
+        .count();
+    assert_eq!(sent, 4);
+    let session = Session::from_json(&session.to_json().unwrap()).unwrap();
+    // Malformed hex is rejected, also when it is not ASCII.
+    let corrupt = r#"{"server":"127.0.0.1:7777","events":[{"direction":"sent","at_us":0,"data":"aéb"}]}"#;
+    assert!(Session::from_json(corrupt).is_err());
+
+    let replay = tokio::runtime::Builder::new_current_thread()
+        .enable_time()
+        .start_paused(true)
+        .build()
+        .unwrap();
+    replay.block_on(async {
+        let client = session.client(config.clone());
+        let started = tokio::time::Instant::now();
+        assert_eq!(client.query_info().await.unwrap(), recorded.0);
+        // Responses are delivered on tokio's millisecond timer.
+        assert!(client.query_ping().await.unwrap().ping_ms.abs_diff(recorded.1.ping_ms) <= 1);
+        assert_eq!(client.query_rules().await.unwrap(), recorded.2);
+        // The dropped request costs a full timeout of virtual time.
+        assert!(started.elapsed() >= Duration::from_millis(200));
+
+        let transport = ReplayTransport::new(session.clone());
+        assert!(!transport.is_finished());
+        let client = Client::with_transport(session.server, transport, config.clone());
+        assert!(client.query_rules().await.is_err());
+    });
+}
+
+#[test]
+fn test_decoders_reject_forged_counts() {
+    // A count of 65535 with no entries behind it must fail cleanly.
+    assert!(decode::rules(&[0xff, 0xff]).is_err());
+    assert!(decode::client_list(&[0xff, 0xff, 1, b'a']).is_err());
+    assert!(decode::detailed_players(&[0xff, 0xff]).is_err());
+
+    let line = encode::rcon_line("Unknown command or variable:").unwrap();
+    assert!(encode::rcon_line(&"x".repeat(usize::from(u16::MAX) + 1)).is_err());
+    assert_eq!(decode::rcon_line(&line).unwrap(), "Unknown command or variable:");
+    assert!(decode::rcon_line(&line[..line.len() - 1]).is_err());
+}
+
+#[test]
+fn test_encoders_reject_oversized_fields() {
+    let player = |name: String| Player { name, score: 0 };
+    // 254 ASCII bytes and a two-byte character: truncating at 255 would split it.
+    let name = format!("{}é", "x".repeat(254));
+    assert!(encode::client_list(&PlayerList {
+        players: vec![player(name)],
+    })
+    .is_err());
+    assert!(encode::client_list(&PlayerList {
+        players: vec![player("x".repeat(255))],
+    })
+    .is_ok());
+    assert!(encode::client_list(&PlayerList {
+        players: vec![player(String::new()); usize::from(u16::MAX) + 1],
+    })
+    .is_err());
+
+    let mut rules = HashMap::new();
+    rules.insert("weburl".to_string(), "x".repeat(256));
+    assert!(encode::rules(&ServerRules { rules }).is_err());
+}
+
+#[test]
+fn test_packet_explanation() {
+    let addr: SocketAddr = "127.0.0.1:7777".parse().unwrap();
+    let request = Packet::create_rcon_query(addr, "secret", "gmx").unwrap();
+    let fields = explain::fields(request.as_bytes(), Direction::Request);
+    let descriptions: Vec<_> = fields.iter().map(|field| field.description.as_str()).collect();
+    assert_eq!(
+        descriptions,
+        [
+            "signature \"SAMP\"",
+            "server IP 127.0.0.1",
+            "server port 7777",
+            "opcode 'x' (RCON)",
+            "password length 6",
+            "password (hidden)",
+            "command length 3",
+            "command \"gmx\"",
+        ]
+    );
+    assert!(fields[5].hidden);
+    let dump = explain::hexdump(request.as_bytes(), Direction::Request);
+    assert!(dump.contains("** ** ** ** ** **"));
+    assert!(!dump.contains("73 65 63"));
+
+    // A response cut off inside the hostname is explained up to the cut.
+    let query = Packet::create_query(addr, QueryType::Information).unwrap();
+    let response = Packet::create_response(&query, &encode::info(&test_info())).unwrap();
+    let truncated = &response.as_bytes()[..24];
+    let fields = explain::fields(truncated, Direction::Response);
+    let last = fields.last().unwrap();
+    assert_eq!(last.description, "truncated");
+    assert_eq!(last.range.end, truncated.len());
+    assert_eq!(fields[fields.len() - 2].description, "hostname length 11");
+
+    let fields = explain::fields(b"\xfe\xfd\x09", Direction::Response);
+    assert_eq!(fields.len(), 1);
+    assert_eq!(fields[0].description, "not a SA-MP query packet");
+}
+
+/// A minimal IPv4 MaxMind database mapping every address to one record made
+/// of `fields`, which are already encoded values.
+fn mmdb(database_type: &str, fields: &[(&str, Vec<u8>)]) -> Vec<u8> {
+    fn string(value: &str) -> Vec<u8> {
+        let mut out = vec![(2 << 5) | value.len() as u8];
+        out.extend_from_slice(value.as_bytes());
+        out
+    }
+    fn uint(kind: u8, value: u64, len: usize) -> Vec<u8> {
+        let mut out = vec![(kind << 5) | len as u8];
+        out.extend_from_slice(&value.to_be_bytes()[8 - len..]);
+        out
+    }
+    fn map(entries: &[(&str, Vec<u8>)]) -> Vec<u8> {
+        let mut out = vec![(7 << 5) | entries.len() as u8];
+        for (key, value) in entries {
+            out.extend(string(key));
+            out.extend_from_slice(value);
+        }
+        out
+    }
+
+    // One node whose two records both point at the start of the data section.
+    let mut db = Vec::new();
+    db.extend_from_slice(&17u32.to_be_bytes()[1..]);
+    db.extend_from_slice(&17u32.to_be_bytes()[1..]);
+    db.extend_from_slice(&[0; 16]);
+    db.extend(map(fields));
+    db.extend_from_slice(b"\xab\xcd\xefMaxMind.com");
+    // build_epoch is a uint64 and languages an array, both extended types.
+    let mut build_epoch = vec![8, 9 - 7];
+    build_epoch.extend_from_slice(&0u64.to_be_bytes());
+    let mut languages = vec![1, 11 - 7];
+    languages.extend(string("en"));
+    db.extend(map(&[
+        ("binary_format_major_version", uint(5, 2, 2)),
+        ("binary_format_minor_version", uint(5, 0, 2)),
+        ("build_epoch", build_epoch),
+        ("database_type", string(database_type)),
+        ("description", map(&[])),
+        ("ip_version", uint(5, 4, 2)),
+        ("languages", languages),
+        ("node_count", uint(6, 1, 4)),
+        ("record_size", uint(5, 24, 2)),
+    ]));
+    db
+}
+
+#[test]
+fn test_geoip_lookup() {
+    let dir = std::env::temp_dir();
+    let country = dir.join(format!("samp-query-country-{}.mmdb", std::process::id()));
+    let asn = dir.join(format!("samp-query-asn-{}.mmdb", std::process::id()));
+    let mut iso_code = vec![(7 << 5) | 1, (2 << 5) | 8];
+    iso_code.extend_from_slice(b"iso_code");
+    iso_code.extend_from_slice(&[(2 << 5) | 2, b'D', b'E']);
+    std::fs::write(&country, mmdb("GeoLite2-Country", &[("country", iso_code)])).unwrap();
+    let mut number = vec![(6 << 5) | 2];
+    number.extend_from_slice(&24940u16.to_be_bytes());
+    std::fs::write(&asn, mmdb("GeoLite2-ASN", &[("autonomous_system_number", number)])).unwrap();
+
+    let ip = "203.0.113.7".parse().unwrap();
+    let geoip = GeoIp::open([&country, &asn]).unwrap();
+    let info = geoip.lookup(ip);
+    assert_eq!(
+        info,
+        GeoInfo {
+            country: Some("DE".to_string()),
+            asn: Some(24940),
+            organization: None,
+        }
+    );
+    assert_eq!(info.to_string(), "DE AS24940");
+
+    let only_country = GeoIp::open([&country]).unwrap().lookup(ip);
+    assert_eq!(only_country.asn, None);
+    assert_eq!(GeoInfo::default().to_string(), "-");
+    assert!(GeoIp::open(Vec::<&str>::new()).is_err());
+    assert!(GeoIp::open([dir.join("samp-query-missing.mmdb")]).is_err());
+
+    std::fs::remove_file(country).unwrap();
+    std::fs::remove_file(asn).unwrap();
+}
@@ -0,0 +1,41 @@
+//! Build tasks, run with `cargo xtask <task>`.
+//!
+//! `cargo xtask assets [DIR]` generates the manual pages and shell
+//! completion scripts of the CLI into `DIR/man` and `DIR/completions`,
+//! `target/assets` by default, for distribution packages.
+
+use anyhow::{bail, Context, Result};
+use clap::ValueEnum;
+use clap_complete::Shell;
+use std::path::{Path, PathBuf};
+
+fn main() -> Result<()> {
+    let mut args = std::env::args().skip(1);
+    match args.next().as_deref() {
+        Some("assets") => {
+            let dir = args.next().map(PathBuf::from).unwrap_or_else(|| workspace_root().join("target/assets"));
+            assets(&dir)
+        }
+        Some(task) => bail!("Unknown task {}, expected assets", task),
+        None => bail!("Usage: cargo xtask assets [DIR]"),
+    }
+}
+
+fn assets(dir: &Path) -> Result<()> {
+    samp_query_cli::generate::man(samp_query_cli::command(), &dir.join("man"))?;
+
+    let completions = dir.join("completions");
+    std::fs::create_dir_all(&completions).with_context(|| format!("Failed to create {}", completions.display()))?;
+    for &shell in Shell::value_variants() {
+        let mut command = samp_query_cli::command();
+        let name = command.get_name().to_string();
+        clap_complete::generate_to(shell, &mut command, name, &completions)
+            .with_context(|| format!("Failed to write the {} completion script", shell))?;
+    }
+    eprintln!("Wrote the completion scripts to {}", completions.display());
+    Ok(())
+}
+
+fn workspace_root() -> PathBuf {
+    Path::new(env!("CARGO_MANIFEST_DIR")).parent().unwrap().to_path_buf()
+}